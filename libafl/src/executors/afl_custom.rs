@@ -0,0 +1,205 @@
+//! Runs the `afl_custom_post_process` hook of an `AFL++` custom mutator on each input,
+//! right before it is handed to the target, see [`crate::mutators::afl_custom`].
+
+use alloc::rc::Rc;
+use core::fmt::{self, Debug, Formatter};
+
+use crate::{
+    executors::{Executor, ExitKind, HasObservers},
+    inputs::HasBytesVec,
+    mutators::AflCustomLibrary,
+    observers::UsesObservers,
+    state::UsesState,
+    Error,
+};
+
+/// Wraps an [`Executor`], running the inputs post-processed by the given [`AflCustomLibrary`].
+///
+/// Only the executed input changes, the input stored in the corpus stays as it was.
+/// If the library rejects an input, the target is not run and [`ExitKind::Ok`] is returned.
+pub struct AflCustomPostProcessExecutor<E> {
+    executor: E,
+    library: Rc<AflCustomLibrary>,
+}
+
+impl<E> Debug for AflCustomPostProcessExecutor<E>
+where
+    E: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AflCustomPostProcessExecutor")
+            .field("executor", &self.executor)
+            .field("library", &self.library)
+            .finish()
+    }
+}
+
+impl<E> AflCustomPostProcessExecutor<E> {
+    /// Wraps `executor`, post-processing each input with `library`
+    pub fn new(executor: E, library: Rc<AflCustomLibrary>) -> Self {
+        Self { executor, library }
+    }
+
+    /// The wrapped executor
+    pub fn executor(&self) -> &E {
+        &self.executor
+    }
+
+    /// The wrapped executor, mutable
+    pub fn executor_mut(&mut self) -> &mut E {
+        &mut self.executor
+    }
+}
+
+impl<E, EM, Z> Executor<EM, Z> for AflCustomPostProcessExecutor<E>
+where
+    E: Executor<EM, Z>,
+    E::Input: HasBytesVec,
+    EM: UsesState<State = E::State>,
+    Z: UsesState<State = E::State>,
+{
+    fn run_target(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut Self::State,
+        mgr: &mut EM,
+        input: &Self::Input,
+    ) -> Result<ExitKind, Error> {
+        if !self.library.has_post_process() {
+            return self.executor.run_target(fuzzer, state, mgr, input);
+        }
+
+        let mut processed = input.clone();
+        match self.library.post_process(processed.bytes_mut()) {
+            Some(bytes) => {
+                *processed.bytes_mut() = bytes;
+                self.executor.run_target(fuzzer, state, mgr, &processed)
+            }
+            None => Ok(ExitKind::Ok),
+        }
+    }
+}
+
+impl<E> UsesState for AflCustomPostProcessExecutor<E>
+where
+    E: UsesState,
+{
+    type State = E::State;
+}
+
+impl<E> UsesObservers for AflCustomPostProcessExecutor<E>
+where
+    E: UsesObservers,
+{
+    type Observers = E::Observers;
+}
+
+impl<E> HasObservers for AflCustomPostProcessExecutor<E>
+where
+    E: HasObservers,
+{
+    #[inline]
+    fn observers(&self) -> &Self::Observers {
+        self.executor.observers()
+    }
+
+    #[inline]
+    fn observers_mut(&mut self) -> &mut Self::Observers {
+        self.executor.observers_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{rc::Rc, vec::Vec};
+    use core::{ffi::c_void, marker::PhantomData};
+
+    use crate::{
+        events::NopEventManager,
+        executors::{AflCustomPostProcessExecutor, Executor, ExitKind},
+        inputs::{BytesInput, HasBytesVec},
+        mutators::AflCustomLibrary,
+        state::{NopState, UsesState},
+        Error, NopFuzzer,
+    };
+
+    /// Remembers each input it runs
+    #[derive(Debug)]
+    struct RecordingExecutor {
+        ran: Vec<Vec<u8>>,
+        phantom: PhantomData<NopState<BytesInput>>,
+    }
+
+    impl UsesState for RecordingExecutor {
+        type State = NopState<BytesInput>;
+    }
+
+    impl<EM, Z> Executor<EM, Z> for RecordingExecutor
+    where
+        EM: UsesState<State = Self::State>,
+        Z: UsesState<State = Self::State>,
+    {
+        fn run_target(
+            &mut self,
+            _fuzzer: &mut Z,
+            _state: &mut Self::State,
+            _mgr: &mut EM,
+            input: &BytesInput,
+        ) -> Result<ExitKind, Error> {
+            self.ran.push(input.bytes().to_vec());
+            Ok(ExitKind::Crash)
+        }
+    }
+
+    /// Upper-cases the input in place, and rejects inputs starting with `!`
+    unsafe extern "C" fn post_process(
+        _data: *mut c_void,
+        buf: *mut u8,
+        buf_size: usize,
+        out_buf: *mut *mut u8,
+    ) -> usize {
+        let bytes = core::slice::from_raw_parts_mut(buf, buf_size);
+        if bytes.first() == Some(&b'!') {
+            return 0;
+        }
+        bytes.make_ascii_uppercase();
+        *out_buf = buf;
+        buf_size
+    }
+
+    #[test]
+    fn test_afl_custom_post_process() {
+        let library = Rc::new(AflCustomLibrary::from_hooks().with_post_process(post_process));
+        let mut executor = AflCustomPostProcessExecutor::new(
+            RecordingExecutor {
+                ran: Vec::new(),
+                phantom: PhantomData,
+            },
+            library,
+        );
+        let mut fuzzer = NopFuzzer::new();
+        let mut state = NopState::new();
+        let mut mgr = NopEventManager::new();
+
+        let input = BytesInput::new(b"abc".to_vec());
+        let exit_kind = executor
+            .run_target(&mut fuzzer, &mut state, &mut mgr, &input)
+            .unwrap();
+        assert_eq!(exit_kind, ExitKind::Crash);
+        // The target sees the processed input, the caller keeps the original
+        assert_eq!(executor.executor().ran, [b"ABC".to_vec()]);
+        assert_eq!(input.bytes(), b"abc");
+
+        // Rejected inputs never reach the target
+        let exit_kind = executor
+            .run_target(
+                &mut fuzzer,
+                &mut state,
+                &mut mgr,
+                &BytesInput::new(b"!abc".to_vec()),
+            )
+            .unwrap();
+        assert_eq!(exit_kind, ExitKind::Ok);
+        assert_eq!(executor.executor().ran.len(), 1);
+    }
+}
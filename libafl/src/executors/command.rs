@@ -1,5 +1,5 @@
 //! The command executor executes a sub program for each run
-use alloc::vec::Vec;
+use alloc::{string::String, vec::Vec};
 use core::{
     fmt::{self, Debug, Formatter},
     marker::PhantomData,
@@ -18,6 +18,7 @@ use std::{
 
 use libafl_bolts::{
     fs::{get_unique_std_input_file, InputFile},
+    ownedref::OwnedSlice,
     tuples::MatchName,
     AsSlice,
 };
@@ -83,75 +84,146 @@ pub struct StdCommandConfigurator {
     timeout: Duration,
     /// true: input gets delivered via stdink
     input_location: InputLocation,
+    /// Where to deliver the named parts of the input, see [`HasTargetBytes::target_parts`]
+    part_locations: Vec<(String, InputLocation)>,
     /// The Command to execute
     command: Command,
 }
 
+/// Delivers `bytes` to the given location: files are written right away,
+/// arguments and stdin are collected to be passed when spawning the child.
+fn deliver_input<'a>(
+    location: &mut InputLocation,
+    bytes: OwnedSlice<'a, u8>,
+    args: &mut Vec<(usize, OwnedSlice<'a, u8>)>,
+    stdin: &mut Option<OwnedSlice<'a, u8>>,
+) -> Result<(), Error> {
+    match location {
+        InputLocation::Arg { argnum } => args.push((*argnum, bytes)),
+        InputLocation::StdIn => *stdin = Some(bytes),
+        InputLocation::File { out_file } => out_file.write_buf(bytes.as_slice())?,
+    }
+    Ok(())
+}
+
+impl StdCommandConfigurator {
+    /// A copy of the command with the given arguments replacing their `DUMMY` placeholders
+    fn command_with_args(&self, input_args: &[(usize, OwnedSlice<u8>)], stdin: bool) -> Command {
+        let args = self.command.get_args();
+        let mut cmd = Command::new(self.command.get_program());
+
+        if stdin {
+            cmd.stdin(Stdio::piped());
+        } else {
+            cmd.stdin(Stdio::null());
+        }
+        if !self.debug_child {
+            cmd.stdout(Stdio::null());
+            cmd.stderr(Stdio::null());
+        }
+
+        if self.has_stdout_observer {
+            cmd.stdout(Stdio::piped());
+        }
+        if self.has_stderr_observer {
+            cmd.stderr(Stdio::piped());
+        }
+
+        for (i, arg) in args.enumerate() {
+            if let Some((_, bytes)) = input_args.iter().find(|(argnum, _)| *argnum == i) {
+                debug_assert_eq!(arg, "DUMMY");
+                #[cfg(unix)]
+                cmd.arg(OsStr::from_bytes(bytes.as_slice()));
+                // There is an issue here that the chars on Windows are 16 bit wide.
+                // I can't really test it. Please open a PR if this goes wrong.
+                #[cfg(not(unix))]
+                cmd.arg(OsString::from_vec(bytes.as_slice().to_vec()));
+            } else {
+                cmd.arg(arg);
+            }
+        }
+        cmd.envs(
+            self.command
+                .get_envs()
+                .filter_map(|(key, value)| value.map(|value| (key, value))),
+        );
+        if let Some(cwd) = self.command.get_current_dir() {
+            cmd.current_dir(cwd);
+        }
+        cmd
+    }
+}
+
 impl CommandConfigurator for StdCommandConfigurator {
     fn spawn_child<I>(&mut self, input: &I) -> Result<Child, Error>
     where
         I: Input + HasTargetBytes,
     {
-        match &mut self.input_location {
-            InputLocation::Arg { argnum } => {
-                let args = self.command.get_args();
-                let mut cmd = Command::new(self.command.get_program());
-
-                if !self.debug_child {
-                    cmd.stdout(Stdio::null());
-                    cmd.stderr(Stdio::null());
-                }
-
-                if self.has_stdout_observer {
-                    cmd.stdout(Stdio::piped());
-                }
-                if self.has_stderr_observer {
-                    cmd.stderr(Stdio::piped());
-                }
-
-                for (i, arg) in args.enumerate() {
-                    if i == *argnum {
-                        debug_assert_eq!(arg, "DUMMY");
-                        #[cfg(unix)]
-                        cmd.arg(OsStr::from_bytes(input.target_bytes().as_slice()));
-                        // There is an issue here that the chars on Windows are 16 bit wide.
-                        // I can't really test it. Please open a PR if this goes wrong.
-                        #[cfg(not(unix))]
-                        cmd.arg(OsString::from_vec(input.target_bytes().as_vec()));
-                    } else {
-                        cmd.arg(arg);
+        let mut args = Vec::new();
+        let mut stdin = None;
+        if self.part_locations.is_empty() {
+            deliver_input(
+                &mut self.input_location,
+                input.target_bytes(),
+                &mut args,
+                &mut stdin,
+            )?;
+        } else {
+            // Parts sharing a name are concatenated, in order, instead of overwriting each other.
+            // Parts without a location of their own go to the input location, concatenated as well
+            let mut parts: Vec<_> = input.target_parts().into_iter().map(Some).collect();
+            for (name, location) in &mut self.part_locations {
+                let mut bytes: Option<OwnedSlice<u8>> = None;
+                for part in &mut parts {
+                    match part {
+                        Some((part_name, _)) if part_name == name => {}
+                        _ => continue,
                     }
+                    let (_, part_bytes) = part.take().unwrap();
+                    bytes = Some(match bytes {
+                        None => part_bytes,
+                        Some(prev) => {
+                            let mut joined = prev.as_slice().to_vec();
+                            joined.extend_from_slice(part_bytes.as_slice());
+                            OwnedSlice::from(joined)
+                        }
+                    });
                 }
-                cmd.envs(
-                    self.command
-                        .get_envs()
-                        .filter_map(|(key, value)| value.map(|value| (key, value))),
-                );
-                if let Some(cwd) = self.command.get_current_dir() {
-                    cmd.current_dir(cwd);
-                }
-                Ok(cmd.spawn()?)
+                // A missing part is delivered empty, not left over from the last run
+                let bytes = bytes.unwrap_or_else(|| OwnedSlice::from(vec![]));
+                deliver_input(location, bytes, &mut args, &mut stdin)?;
             }
-            InputLocation::StdIn => {
-                let mut handle = self.command.stdin(Stdio::piped()).spawn()?;
-                let mut stdin = handle.stdin.take().unwrap();
-                if let Err(err) = stdin.write_all(input.target_bytes().as_slice()) {
-                    if err.kind() != std::io::ErrorKind::BrokenPipe {
-                        return Err(err.into());
-                    }
-                } else if let Err(err) = stdin.flush() {
-                    if err.kind() != std::io::ErrorKind::BrokenPipe {
-                        return Err(err.into());
-                    }
-                }
-                drop(stdin);
-                Ok(handle)
+            let mut rest = Vec::new();
+            for (_, bytes) in parts.into_iter().flatten() {
+                rest.extend_from_slice(bytes.as_slice());
             }
-            InputLocation::File { out_file } => {
-                out_file.write_buf(input.target_bytes().as_slice())?;
-                Ok(self.command.spawn()?)
+            deliver_input(
+                &mut self.input_location,
+                OwnedSlice::from(rest),
+                &mut args,
+                &mut stdin,
+            )?;
+        }
+
+        let mut handle = if args.is_empty() {
+            self.command.spawn()?
+        } else {
+            self.command_with_args(&args, stdin.is_some()).spawn()?
+        };
+        if let Some(bytes) = stdin {
+            let mut stdin = handle.stdin.take().unwrap();
+            if let Err(err) = stdin.write_all(bytes.as_slice()) {
+                if err.kind() != std::io::ErrorKind::BrokenPipe {
+                    return Err(err.into());
+                }
+            } else if let Err(err) = stdin.flush() {
+                if err.kind() != std::io::ErrorKind::BrokenPipe {
+                    return Err(err.into());
+                }
             }
+            drop(stdin);
         }
+        Ok(handle)
     }
 
     fn exec_timeout(&self) -> Duration {
@@ -183,6 +255,9 @@ impl CommandExecutor<(), (), ()> {
     /// * `arg_input_file` for input via a file of a specific name
     /// * `arg_input_file_std` for a file with default name
     /// (at the right location in the arguments)
+    ///
+    /// The parts of a [`crate::inputs::MultipartInput`] can be delivered on their own, using
+    /// `arg_input_part_arg` and `arg_input_part_file`.
     #[must_use]
     pub fn builder() -> CommandExecutorBuilder {
         CommandExecutorBuilder::new()
@@ -252,6 +327,7 @@ where
                 input_location: InputLocation::File {
                     out_file: InputFile::create(path)?,
                 },
+                part_locations: Vec::new(),
                 command,
                 debug_child,
                 has_stdout_observer,
@@ -404,6 +480,13 @@ where
     }
 }
 
+/// Where a [`CommandExecutorBuilder`] delivers a named part; files are only created on build
+#[derive(Debug, Clone)]
+enum PartLocation {
+    Arg { argnum: usize },
+    File { path: PathBuf },
+}
+
 /// The builder for a default [`CommandExecutor`] that should fit most use-cases.
 #[derive(Debug, Clone)]
 pub struct CommandExecutorBuilder {
@@ -411,6 +494,7 @@ pub struct CommandExecutorBuilder {
     program: Option<OsString>,
     args: Vec<OsString>,
     input_location: InputLocation,
+    part_locations: Vec<(String, PartLocation)>,
     cwd: Option<PathBuf>,
     envs: Vec<(OsString, OsString)>,
     timeout: Duration,
@@ -430,6 +514,7 @@ impl CommandExecutorBuilder {
             program: None,
             args: vec![],
             input_location: InputLocation::StdIn,
+            part_locations: vec![],
             cwd: None,
             envs: vec![],
             timeout: Duration::from_secs(5),
@@ -490,6 +575,37 @@ impl CommandExecutorBuilder {
         self
     }
 
+    /// Delivers the parts of a multipart input with the given name, see [`HasTargetBytes::target_parts`],
+    /// _as argument_ at the current position.
+    /// Several parts with this name are concatenated, in order.
+    /// Parts without a location of their own are delivered, concatenated, to the input location.
+    pub fn arg_input_part_arg<N: Into<String>>(&mut self, name: N) -> &mut Self {
+        let argnum = self.args.len();
+        self.part_locations
+            .push((name.into(), PartLocation::Arg { argnum }));
+        self.arg("DUMMY");
+        self
+    }
+
+    /// Delivers the parts of a multipart input with the given name, see [`HasTargetBytes::target_parts`],
+    /// via a file of the given name, and adds the filename as arg at the current position.
+    /// Several parts with this name are concatenated, in order.
+    /// The file is created in [`Self::build`].
+    pub fn arg_input_part_file<N: Into<String>, P: AsRef<Path>>(
+        &mut self,
+        name: N,
+        path: P,
+    ) -> &mut Self {
+        self.arg(path.as_ref());
+        self.part_locations.push((
+            name.into(),
+            PartLocation::File {
+                path: path.as_ref().to_owned(),
+            },
+        ));
+        self
+    }
+
     /// Adds an argument to the program's commandline.
     ///
     /// You may want to use [`CommandExecutor::parse_afl_cmdline`] if you're going to pass `@@`
@@ -572,6 +688,25 @@ impl CommandExecutorBuilder {
             ));
         };
 
+        let mut part_locations = Vec::with_capacity(self.part_locations.len());
+        for (idx, (name, location)) in self.part_locations.iter().enumerate() {
+            if self.part_locations[..idx]
+                .iter()
+                .any(|(other, _)| other == name)
+            {
+                return Err(Error::illegal_argument(format!(
+                    "CommandExecutor::builder: more than one location for the part {name}"
+                )));
+            }
+            let location = match location {
+                PartLocation::Arg { argnum } => InputLocation::Arg { argnum: *argnum },
+                PartLocation::File { path } => InputLocation::File {
+                    out_file: InputFile::create(path)?,
+                },
+            };
+            part_locations.push((name.clone(), location));
+        }
+
         let mut command = Command::new(program);
         match &self.input_location {
             InputLocation::StdIn => {
@@ -607,6 +742,7 @@ impl CommandExecutorBuilder {
             has_stdout_observer: observers.observes_stdout(),
             has_stderr_observer: observers.observes_stderr(),
             input_location: self.input_location.clone(),
+            part_locations,
             timeout: self.timeout,
             command,
         };
@@ -686,9 +822,9 @@ mod tests {
         events::SimpleEventManager,
         executors::{
             command::{CommandExecutor, InputLocation},
-            Executor,
+            Executor, ExitKind,
         },
-        inputs::BytesInput,
+        inputs::{BytesInput, MultipartInput},
         monitors::SimpleMonitor,
         state::NopState,
         NopFuzzer,
@@ -746,4 +882,84 @@ mod tests {
             )
             .unwrap();
     }
+
+    #[test]
+    #[cfg(unix)]
+    #[cfg_attr(miri, ignore)]
+    fn test_multipart_delivery() {
+        let mut mgr = SimpleEventManager::new(SimpleMonitor::new(|status| {
+            log::info!("{status}");
+        }));
+
+        // Crashes unless the config part is the first argument, and the image part is in the file
+        let mut executor = CommandExecutor::builder();
+        executor
+            .program("sh")
+            .arg("-c")
+            .arg(r#"[ "$0" = "scale=2" ] && [ "$(cat "$1")" = "PNG" ] || kill -SEGV $$"#)
+            .arg_input_part_arg("config")
+            .arg_input_part_file("image", ".cur_input_multipart_test");
+        let mut executor = executor.build(()).unwrap();
+
+        let mut run = |config: &[u8]| {
+            let input = MultipartInput::from(vec![
+                ("image", BytesInput::new(b"PNG".to_vec())),
+                ("config", BytesInput::new(config.to_vec())),
+            ]);
+            executor
+                .run_target(
+                    &mut NopFuzzer::new(),
+                    &mut NopState::new(),
+                    &mut mgr,
+                    &input,
+                )
+                .unwrap()
+        };
+        assert_eq!(run(b"scale=2"), ExitKind::Ok);
+        assert_eq!(run(b"scale=3"), ExitKind::Crash);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    #[cfg_attr(miri, ignore)]
+    fn test_multipart_shared_names() {
+        let mut mgr = SimpleEventManager::new(SimpleMonitor::new(|status| {
+            log::info!("{status}");
+        }));
+
+        // Parts sharing a name are concatenated, not written over each other
+        let mut executor = CommandExecutor::builder();
+        executor
+            .program("sh")
+            .arg("-c")
+            .arg(r#"[ "$(cat "$0")" = "ab" ] || kill -SEGV $$"#)
+            .arg_input_part_file("chunk", ".cur_input_multipart_shared_test");
+        let mut executor = executor.build(()).unwrap();
+        let input = MultipartInput::from(vec![
+            ("chunk", BytesInput::new(b"a".to_vec())),
+            ("chunk", BytesInput::new(b"b".to_vec())),
+        ]);
+        let exit_kind = executor
+            .run_target(
+                &mut NopFuzzer::new(),
+                &mut NopState::new(),
+                &mut mgr,
+                &input,
+            )
+            .unwrap();
+        assert_eq!(exit_kind, ExitKind::Ok);
+
+        // Each part gets one location, and unwritable files fail the build, not the builder
+        let mut executor = CommandExecutor::builder();
+        executor
+            .program("true")
+            .arg_input_part_arg("chunk")
+            .arg_input_part_file("chunk", ".cur_input_multipart_shared_test");
+        assert!(executor.build::<(), NopState<BytesInput>>(()).is_err());
+        let mut executor = CommandExecutor::builder();
+        executor
+            .program("true")
+            .arg_input_part_file("chunk", "/nonexistent/dir/.cur_input");
+        assert!(executor.build::<(), NopState<BytesInput>>(()).is_err());
+    }
 }
@@ -2,6 +2,7 @@
 //! It wraps two executors that will be run after each other with the same input.
 //! In comparison to the [`crate::executors::CombinedExecutor`] it also runs the secondary executor in `run_target`.
 //!
+use alloc::vec::Vec;
 use core::{cell::UnsafeCell, fmt::Debug};
 
 use libafl_bolts::{ownedref::OwnedMutPtr, tuples::MatchName};
@@ -177,6 +178,18 @@ where
         self.primary.as_mut().observe_stderr(stderr);
         self.secondary.as_mut().observe_stderr(stderr);
     }
+
+    /// Returns true if a responses observer was added to the list
+    #[inline]
+    fn observes_responses(&self) -> bool {
+        self.primary.as_ref().observes_responses() || self.secondary.as_ref().observes_responses()
+    }
+
+    /// Runs `observe_responses` for all responses observers in the list
+    fn observe_responses(&mut self, responses: &[Vec<u8>]) {
+        self.primary.as_mut().observe_responses(responses);
+        self.secondary.as_mut().observe_responses(responses);
+    }
 }
 
 impl<A, B, DOT> MatchName for ProxyObserversTuple<A, B, DOT>
@@ -1,6 +1,10 @@
 //! Expose an `Executor` based on a `Forkserver` in order to execute AFL/AFL++ binaries
 
-use alloc::{borrow::ToOwned, string::ToString, vec::Vec};
+use alloc::{
+    borrow::ToOwned,
+    string::{String, ToString},
+    vec::Vec,
+};
 use core::{
     fmt::{self, Debug, Formatter},
     marker::PhantomData,
@@ -18,6 +22,7 @@ use std::{
 use libafl_bolts::{
     fs::{get_unique_std_input_file, InputFile},
     os::{dup2, pipes::Pipe},
+    ownedref::OwnedSlice,
     shmem::{ShMem, ShMemProvider, UnixShMemProvider},
     tuples::{MatchName, Prepend},
     AsMutSlice, AsSlice, Truncate,
@@ -533,6 +538,8 @@ where
     target: OsString,
     args: Vec<OsString>,
     input_file: InputFile,
+    /// The files the named parts of the input are written to, see [`HasTargetBytes::target_parts`]
+    part_files: Vec<(String, InputFile)>,
     uses_shmem_testcase: bool,
     forkserver: Forkserver,
     observers: OT,
@@ -553,6 +560,7 @@ where
             .field("target", &self.target)
             .field("args", &self.args)
             .field("input_file", &self.input_file)
+            .field("part_files", &self.part_files)
             .field("uses_shmem_testcase", &self.uses_shmem_testcase)
             .field("forkserver", &self.forkserver)
             .field("observers", &self.observers)
@@ -595,6 +603,11 @@ where
         &self.input_file
     }
 
+    /// The [`InputFile`]s the named parts of the input are written to, see [`HasTargetBytes::target_parts`]
+    pub fn part_files(&self) -> &[(String, InputFile)] {
+        &self.part_files
+    }
+
     /// The coverage map size if specified by the target
     pub fn coverage_map_size(&self) -> Option<usize> {
         self.map_size
@@ -615,6 +628,7 @@ pub struct ForkserverExecutorBuilder<'a, SP> {
     is_deferred_frksrv: bool,
     autotokens: Option<&'a mut Tokens>,
     input_filename: Option<OsString>,
+    part_filenames: Vec<(String, OsString)>,
     shmem_provider: Option<&'a mut SP>,
     max_input_size: usize,
     map_size: Option<usize>,
@@ -649,6 +663,7 @@ impl<'a, SP> ForkserverExecutorBuilder<'a, SP> {
             target,
             args: self.arguments.clone(),
             input_file,
+            part_files: self.part_files()?,
             uses_shmem_testcase: self.uses_shmem_testcase,
             forkserver,
             observers,
@@ -694,6 +709,7 @@ impl<'a, SP> ForkserverExecutorBuilder<'a, SP> {
             target,
             args: self.arguments.clone(),
             input_file,
+            part_files: self.part_files()?,
             uses_shmem_testcase: self.uses_shmem_testcase,
             forkserver,
             observers,
@@ -704,6 +720,14 @@ impl<'a, SP> ForkserverExecutorBuilder<'a, SP> {
         })
     }
 
+    /// Creates the files for the named parts of the input
+    fn part_files(&self) -> Result<Vec<(String, InputFile)>, Error> {
+        self.part_filenames
+            .iter()
+            .map(|(name, filename)| Ok((name.clone(), InputFile::create(filename)?)))
+            .collect()
+    }
+
     #[allow(clippy::pedantic)]
     fn build_helper(&mut self) -> Result<(Forkserver, InputFile, Option<SP::ShMem>), Error>
     where
@@ -983,6 +1007,18 @@ impl<'a, SP> ForkserverExecutorBuilder<'a, SP> {
         moved
     }
 
+    #[must_use]
+    /// Place the file for the parts of a multipart input with the given name at this position,
+    /// see [`HasTargetBytes::target_parts`].
+    /// Parts without a file of their own are delivered, concatenated, as the input.
+    pub fn arg_input_part_file<N: Into<String>, P: AsRef<Path>>(self, name: N, path: P) -> Self {
+        let mut moved = self.arg(path.as_ref());
+        moved
+            .part_filenames
+            .push((name.into(), path.as_ref().as_os_str().to_os_string()));
+        moved
+    }
+
     #[must_use]
     /// Place the input at this position and set the default filename for the input.
     /// The filename includes the PID of the fuzzer to ensure that no two fuzzers write to the same file
@@ -1039,6 +1075,7 @@ impl<'a> ForkserverExecutorBuilder<'a, UnixShMemProvider> {
             is_deferred_frksrv: false,
             autotokens: None,
             input_filename: None,
+            part_filenames: vec![],
             shmem_provider: None,
             map_size: None,
             real_map_size: 0,
@@ -1062,6 +1099,7 @@ impl<'a> ForkserverExecutorBuilder<'a, UnixShMemProvider> {
             is_deferred_frksrv: self.is_deferred_frksrv,
             autotokens: self.autotokens,
             input_filename: self.input_filename,
+            part_filenames: self.part_filenames,
             shmem_provider: Some(shmem_provider),
             map_size: self.map_size,
             real_map_size: self.real_map_size,
@@ -1095,10 +1133,27 @@ where
     ) -> Result<ExitKind, Error> {
         let mut exit_kind = ExitKind::Ok;
 
+        let target_bytes = if self.part_files.is_empty() {
+            input.target_bytes()
+        } else {
+            // Parts without a file of their own are delivered as the input, concatenated
+            let mut rest = Vec::new();
+            for (name, bytes) in input.target_parts() {
+                match self
+                    .part_files
+                    .iter_mut()
+                    .find(|(part_name, _)| part_name == name)
+                {
+                    Some((_, part_file)) => part_file.write_buf(bytes.as_slice())?,
+                    None => rest.extend_from_slice(bytes.as_slice()),
+                }
+            }
+            OwnedSlice::from(rest)
+        };
+
         // Write to testcase
         if self.uses_shmem_testcase {
             let map = unsafe { self.map.as_mut().unwrap_unchecked() };
-            let target_bytes = input.target_bytes();
             let mut size = target_bytes.as_slice().len();
             let max_size = map.len() - SHMEM_FUZZ_HDR_SIZE;
             if size > max_size {
@@ -1112,7 +1167,7 @@ where
             map.as_mut_slice()[SHMEM_FUZZ_HDR_SIZE..(SHMEM_FUZZ_HDR_SIZE + size)]
                 .copy_from_slice(&target_bytes.as_slice()[..size]);
         } else {
-            self.input_file.write_buf(input.target_bytes().as_slice())?;
+            self.input_file.write_buf(target_bytes.as_slice())?;
         }
 
         // Don't tell the forkserver to spawn a new process before clearing the cov map
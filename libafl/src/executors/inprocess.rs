@@ -23,7 +23,7 @@ use core::{
     sync::atomic::{compiler_fence, Ordering},
 };
 #[cfg(all(feature = "std", unix))]
-use std::intrinsics::transmute;
+use std::{intrinsics::transmute, io};
 
 use libafl_bolts::current_time;
 #[cfg(all(unix, not(miri)))]
@@ -1471,6 +1471,10 @@ pub(crate) struct InProcessForkExecutorGlobalData {
     pub crash_handler: *const c_void,
     /// Stores a pointer to the timeout_handler function
     pub timeout_handler: *const c_void,
+    /// If crashes caused by the [`ResourceLimits`] of the child are reported as such
+    pub report_limit_violations: bool,
+    /// If the child ran out of memory, see [`exit_out_of_memory`]
+    pub out_of_memory: bool,
 }
 
 #[cfg(all(feature = "std", unix))]
@@ -1512,6 +1516,8 @@ pub(crate) static mut FORK_EXECUTOR_GLOBAL_DATA: InProcessForkExecutorGlobalData
         current_input_ptr: ptr::null(),
         crash_handler: ptr::null(),
         timeout_handler: ptr::null(),
+        report_limit_violations: false,
+        out_of_memory: false,
     };
 
 #[cfg(all(feature = "std", unix))]
@@ -1591,6 +1597,196 @@ extern "C" {
 #[cfg(all(feature = "std", unix, not(target_os = "linux")))]
 const ITIMER_REAL: libc::c_int = 0;
 
+/// The exit code of a forked child crashing because of its [`ResourceLimits`], plus the [`ResourceLimitViolation`].
+///
+/// Once limits are set, a harness exiting normally with this code is reported as [`ExitKind::Oom`], too.
+#[cfg(all(feature = "std", unix))]
+const LIMIT_VIOLATION_EXIT_CODE: i32 = 0xe0;
+
+#[cfg(all(feature = "std", unix, target_os = "linux", target_env = "gnu"))]
+type RlimitResource = libc::__rlimit_resource_t;
+#[cfg(all(
+    feature = "std",
+    unix,
+    not(all(target_os = "linux", target_env = "gnu"))
+))]
+type RlimitResource = libc::c_int;
+
+/// A resource limit of the forked child the target ran into
+#[cfg(all(feature = "std", unix))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ResourceLimitViolation {
+    /// The target ran out of memory, see [`exit_out_of_memory`]
+    Memory,
+    /// The target wrote beyond the file size limit
+    FileSize,
+}
+
+#[cfg(all(feature = "std", unix))]
+impl ResourceLimitViolation {
+    /// The exit code the child reports this violation with
+    fn exit_code(self) -> i32 {
+        LIMIT_VIOLATION_EXIT_CODE + self as i32
+    }
+
+    /// The violation a child ran into, from how it ended.
+    ///
+    /// The child can not be told apart from a harness calling `exit(0xe0)` itself, which is taken for [`Self::Memory`].
+    fn from_wait_status(status: &WaitStatus) -> Option<Self> {
+        match *status {
+            // the default action of the signal sent for writes beyond the file size limit
+            WaitStatus::Signaled(_, nix::sys::signal::Signal::SIGXFSZ, _) => Some(Self::FileSize),
+            WaitStatus::Exited(_, code) if code == Self::Memory.exit_code() => Some(Self::Memory),
+            _ => None,
+        }
+    }
+}
+
+/// Ends the forked child of an [`InProcessForkExecutor`] or a [`TimeoutInProcessForkExecutor`] for running out of memory,
+/// which is reported as [`ExitKind::Oom`] if the child has [`ResourceLimits`], and as a crash otherwise.
+///
+/// A failed allocation looks like any other crash from the outside, so this is meant to be called by the
+/// allocation failure handler of the target, such as a [`core::alloc::GlobalAlloc`] wrapper of a Rust
+/// harness noticing a null allocation, or the `malloc` failure hook of a sanitizer.
+#[cfg(all(feature = "std", unix))]
+pub fn exit_out_of_memory() -> ! {
+    unsafe {
+        let data = &mut FORK_EXECUTOR_GLOBAL_DATA;
+        if data.report_limit_violations {
+            write_volatile(&mut data.out_of_memory, true);
+            // the crash handler runs the observers, and then exits for the violation
+            if data.crash_handler.is_null() {
+                libc::_exit(ResourceLimitViolation::Memory.exit_code());
+            }
+        }
+        libc::abort()
+    }
+}
+
+/// The `setrlimit` based limits of the forked child of an [`InProcessForkExecutor`] or a [`TimeoutInProcessForkExecutor`],
+/// similar to the `setlimit` of the forkserver.
+///
+/// A crash caused by one of the limits is reported as [`ExitKind::Oom`], for an [`crate::feedbacks::OomFeedback`] to match on.
+/// Writes beyond the file size limit are caught by their `SIGXFSZ`. Running out of memory is only recognized if the target
+/// calls [`exit_out_of_memory`] on a failed allocation, and running out of file descriptors not at all: a crash following a
+/// failed call is a crash like any other.
+#[cfg(all(feature = "std", unix))]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ResourceLimits {
+    address_space: Option<u64>,
+    data_size: Option<u64>,
+    file_size: Option<u64>,
+    open_files: Option<u64>,
+    disable_core_dumps: bool,
+}
+
+#[cfg(all(feature = "std", unix))]
+impl ResourceLimits {
+    /// No limits at all, add them with the setters
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limits the virtual memory of the child, in bytes
+    #[must_use]
+    pub fn address_space(mut self, bytes: u64) -> Self {
+        self.address_space = Some(bytes);
+        self
+    }
+
+    /// Limits the data segment, and on newer kernels all the private mappings, of the child, in bytes
+    #[must_use]
+    pub fn data_size(mut self, bytes: u64) -> Self {
+        self.data_size = Some(bytes);
+        self
+    }
+
+    /// Limits the size of the files the child writes, in bytes
+    #[must_use]
+    pub fn file_size(mut self, bytes: u64) -> Self {
+        self.file_size = Some(bytes);
+        self
+    }
+
+    /// Limits the number of file descriptors the child may open
+    #[must_use]
+    pub fn open_files(mut self, count: u64) -> Self {
+        self.open_files = Some(count);
+        self
+    }
+
+    /// Keeps the child from writing core dumps when it crashes
+    #[must_use]
+    pub fn disable_core_dumps(mut self) -> Self {
+        self.disable_core_dumps = true;
+        self
+    }
+
+    /// If no limit is set
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Applies the limits to the current process
+    #[allow(trivial_numeric_casts)]
+    pub fn apply(&self) -> Result<(), Error> {
+        fn set(resource: RlimitResource, limit: u64) -> Result<(), Error> {
+            let r = libc::rlimit {
+                rlim_cur: limit as libc::rlim_t,
+                rlim_max: limit as libc::rlim_t,
+            };
+            if unsafe { libc::setrlimit(resource, &r) } < 0 {
+                return Err(io::Error::last_os_error().into());
+            }
+            Ok(())
+        }
+
+        if let Some(limit) = self.address_space {
+            #[cfg(target_os = "openbsd")]
+            set(libc::RLIMIT_RSS, limit)?;
+            #[cfg(not(target_os = "openbsd"))]
+            set(libc::RLIMIT_AS, limit)?;
+        }
+        if let Some(limit) = self.data_size {
+            set(libc::RLIMIT_DATA, limit)?;
+        }
+        if let Some(limit) = self.file_size {
+            set(libc::RLIMIT_FSIZE, limit)?;
+        }
+        if let Some(limit) = self.open_files {
+            set(libc::RLIMIT_NOFILE, limit)?;
+        }
+        if self.disable_core_dumps {
+            set(libc::RLIMIT_CORE, 0)?;
+        }
+        Ok(())
+    }
+
+    /// Applies the limits in the forked child, and reports the crashes they cause from now on
+    fn apply_in_child(&self) {
+        if self.is_empty() {
+            return;
+        }
+        self.apply()
+            .expect("Failed to set the resource limits of the child");
+        unsafe {
+            write_volatile(&mut FORK_EXECUTOR_GLOBAL_DATA.report_limit_violations, true);
+        }
+    }
+
+    /// The [`ExitKind`] of a forked child that ran into the limits
+    fn exit_kind(&self, status: &WaitStatus) -> Option<ExitKind> {
+        if self.is_empty() {
+            return None;
+        }
+        let violation = ResourceLimitViolation::from_wait_status(status)?;
+        log::debug!("The child ran into its resource limits: {violation:?}");
+        Some(ExitKind::Oom)
+    }
+}
+
 /// [`InProcessForkExecutor`] is an executor that forks the current process before each execution.
 #[cfg(all(feature = "std", unix))]
 pub struct InProcessForkExecutor<'a, H, OT, S, SP>
@@ -1604,6 +1800,7 @@ where
     shmem_provider: SP,
     observers: OT,
     handlers: InChildProcessHandlers,
+    limits: ResourceLimits,
     phantom: PhantomData<S>,
 }
 
@@ -1620,6 +1817,7 @@ where
     shmem_provider: SP,
     observers: OT,
     handlers: InChildProcessHandlers,
+    limits: ResourceLimits,
     #[cfg(target_os = "linux")]
     itimerspec: libc::itimerspec,
     #[cfg(all(unix, not(target_os = "linux")))]
@@ -1726,6 +1924,8 @@ where
                         .pre_exec_child_all(state, input)
                         .expect("Failed to run post_exec on observers");
 
+                    self.limits.apply_in_child();
+
                     (self.harness_fn)(input);
 
                     self.observers
@@ -1742,6 +1942,9 @@ where
                     self.shmem_provider.post_fork(false)?;
 
                     let res = waitpid(child, None)?;
+                    if let Some(exit_kind) = self.limits.exit_kind(&res) {
+                        return Ok(exit_kind);
+                    }
 
                     match res {
                         WaitStatus::Signaled(_, _, _) => Ok(ExitKind::Crash),
@@ -1794,6 +1997,8 @@ where
                         .pre_exec_child_all(state, input)
                         .expect("Failed to run post_exec on observers");
 
+                    self.limits.apply_in_child();
+
                     #[cfg(target_os = "linux")]
                     {
                         let mut timerid: libc::timer_t = null_mut();
@@ -1835,6 +2040,9 @@ where
 
                     let res = waitpid(child, None)?;
                     log::trace!("{res:#?}");
+                    if let Some(exit_kind) = self.limits.exit_kind(&res) {
+                        return Ok(exit_kind);
+                    }
                     match res {
                         WaitStatus::Signaled(_, signal, _) => match signal {
                             nix::sys::signal::Signal::SIGALRM
@@ -1897,6 +2105,7 @@ where
             shmem_provider,
             observers,
             handlers,
+            limits: ResourceLimits::new(),
             phantom: PhantomData,
         })
     }
@@ -1962,6 +2171,7 @@ where
             shmem_provider,
             observers,
             handlers,
+            limits: ResourceLimits::new(),
             itimerspec,
             phantom: PhantomData,
         })
@@ -2007,6 +2217,7 @@ where
             shmem_provider,
             observers,
             handlers,
+            limits: ResourceLimits::new(),
             itimerval,
             phantom: PhantomData,
         })
@@ -2025,6 +2236,48 @@ where
     }
 }
 
+#[cfg(all(feature = "std", unix))]
+impl<'a, H, OT, S, SP> InProcessForkExecutor<'a, H, OT, S, SP>
+where
+    H: FnMut(&S::Input) -> ExitKind + ?Sized,
+    OT: ObserversTuple<S>,
+    S: UsesInput,
+    SP: ShMemProvider,
+{
+    /// The resource limits of the forked child
+    #[inline]
+    pub fn limits(&self) -> &ResourceLimits {
+        &self.limits
+    }
+
+    /// Sets the resource limits of the forked child
+    #[inline]
+    pub fn set_limits(&mut self, limits: ResourceLimits) {
+        self.limits = limits;
+    }
+}
+
+#[cfg(all(feature = "std", unix))]
+impl<'a, H, OT, S, SP> TimeoutInProcessForkExecutor<'a, H, OT, S, SP>
+where
+    H: FnMut(&S::Input) -> ExitKind + ?Sized,
+    OT: ObserversTuple<S>,
+    S: UsesInput,
+    SP: ShMemProvider,
+{
+    /// The resource limits of the forked child
+    #[inline]
+    pub fn limits(&self) -> &ResourceLimits {
+        &self.limits
+    }
+
+    /// Sets the resource limits of the forked child
+    #[inline]
+    pub fn set_limits(&mut self, limits: ResourceLimits) {
+        self.limits = limits;
+    }
+}
+
 #[cfg(all(feature = "std", unix))]
 impl<'a, H, OT, S, SP> UsesObservers for InProcessForkExecutor<'a, H, OT, S, SP>
 where
@@ -2094,7 +2347,9 @@ pub mod child_signal_handlers {
     use libafl_bolts::os::unix_signals::{ucontext_t, Signal};
     use libc::siginfo_t;
 
-    use super::{InProcessForkExecutorGlobalData, FORK_EXECUTOR_GLOBAL_DATA};
+    use super::{
+        InProcessForkExecutorGlobalData, ResourceLimitViolation, FORK_EXECUTOR_GLOBAL_DATA,
+    };
     use crate::{
         executors::{ExitKind, HasObservers},
         inputs::UsesInput,
@@ -2150,6 +2405,10 @@ pub mod child_signal_handlers {
                 .expect("Failed to run post_exec on observers");
         }
 
+        // the target reported its failed allocation, see `exit_out_of_memory`
+        if data.report_limit_violations && data.out_of_memory {
+            libc::_exit(ResourceLimitViolation::Memory.exit_code());
+        }
         libc::_exit(128 + (_signal as i32));
     }
 
@@ -2185,7 +2444,10 @@ mod tests {
 
     use crate::{
         events::NopEventManager,
-        executors::{inprocess::InProcessHandlers, Executor, ExitKind, InProcessExecutor},
+        executors::{
+            inprocess::{InProcessHandlers, ResourceLimits},
+            Executor, ExitKind, InProcessExecutor,
+        },
         inputs::{NopInput, UsesInput},
         state::NopState,
         NopFuzzer,
@@ -2238,6 +2500,7 @@ mod tests {
             shmem_provider: provider,
             observers: tuple_list!(),
             handlers: InChildProcessHandlers::nop(),
+            limits: ResourceLimits::new(),
             phantom: PhantomData,
         };
         let input = NopInput {};
@@ -2248,6 +2511,155 @@ mod tests {
             .run_target(&mut fuzzer, &mut state, &mut mgr, &input)
             .unwrap();
     }
+
+    #[test]
+    #[serial]
+    #[cfg_attr(miri, ignore)]
+    #[cfg(all(feature = "std", feature = "fork", unix))]
+    fn test_inprocessfork_limits() {
+        use std::{env, fs};
+
+        use libafl_bolts::shmem::{ShMemProvider, StdShMemProvider};
+
+        use crate::{
+            events::SimpleEventManager,
+            executors::{inprocess::InChildProcessHandlers, InProcessForkExecutor},
+            state::NopState,
+            NopFuzzer,
+        };
+
+        let path = env::temp_dir().join(format!("libafl_limits_{}", std::process::id()));
+        let mut harness = |_buf: &NopInput| {
+            fs::write(&path, [0; 4096]).unwrap();
+            ExitKind::Ok
+        };
+        let mut in_process_fork_executor = InProcessForkExecutor::<_, (), _, _> {
+            harness_fn: &mut harness,
+            shmem_provider: StdShMemProvider::new().unwrap(),
+            observers: tuple_list!(),
+            handlers: InChildProcessHandlers::nop(),
+            limits: ResourceLimits::new(),
+            phantom: PhantomData,
+        };
+        let run = |executor: &mut InProcessForkExecutor<_, (), _, _>| {
+            executor
+                .run_target(
+                    &mut NopFuzzer::new(),
+                    &mut NopState::new(),
+                    &mut SimpleEventManager::printing(),
+                    &NopInput {},
+                )
+                .unwrap()
+        };
+
+        assert_eq!(run(&mut in_process_fork_executor), ExitKind::Ok);
+        in_process_fork_executor
+            .set_limits(ResourceLimits::new().file_size(1024).disable_core_dumps());
+        assert_eq!(run(&mut in_process_fork_executor), ExitKind::Oom);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    #[serial]
+    #[cfg_attr(miri, ignore)]
+    #[cfg(all(feature = "std", feature = "fork", unix))]
+    fn test_inprocessfork_memory_limit() {
+        use alloc::vec::Vec;
+
+        use libafl_bolts::shmem::{ShMemProvider, StdShMemProvider};
+
+        use crate::{
+            events::SimpleEventManager,
+            executors::{
+                inprocess::{exit_out_of_memory, InChildProcessHandlers},
+                InProcessForkExecutor,
+            },
+            state::NopState,
+            NopFuzzer,
+        };
+
+        // like an allocator reporting its failed allocations
+        let mut harness = |_buf: &NopInput| {
+            let mut buf = Vec::<u8>::new();
+            if buf.try_reserve(1 << 30).is_err() {
+                exit_out_of_memory();
+            }
+            ExitKind::Ok
+        };
+        let mut in_process_fork_executor = InProcessForkExecutor::<_, (), _, _> {
+            harness_fn: &mut harness,
+            shmem_provider: StdShMemProvider::new().unwrap(),
+            observers: tuple_list!(),
+            handlers: InChildProcessHandlers::nop(),
+            limits: ResourceLimits::new(),
+            phantom: PhantomData,
+        };
+        let run = |executor: &mut InProcessForkExecutor<_, (), _, _>| {
+            executor
+                .run_target(
+                    &mut NopFuzzer::new(),
+                    &mut NopState::new(),
+                    &mut SimpleEventManager::printing(),
+                    &NopInput {},
+                )
+                .unwrap()
+        };
+
+        assert_eq!(run(&mut in_process_fork_executor), ExitKind::Ok);
+        in_process_fork_executor.set_limits(ResourceLimits::new().address_space(1 << 29));
+        assert_eq!(run(&mut in_process_fork_executor), ExitKind::Oom);
+    }
+
+    #[test]
+    #[serial]
+    #[cfg_attr(miri, ignore)]
+    #[cfg(all(feature = "std", feature = "fork", unix))]
+    fn test_inprocessfork_open_files_limit() {
+        use alloc::vec::Vec;
+        use std::fs::File;
+
+        use libafl_bolts::shmem::{ShMemProvider, StdShMemProvider};
+
+        use crate::{
+            events::SimpleEventManager,
+            executors::{inprocess::InChildProcessHandlers, InProcessForkExecutor},
+            state::NopState,
+            NopFuzzer,
+        };
+
+        let mut harness = |_buf: &NopInput| {
+            let files = (0..64)
+                .map(|_| File::open("/dev/null"))
+                .collect::<Result<Vec<_>, _>>();
+            if files.is_err() {
+                unsafe { libc::abort() };
+            }
+            ExitKind::Ok
+        };
+        let mut in_process_fork_executor = InProcessForkExecutor::<_, (), _, _> {
+            harness_fn: &mut harness,
+            shmem_provider: StdShMemProvider::new().unwrap(),
+            observers: tuple_list!(),
+            handlers: InChildProcessHandlers::nop(),
+            limits: ResourceLimits::new(),
+            phantom: PhantomData,
+        };
+        let run = |executor: &mut InProcessForkExecutor<_, (), _, _>| {
+            executor
+                .run_target(
+                    &mut NopFuzzer::new(),
+                    &mut NopState::new(),
+                    &mut SimpleEventManager::printing(),
+                    &NopInput {},
+                )
+                .unwrap()
+        };
+
+        assert_eq!(run(&mut in_process_fork_executor), ExitKind::Ok);
+        // the limit holds, but running into it is a crash like any other
+        in_process_fork_executor.set_limits(ResourceLimits::new().open_files(32));
+        assert_eq!(run(&mut in_process_fork_executor), ExitKind::Crash);
+    }
 }
 
 #[cfg(feature = "python")]
@@ -3,7 +3,7 @@
 pub mod inprocess;
 pub use inprocess::InProcessExecutor;
 #[cfg(all(feature = "std", feature = "fork", unix))]
-pub use inprocess::InProcessForkExecutor;
+pub use inprocess::{InProcessForkExecutor, ResourceLimits};
 
 pub mod differential;
 pub use differential::DiffExecutor;
@@ -20,6 +20,11 @@ pub mod forkserver;
 #[cfg(all(feature = "std", feature = "fork", unix))]
 pub use forkserver::{Forkserver, ForkserverExecutor, TimeoutForkserverExecutor};
 
+#[cfg(all(feature = "std", feature = "fork", unix))]
+pub mod network;
+#[cfg(all(feature = "std", feature = "fork", unix))]
+pub use network::{NetworkForkserverExecutor, NetworkProtocol};
+
 pub mod combined;
 pub use combined::CombinedExecutor;
 
@@ -29,6 +34,29 @@ pub use shadow::ShadowExecutor;
 pub mod with_observers;
 pub use with_observers::WithObservers;
 
+#[cfg(all(feature = "std", unix))]
+pub mod afl_custom;
+#[cfg(all(feature = "std", unix))]
+pub use afl_custom::AflCustomPostProcessExecutor;
+
+#[cfg(all(feature = "std", target_os = "linux", target_arch = "x86_64"))]
+pub mod ptrace;
+#[cfg(all(feature = "std", target_os = "linux", target_arch = "x86_64"))]
+pub use ptrace::{PtraceExecutor, PtraceExecutorBuilder};
+
+#[cfg(all(feature = "std", unix))]
+pub mod persistent_command;
+#[cfg(all(feature = "std", unix))]
+pub use persistent_command::{
+    LengthPrefixedProtocol, PersistentCommandExecutor, PersistentCommandExecutorBuilder,
+    PersistentProtocol,
+};
+
+#[cfg(all(feature = "std", target_os = "linux"))]
+pub mod snapshot;
+#[cfg(all(feature = "std", target_os = "linux"))]
+pub use snapshot::SnapshotInProcessExecutor;
+
 #[cfg(all(feature = "std", any(unix, doc)))]
 pub mod command;
 use core::{fmt::Debug, marker::PhantomData};
@@ -56,7 +84,7 @@ pub enum ExitKind {
     Ok,
     /// The run resulted in a target crash.
     Crash,
-    /// The run hit an out of memory error.
+    /// The run hit an out of memory error, or another resource limit.
     Oom,
     /// The run timed out
     Timeout,
@@ -0,0 +1,468 @@
+//! An [`Executor`] fuzzing network servers running under a `Forkserver`, as in `AFLNet`.
+//! Each packet of a [`PacketSequenceInput`] is sent to the server as a message of its own.
+
+use alloc::{string::ToString, vec::Vec};
+use core::{fmt::Debug, time::Duration};
+use std::{
+    io::{self, ErrorKind, Read, Write},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream, UdpSocket},
+    thread,
+    time::Instant,
+};
+
+use libafl_bolts::AsSlice;
+use nix::{
+    sys::{
+        signal::{kill, Signal},
+        time::{TimeSpec, TimeValLike},
+    },
+    unistd::Pid,
+};
+
+use crate::{
+    executors::{forkserver::HasForkserver, Executor, ExitKind, HasObservers},
+    inputs::{HasBytesVec, HasTargetBytes, PacketSequenceInput, UsesInput},
+    observers::{ObserversTuple, UsesObservers},
+    state::UsesState,
+    Error,
+};
+
+/// How long to wait between two attempts to connect to a server that is not listening yet
+const CONNECT_RETRY_INTERVAL: Duration = Duration::from_millis(1);
+/// The size of the buffer responses are received into
+const RECV_BUF_SIZE: usize = 4096;
+
+/// The transport protocol used to talk to the target
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkProtocol {
+    /// Connect to the target, and send the packets over the stream
+    Tcp,
+    /// Send each packet as a datagram of its own
+    Udp,
+}
+
+/// A connection to the target, over either protocol
+#[derive(Debug)]
+enum Connection {
+    Tcp(TcpStream),
+    Udp(UdpSocket),
+}
+
+impl Connection {
+    /// Connect to the server at `address`, retrying until `deadline`.
+    /// Returns `None` if the server did not accept the connection in time.
+    fn connect(
+        address: SocketAddr,
+        protocol: NetworkProtocol,
+        deadline: Instant,
+    ) -> Result<Option<Self>, Error> {
+        match protocol {
+            NetworkProtocol::Tcp => loop {
+                let now = Instant::now();
+                if now >= deadline {
+                    return Ok(None);
+                }
+                if let Ok(stream) = TcpStream::connect_timeout(&address, deadline - now) {
+                    stream.set_nodelay(true)?;
+                    return Ok(Some(Self::Tcp(stream)));
+                }
+                thread::sleep(CONNECT_RETRY_INTERVAL);
+            },
+            NetworkProtocol::Udp => {
+                let local: IpAddr = if address.is_ipv4() {
+                    Ipv4Addr::UNSPECIFIED.into()
+                } else {
+                    Ipv6Addr::UNSPECIFIED.into()
+                };
+                let socket = UdpSocket::bind(SocketAddr::new(local, 0))?;
+                socket.connect(address)?;
+                Ok(Some(Self::Udp(socket)))
+            }
+        }
+    }
+
+    fn set_read_timeout(&self, timeout: Duration) -> io::Result<()> {
+        // `set_read_timeout` rejects a zero timeout with `InvalidInput`
+        let timeout = Some(timeout.max(Duration::from_micros(1)));
+        match self {
+            Self::Tcp(stream) => stream.set_read_timeout(timeout),
+            Self::Udp(socket) => socket.set_read_timeout(timeout),
+        }
+    }
+
+    fn send(&mut self, buf: &[u8]) -> io::Result<()> {
+        match self {
+            Self::Tcp(stream) => stream.write_all(buf),
+            Self::Udp(socket) => socket.send(buf).map(|_| ()),
+        }
+    }
+
+    fn recv(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Tcp(stream) => stream.read(buf),
+            Self::Udp(socket) => socket.recv(buf),
+        }
+    }
+
+    /// Receive whatever the server answers, until it goes quiet for `wait`, or `deadline` is reached.
+    fn recv_response(&mut self, wait: Duration, deadline: Instant) -> io::Result<Vec<u8>> {
+        let mut response = Vec::new();
+        let mut buf = [0; RECV_BUF_SIZE];
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            self.set_read_timeout(wait.min(deadline - now))?;
+            match self.recv(&mut buf) {
+                Ok(0) => break,
+                Ok(len) => response.extend_from_slice(&buf[..len]),
+                Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {
+                    break
+                }
+                // The server closed the connection, or went down
+                Err(_) => break,
+            }
+        }
+        Ok(response)
+    }
+}
+
+/// Send the `packets` to the server at `address`, one by one, and collect the response to each.
+///
+/// Stops early once the server refuses further packets. Returns `None` if no connection
+/// could be established before `deadline`.
+pub(crate) fn deliver_packets(
+    address: SocketAddr,
+    protocol: NetworkProtocol,
+    packets: &[impl HasBytesVec],
+    inter_message_wait: Duration,
+    deadline: Instant,
+) -> Result<Option<Vec<Vec<u8>>>, Error> {
+    let Some(mut connection) = Connection::connect(address, protocol, deadline)? else {
+        return Ok(None);
+    };
+
+    let mut responses = Vec::with_capacity(packets.len());
+    for packet in packets {
+        if Instant::now() >= deadline || connection.send(packet.bytes()).is_err() {
+            break;
+        }
+        responses.push(connection.recv_response(inter_message_wait, deadline)?);
+    }
+    Ok(Some(responses))
+}
+
+/// An [`Executor`] for network servers built for AFL/AFL++, wrapping a forkserver executor.
+///
+/// For each run, the server is forked, and the packets of the [`PacketSequenceInput`] are sent
+/// to it over TCP or UDP on `address`, waiting `inter_message_wait` for a response to each.
+/// The responses are handed to the observers that observe them, such as the [`crate::observers::ResponseCodeObserver`].
+/// Afterwards, the server is terminated with `SIGTERM`, unless it went down on its own.
+/// The wrapped executor should be built with [`crate::executors::forkserver::ForkserverExecutorBuilder::is_persistent`] off.
+#[derive(Debug)]
+pub struct NetworkForkserverExecutor<E> {
+    executor: E,
+    address: SocketAddr,
+    protocol: NetworkProtocol,
+    timeout: Duration,
+    server_wait: Duration,
+    inter_message_wait: Duration,
+    signal: Signal,
+    responses: Vec<Vec<u8>>,
+}
+
+impl<E> NetworkForkserverExecutor<E> {
+    /// Create a new [`NetworkForkserverExecutor`], sending the packets to `address` over `protocol`.
+    ///
+    /// A run taking longer than `timeout`, including the time the server needs to come up, is a timeout.
+    #[must_use]
+    pub fn new(
+        executor: E,
+        address: SocketAddr,
+        protocol: NetworkProtocol,
+        timeout: Duration,
+    ) -> Self {
+        Self {
+            executor,
+            address,
+            protocol,
+            timeout,
+            server_wait: Duration::ZERO,
+            inter_message_wait: Duration::from_millis(10),
+            signal: Signal::SIGTERM,
+            responses: Vec::new(),
+        }
+    }
+
+    /// Wait this long after forking, before the first connection attempt or datagram,
+    /// to give the server some time to start listening
+    #[must_use]
+    pub fn server_wait(mut self, server_wait: Duration) -> Self {
+        self.server_wait = server_wait;
+        self
+    }
+
+    /// Wait this long for the server to respond to a message, before sending the next one
+    #[must_use]
+    pub fn inter_message_wait(mut self, inter_message_wait: Duration) -> Self {
+        self.inter_message_wait = inter_message_wait;
+        self
+    }
+
+    /// Terminate the server with this signal after each run, instead of `SIGTERM`
+    #[must_use]
+    pub fn kill_signal(mut self, signal: Signal) -> Self {
+        self.signal = signal;
+        self
+    }
+
+    /// The wrapped executor
+    #[must_use]
+    pub fn inner(&self) -> &E {
+        &self.executor
+    }
+
+    /// The responses of the server to the packets sent in the last run, in order.
+    /// There may be fewer responses than packets, if the server stopped accepting them.
+    #[must_use]
+    pub fn responses(&self) -> &[Vec<u8>] {
+        &self.responses
+    }
+}
+
+impl<E, EM, Z> Executor<EM, Z> for NetworkForkserverExecutor<E>
+where
+    E: Executor<EM, Z> + HasForkserver + HasObservers + Debug,
+    E::State: UsesInput<Input = PacketSequenceInput>,
+    EM: UsesState<State = E::State>,
+    Z: UsesState<State = E::State>,
+{
+    fn run_target(
+        &mut self,
+        _fuzzer: &mut Z,
+        _state: &mut Self::State,
+        _mgr: &mut EM,
+        input: &Self::Input,
+    ) -> Result<ExitKind, Error> {
+        let last_run_timed_out = self.executor.forkserver().last_run_timed_out();
+
+        // Also leave the concatenated packets where the forkserver expects the input
+        self.executor
+            .input_file_mut()
+            .write_buf(input.target_bytes().as_slice())?;
+
+        let send_len = self
+            .executor
+            .forkserver_mut()
+            .write_ctl(last_run_timed_out)?;
+        self.executor.forkserver_mut().set_last_run_timed_out(0);
+        if send_len != 4 {
+            return Err(Error::unknown(
+                "Unable to request new process from fork server (OOM?)".to_string(),
+            ));
+        }
+
+        let (recv_pid_len, pid) = self.executor.forkserver_mut().read_st()?;
+        if recv_pid_len != 4 {
+            return Err(Error::unknown(
+                "Unable to request new process from fork server (OOM?)".to_string(),
+            ));
+        }
+        if pid <= 0 {
+            return Err(Error::unknown(
+                "Fork server is misbehaving (OOM?)".to_string(),
+            ));
+        }
+        let pid = Pid::from_raw(pid);
+        self.executor.forkserver_mut().set_child_pid(pid);
+
+        let deadline = Instant::now() + self.timeout;
+        thread::sleep(self.server_wait);
+        let responses = deliver_packets(
+            self.address,
+            self.protocol,
+            input.packets(),
+            self.inter_message_wait,
+            deadline,
+        )?;
+        let connected = responses.is_some();
+        self.responses = responses.unwrap_or_default();
+        if self.executor.observers().observes_responses() {
+            self.executor
+                .observers_mut()
+                .observe_responses(&self.responses);
+        }
+
+        let exit_kind = if let Some(status) = self
+            .executor
+            .forkserver_mut()
+            .read_st_timed(&TimeSpec::zero())?
+        {
+            // The server went down on its own
+            self.executor.forkserver_mut().set_status(status);
+            if libc::WIFSIGNALED(status) {
+                ExitKind::Crash
+            } else {
+                ExitKind::Ok
+            }
+        } else {
+            let _: Result<(), nix::errno::Errno> = kill(pid, self.signal);
+            let (recv_status_len, status) = self.executor.forkserver_mut().read_st()?;
+            if recv_status_len != 4 {
+                return Err(Error::unknown("Could not kill the server".to_string()));
+            }
+            self.executor.forkserver_mut().set_status(status);
+            self.executor.forkserver_mut().set_last_run_timed_out(1);
+
+            if libc::WIFSIGNALED(status) && libc::WTERMSIG(status) != self.signal as i32 {
+                // It crashed before our signal arrived
+                ExitKind::Crash
+            } else if connected {
+                ExitKind::Ok
+            } else {
+                ExitKind::Timeout
+            }
+        };
+
+        self.executor
+            .forkserver_mut()
+            .set_child_pid(Pid::from_raw(0));
+
+        Ok(exit_kind)
+    }
+}
+
+impl<E> UsesState for NetworkForkserverExecutor<E>
+where
+    E: UsesState,
+{
+    type State = E::State;
+}
+
+impl<E> UsesObservers for NetworkForkserverExecutor<E>
+where
+    E: UsesObservers,
+{
+    type Observers = E::Observers;
+}
+
+impl<E> HasObservers for NetworkForkserverExecutor<E>
+where
+    E: HasObservers,
+{
+    #[inline]
+    fn observers(&self) -> &Self::Observers {
+        self.executor.observers()
+    }
+
+    #[inline]
+    fn observers_mut(&mut self) -> &mut Self::Observers {
+        self.executor.observers_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::String, vec::Vec};
+    use core::time::Duration;
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::{TcpListener, UdpSocket},
+        thread,
+        time::Instant,
+    };
+
+    use crate::{
+        executors::network::{deliver_packets, NetworkProtocol},
+        inputs::PacketSequenceInput,
+    };
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_deliver_tcp() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+
+        // A tiny state machine: commands are only accepted after logging in
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut stream = stream;
+            let mut logged_in = false;
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 0 {
+                let reply: &[u8] = match (line.trim_end(), logged_in) {
+                    ("LOGIN", _) => {
+                        logged_in = true;
+                        b"230\r\n"
+                    }
+                    ("QUIT", _) => {
+                        stream.write_all(b"221\r\n").unwrap();
+                        return;
+                    }
+                    (_, true) => b"200\r\n",
+                    (_, false) => b"530\r\n",
+                };
+                stream.write_all(reply).unwrap();
+                line.clear();
+            }
+        });
+
+        let input = PacketSequenceInput::from(vec![
+            b"LIST\n".to_vec(),
+            b"LOGIN\n".to_vec(),
+            b"LIST\n".to_vec(),
+            b"QUIT\n".to_vec(),
+            b"LIST\n".to_vec(),
+        ]);
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let responses = deliver_packets(
+            address,
+            NetworkProtocol::Tcp,
+            input.packets(),
+            Duration::from_millis(200),
+            deadline,
+        )
+        .unwrap()
+        .unwrap();
+        server.join().unwrap();
+
+        assert_eq!(
+            responses[..4],
+            [b"530\r\n", b"230\r\n", b"200\r\n", b"221\r\n"]
+        );
+        // The server is gone, nothing answers the last one
+        assert!(responses.get(4).map_or(true, Vec::is_empty));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_deliver_udp() {
+        let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let address = server.local_addr().unwrap();
+
+        let echo = thread::spawn(move || {
+            let mut buf = [0; 64];
+            for _ in 0..2 {
+                let (len, peer) = server.recv_from(&mut buf).unwrap();
+                server.send_to(&buf[..len], peer).unwrap();
+            }
+        });
+
+        let input = PacketSequenceInput::from(vec![b"ping".to_vec(), b"pong".to_vec()]);
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let responses = deliver_packets(
+            address,
+            NetworkProtocol::Udp,
+            input.packets(),
+            Duration::from_millis(200),
+            deadline,
+        )
+        .unwrap()
+        .unwrap();
+        echo.join().unwrap();
+
+        assert_eq!(responses, [b"ping".to_vec(), b"pong".to_vec()]);
+    }
+}
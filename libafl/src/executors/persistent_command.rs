@@ -0,0 +1,822 @@
+//! The persistent command executor keeps one sub program alive, and sends it an input per run over
+//! its stdin, as interpreters and language servers looping over their inputs allow.
+//!
+//! Each input is framed by a [`PersistentProtocol`], by default the [`LengthPrefixedProtocol`],
+//! and the target answers each one with a status reply on a pipe of its own, at fd `3` by default.
+//! The output of the target between two replies goes to the `StdOutObserver` and `StdErrObserver`.
+
+use alloc::vec::Vec;
+use core::{
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+    time::Duration,
+};
+use std::{
+    borrow::ToOwned,
+    ffi::{OsStr, OsString},
+    fs::File,
+    io::{ErrorKind, Read, Write},
+    os::unix::{
+        io::{AsRawFd, FromRawFd, OwnedFd, RawFd},
+        process::{CommandExt, ExitStatusExt},
+    },
+    path::{Path, PathBuf},
+    process::{Child, ChildStderr, ChildStdin, ChildStdout, Command, ExitStatus, Stdio},
+    thread,
+    time::Instant,
+};
+
+use libafl_bolts::AsSlice;
+use nix::{
+    fcntl::{fcntl, FcntlArg, FdFlag, OFlag},
+    libc,
+    poll::{poll, PollFd, PollFlags},
+    unistd::{dup2, pipe2},
+};
+
+use crate::{
+    executors::{Executor, ExitKind, HasObservers},
+    inputs::{HasTargetBytes, UsesInput},
+    observers::{ObserversTuple, UsesObservers},
+    state::UsesState,
+    Error,
+};
+
+/// The size of the chunks the output of the target is read in
+const READ_CHUNK_SIZE: usize = 4096;
+/// How often to check if a target that closed its status pipe is gone
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// How the inputs are sent to a persistent target, and how it replies
+pub trait PersistentProtocol: Debug {
+    /// Appends the frame of `input` to `frame`, as the target reads it from its stdin
+    fn frame_input(&mut self, input: &[u8], frame: &mut Vec<u8>) -> Result<(), Error>;
+
+    /// The size of the status reply of the target, after each input
+    fn status_len(&self) -> usize;
+
+    /// The [`ExitKind`] of the run, from the status reply of the target
+    fn exit_kind(&mut self, status: &[u8]) -> ExitKind;
+}
+
+/// Each input is preceded by its length, as a 32-bit integer.
+/// The target replies with a 32-bit status, in the same byte order: `0` if the input was processed
+/// fine, anything else for a failure that counts as a crash, such as an uncaught exception.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LengthPrefixedProtocol {
+    big_endian: bool,
+}
+
+impl LengthPrefixedProtocol {
+    /// A [`LengthPrefixedProtocol`] in little endian
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A [`LengthPrefixedProtocol`] in big endian, or network byte order
+    #[must_use]
+    pub fn big_endian() -> Self {
+        Self { big_endian: true }
+    }
+}
+
+impl PersistentProtocol for LengthPrefixedProtocol {
+    fn frame_input(&mut self, input: &[u8], frame: &mut Vec<u8>) -> Result<(), Error> {
+        let len = u32::try_from(input.len())?;
+        if self.big_endian {
+            frame.extend_from_slice(&len.to_be_bytes());
+        } else {
+            frame.extend_from_slice(&len.to_le_bytes());
+        }
+        frame.extend_from_slice(input);
+        Ok(())
+    }
+
+    fn status_len(&self) -> usize {
+        4
+    }
+
+    fn exit_kind(&mut self, status: &[u8]) -> ExitKind {
+        let status: [u8; 4] = status.try_into().unwrap();
+        let status = if self.big_endian {
+            u32::from_be_bytes(status)
+        } else {
+            u32::from_le_bytes(status)
+        };
+        if status == 0 {
+            ExitKind::Ok
+        } else {
+            ExitKind::Crash
+        }
+    }
+}
+
+/// A running target, with the pipes to talk to it
+#[derive(Debug)]
+struct PersistentChild {
+    child: Child,
+    stdin: ChildStdin,
+    status: File,
+    stdout: Option<ChildStdout>,
+    stderr: Option<ChildStderr>,
+}
+
+impl PersistentChild {
+    /// Kills the target, if it is still around, and waits for it
+    fn kill(mut self) {
+        drop(self.child.kill());
+        drop(self.child.wait());
+    }
+}
+
+/// How a run of the target ended
+enum Outcome {
+    /// The target replied with this status
+    Replied(ExitKind),
+    /// The target went away without a reply
+    Died,
+    /// The target did not reply in time
+    Timeout,
+}
+
+/// Reads what is available of `pipe` into `buf`, after `poll` reported it.
+/// Returns `false` once the pipe is closed.
+fn read_available<R: Read>(pipe: &mut R, buf: &mut Vec<u8>) -> Result<bool, Error> {
+    let mut chunk = [0; READ_CHUNK_SIZE];
+    match pipe.read(&mut chunk) {
+        Ok(0) => Ok(false),
+        Ok(len) => {
+            buf.extend_from_slice(&chunk[..len]);
+            Ok(true)
+        }
+        Err(err) if err.kind() == ErrorKind::Interrupted => Ok(true),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Writes what the non-blocking `pipe` takes of `frame`, from `sent` on, and advances `sent`.
+/// Returns `false` once the pipe is closed.
+fn write_available(pipe: &mut ChildStdin, frame: &[u8], sent: &mut usize) -> Result<bool, Error> {
+    match pipe.write(&frame[*sent..]) {
+        Ok(len) => {
+            *sent += len;
+            Ok(true)
+        }
+        Err(err) if matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::Interrupted) => {
+            Ok(true)
+        }
+        Err(err) if err.kind() == ErrorKind::BrokenPipe => Ok(false),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// If `poll` reported `fd` as readable, or closed
+fn is_ready(fd: PollFd) -> bool {
+    fd.revents()
+        .is_some_and(|revents| revents.intersects(PollFlags::POLLIN | PollFlags::POLLHUP))
+}
+
+/// If `poll` reported `fd` as writable, or failed, so that a write does not block
+fn is_writable(fd: PollFd) -> bool {
+    fd.revents().is_some_and(|revents| !revents.is_empty())
+}
+
+/// Waits for `child` to exit, until `deadline`
+fn wait_until(child: &mut Child, deadline: Instant) -> Result<Option<ExitStatus>, Error> {
+    loop {
+        if let Some(status) = child.try_wait()? {
+            return Ok(Some(status));
+        }
+        if Instant::now() >= deadline {
+            return Ok(None);
+        }
+        thread::sleep(EXIT_POLL_INTERVAL);
+    }
+}
+
+/// An [`Executor`] keeping one process of the target alive across the runs, see the [module docs](self).
+///
+/// The target is restarted after a crash or a timeout, and whenever it goes away on its own.
+/// It should flush its output before each reply, so that the output ends up with the right run.
+pub struct PersistentCommandExecutor<OT, P, S> {
+    program: OsString,
+    args: Vec<OsString>,
+    envs: Vec<(OsString, OsString)>,
+    cwd: Option<PathBuf>,
+    debug_child: bool,
+    status_fd: RawFd,
+    timeout: Duration,
+    protocol: P,
+    child: Option<PersistentChild>,
+    frame: Vec<u8>,
+    observers: OT,
+    phantom: PhantomData<S>,
+}
+
+impl<OT, P, S> Debug for PersistentCommandExecutor<OT, P, S>
+where
+    OT: Debug,
+    P: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PersistentCommandExecutor")
+            .field("program", &self.program)
+            .field("args", &self.args)
+            .field("status_fd", &self.status_fd)
+            .field("timeout", &self.timeout)
+            .field("protocol", &self.protocol)
+            .field("child", &self.child)
+            .field("observers", &self.observers)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PersistentCommandExecutor<(), LengthPrefixedProtocol, ()> {
+    /// Creates a builder for a [`PersistentCommandExecutor`]
+    #[must_use]
+    pub fn builder() -> PersistentCommandExecutorBuilder {
+        PersistentCommandExecutorBuilder::new()
+    }
+}
+
+impl<OT, P, S> PersistentCommandExecutor<OT, P, S>
+where
+    OT: ObserversTuple<S>,
+    P: PersistentProtocol,
+    S: UsesInput,
+{
+    /// The protocol of the target
+    pub fn protocol(&self) -> &P {
+        &self.protocol
+    }
+
+    /// Starts the target, with the write end of a new status pipe at `status_fd`
+    fn spawn(&mut self) -> Result<PersistentChild, Error> {
+        let (status_read, status_write) = pipe2(OFlag::O_CLOEXEC)?;
+        // SAFETY: both ends are fresh and owned here
+        let status = unsafe { File::from_raw_fd(status_read) };
+        let status_write = unsafe { OwnedFd::from_raw_fd(status_write) };
+
+        let mut command = Command::new(&self.program);
+        command.args(&self.args).envs(
+            self.envs
+                .iter()
+                .map(|(k, v)| (k.as_os_str(), v.as_os_str())),
+        );
+        if let Some(cwd) = &self.cwd {
+            command.current_dir(cwd);
+        }
+        command.stdin(Stdio::piped());
+        if self.observers.observes_stdout() {
+            command.stdout(Stdio::piped());
+        } else if !self.debug_child {
+            command.stdout(Stdio::null());
+        }
+        if self.observers.observes_stderr() {
+            command.stderr(Stdio::piped());
+        } else if !self.debug_child {
+            command.stderr(Stdio::null());
+        }
+
+        let write_fd = status_write.as_raw_fd();
+        let status_fd = self.status_fd;
+        unsafe {
+            command.pre_exec(move || {
+                if write_fd == status_fd {
+                    fcntl(write_fd, FcntlArg::F_SETFD(FdFlag::empty()))?;
+                } else {
+                    dup2(write_fd, status_fd)?;
+                }
+                Ok(())
+            });
+        }
+
+        let mut child = command.spawn()?;
+        // only the target holds the write end now, so the pipe closes once it goes away
+        drop(status_write);
+
+        let stdin = child.stdin.take().unwrap();
+        // the input is written along with the reads of the output, so a full pipe never blocks
+        fcntl(stdin.as_raw_fd(), FcntlArg::F_SETFL(OFlag::O_NONBLOCK))?;
+
+        Ok(PersistentChild {
+            stdin,
+            stdout: child.stdout.take(),
+            stderr: child.stderr.take(),
+            status,
+            child,
+        })
+    }
+
+    /// Starts sending the framed input to the target, restarting it if it went away since the last run.
+    /// Returns how much of the frame is sent, the rest follows while waiting for the reply.
+    fn start_frame(&mut self) -> Result<usize, Error> {
+        let mut sent = 0;
+        if let Some(mut child) = self.child.take() {
+            match write_available(&mut child.stdin, &self.frame, &mut sent) {
+                Ok(true) => {
+                    self.child = Some(child);
+                    return Ok(sent);
+                }
+                Ok(false) => child.kill(),
+                Err(err) => {
+                    child.kill();
+                    return Err(err);
+                }
+            }
+        }
+
+        let mut child = self.spawn()?;
+        let written = write_available(&mut child.stdin, &self.frame, &mut sent);
+        self.child = Some(child);
+        // if the new target closed its stdin right away, its status pipe tells how it went
+        written?;
+        Ok(sent)
+    }
+
+    /// Sends the rest of the frame from `sent` on, and waits for the status reply of the target
+    /// until `deadline`, collecting its output meanwhile
+    fn wait_for_reply(
+        &mut self,
+        mut sent: usize,
+        deadline: Instant,
+        stdout: &mut Vec<u8>,
+        stderr: &mut Vec<u8>,
+    ) -> Result<Outcome, Error> {
+        let status_len = self.protocol.status_len();
+        let child = self.child.as_mut().unwrap();
+        let mut status = Vec::with_capacity(status_len);
+        let mut stdout_open = child.stdout.is_some();
+        let mut stderr_open = child.stderr.is_some();
+        let mut died = false;
+
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let replied = died || status.len() == status_len;
+            let mut outcome = || {
+                if died {
+                    Outcome::Died
+                } else {
+                    Outcome::Replied(self.protocol.exit_kind(&status))
+                }
+            };
+            if remaining.is_zero() {
+                return Ok(if replied { outcome() } else { Outcome::Timeout });
+            }
+            // once replied, only drain the output written before the reply
+            let timeout_ms = if replied {
+                0
+            } else {
+                libc::c_int::try_from(remaining.as_micros().div_ceil(1000))
+                    .unwrap_or(libc::c_int::MAX)
+            };
+
+            let writing = !replied && sent < self.frame.len();
+            let mut fds = Vec::with_capacity(4);
+            if writing {
+                fds.push(PollFd::new(child.stdin.as_raw_fd(), PollFlags::POLLOUT));
+            }
+            if !replied {
+                fds.push(PollFd::new(child.status.as_raw_fd(), PollFlags::POLLIN));
+            }
+            if let Some(pipe) = child.stdout.as_ref().filter(|_| stdout_open) {
+                fds.push(PollFd::new(pipe.as_raw_fd(), PollFlags::POLLIN));
+            }
+            if let Some(pipe) = child.stderr.as_ref().filter(|_| stderr_open) {
+                fds.push(PollFd::new(pipe.as_raw_fd(), PollFlags::POLLIN));
+            }
+            if fds.is_empty() {
+                return Ok(outcome());
+            }
+
+            match poll(&mut fds, timeout_ms) {
+                Ok(0) if replied => return Ok(outcome()),
+                Ok(_) => {}
+                Err(nix::errno::Errno::EINTR) => continue,
+                Err(err) => return Err(err.into()),
+            }
+
+            let mut fds = fds.iter();
+            if writing
+                && is_writable(*fds.next().unwrap())
+                && !write_available(&mut child.stdin, &self.frame, &mut sent)?
+            {
+                // the target stopped reading, its status pipe tells why
+                sent = self.frame.len();
+            }
+            if !replied && is_ready(*fds.next().unwrap()) {
+                let mut reply = vec![0; status_len - status.len()];
+                match child.status.read(&mut reply) {
+                    // the target is gone, collect what it wrote before
+                    Ok(0) => died = true,
+                    Ok(len) => status.extend_from_slice(&reply[..len]),
+                    Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                    Err(err) => return Err(err.into()),
+                }
+            }
+            if stdout_open && is_ready(*fds.next().unwrap()) {
+                stdout_open = read_available(child.stdout.as_mut().unwrap(), stdout)?;
+            }
+            if stderr_open && is_ready(*fds.next().unwrap()) {
+                stderr_open = read_available(child.stderr.as_mut().unwrap(), stderr)?;
+            }
+        }
+    }
+}
+
+impl<OT, P, S> Drop for PersistentCommandExecutor<OT, P, S> {
+    fn drop(&mut self) {
+        if let Some(child) = self.child.take() {
+            child.kill();
+        }
+    }
+}
+
+impl<EM, OT, P, S, Z> Executor<EM, Z> for PersistentCommandExecutor<OT, P, S>
+where
+    EM: UsesState<State = S>,
+    S: UsesInput,
+    S::Input: HasTargetBytes,
+    OT: Debug + ObserversTuple<S>,
+    P: PersistentProtocol,
+    Z: UsesState<State = S>,
+{
+    fn run_target(
+        &mut self,
+        _fuzzer: &mut Z,
+        _state: &mut Self::State,
+        _mgr: &mut EM,
+        input: &Self::Input,
+    ) -> Result<ExitKind, Error> {
+        self.frame.clear();
+        self.protocol
+            .frame_input(input.target_bytes().as_slice(), &mut self.frame)?;
+        let deadline = Instant::now() + self.timeout;
+        let sent = self.start_frame()?;
+
+        let mut stdout = Vec::new();
+        let mut stderr = Vec::new();
+        let exit_kind = match self.wait_for_reply(sent, deadline, &mut stdout, &mut stderr)? {
+            Outcome::Replied(exit_kind) => {
+                // the state of the target may be broken after a crash
+                if exit_kind != ExitKind::Ok {
+                    self.child.take().unwrap().kill();
+                }
+                exit_kind
+            }
+            Outcome::Died => {
+                let mut child = self.child.take().unwrap();
+                // the target may have closed its status pipe without going away
+                if let Some(status) = wait_until(&mut child.child, deadline)? {
+                    if status.signal() == Some(9) {
+                        // for reference: https://www.man7.org/linux/man-pages/man7/signal.7.html
+                        ExitKind::Oom
+                    } else {
+                        // a persistent target should not exit in the middle of a run
+                        ExitKind::Crash
+                    }
+                } else {
+                    child.kill();
+                    ExitKind::Timeout
+                }
+            }
+            Outcome::Timeout => {
+                self.child.take().unwrap().kill();
+                ExitKind::Timeout
+            }
+        };
+
+        if self.observers.observes_stdout() {
+            self.observers.observe_stdout(&stdout);
+        }
+        if self.observers.observes_stderr() {
+            self.observers.observe_stderr(&stderr);
+        }
+        Ok(exit_kind)
+    }
+}
+
+impl<OT, P, S> UsesState for PersistentCommandExecutor<OT, P, S>
+where
+    S: UsesInput,
+{
+    type State = S;
+}
+
+impl<OT, P, S> UsesObservers for PersistentCommandExecutor<OT, P, S>
+where
+    OT: ObserversTuple<S>,
+    S: UsesInput,
+{
+    type Observers = OT;
+}
+
+impl<OT, P, S> HasObservers for PersistentCommandExecutor<OT, P, S>
+where
+    OT: ObserversTuple<S>,
+    S: UsesInput,
+{
+    fn observers(&self) -> &OT {
+        &self.observers
+    }
+
+    fn observers_mut(&mut self) -> &mut OT {
+        &mut self.observers
+    }
+}
+
+/// The builder for a [`PersistentCommandExecutor`]
+#[derive(Debug, Clone)]
+pub struct PersistentCommandExecutorBuilder {
+    debug_child: bool,
+    program: Option<OsString>,
+    args: Vec<OsString>,
+    cwd: Option<PathBuf>,
+    envs: Vec<(OsString, OsString)>,
+    status_fd: RawFd,
+    timeout: Duration,
+}
+
+impl Default for PersistentCommandExecutorBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PersistentCommandExecutorBuilder {
+    /// Create a new [`PersistentCommandExecutorBuilder`]
+    #[must_use]
+    fn new() -> PersistentCommandExecutorBuilder {
+        PersistentCommandExecutorBuilder {
+            program: None,
+            args: vec![],
+            cwd: None,
+            envs: vec![],
+            status_fd: 3,
+            timeout: Duration::from_secs(5),
+            debug_child: false,
+        }
+    }
+
+    /// Set the binary to execute
+    /// This option is required.
+    pub fn program<O>(&mut self, program: O) -> &mut Self
+    where
+        O: AsRef<OsStr>,
+    {
+        self.program = Some(program.as_ref().to_owned());
+        self
+    }
+
+    /// Adds an argument to the program's commandline.
+    pub fn arg<O: AsRef<OsStr>>(&mut self, arg: O) -> &mut Self {
+        self.args.push(arg.as_ref().to_owned());
+        self
+    }
+
+    /// Adds a range of arguments to the program's commandline.
+    pub fn args<IT, O>(&mut self, args: IT) -> &mut Self
+    where
+        IT: IntoIterator<Item = O>,
+        O: AsRef<OsStr>,
+    {
+        for arg in args {
+            self.arg(arg.as_ref());
+        }
+        self
+    }
+
+    /// Adds a range of environment variables to the executed command.
+    pub fn envs<IT, K, V>(&mut self, vars: IT) -> &mut Self
+    where
+        IT: IntoIterator<Item = (K, V)>,
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        for (ref key, ref val) in vars {
+            self.env(key.as_ref(), val.as_ref());
+        }
+        self
+    }
+
+    /// Adds an environment variable to the executed command.
+    pub fn env<K, V>(&mut self, key: K, val: V) -> &mut Self
+    where
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.envs
+            .push((key.as_ref().to_owned(), val.as_ref().to_owned()));
+        self
+    }
+
+    /// Sets the working directory for the child process.
+    pub fn current_dir<P: AsRef<Path>>(&mut self, dir: P) -> &mut Self {
+        self.cwd = Some(dir.as_ref().to_owned());
+        self
+    }
+
+    /// If set to true, the child's output won't be redirecited to `/dev/null`.
+    /// Defaults to `false`.
+    pub fn debug_child(&mut self, debug_child: bool) -> &mut Self {
+        self.debug_child = debug_child;
+        self
+    }
+
+    /// The fd of the target the status replies are written to.
+    /// Defaults to `3`.
+    pub fn status_fd(&mut self, status_fd: RawFd) -> &mut Self {
+        self.status_fd = status_fd;
+        self
+    }
+
+    /// Sets the timeout of a run, until the status reply of the target.
+    pub fn timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Builds the [`PersistentCommandExecutor`], with the [`LengthPrefixedProtocol`]
+    pub fn build<OT, S>(
+        &self,
+        observers: OT,
+    ) -> Result<PersistentCommandExecutor<OT, LengthPrefixedProtocol, S>, Error>
+    where
+        OT: ObserversTuple<S>,
+        S: UsesInput,
+    {
+        self.build_with_protocol(LengthPrefixedProtocol::new(), observers)
+    }
+
+    /// Builds the [`PersistentCommandExecutor`], with a protocol of its own
+    pub fn build_with_protocol<OT, P, S>(
+        &self,
+        protocol: P,
+        observers: OT,
+    ) -> Result<PersistentCommandExecutor<OT, P, S>, Error>
+    where
+        OT: ObserversTuple<S>,
+        P: PersistentProtocol,
+        S: UsesInput,
+    {
+        let Some(program) = &self.program else {
+            return Err(Error::illegal_argument(
+                "PersistentCommandExecutor::builder: no program set!",
+            ));
+        };
+        if self.status_fd <= libc::STDERR_FILENO {
+            return Err(Error::illegal_argument(
+                "PersistentCommandExecutor::builder: the status fd must not be a stdio fd",
+            ));
+        }
+
+        Ok(PersistentCommandExecutor {
+            program: program.clone(),
+            args: self.args.clone(),
+            envs: self.envs.clone(),
+            cwd: self.cwd.clone(),
+            debug_child: self.debug_child,
+            status_fd: self.status_fd,
+            timeout: self.timeout,
+            protocol,
+            child: None,
+            frame: Vec::new(),
+            observers,
+            phantom: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::{String, ToString};
+    use core::time::Duration;
+    use std::time::Instant;
+
+    use libafl_bolts::tuples::tuple_list;
+
+    use crate::{
+        events::NopEventManager,
+        executors::{
+            persistent_command::{LengthPrefixedProtocol, PersistentProtocol},
+            Executor, ExitKind, HasObservers, PersistentCommandExecutor,
+        },
+        inputs::BytesInput,
+        observers::StdOutObserver,
+        state::NopState,
+        NopFuzzer,
+    };
+
+    /// Echoes each input, and replies with a status depending on it
+    const TARGET: &str = r#"
+import os, signal, struct, sys, time
+status = os.fdopen(3, "wb", buffering=0)
+while True:
+    header = sys.stdin.buffer.read(4)
+    if len(header) < 4:
+        break
+    data = sys.stdin.buffer.read(struct.unpack("<I", header)[0])
+    sys.stdout.buffer.write(b"pid %d: %s" % (os.getpid(), data))
+    sys.stdout.flush()
+    if data == b"crash":
+        os.kill(os.getpid(), signal.SIGSEGV)
+    if data == b"hang":
+        time.sleep(10)
+    status.write(struct.pack("<I", 1 if data == b"fail" else 0))
+"#;
+
+    #[test]
+    fn test_length_prefixed_protocol() {
+        let mut frame = vec![];
+        LengthPrefixedProtocol::big_endian()
+            .frame_input(b"abc", &mut frame)
+            .unwrap();
+        assert_eq!(frame, b"\0\0\0\x03abc");
+        let mut protocol = LengthPrefixedProtocol::new();
+        assert_eq!(protocol.exit_kind(&[0, 0, 0, 0]), ExitKind::Ok);
+        assert_eq!(protocol.exit_kind(&[1, 0, 0, 0]), ExitKind::Crash);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_persistent_command() {
+        let mut executor = PersistentCommandExecutor::builder()
+            .program("python3")
+            .arg("-c")
+            .arg(TARGET)
+            .timeout(Duration::from_secs(1))
+            .build(tuple_list!(StdOutObserver::new("stdout".to_string())))
+            .unwrap();
+
+        let mut run = |input: &[u8]| {
+            let exit_kind = executor
+                .run_target(
+                    &mut NopFuzzer::new(),
+                    &mut NopState::new(),
+                    &mut NopEventManager::new(),
+                    &BytesInput::new(input.to_vec()),
+                )
+                .unwrap();
+            let stdout = executor.observers().0.stdout.clone().unwrap();
+            let stdout = String::from_utf8(stdout).unwrap();
+            let (pid, output) = stdout.split_once(": ").unwrap();
+            assert_eq!(output.as_bytes(), input);
+            (exit_kind, pid.to_string())
+        };
+
+        let (exit_kind, pid) = run(b"a");
+        assert_eq!(exit_kind, ExitKind::Ok);
+        // the same process handles the next input
+        assert_eq!(run(b"bb"), (ExitKind::Ok, pid.clone()));
+        assert_eq!(run(b"fail"), (ExitKind::Crash, pid.clone()));
+
+        // restarted after each failure
+        let (exit_kind, restarted) = run(b"crash");
+        assert_eq!(exit_kind, ExitKind::Crash);
+        assert_ne!(restarted, pid);
+        let (exit_kind, restarted_again) = run(b"hang");
+        assert_eq!(exit_kind, ExitKind::Timeout);
+        assert_ne!(restarted_again, restarted);
+        assert_eq!(run(b"c").0, ExitKind::Ok);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_persistent_command_deadline() {
+        let run = |script: &str, input: &[u8]| {
+            let mut executor = PersistentCommandExecutor::builder()
+                .program("python3")
+                .arg("-c")
+                .arg(script)
+                .timeout(Duration::from_secs(1))
+                .build(tuple_list!(StdOutObserver::new("stdout".to_string())))
+                .unwrap();
+            let start = Instant::now();
+            let exit_kind = executor
+                .run_target(
+                    &mut NopFuzzer::new(),
+                    &mut NopState::new(),
+                    &mut NopEventManager::new(),
+                    &BytesInput::new(input.to_vec()),
+                )
+                .unwrap();
+            assert!(start.elapsed() < Duration::from_secs(3));
+            exit_kind
+        };
+
+        // a target not reading an input larger than the pipe buffer
+        assert_eq!(
+            run("import time; time.sleep(10)", &vec![0; 1 << 20]),
+            ExitKind::Timeout
+        );
+        // a target crashing while a child of it keeps its stdout open
+        assert_eq!(
+            run(
+                "import os, signal, subprocess; subprocess.Popen(['sleep', '10']); os.kill(os.getpid(), signal.SIGSEGV)",
+                b"a"
+            ),
+            ExitKind::Crash
+        );
+    }
+}
@@ -0,0 +1,1008 @@
+//! An [`Executor`] collecting the coverage of uninstrumented Linux binaries with breakpoints,
+//! as `honggfuzz` and `TinyInst` do.
+//!
+//! A one-shot `int3` is placed at the start of each basic block of the target, from a static list
+//! of blocks, such as one exported from a disassembler. The first time a block is reached, its
+//! breakpoint is removed for good, and the hit is recorded in a coverage map.
+//! After a short warm-up, the target runs at native speed, and only stops on new coverage.
+//!
+//! Optionally, the target is forked from a snapshot at the entry of a given function for each run,
+//! skipping its startup, in the spirit of a persistent mode.
+
+use alloc::{borrow::ToOwned, vec::Vec};
+use core::{
+    fmt::{self, Debug, Formatter},
+    marker::PhantomData,
+    time::Duration,
+};
+use std::{
+    collections::{btree_map::Entry, BTreeMap, HashMap},
+    ffi::{OsStr, OsString},
+    fs::{self, File, OpenOptions},
+    io,
+    os::unix::{fs::FileExt, process::CommandExt},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+    time::Instant,
+};
+
+use libafl_bolts::{
+    fs::{get_unique_std_input_file, InputFile},
+    AsSlice,
+};
+use nix::{
+    libc::{self, user_regs_struct},
+    sys::{
+        ptrace::{self, Options},
+        signal::{kill, Signal},
+        wait::{waitpid, WaitPidFlag, WaitStatus},
+    },
+    unistd::Pid,
+};
+
+use crate::{
+    executors::{Executor, ExitKind, HasObservers},
+    inputs::{HasTargetBytes, UsesInput},
+    observers::{ObserversTuple, UsesObservers},
+    state::UsesState,
+    Error,
+};
+
+/// The `int3` instruction
+const INT3: u8 = 0xcc;
+/// The `syscall` instruction
+const SYSCALL: [u8; 2] = [0x0f, 0x05];
+/// The size of the pages the breakpoints are written in
+const PAGE_SIZE: u64 = 4096;
+
+/// The offsets of the basic blocks in a list, one hexadecimal offset per line.
+/// Empty lines and lines starting with `#` are skipped.
+fn parse_basic_blocks(list: &str) -> Result<Vec<u64>, Error> {
+    let mut blocks = Vec::new();
+    for (i, line) in list.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let digits = line.strip_prefix("0x").unwrap_or(line);
+        let offset = u64::from_str_radix(digits, 16).map_err(|_| {
+            Error::illegal_argument(format!(
+                "Invalid basic block offset on line {}: {line}",
+                i + 1
+            ))
+        })?;
+        blocks.push(offset);
+    }
+    Ok(blocks)
+}
+
+/// If the 64-bit ELF executable with this header is position independent, and so its basic blocks
+/// are relative to its load address
+fn is_pie(header: &[u8]) -> Result<bool, Error> {
+    if header.len() < 18 || !header.starts_with(b"\x7fELF") {
+        return Err(Error::illegal_argument(
+            "The target is not an ELF executable",
+        ));
+    }
+    if header[4] != 2 {
+        return Err(Error::illegal_argument("The target is not a 64-bit ELF"));
+    }
+    match u16::from_le_bytes([header[16], header[17]]) {
+        // ET_EXEC
+        2 => Ok(false),
+        // ET_DYN
+        3 => Ok(true),
+        e_type => Err(Error::illegal_argument(format!(
+            "The target is not an executable, its ELF type is {e_type}"
+        ))),
+    }
+}
+
+/// The address the executable of the process is loaded at
+fn load_address(pid: Pid) -> Result<u64, Error> {
+    let exe = fs::read_link(format!("/proc/{pid}/exe"))?;
+    let maps = fs::read_to_string(format!("/proc/{pid}/maps"))?;
+    maps.lines()
+        .find(|line| line.split_whitespace().nth(5).map(Path::new) == Some(exe.as_path()))
+        .and_then(|line| line.split('-').next())
+        .and_then(|start| u64::from_str_radix(start, 16).ok())
+        .ok_or_else(|| {
+            Error::illegal_state(format!("No mapping of {} in the target", exe.display()))
+        })
+}
+
+/// The longest pause in between two polls of the traced threads in [`wait_until`]
+const MAX_POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Waits for a change of state of one of the traced `threads`, until `deadline`.
+///
+/// Polls, with pauses growing up to [`MAX_POLL_INTERVAL`], as `SIGCHLD` may go to any thread of the fuzzer.
+fn wait_until(threads: &[Pid], deadline: Instant) -> Result<Option<WaitStatus>, Error> {
+    let mut interval = Duration::from_micros(1);
+    loop {
+        for &tid in threads {
+            match waitpid(tid, Some(WaitPidFlag::WNOHANG | WaitPidFlag::__WALL))? {
+                WaitStatus::StillAlive => {}
+                status => return Ok(Some(status)),
+            }
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return Ok(None);
+        }
+        thread::sleep(interval.min(deadline - now));
+        interval = (interval * 2).min(MAX_POLL_INTERVAL);
+    }
+}
+
+/// Kills the traced `pid` and waits for it and its threads to go
+fn kill_and_wait(pid: Pid) -> Result<(), Error> {
+    // it may have died on its own meanwhile
+    kill(pid, Signal::SIGKILL).ok();
+    // the main thread is only reported once the other traced threads are reaped,
+    // and no new thread can start with the `SIGKILL` pending
+    if let Ok(tasks) = fs::read_dir(format!("/proc/{pid}/task")) {
+        let threads = tasks
+            .filter_map(|task| task.ok()?.file_name().to_str()?.parse().ok())
+            .map(Pid::from_raw)
+            .filter(|&tid| tid != pid);
+        for tid in threads {
+            // the thread may be gone already
+            while let Ok(status) = waitpid(tid, Some(WaitPidFlag::__WALL)) {
+                if let WaitStatus::Exited(..) | WaitStatus::Signaled(..) = status {
+                    break;
+                }
+            }
+        }
+    }
+    loop {
+        match waitpid(pid, Some(WaitPidFlag::__WALL))? {
+            WaitStatus::Exited(..) | WaitStatus::Signaled(..) => return Ok(()),
+            _ => {}
+        }
+    }
+}
+
+/// Opens the memory of the traced `pid`, which is writable even where its mapping is not
+fn open_mem(pid: Pid) -> Result<File, Error> {
+    Ok(OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(format!("/proc/{pid}/mem"))?)
+}
+
+/// Runs a syscall in the traced `pid`, stopped with the registers `regs`, and returns its result.
+/// Afterwards, the process is back in the state of `regs`.
+fn inject_syscall(
+    pid: Pid,
+    mem: &File,
+    regs: &user_regs_struct,
+    nr: u64,
+    args: [u64; 4],
+) -> Result<u64, Error> {
+    let mut saved = [0; SYSCALL.len()];
+    mem.read_exact_at(&mut saved, regs.rip)?;
+    mem.write_all_at(&SYSCALL, regs.rip)?;
+
+    let mut call = *regs;
+    call.rax = nr;
+    call.rdi = args[0];
+    call.rsi = args[1];
+    call.rdx = args[2];
+    call.r10 = args[3];
+    ptrace::setregs(pid, call)?;
+
+    // step over the syscall, past its ptrace events and the signals the process receives meanwhile,
+    // such as the `SIGCHLD` of its children, which are suppressed
+    loop {
+        ptrace::step(pid, None)?;
+        match waitpid(pid, Some(WaitPidFlag::__WALL))? {
+            WaitStatus::Stopped(_, Signal::SIGTRAP) => break,
+            WaitStatus::Stopped(..) | WaitStatus::PtraceEvent(..) => {}
+            status => {
+                return Err(Error::illegal_state(format!(
+                    "The snapshot of the target went away: {status:?}"
+                )))
+            }
+        }
+    }
+    let result = ptrace::getregs(pid)?.rax;
+
+    mem.write_all_at(&saved, regs.rip)?;
+    ptrace::setregs(pid, *regs)?;
+    Ok(result)
+}
+
+/// The breakpoint at a basic block
+#[derive(Debug, Clone, Copy)]
+struct Breakpoint {
+    /// The index of the block, in the list of blocks
+    index: usize,
+    /// The original byte under the `int3`
+    orig: u8,
+}
+
+/// A process of the target stopped at the entry of the snapshot function, forked for each run
+struct Snapshot {
+    pid: Pid,
+    mem: File,
+    regs: user_regs_struct,
+}
+
+/// An [`Executor`] for uninstrumented Linux x86-64 binaries, collecting their coverage with
+/// one-shot breakpoints on their basic blocks, see the [module docs](self).
+///
+/// The hit blocks are set to `1` in the coverage map, such as the map of a
+/// [`crate::observers::StdMapObserver`], at the index of the block in the list, modulo the size of
+/// the map. As each block is reported only once, by the first run reaching it, the coverage of an
+/// input is only seen by its first run: the `CalibrationStage` does not fit.
+///
+/// The threads of the target are traced, too. Its forks are not, and the runs fail with an error if
+/// it executes another program. With a snapshot, the target must not start threads before the
+/// snapshot function.
+pub struct PtraceExecutor<OT, S> {
+    program: OsString,
+    args: Vec<OsString>,
+    envs: Vec<(OsString, OsString)>,
+    input_file: InputFile,
+    input_in_stdin: bool,
+    debug_child: bool,
+    timeout: Duration,
+    pie: bool,
+    /// The offsets of the basic blocks, relative to the load address of a position independent target
+    blocks: Vec<u64>,
+    /// The breakpoints not hit yet, by offset
+    breakpoints: HashMap<u64, Breakpoint>,
+    /// The original pages of the target with the breakpoints not hit yet, by offset, and how many
+    /// breakpoints each has left. Read from the first process, once it is loaded.
+    pages: Option<BTreeMap<u64, (Vec<u8>, usize)>>,
+    /// The load address of the target
+    base: u64,
+    snapshot_offset: Option<u64>,
+    snapshot: Option<Snapshot>,
+    map_ptr: *mut u8,
+    map_len: usize,
+    observers: OT,
+    phantom: PhantomData<S>,
+}
+
+impl<OT, S> Debug for PtraceExecutor<OT, S>
+where
+    OT: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("PtraceExecutor")
+            .field("program", &self.program)
+            .field("args", &self.args)
+            .field("timeout", &self.timeout)
+            .field("blocks", &self.blocks.len())
+            .field("breakpoints", &self.breakpoints.len())
+            .field("snapshot_offset", &self.snapshot_offset)
+            .field("observers", &self.observers)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PtraceExecutor<(), ()> {
+    /// Creates a builder for a [`PtraceExecutor`]
+    #[must_use]
+    pub fn builder() -> PtraceExecutorBuilder {
+        PtraceExecutorBuilder::new()
+    }
+}
+
+impl<OT, S> PtraceExecutor<OT, S> {
+    /// The number of basic blocks not reached yet
+    #[must_use]
+    pub fn remaining_breakpoints(&self) -> usize {
+        self.breakpoints.len()
+    }
+
+    /// Starts the target, stopped right after its `exec`, with the breakpoints not hit yet in place.
+    /// Returns its pid and its memory.
+    fn spawn(&mut self) -> Result<(Pid, File), Error> {
+        let mut command = Command::new(&self.program);
+        command.args(&self.args).envs(
+            self.envs
+                .iter()
+                .map(|(k, v)| (k.as_os_str(), v.as_os_str())),
+        );
+        if self.input_in_stdin {
+            command.stdin(Stdio::from(self.input_file.file.try_clone()?));
+        } else {
+            command.stdin(Stdio::null());
+        }
+        if !self.debug_child {
+            command.stdout(Stdio::null());
+            command.stderr(Stdio::null());
+        }
+        unsafe {
+            command.pre_exec(|| ptrace::traceme().map_err(io::Error::from));
+        }
+
+        let child = command.spawn()?;
+        let pid = Pid::from_raw(i32::try_from(child.id())?);
+        match waitpid(pid, Some(WaitPidFlag::__WALL))? {
+            WaitStatus::Stopped(_, Signal::SIGTRAP) => {}
+            status => {
+                return Err(Error::illegal_state(format!(
+                    "The target did not stop at its exec: {status:?}"
+                )))
+            }
+        }
+        ptrace::setoptions(pid, Self::trace_options())?;
+
+        self.base = if self.pie { load_address(pid)? } else { 0 };
+        let mem = open_mem(pid)?;
+        if self.pages.is_none() {
+            self.pages = Some(self.read_pages(&mem)?);
+        }
+        // the pages are as loaded from the file, before any relocation, so they can be written whole
+        for (offset, (page, _)) in self.pages.as_ref().unwrap() {
+            mem.write_all_at(page, self.base + offset)?;
+        }
+        Ok((pid, mem))
+    }
+
+    /// Reads the pages of the basic blocks from the freshly loaded target, and sets their breakpoints
+    fn read_pages(&mut self, mem: &File) -> Result<BTreeMap<u64, (Vec<u8>, usize)>, Error> {
+        let mut pages = BTreeMap::new();
+        for (index, &offset) in self.blocks.iter().enumerate() {
+            let page_offset = offset & !(PAGE_SIZE - 1);
+            let (page, remaining) = match pages.entry(page_offset) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let mut page = vec![0; PAGE_SIZE as usize];
+                    mem.read_exact_at(&mut page, self.base + page_offset)?;
+                    entry.insert((page, 0))
+                }
+            };
+            if self.breakpoints.contains_key(&offset) {
+                continue;
+            }
+            let orig = page[(offset - page_offset) as usize];
+            self.breakpoints.insert(offset, Breakpoint { index, orig });
+            *remaining += 1;
+        }
+        for offset in self.breakpoints.keys() {
+            let page_offset = offset & !(PAGE_SIZE - 1);
+            pages.get_mut(&page_offset).unwrap().0[(offset - page_offset) as usize] = INT3;
+        }
+        Ok(pages)
+    }
+
+    /// The ptrace options of a run of the target: its threads are traced, and an `exec` is reported
+    fn trace_options() -> Options {
+        Options::PTRACE_O_EXITKILL | Options::PTRACE_O_TRACECLONE | Options::PTRACE_O_TRACEEXEC
+    }
+
+    /// Handles the `SIGTRAP` of the traced thread `pid` if it comes from one of our breakpoints:
+    /// records the block, removes the breakpoint for good, and resumes at the block
+    fn handle_breakpoint(&mut self, pid: Pid, mem: &File) -> Result<bool, Error> {
+        let mut regs = ptrace::getregs(pid)?;
+        let addr = regs.rip - 1;
+        let offset = addr.wrapping_sub(self.base);
+        let Some(bp) = self.breakpoints.remove(&offset) else {
+            // another thread may have hit the same breakpoint right before it was removed
+            let mut byte = [0];
+            if ptrace::getsiginfo(pid)?.si_code == libc::SI_KERNEL
+                && self.blocks.contains(&offset)
+                && mem.read_exact_at(&mut byte, addr).is_ok()
+                && byte[0] != INT3
+            {
+                regs.rip = addr;
+                ptrace::setregs(pid, regs)?;
+                return Ok(true);
+            }
+            return Ok(false);
+        };
+
+        unsafe {
+            *self.map_ptr.add(bp.index % self.map_len) = 1;
+        }
+
+        mem.write_all_at(&[bp.orig], addr)?;
+        if let Some(snapshot) = &self.snapshot {
+            if snapshot.pid != pid {
+                snapshot.mem.write_all_at(&[bp.orig], addr)?;
+            }
+        }
+        let page_offset = offset & !(PAGE_SIZE - 1);
+        let pages = self.pages.as_mut().unwrap();
+        let (page, remaining) = pages.get_mut(&page_offset).unwrap();
+        page[(offset - page_offset) as usize] = bp.orig;
+        *remaining -= 1;
+        if *remaining == 0 {
+            pages.remove(&page_offset);
+        }
+
+        regs.rip = addr;
+        ptrace::setregs(pid, regs)?;
+        Ok(true)
+    }
+
+    /// Runs the traced `pid` until it exits, handling the breakpoints of all its threads along the way.
+    /// Fails if the target executes another program, as its breakpoints would be meaningless.
+    fn trace(&mut self, pid: Pid, mem: &File) -> Result<ExitKind, Error> {
+        let deadline = Instant::now() + self.timeout;
+        // the main thread first, then the threads it started
+        let mut threads = vec![pid];
+        // the started threads not seen in their initial `SIGSTOP` yet
+        let mut starting = Vec::new();
+        ptrace::cont(pid, None)?;
+        loop {
+            let Some(status) = wait_until(&threads, deadline)? else {
+                kill_and_wait(pid)?;
+                return Ok(ExitKind::Timeout);
+            };
+            let tid = status.pid().unwrap();
+            match status {
+                // the process ends with its main thread
+                WaitStatus::Exited(..) | WaitStatus::Signaled(..) if tid != pid => {
+                    threads.retain(|&thread| thread != tid);
+                }
+                WaitStatus::Exited(..) => return Ok(ExitKind::Ok),
+                // a `SIGKILL` from anyone but the executor, which reports its own kills as timeouts,
+                // is no evidence of memory exhaustion
+                WaitStatus::Signaled(..) => return Ok(ExitKind::Crash),
+                WaitStatus::PtraceEvent(_, _, libc::PTRACE_EVENT_CLONE) => {
+                    let thread = Pid::from_raw(i32::try_from(ptrace::getevent(tid)?)?);
+                    threads.push(thread);
+                    starting.push(thread);
+                    ptrace::cont(tid, None)?;
+                }
+                WaitStatus::PtraceEvent(_, _, libc::PTRACE_EVENT_EXEC) => {
+                    kill_and_wait(pid)?;
+                    return Err(Error::unsupported(
+                        "The target executed another program, which the PtraceExecutor can not trace",
+                    ));
+                }
+                WaitStatus::Stopped(_, Signal::SIGSTOP) if starting.contains(&tid) => {
+                    starting.retain(|&thread| thread != tid);
+                    ptrace::cont(tid, None)?;
+                }
+                WaitStatus::Stopped(_, Signal::SIGTRAP) => {
+                    if self.handle_breakpoint(tid, mem)? {
+                        ptrace::cont(tid, None)?;
+                    } else {
+                        // a trap of the target itself
+                        ptrace::cont(tid, Signal::SIGTRAP)?;
+                    }
+                }
+                // deliver the signals of the target, crashing it if it does not handle them
+                WaitStatus::Stopped(_, signal) => ptrace::cont(tid, signal)?,
+                _ => ptrace::cont(tid, None)?,
+            }
+        }
+    }
+
+    /// Starts the target and runs it to the entry of the snapshot function, at `offset`
+    fn take_snapshot(&mut self, offset: u64) -> Result<Snapshot, Error> {
+        let (pid, mem) = self.spawn()?;
+        let addr = self.base + offset;
+        // the entry may be a block with its breakpoint already in place
+        let orig = if let Some(bp) = self.breakpoints.get(&offset) {
+            bp.orig
+        } else {
+            let mut orig = [0];
+            mem.read_exact_at(&mut orig, addr)?;
+            orig[0]
+        };
+        mem.write_all_at(&[INT3], addr)?;
+
+        let deadline = Instant::now() + self.timeout;
+        ptrace::cont(pid, None)?;
+        loop {
+            let Some(status) = wait_until(&[pid], deadline)? else {
+                kill_and_wait(pid)?;
+                return Err(Error::illegal_state(
+                    "The target timed out before reaching the snapshot",
+                ));
+            };
+            match status {
+                WaitStatus::Stopped(_, Signal::SIGTRAP) => {
+                    let handled = self.handle_breakpoint(pid, &mem)?;
+                    let mut regs = ptrace::getregs(pid)?;
+                    if regs.rip == addr || (!handled && regs.rip == addr + 1) {
+                        mem.write_all_at(&[orig], addr)?;
+                        regs.rip = addr;
+                        ptrace::setregs(pid, regs)?;
+                        // the snapshot forks for each run, trace its children, too
+                        ptrace::setoptions(
+                            pid,
+                            Options::PTRACE_O_EXITKILL | Options::PTRACE_O_TRACEFORK,
+                        )?;
+                        return Ok(Snapshot { pid, mem, regs });
+                    }
+                    ptrace::cont(pid, (!handled).then_some(Signal::SIGTRAP))?;
+                }
+                WaitStatus::Stopped(_, signal) => ptrace::cont(pid, signal)?,
+                WaitStatus::Exited(..) | WaitStatus::Signaled(..) => {
+                    return Err(Error::illegal_state(
+                        "The target exited before reaching the snapshot",
+                    ))
+                }
+                // only the forking thread would be in the snapshot
+                WaitStatus::PtraceEvent(_, _, libc::PTRACE_EVENT_CLONE) => {
+                    kill_and_wait(pid)?;
+                    return Err(Error::unsupported(
+                        "The target started a thread before reaching the snapshot",
+                    ));
+                }
+                WaitStatus::PtraceEvent(_, _, libc::PTRACE_EVENT_EXEC) => {
+                    kill_and_wait(pid)?;
+                    return Err(Error::unsupported(
+                        "The target executed another program before reaching the snapshot",
+                    ));
+                }
+                _ => ptrace::cont(pid, None)?,
+            }
+        }
+    }
+
+    /// Forks a new process of the target from the snapshot, stopped at the entry of the snapshot function
+    fn fork_snapshot(&self) -> Result<(Pid, File), Error> {
+        let snapshot = self.snapshot.as_ref().unwrap();
+        let child = inject_syscall(
+            snapshot.pid,
+            &snapshot.mem,
+            &snapshot.regs,
+            libc::SYS_fork.unsigned_abs(),
+            [0; 4],
+        )?;
+        // a negative errno on failure
+        let Ok(child) = i32::try_from(child) else {
+            return Err(Error::illegal_state(format!(
+                "Fork of the snapshot failed with errno {}",
+                child.wrapping_neg()
+            )));
+        };
+        let pid = Pid::from_raw(child);
+
+        // traced from its start, stopped with a `SIGSTOP`
+        loop {
+            if let WaitStatus::Stopped(_, Signal::SIGSTOP) =
+                waitpid(pid, Some(WaitPidFlag::__WALL))?
+            {
+                break;
+            }
+        }
+        // the target should not trace the forks of the runs
+        ptrace::setoptions(pid, Self::trace_options())?;
+
+        // the child is a copy of the snapshot in the middle of the injected syscall
+        let mem = open_mem(pid)?;
+        let mut code = [0; SYSCALL.len()];
+        snapshot.mem.read_exact_at(&mut code, snapshot.regs.rip)?;
+        mem.write_all_at(&code, snapshot.regs.rip)?;
+        ptrace::setregs(pid, snapshot.regs)?;
+        Ok((pid, mem))
+    }
+
+    /// Reaps the exited `pid`, a child of the snapshot
+    fn reap_snapshot_child(&self, pid: Pid) -> Result<(), Error> {
+        let snapshot = self.snapshot.as_ref().unwrap();
+        inject_syscall(
+            snapshot.pid,
+            &snapshot.mem,
+            &snapshot.regs,
+            libc::SYS_wait4.unsigned_abs(),
+            [u64::try_from(pid.as_raw())?, 0, 0, 0],
+        )?;
+        Ok(())
+    }
+}
+
+impl<OT, S> Drop for PtraceExecutor<OT, S> {
+    fn drop(&mut self) {
+        if let Some(snapshot) = self.snapshot.take() {
+            drop(kill_and_wait(snapshot.pid));
+        }
+    }
+}
+
+impl<EM, OT, S, Z> Executor<EM, Z> for PtraceExecutor<OT, S>
+where
+    EM: UsesState<State = S>,
+    S: UsesInput,
+    S::Input: HasTargetBytes,
+    OT: Debug + ObserversTuple<S>,
+    Z: UsesState<State = S>,
+{
+    fn run_target(
+        &mut self,
+        _fuzzer: &mut Z,
+        _state: &mut Self::State,
+        _mgr: &mut EM,
+        input: &Self::Input,
+    ) -> Result<ExitKind, Error> {
+        self.input_file.write_buf(input.target_bytes().as_slice())?;
+
+        let Some(offset) = self.snapshot_offset else {
+            let (pid, mem) = self.spawn()?;
+            return self.trace(pid, &mem);
+        };
+
+        if self.snapshot.is_none() {
+            self.snapshot = Some(self.take_snapshot(offset)?);
+        }
+        let (pid, mem) = self.fork_snapshot()?;
+        let exit_kind = self.trace(pid, &mem);
+        // dead either way, even if the run failed
+        self.reap_snapshot_child(pid)?;
+        exit_kind
+    }
+}
+
+impl<OT, S> UsesState for PtraceExecutor<OT, S>
+where
+    S: UsesInput,
+{
+    type State = S;
+}
+
+impl<OT, S> UsesObservers for PtraceExecutor<OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: UsesInput,
+{
+    type Observers = OT;
+}
+
+impl<OT, S> HasObservers for PtraceExecutor<OT, S>
+where
+    OT: ObserversTuple<S>,
+    S: UsesInput,
+{
+    fn observers(&self) -> &OT {
+        &self.observers
+    }
+
+    fn observers_mut(&mut self) -> &mut OT {
+        &mut self.observers
+    }
+}
+
+/// The builder for a [`PtraceExecutor`]
+#[derive(Debug, Default)]
+pub struct PtraceExecutorBuilder {
+    program: Option<OsString>,
+    args: Vec<OsString>,
+    envs: Vec<(OsString, OsString)>,
+    input_file: Option<PathBuf>,
+    debug_child: bool,
+    timeout: Option<Duration>,
+    blocks: Vec<u64>,
+    snapshot_offset: Option<u64>,
+    map: Option<(*mut u8, usize)>,
+}
+
+impl PtraceExecutorBuilder {
+    /// Creates a new [`PtraceExecutorBuilder`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The binary to execute. This option is required.
+    #[must_use]
+    pub fn program<O>(mut self, program: O) -> Self
+    where
+        O: AsRef<OsStr>,
+    {
+        self.program = Some(program.as_ref().to_owned());
+        self
+    }
+
+    /// Adds an argument to the program's commandline
+    #[must_use]
+    pub fn arg<O>(mut self, arg: O) -> Self
+    where
+        O: AsRef<OsStr>,
+    {
+        self.args.push(arg.as_ref().to_owned());
+        self
+    }
+
+    /// Adds a range of arguments to the program's commandline
+    #[must_use]
+    pub fn args<IT, O>(mut self, args: IT) -> Self
+    where
+        IT: IntoIterator<Item = O>,
+        O: AsRef<OsStr>,
+    {
+        for arg in args {
+            self = self.arg(arg);
+        }
+        self
+    }
+
+    /// Parses an `afl-fuzz` style commandline, program first, with `@@` for the input file.
+    /// Without `@@`, the input is given on stdin.
+    #[must_use]
+    pub fn parse_afl_cmdline<IT, O>(mut self, args: IT) -> Self
+    where
+        IT: IntoIterator<Item = O>,
+        O: AsRef<OsStr>,
+    {
+        for (pos, arg) in args.into_iter().enumerate() {
+            if pos == 0 {
+                self = self.program(arg);
+            } else if arg.as_ref() == "@@" {
+                self = self.arg_input_file_std();
+            } else {
+                self = self.arg(arg);
+            }
+        }
+        self
+    }
+
+    /// Passes the input in a file of a default name, added as an argument at the current position
+    #[must_use]
+    pub fn arg_input_file_std(self) -> Self {
+        self.arg_input_file(get_unique_std_input_file())
+    }
+
+    /// Passes the input in the file at `path`, added as an argument at the current position.
+    /// Otherwise, the input is given on stdin.
+    #[must_use]
+    pub fn arg_input_file<P>(mut self, path: P) -> Self
+    where
+        P: AsRef<Path>,
+    {
+        self = self.arg(path.as_ref());
+        self.input_file = Some(path.as_ref().to_owned());
+        self
+    }
+
+    /// Adds an environment variable to the executed command
+    #[must_use]
+    pub fn env<K, V>(mut self, key: K, val: V) -> Self
+    where
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        self.envs
+            .push((key.as_ref().to_owned(), val.as_ref().to_owned()));
+        self
+    }
+
+    /// Adds a range of environment variables to the executed command
+    #[must_use]
+    pub fn envs<IT, K, V>(mut self, vars: IT) -> Self
+    where
+        IT: IntoIterator<Item = (K, V)>,
+        K: AsRef<OsStr>,
+        V: AsRef<OsStr>,
+    {
+        for (key, val) in vars {
+            self = self.env(key, val);
+        }
+        self
+    }
+
+    /// If set to true, the child's output won't be redirected to `/dev/null`.
+    /// Defaults to `false`.
+    #[must_use]
+    pub fn debug_child(mut self, debug_child: bool) -> Self {
+        self.debug_child = debug_child;
+        self
+    }
+
+    /// The timeout of a run. Defaults to 5 seconds.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// The offsets of the basic blocks to put breakpoints on, from the start of the file mapping
+    /// of a position independent target, or absolute addresses otherwise
+    #[must_use]
+    pub fn basic_blocks<IT>(mut self, blocks: IT) -> Self
+    where
+        IT: IntoIterator<Item = u64>,
+    {
+        self.blocks.extend(blocks);
+        self
+    }
+
+    /// Reads the offsets of the basic blocks, see [`Self::basic_blocks`], from a file with one
+    /// hexadecimal offset per line, such as one exported from a disassembler.
+    /// Empty lines and lines starting with `#` are skipped.
+    pub fn basic_blocks_file<P>(self, path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let blocks = parse_basic_blocks(&fs::read_to_string(path)?)?;
+        Ok(self.basic_blocks(blocks))
+    }
+
+    /// Forks each run from a snapshot of the target at the entry of the function at this offset,
+    /// see [`Self::basic_blocks`].
+    /// The target should read its input only after this point, and should not call `wait` on
+    /// children it did not fork itself.
+    #[must_use]
+    pub fn snapshot_at(mut self, offset: u64) -> Self {
+        self.snapshot_offset = Some(offset);
+        self
+    }
+
+    /// The coverage map the hit blocks are recorded into. This option is required.
+    ///
+    /// # Safety
+    /// The map must be valid for `map_len` bytes for the lifetime of the executor.
+    /// It is usually shared with an observer, such as a [`crate::observers::StdMapObserver`].
+    #[must_use]
+    pub unsafe fn coverage_map(mut self, map_ptr: *mut u8, map_len: usize) -> Self {
+        self.map = Some((map_ptr, map_len));
+        self
+    }
+
+    /// Builds the [`PtraceExecutor`].
+    pub fn build<OT, S>(self, observers: OT) -> Result<PtraceExecutor<OT, S>, Error>
+    where
+        OT: ObserversTuple<S>,
+        S: UsesInput,
+    {
+        let Some(program) = self.program else {
+            return Err(Error::illegal_argument(
+                "PtraceExecutor::builder: no program set!",
+            ));
+        };
+        let Some((map_ptr, map_len)) = self.map.filter(|(_, len)| *len > 0) else {
+            return Err(Error::illegal_argument(
+                "PtraceExecutor::builder: no coverage map set!",
+            ));
+        };
+        if self.blocks.is_empty() {
+            return Err(Error::illegal_argument(
+                "PtraceExecutor::builder: no basic blocks set!",
+            ));
+        }
+
+        let mut header = [0; 18];
+        File::open(&program)?.read_exact_at(&mut header, 0)?;
+        let pie = is_pie(&header)?;
+
+        let input_in_stdin = self.input_file.is_none();
+        let input_file = InputFile::create(
+            self.input_file
+                .unwrap_or_else(|| get_unique_std_input_file().into()),
+        )?;
+
+        Ok(PtraceExecutor {
+            program,
+            args: self.args,
+            envs: self.envs,
+            input_file,
+            input_in_stdin,
+            debug_child: self.debug_child,
+            timeout: self.timeout.unwrap_or(Duration::from_secs(5)),
+            pie,
+            blocks: self.blocks,
+            breakpoints: HashMap::new(),
+            pages: None,
+            base: 0,
+            snapshot_offset: self.snapshot_offset,
+            snapshot: None,
+            map_ptr,
+            map_len,
+            observers,
+            phantom: PhantomData,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, os::unix::fs::FileExt};
+
+    use super::{is_pie, parse_basic_blocks, PtraceExecutor};
+    use crate::{
+        events::NopEventManager,
+        executors::{Executor, ExitKind},
+        inputs::BytesInput,
+        state::NopState,
+        Error, NopFuzzer,
+    };
+
+    /// The entry point of the ELF executable at `path`, a basic block reached by every run
+    fn entry(path: &str) -> u64 {
+        let mut entry = [0; 8];
+        File::open(path)
+            .unwrap()
+            .read_exact_at(&mut entry, 24)
+            .unwrap();
+        u64::from_le_bytes(entry)
+    }
+
+    fn run(executor: &mut PtraceExecutor<(), NopState<BytesInput>>) -> Result<ExitKind, Error> {
+        executor.run_target(
+            &mut NopFuzzer::new(),
+            &mut NopState::new(),
+            &mut NopEventManager::new(),
+            &BytesInput::new(b"test".to_vec()),
+        )
+    }
+
+    #[test]
+    fn test_parse_basic_blocks() {
+        let blocks = parse_basic_blocks("# main\n0x1139\n\n  1150 \n").unwrap();
+        assert_eq!(blocks, [0x1139, 0x1150]);
+        assert!(parse_basic_blocks("0x11g9").is_err());
+    }
+
+    #[test]
+    fn test_is_pie() {
+        let mut header = *b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0\x03\0";
+        assert!(is_pie(&header).unwrap());
+        header[16] = 2;
+        assert!(!is_pie(&header).unwrap());
+        header[4] = 1;
+        assert!(is_pie(&header).is_err());
+        assert!(is_pie(b"#!/bin/sh").is_err());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_ptrace_executor() {
+        for snapshot in [false, true] {
+            let mut map = [0_u8; 4];
+            let mut builder = PtraceExecutor::builder()
+                .program("/bin/true")
+                .basic_blocks([entry("/bin/true")]);
+            if snapshot {
+                builder = builder.snapshot_at(entry("/bin/true"));
+            }
+            let mut executor = unsafe { builder.coverage_map(map.as_mut_ptr(), map.len()) }
+                .build(())
+                .unwrap();
+
+            assert_eq!(run(&mut executor).unwrap(), ExitKind::Ok);
+            assert_eq!(map[0], 1);
+            assert_eq!(executor.remaining_breakpoints(), 0);
+
+            // the breakpoint is gone for good, from the snapshot, too
+            map[0] = 0;
+            for _ in 0..3 {
+                assert_eq!(run(&mut executor).unwrap(), ExitKind::Ok);
+            }
+            assert_eq!(map[0], 0);
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_ptrace_executor_exec() {
+        let mut map = [0_u8; 4];
+        let mut executor = unsafe {
+            PtraceExecutor::builder()
+                .program("/bin/sh")
+                .args(["-c", "exec /bin/true"])
+                .basic_blocks([entry("/bin/sh")])
+                .coverage_map(map.as_mut_ptr(), map.len())
+        }
+        .build(())
+        .unwrap();
+        assert!(matches!(run(&mut executor), Err(Error::Unsupported(..))));
+        assert_eq!(map[0], 1);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_ptrace_executor_sigkill() {
+        let mut map = [0_u8; 4];
+        let mut executor = unsafe {
+            PtraceExecutor::builder()
+                .program("/bin/sh")
+                .args(["-c", "kill -9 $$"])
+                .basic_blocks([entry("/bin/sh")])
+                .coverage_map(map.as_mut_ptr(), map.len())
+        }
+        .build(())
+        .unwrap();
+        assert_eq!(run(&mut executor).unwrap(), ExitKind::Crash);
+    }
+}
@@ -0,0 +1,795 @@
+//! A [`SnapshotInProcessExecutor`] restores the global state of an in-process target after each run, without forking.
+//!
+//! At the first run, the writable memory of the selected modules is copied aside.
+//! After each run, the executor finds the pages that were written to, either through the kernel's soft-dirty bits
+//! in `/proc/self/pagemap` or, if the kernel lacks `CONFIG_MEM_SOFT_DIRTY`, by comparing them to the snapshot.
+//! These pages are then restored right before the next run, so that observers can still inspect the last run.
+//! Optionally, the `brk` heap is restored as well, and file descriptors opened during a run are closed.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::{
+    fmt::{self, Debug, Formatter},
+    mem::size_of,
+    ops::Range,
+    ptr, slice,
+};
+use std::{env, os::unix::io::RawFd};
+
+use libafl_bolts::os::procfs::{
+    clear_soft_dirty, mappings, open_fds, page_size, program_break, set_program_break,
+    soft_dirty_supported, PageMap,
+};
+
+use crate::{
+    executors::{Executor, ExitKind, HasObservers},
+    observers::UsesObservers,
+    state::UsesState,
+    Error,
+};
+
+/// An anonymous mapping holding the snapshot, kept out of the (possibly restored) heap
+struct AnonMapping {
+    ptr: *mut u8,
+    len: usize,
+}
+
+impl AnonMapping {
+    fn new(len: usize) -> Result<Self, Error> {
+        // # Safety
+        // A fresh private mapping, owned by the returned struct
+        let ptr = unsafe {
+            libc::mmap(
+                ptr::null_mut(),
+                len.max(1),
+                libc::PROT_READ | libc::PROT_WRITE,
+                libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+                -1,
+                0,
+            )
+        };
+        if ptr == libc::MAP_FAILED {
+            return Err(Error::unknown(format!(
+                "Could not map {len} bytes for the snapshot: {}",
+                std::io::Error::last_os_error()
+            )));
+        }
+        Ok(Self {
+            ptr: ptr.cast(),
+            len: len.max(1),
+        })
+    }
+}
+
+impl AnonMapping {
+    /// The mapping, as slice of `usize`
+    #[allow(clippy::cast_ptr_alignment)]
+    fn as_usize_slice_mut(&mut self) -> &mut [usize] {
+        // # Safety
+        // The mapping is page aligned and owned by us
+        unsafe {
+            slice::from_raw_parts_mut(self.ptr.cast::<usize>(), self.len / size_of::<usize>())
+        }
+    }
+}
+
+impl Drop for AnonMapping {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr.cast(), self.len);
+        }
+    }
+}
+
+/// A contiguous range of snapshotted memory
+#[derive(Debug, Clone)]
+struct SnapshotRegion {
+    start: usize,
+    end: usize,
+    /// The offset of the copy in the snapshot data
+    offset: usize,
+    /// If this is the `brk` heap, which may shrink and grow in between
+    heap: bool,
+}
+
+/// The memory of the process, taken at the first run
+struct MemorySnapshot {
+    regions: Vec<SnapshotRegion>,
+    data: AnonMapping,
+    /// The pages written to in the last run, never more than the snapshot has pages
+    dirty: AnonMapping,
+    dirty_len: usize,
+    brk: Option<usize>,
+    /// Used to find dirtied pages, if the kernel tracks soft-dirty bits
+    pagemap: Option<PageMap>,
+    page_size: usize,
+}
+
+impl MemorySnapshot {
+    /// Copy the regions aside, tracking writes through soft-dirty bits if `soft_dirty` is set
+    fn take(regions: Vec<SnapshotRegion>, heap: bool, soft_dirty: bool) -> Result<Self, Error> {
+        let page_size = page_size();
+        let len = regions.last().map_or(0, |r| r.offset + (r.end - r.start));
+        let pages = len / page_size;
+
+        let pagemap = if soft_dirty {
+            let mut pagemap = PageMap::new()?;
+            pagemap.reserve(
+                regions
+                    .iter()
+                    .map(|r| (r.end - r.start) / page_size)
+                    .max()
+                    .unwrap_or(0),
+            );
+            Some(pagemap)
+        } else {
+            log::info!("Soft-dirty bits are not supported by this kernel, comparing pages instead");
+            None
+        };
+
+        let data = AnonMapping::new(len)?;
+        let dirty = AnonMapping::new(pages * size_of::<usize>())?;
+        for region in &regions {
+            // # Safety
+            // The regions are writable mappings of this process, the snapshot data is large enough
+            unsafe {
+                ptr::copy_nonoverlapping(
+                    region.start as *const u8,
+                    data.ptr.add(region.offset),
+                    region.end - region.start,
+                );
+            }
+        }
+
+        Ok(Self {
+            regions,
+            data,
+            dirty,
+            dirty_len: 0,
+            brk: heap.then(program_break),
+            pagemap,
+            page_size,
+        })
+    }
+
+    /// Start tracking the pages written from here on
+    fn start_tracking(&mut self) -> Result<(), Error> {
+        if self.pagemap.is_some() {
+            clear_soft_dirty()?;
+        }
+        Ok(())
+    }
+
+    /// Remember all pages written to since [`Self::start_tracking`]
+    fn collect_dirty(&mut self) -> Result<(), Error> {
+        let Self {
+            regions,
+            data,
+            dirty,
+            dirty_len,
+            pagemap,
+            page_size,
+            ..
+        } = self;
+        let page_size = *page_size;
+        let brk = program_break();
+        let dirty = dirty.as_usize_slice_mut();
+        *dirty_len = 0;
+
+        for region in regions.iter() {
+            // Parts of the heap above the current break are gone and need to come back in any case
+            let mapped_end = if region.heap {
+                region.end.min(brk.max(region.start))
+            } else {
+                region.end
+            };
+
+            if let Some(pagemap) = pagemap.as_mut() {
+                if mapped_end > region.start {
+                    for page in pagemap.dirty_pages(region.start, mapped_end)? {
+                        dirty[*dirty_len] = page;
+                        *dirty_len += 1;
+                    }
+                }
+            } else {
+                for page in (region.start..mapped_end).step_by(page_size) {
+                    // # Safety
+                    // Both the page and its copy are mapped and `page_size` long
+                    let changed = unsafe {
+                        let current = slice::from_raw_parts(page as *const u8, page_size);
+                        let saved = slice::from_raw_parts(
+                            data.ptr.add(region.offset + page - region.start),
+                            page_size,
+                        );
+                        current != saved
+                    };
+                    if changed {
+                        dirty[*dirty_len] = page;
+                        *dirty_len += 1;
+                    }
+                }
+            }
+
+            for page in (mapped_end..region.end).step_by(page_size) {
+                dirty[*dirty_len] = page;
+                *dirty_len += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Restore the pages written to in the last run, and the program break
+    ///
+    /// # Safety
+    /// Overwrites the memory of the process with the snapshot.
+    unsafe fn restore(&mut self) -> Result<(), Error> {
+        if let Some(brk) = self.brk {
+            if program_break() != brk {
+                set_program_break(brk)?;
+            }
+        }
+
+        let page_size = self.page_size;
+        let dirty_len = core::mem::take(&mut self.dirty_len);
+
+        // Both the regions and the dirty pages are sorted by address
+        let mut regions = self.regions.iter();
+        let mut region = regions.next();
+        let data = self.data.ptr;
+        for &page in &self.dirty.as_usize_slice_mut()[..dirty_len] {
+            while let Some(r) = region {
+                if page < r.end {
+                    break;
+                }
+                region = regions.next();
+            }
+            let Some(r) = region else {
+                break;
+            };
+            ptr::copy_nonoverlapping(
+                data.add(r.offset + page - r.start),
+                page as *mut u8,
+                page_size,
+            );
+        }
+        Ok(())
+    }
+}
+
+/// The configuration of which memory to snapshot
+#[derive(Debug, Clone)]
+struct SnapshotConfig {
+    main_module: bool,
+    modules: Vec<String>,
+    ranges: Vec<Range<usize>>,
+    heap: bool,
+}
+
+impl SnapshotConfig {
+    /// Find the memory regions to snapshot, sorted and without overlaps
+    fn regions(&self, page_size: usize) -> Result<Vec<SnapshotRegion>, Error> {
+        let mut modules = self.modules.clone();
+        if self.main_module {
+            modules.push(env::current_exe()?.to_string_lossy().to_string());
+        }
+
+        let mut ranges: Vec<(Range<usize>, bool)> = vec![];
+        // Anonymous mappings right after the data of a module hold its `.bss`
+        let mut prev_included = false;
+        let mut prev_end = 0;
+        for mapping in mappings()? {
+            let included = mapping.writable
+                && mapping.private
+                && !mapping.is_pseudo()
+                && match mapping.path.as_deref() {
+                    Some(_) if mapping.is_heap() => self.heap,
+                    Some(path) => modules.iter().any(|module| path.ends_with(module.as_str())),
+                    None => prev_included && prev_end == mapping.start,
+                };
+            if included {
+                ranges.push((mapping.start..mapping.end, mapping.is_heap()));
+            }
+            prev_included = included;
+            prev_end = mapping.end;
+        }
+
+        for range in &self.ranges {
+            let start = range.start / page_size * page_size;
+            let end = (range.end + page_size - 1) / page_size * page_size;
+            ranges.push((start..end, false));
+        }
+        ranges.sort_by_key(|(range, _)| range.start);
+
+        let mut regions: Vec<SnapshotRegion> = vec![];
+        let mut offset = 0;
+        for (range, heap) in ranges {
+            match regions.last_mut() {
+                Some(last) if range.start <= last.end => {
+                    if range.end > last.end {
+                        offset += range.end - last.end;
+                        last.end = range.end;
+                    }
+                    last.heap |= heap;
+                }
+                _ => {
+                    regions.push(SnapshotRegion {
+                        start: range.start,
+                        end: range.end,
+                        offset,
+                        heap,
+                    });
+                    offset += range.end - range.start;
+                }
+            }
+        }
+        Ok(regions)
+    }
+}
+
+/// An executor wrapping an in-process executor, such as the [`crate::executors::InProcessExecutor`],
+/// and restoring the memory of the target after each run instead of forking.
+///
+/// By default, the writable data and `.bss` of the main executable are restored.
+/// Pages are only restored if they were written to during a run, so static data of the fuzzer itself living
+/// on the same pages as target data will be reset as well.
+/// Restoring the `brk` heap, see [`SnapshotInProcessExecutor::heap`], is only sound if the fuzzer itself
+/// does not allocate from it, for example by using an `mmap` based `#[global_allocator]`,
+/// and if the allocator state of the target, such as the `.data` of `libc`, is part of the snapshot.
+pub struct SnapshotInProcessExecutor<E> {
+    executor: E,
+    config: SnapshotConfig,
+    close_fds: bool,
+    snapshot: Option<MemorySnapshot>,
+    /// The file descriptors open before the current run
+    fds: Vec<RawFd>,
+}
+
+impl<E> Debug for SnapshotInProcessExecutor<E>
+where
+    E: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SnapshotInProcessExecutor")
+            .field("executor", &self.executor)
+            .field("config", &self.config)
+            .field("close_fds", &self.close_fds)
+            .field(
+                "snapshot_regions",
+                &self.snapshot.as_ref().map(|s| s.regions.len()),
+            )
+            .finish_non_exhaustive()
+    }
+}
+
+impl<E> SnapshotInProcessExecutor<E> {
+    /// Wrap an in-process executor, snapshotting the main executable at the first run
+    pub fn new(executor: E) -> Self {
+        Self {
+            executor,
+            config: SnapshotConfig {
+                main_module: true,
+                modules: vec![],
+                ranges: vec![],
+                heap: false,
+            },
+            close_fds: false,
+            snapshot: None,
+            fds: vec![],
+        }
+    }
+
+    /// Whether to snapshot the writable memory of the main executable (default: `true`)
+    #[must_use]
+    pub fn main_module(mut self, snapshot: bool) -> Self {
+        self.config.main_module = snapshot;
+        self
+    }
+
+    /// Also snapshot the writable memory of the module whose path ends with `module`, e.g., `libtarget.so`
+    #[must_use]
+    pub fn module<M>(mut self, module: M) -> Self
+    where
+        M: Into<String>,
+    {
+        self.config.modules.push(module.into());
+        self
+    }
+
+    /// Also snapshot the given range of writable memory, extended to page boundaries
+    #[must_use]
+    pub fn range(mut self, range: Range<usize>) -> Self {
+        self.config.ranges.push(range);
+        self
+    }
+
+    /// Whether to restore the `brk` heap and program break (default: `false`)
+    #[must_use]
+    pub fn heap(mut self, restore: bool) -> Self {
+        self.config.heap = restore;
+        self
+    }
+
+    /// Whether to close the file descriptors left open by the target after each run (default: `false`)
+    ///
+    /// Every file descriptor opened during a run is considered to belong to the target,
+    /// so this is only sound if no other thread of the fuzzer opens files concurrently.
+    #[must_use]
+    pub fn close_fds(mut self, close: bool) -> Self {
+        self.close_fds = close;
+        self
+    }
+
+    /// The wrapped executor
+    pub fn inner(&self) -> &E {
+        &self.executor
+    }
+
+    /// The wrapped executor, mutable
+    pub fn inner_mut(&mut self) -> &mut E {
+        &mut self.executor
+    }
+
+    /// Take the snapshot, or restore the pages dirtied in the last run
+    fn prepare(&mut self) -> Result<(), Error> {
+        if let Some(snapshot) = self.snapshot.as_mut() {
+            // # Safety
+            // Restoring the state of the target is the point of this executor
+            unsafe { snapshot.restore()? };
+        } else {
+            let regions = self.config.regions(page_size())?;
+            self.snapshot = Some(MemorySnapshot::take(
+                regions,
+                self.config.heap,
+                soft_dirty_supported(),
+            )?);
+        }
+
+        if self.close_fds {
+            self.fds = open_fds()?;
+        }
+        self.snapshot.as_mut().unwrap().start_tracking()
+    }
+
+    /// Remember the dirtied pages and close the file descriptors opened by the target
+    fn finish(&mut self) -> Result<(), Error> {
+        if let Some(snapshot) = self.snapshot.as_mut() {
+            snapshot.collect_dirty()?;
+        }
+
+        if self.close_fds {
+            for fd in open_fds()? {
+                if !self.fds.contains(&fd) {
+                    // # Safety
+                    // The fd was opened by the target during the last run
+                    unsafe {
+                        libc::close(fd);
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<E, EM, Z> Executor<EM, Z> for SnapshotInProcessExecutor<E>
+where
+    E: Executor<EM, Z>,
+    EM: UsesState<State = E::State>,
+    Z: UsesState<State = E::State>,
+{
+    fn run_target(
+        &mut self,
+        fuzzer: &mut Z,
+        state: &mut Self::State,
+        mgr: &mut EM,
+        input: &Self::Input,
+    ) -> Result<ExitKind, Error> {
+        self.prepare()?;
+        let ret = self.executor.run_target(fuzzer, state, mgr, input);
+        self.finish()?;
+        ret
+    }
+
+    fn post_run_reset(&mut self) {
+        self.executor.post_run_reset();
+    }
+}
+
+impl<E> UsesState for SnapshotInProcessExecutor<E>
+where
+    E: UsesState,
+{
+    type State = E::State;
+}
+
+impl<E> UsesObservers for SnapshotInProcessExecutor<E>
+where
+    E: UsesObservers,
+{
+    type Observers = E::Observers;
+}
+
+impl<E> HasObservers for SnapshotInProcessExecutor<E>
+where
+    E: HasObservers,
+{
+    #[inline]
+    fn observers(&self) -> &Self::Observers {
+        self.executor.observers()
+    }
+
+    #[inline]
+    fn observers_mut(&mut self) -> &mut Self::Observers {
+        self.executor.observers_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::{
+        cell::UnsafeCell,
+        marker::PhantomData,
+        sync::atomic::{AtomicI32, AtomicUsize, Ordering},
+    };
+
+    use libafl_bolts::{
+        os::procfs::{page_size, program_break, soft_dirty_supported},
+        AsSlice,
+    };
+
+    use super::{MemorySnapshot, SnapshotConfig, SnapshotInProcessExecutor, SnapshotRegion};
+    use crate::{
+        events::NopEventManager,
+        executors::{Executor, ExitKind},
+        inputs::{BytesInput, HasTargetBytes},
+        state::{NopState, UsesState},
+        Error, NopFuzzer,
+    };
+
+    /// Target data on a page of its own, so that the snapshot covers nothing else
+    #[repr(C, align(4096))]
+    struct TargetPage(UnsafeCell<[u8; 4096]>);
+
+    unsafe impl Sync for TargetPage {}
+
+    static TARGET_PAGE: TargetPage = TargetPage(UnsafeCell::new([0; 4096]));
+
+    static COMPARED_PAGE: TargetPage = TargetPage(UnsafeCell::new([0; 4096]));
+
+    /// Run `test` in a forked child, where no other test thread touches the global state being restored
+    fn in_child<F>(test: F)
+    where
+        F: FnOnce() -> bool,
+    {
+        unsafe {
+            let pid = libc::fork();
+            assert!(pid >= 0, "fork failed");
+            if pid == 0 {
+                libc::_exit(i32::from(!test()));
+            }
+            let mut status = 0;
+            assert_eq!(libc::waitpid(pid, &mut status, 0), pid);
+            assert!(libc::WIFEXITED(status) && libc::WEXITSTATUS(status) == 0);
+        }
+    }
+
+    /// Runs a function as the target
+    #[derive(Debug)]
+    struct FnExecutor {
+        target: fn() -> ExitKind,
+        phantom: PhantomData<NopState<BytesInput>>,
+    }
+
+    impl FnExecutor {
+        fn new(target: fn() -> ExitKind) -> Self {
+            Self {
+                target,
+                phantom: PhantomData,
+            }
+        }
+    }
+
+    impl UsesState for FnExecutor {
+        type State = NopState<BytesInput>;
+    }
+
+    impl<EM, Z> Executor<EM, Z> for FnExecutor
+    where
+        EM: UsesState<State = Self::State>,
+        Z: UsesState<State = Self::State>,
+    {
+        fn run_target(
+            &mut self,
+            _fuzzer: &mut Z,
+            _state: &mut Self::State,
+            _mgr: &mut EM,
+            _input: &BytesInput,
+        ) -> Result<ExitKind, Error> {
+            Ok((self.target)())
+        }
+    }
+
+    /// Run the executor three times, expecting [`ExitKind::Ok`] each time
+    fn run_thrice<E>(executor: &mut E) -> bool
+    where
+        E: Executor<
+            NopEventManager<NopState<BytesInput>>,
+            NopFuzzer<BytesInput>,
+            State = NopState<BytesInput>,
+        >,
+    {
+        let mut fuzzer = NopFuzzer::new();
+        let mut state = NopState::new();
+        let mut mgr = NopEventManager::new();
+        let input = BytesInput::new(vec![]);
+        (0..3).all(|_| {
+            executor
+                .run_target(&mut fuzzer, &mut state, &mut mgr, &input)
+                .is_ok_and(|exit_kind| exit_kind == ExitKind::Ok)
+        })
+    }
+
+    /// Writes the input to the target page, crashing if it is not clean
+    #[derive(Debug)]
+    struct DirtyingExecutor {
+        phantom: PhantomData<NopState<BytesInput>>,
+    }
+
+    impl UsesState for DirtyingExecutor {
+        type State = NopState<BytesInput>;
+    }
+
+    impl<EM, Z> Executor<EM, Z> for DirtyingExecutor
+    where
+        EM: UsesState<State = Self::State>,
+        Z: UsesState<State = Self::State>,
+    {
+        fn run_target(
+            &mut self,
+            _fuzzer: &mut Z,
+            _state: &mut Self::State,
+            _mgr: &mut EM,
+            input: &BytesInput,
+        ) -> Result<ExitKind, Error> {
+            let target = unsafe { &mut *TARGET_PAGE.0.get() };
+            let clean = target.iter().all(|b| *b == 0);
+            let bytes = input.target_bytes();
+            target[..bytes.as_slice().len()].copy_from_slice(bytes.as_slice());
+            Ok(if clean { ExitKind::Ok } else { ExitKind::Crash })
+        }
+    }
+
+    #[test]
+    fn test_snapshot_inprocess() {
+        let start = TARGET_PAGE.0.get() as usize;
+        let mut executor = SnapshotInProcessExecutor::new(DirtyingExecutor {
+            phantom: PhantomData,
+        })
+        .main_module(false)
+        .range(start..start + 4096);
+
+        let mut fuzzer = NopFuzzer::new();
+        let mut state = NopState::new();
+        let mut mgr = NopEventManager::new();
+        let input = BytesInput::new(b"dirty".to_vec());
+        for _ in 0..3 {
+            let exit_kind = executor
+                .run_target(&mut fuzzer, &mut state, &mut mgr, &input)
+                .unwrap();
+            assert_eq!(exit_kind, ExitKind::Ok);
+            assert_eq!(unsafe { &*TARGET_PAGE.0.get() }[..5], *b"dirty");
+        }
+    }
+
+    #[test]
+    fn test_snapshot_main_module() {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        in_child(|| {
+            let mut executor = SnapshotInProcessExecutor::new(FnExecutor::new(|| {
+                if COUNTER.fetch_add(1, Ordering::Relaxed) == 0 {
+                    ExitKind::Ok
+                } else {
+                    ExitKind::Crash
+                }
+            }));
+            run_thrice(&mut executor) && COUNTER.load(Ordering::Relaxed) == 1
+        });
+    }
+
+    #[test]
+    fn test_snapshot_page_compare() {
+        let start = COMPARED_PAGE.0.get() as usize;
+        let regions = vec![SnapshotRegion {
+            start,
+            end: start + 4096,
+            offset: 0,
+            heap: false,
+        }];
+        let mut snapshot = MemorySnapshot::take(regions, false, false).unwrap();
+        assert!(snapshot.pagemap.is_none());
+
+        snapshot.start_tracking().unwrap();
+        snapshot.collect_dirty().unwrap();
+        assert_eq!(snapshot.dirty_len, 0);
+
+        for _ in 0..2 {
+            snapshot.start_tracking().unwrap();
+            unsafe { (*COMPARED_PAGE.0.get())[42] = 1 };
+            snapshot.collect_dirty().unwrap();
+            assert_eq!(snapshot.dirty_len, 1);
+            unsafe { snapshot.restore().unwrap() };
+            assert_eq!(unsafe { &*COMPARED_PAGE.0.get() }[42], 0);
+        }
+    }
+
+    #[test]
+    fn test_snapshot_heap() {
+        in_child(|| unsafe {
+            let page_size = page_size();
+            let grow = |len: usize| {
+                let old = libc::sbrk(libc::intptr_t::try_from(len).unwrap());
+                (old as isize != -1).then_some(old.cast::<u8>())
+            };
+            // Memory of our own at the top of the heap, so that the allocator is not disturbed
+            let Some(owned) = grow(page_size) else {
+                return false;
+            };
+            owned.write_bytes(0, page_size);
+            let brk = program_break();
+
+            let config = SnapshotConfig {
+                main_module: false,
+                modules: vec![],
+                ranges: vec![],
+                heap: true,
+            };
+            let Ok(regions) = config.regions(page_size) else {
+                return false;
+            };
+            let heap_covered = regions.iter().any(|r| {
+                r.heap && r.start <= owned as usize && owned as usize + page_size <= r.end
+            });
+            let Ok(mut snapshot) = MemorySnapshot::take(regions, true, soft_dirty_supported())
+            else {
+                return false;
+            };
+
+            let mut restored = true;
+            for _ in 0..2 {
+                restored &= snapshot.start_tracking().is_ok();
+                owned.write_bytes(0xff, page_size);
+                let Some(grown) = grow(4 * page_size) else {
+                    return false;
+                };
+                grown.write_bytes(0xff, 4 * page_size);
+
+                restored &= snapshot.collect_dirty().is_ok() && snapshot.restore().is_ok();
+                restored &= program_break() == brk && *owned == 0 && *owned.add(page_size - 1) == 0;
+            }
+            heap_covered && restored
+        });
+    }
+
+    #[test]
+    fn test_snapshot_close_fds() {
+        static OPENED: AtomicI32 = AtomicI32::new(-1);
+
+        in_child(|| {
+            let mut executor = SnapshotInProcessExecutor::new(FnExecutor::new(|| {
+                let fd = unsafe { libc::open(b"/dev/null\0".as_ptr().cast(), libc::O_RDONLY) };
+                OPENED.store(fd, Ordering::Relaxed);
+                ExitKind::Ok
+            }))
+            .main_module(false)
+            .close_fds(true);
+            let kept = unsafe { libc::open(b"/dev/null\0".as_ptr().cast(), libc::O_RDONLY) };
+
+            run_thrice(&mut executor)
+                && unsafe { libc::fcntl(OPENED.load(Ordering::Relaxed), libc::F_GETFD) } == -1
+                && unsafe { libc::fcntl(kept, libc::F_GETFD) } != -1
+        });
+    }
+}
@@ -0,0 +1,215 @@
+//! The ``MaxValueFeedback`` keeps the inputs that raise the highest value seen so far of an observer,
+//! such as the stack depth, to push the target towards deep recursion and stack exhaustion
+
+use alloc::string::{String, ToString};
+use core::{fmt::Debug, marker::PhantomData};
+
+use libafl_bolts::Named;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    events::EventFirer,
+    executors::ExitKind,
+    feedbacks::{Feedback, HasObserverName},
+    inputs::UsesInput,
+    observers::{ObserverWithValue, ObserversTuple},
+    state::{HasClientPerfMonitor, HasNamedMetadata},
+    Error,
+};
+
+/// The prefix of the metadata names
+pub const MAXVALUEFEEDBACK_PREFIX: &str = "maxvaluefeedback_metadata_";
+
+/// The state of [`MaxValueFeedback`]
+#[derive(Default, Serialize, Deserialize, Clone, Debug)]
+pub struct MaxValueFeedbackMetadata {
+    /// The highest value seen so far
+    pub max_value: u64,
+}
+
+libafl_bolts::impl_serdeany!(MaxValueFeedbackMetadata);
+
+impl MaxValueFeedbackMetadata {
+    /// Create a new [`MaxValueFeedbackMetadata`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reset the highest value seen so far
+    pub fn reset(&mut self) -> Result<(), Error> {
+        self.max_value = 0;
+        Ok(())
+    }
+}
+
+/// A [`MaxValueFeedback`] considers interesting the runs in which the value of an
+/// [`ObserverWithValue`] exceeds the highest one seen so far
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct MaxValueFeedback<O, S> {
+    name: String,
+    observer_name: String,
+    o_type: PhantomData<(O, S)>,
+}
+
+impl<O, S> Feedback<S> for MaxValueFeedback<O, S>
+where
+    O: ObserverWithValue + Named + Debug,
+    S: UsesInput + Debug + HasNamedMetadata + HasClientPerfMonitor,
+{
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error> {
+        state.add_named_metadata(MaxValueFeedbackMetadata::new(), &self.name);
+        Ok(())
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    fn is_interesting<EM, OT>(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _input: &<S as UsesInput>::Input,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let observer = observers
+            .match_name::<O>(&self.observer_name)
+            .expect("A MaxValueFeedback needs an ObserverWithValue");
+
+        let Some(value) = observer.value() else {
+            return Ok(false);
+        };
+
+        let meta = state
+            .named_metadata_map_mut()
+            .get_mut::<MaxValueFeedbackMetadata>(&self.name)
+            .unwrap();
+        if value > meta.max_value {
+            meta.max_value = value;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+}
+
+impl<O, S> Named for MaxValueFeedback<O, S> {
+    #[inline]
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<O, S> HasObserverName for MaxValueFeedback<O, S> {
+    #[inline]
+    fn observer_name(&self) -> &str {
+        &self.observer_name
+    }
+}
+
+impl<O, S> MaxValueFeedback<O, S>
+where
+    O: ObserverWithValue + Named + Debug,
+{
+    /// Returns a new [`MaxValueFeedback`].
+    /// Setting an observer name that doesn't exist would eventually trigger a panic.
+    #[must_use]
+    pub fn with_names(name: &str, observer_name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            observer_name: observer_name.to_string(),
+            o_type: PhantomData,
+        }
+    }
+
+    /// Returns a new [`MaxValueFeedback`].
+    #[must_use]
+    pub fn new(observer: &O) -> Self {
+        Self::with_names(
+            &(MAXVALUEFEEDBACK_PREFIX.to_string() + observer.name()),
+            observer.name(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::string::{String, ToString};
+
+    use libafl_bolts::{rands::StdRand, tuples::tuple_list, Named};
+
+    use crate::{
+        corpus::InMemoryCorpus,
+        events::NopEventManager,
+        executors::ExitKind,
+        feedbacks::{ConstFeedback, Feedback, MaxValueFeedback},
+        inputs::{BytesInput, UsesInput},
+        observers::{Observer, ObserverWithValue},
+        state::StdState,
+    };
+
+    #[derive(Debug)]
+    struct DepthObserver {
+        name: String,
+        depth: Option<u64>,
+    }
+
+    impl<S> Observer<S> for DepthObserver where S: UsesInput {}
+
+    impl Named for DepthObserver {
+        fn name(&self) -> &str {
+            &self.name
+        }
+    }
+
+    impl ObserverWithValue for DepthObserver {
+        fn value(&self) -> Option<u64> {
+            self.depth
+        }
+    }
+
+    #[test]
+    fn test_max_value_feedback() {
+        let observer = DepthObserver {
+            name: "depth".to_string(),
+            depth: None,
+        };
+        let mut feedback = MaxValueFeedback::new(&observer);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+
+        let mut observers = tuple_list!(observer);
+        let input = BytesInput::new(vec![0]);
+        for (depth, interesting) in [
+            (None, false),
+            (Some(16), true),
+            (Some(8), false),
+            (Some(16), false),
+            (Some(32), true),
+        ] {
+            observers.0.depth = depth;
+            assert_eq!(
+                feedback
+                    .is_interesting(
+                        &mut state,
+                        &mut NopEventManager::new(),
+                        &input,
+                        &observers,
+                        &ExitKind::Ok
+                    )
+                    .unwrap(),
+                interesting
+            );
+        }
+    }
+}
@@ -21,6 +21,14 @@ pub use new_hash_feedback::NewHashFeedback;
 #[cfg(feature = "std")]
 pub use new_hash_feedback::NewHashFeedbackMetadata;
 
+pub mod max_value;
+pub use max_value::{MaxValueFeedback, MaxValueFeedbackMetadata};
+
+pub mod state_coverage;
+pub use state_coverage::{
+    StateCoverageFeedback, StateGraphMetadata, StateInfo, StateSequenceMetadata,
+};
+
 #[cfg(feature = "nautilus")]
 pub mod nautilus;
 use alloc::string::{String, ToString};
@@ -883,6 +891,60 @@ impl Default for TimeoutFeedback {
 /// A feedback factory for timeout feedbacks
 pub type TimeoutFeedbackFactory = DefaultFeedbackFactory<TimeoutFeedback>;
 
+/// An [`OomFeedback`] reports as interesting if the target ran out of memory,
+/// or into another of its resource limits.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct OomFeedback {}
+
+impl<S> Feedback<S> for OomFeedback
+where
+    S: UsesInput + HasClientPerfMonitor,
+{
+    #[allow(clippy::wrong_self_convention)]
+    fn is_interesting<EM, OT>(
+        &mut self,
+        _state: &mut S,
+        _manager: &mut EM,
+        _input: &S::Input,
+        _observers: &OT,
+        exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        if let ExitKind::Oom = exit_kind {
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+}
+
+impl Named for OomFeedback {
+    #[inline]
+    fn name(&self) -> &str {
+        "OomFeedback"
+    }
+}
+
+impl OomFeedback {
+    /// Returns a new [`OomFeedback`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+impl Default for OomFeedback {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A feedback factory for oom feedbacks
+pub type OomFeedbackFactory = DefaultFeedbackFactory<OomFeedback>;
+
 /// Nop feedback that annotates execution time in the new testcase, if any
 /// for this Feedback, the testcase is never interesting (use with an OR).
 /// It decides, if the given [`TimeObserver`] value of a run is interesting.
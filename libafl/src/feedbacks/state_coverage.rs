@@ -0,0 +1,290 @@
+//! The [`StateCoverageFeedback`] keeps inputs reaching new states, or new transitions between states,
+//! of the target's protocol state machine, as inferred from the response codes, as in `AFLNet`.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::Write;
+
+use hashbrown::{HashMap, HashSet};
+use libafl_bolts::Named;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::Testcase,
+    events::EventFirer,
+    executors::ExitKind,
+    feedbacks::{Feedback, HasObserverName},
+    inputs::UsesInput,
+    observers::{ObserversTuple, ResponseCodeObserver},
+    state::{HasClientPerfMonitor, HasMetadata},
+    Error,
+};
+
+/// The state every sequence of responses starts in, before the first message
+pub const INITIAL_STATE: u32 = 0;
+
+/// What is known about a single state of the state machine
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+pub struct StateInfo {
+    /// How many corpus entries reach this state
+    pub paths: u64,
+    /// How often a corpus entry got scheduled to explore this state
+    pub selected_times: u64,
+    /// How many executions were spent exploring this state
+    pub fuzzs: u64,
+    /// How many new corpus entries were found while exploring this state
+    pub paths_discovered: u64,
+}
+
+/// The state machine of the target, as discovered so far
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct StateGraphMetadata {
+    states: HashMap<u32, StateInfo>,
+    /// How many corpus entries take each transition
+    transitions: HashMap<(u32, u32), u64>,
+    /// The state the current corpus entry got scheduled to explore, if any
+    target_state: Option<u32>,
+}
+
+libafl_bolts::impl_serdeany!(StateGraphMetadata);
+
+impl StateGraphMetadata {
+    /// Creates a new, empty, [`struct@StateGraphMetadata`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The states discovered so far
+    #[must_use]
+    pub fn states(&self) -> &HashMap<u32, StateInfo> {
+        &self.states
+    }
+
+    /// The states discovered so far, mutable
+    pub fn states_mut(&mut self) -> &mut HashMap<u32, StateInfo> {
+        &mut self.states
+    }
+
+    /// The transitions between states discovered so far, with the number of corpus entries taking them
+    #[must_use]
+    pub fn transitions(&self) -> &HashMap<(u32, u32), u64> {
+        &self.transitions
+    }
+
+    /// The state the current corpus entry got scheduled to explore, if any
+    #[must_use]
+    pub fn target_state(&self) -> Option<u32> {
+        self.target_state
+    }
+
+    /// Set the state the current corpus entry is explored for
+    pub fn set_target_state(&mut self, target_state: Option<u32>) {
+        self.target_state = target_state;
+    }
+
+    /// Whether the `sequence` of states reaches a state, or takes a transition, not seen before
+    #[must_use]
+    pub fn is_novel(&self, sequence: &[u32]) -> bool {
+        sequence
+            .iter()
+            .any(|state| !self.states.contains_key(state))
+            || sequence
+                .windows(2)
+                .any(|pair| !self.transitions.contains_key(&(pair[0], pair[1])))
+    }
+
+    /// Account for a new corpus entry going through the `sequence` of states
+    pub fn add_sequence(&mut self, sequence: &[u32]) {
+        let states: HashSet<u32> = sequence.iter().copied().collect();
+        for state in states {
+            self.states.entry(state).or_default().paths += 1;
+        }
+        let transitions: HashSet<(u32, u32)> =
+            sequence.windows(2).map(|pair| (pair[0], pair[1])).collect();
+        for transition in transitions {
+            *self.transitions.entry(transition).or_default() += 1;
+        }
+    }
+
+    /// The state machine in the DOT format of graphviz.
+    /// Each state is labeled with its corpus entries, each transition with the entries taking it.
+    #[must_use]
+    pub fn to_dot(&self) -> String {
+        let mut states: Vec<_> = self.states.iter().collect();
+        states.sort_unstable_by_key(|(state, _)| **state);
+        let mut transitions: Vec<_> = self.transitions.iter().collect();
+        transitions.sort_unstable_by_key(|(transition, _)| **transition);
+
+        let mut dot = String::from("digraph state_machine {\n");
+        for (state, info) in states {
+            writeln!(
+                dot,
+                "    {state} [label=\"{state}\\n{} paths\"];",
+                info.paths
+            )
+            .unwrap();
+        }
+        for ((from, to), paths) in transitions {
+            writeln!(dot, "    {from} -> {to} [label=\"{paths}\"];").unwrap();
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// The states a corpus entry goes through, starting with [`INITIAL_STATE`]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct StateSequenceMetadata {
+    /// The sequence of states
+    pub states: Vec<u32>,
+}
+
+libafl_bolts::impl_serdeany!(StateSequenceMetadata);
+
+impl StateSequenceMetadata {
+    /// Creates a new [`struct@StateSequenceMetadata`]
+    #[must_use]
+    pub fn new(states: Vec<u32>) -> Self {
+        Self { states }
+    }
+
+    /// Whether the corpus entry goes through `state`
+    #[must_use]
+    pub fn reaches(&self, state: u32) -> bool {
+        self.states.contains(&state)
+    }
+}
+
+/// A [`StateCoverageFeedback`] considers an input interesting if the response codes observed by
+/// a [`ResponseCodeObserver`] reach a state, or a transition between states, that no corpus entry did before.
+///
+/// The state machine is kept in the [`struct@StateGraphMetadata`] of the fuzzer state,
+/// the states of each corpus entry in its [`struct@StateSequenceMetadata`].
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct StateCoverageFeedback {
+    name: String,
+    observer_name: String,
+}
+
+impl StateCoverageFeedback {
+    /// Creates a new [`StateCoverageFeedback`] for the states observed by `observer`
+    #[must_use]
+    pub fn new(observer: &ResponseCodeObserver) -> Self {
+        Self {
+            name: "StateCoverageFeedback".to_string(),
+            observer_name: observer.name().to_string(),
+        }
+    }
+
+    fn state_sequence<OT, S>(&self, observers: &OT) -> Result<Vec<u32>, Error>
+    where
+        OT: ObserversTuple<S>,
+        S: UsesInput,
+    {
+        let observer = observers
+            .match_name::<ResponseCodeObserver>(&self.observer_name)
+            .ok_or_else(|| Error::key_not_found("ResponseCodeObserver not found".to_string()))?;
+        let mut sequence = Vec::with_capacity(observer.codes().len() + 1);
+        sequence.push(INITIAL_STATE);
+        sequence.extend_from_slice(observer.codes());
+        Ok(sequence)
+    }
+}
+
+impl<S> Feedback<S> for StateCoverageFeedback
+where
+    S: UsesInput + HasClientPerfMonitor + HasMetadata,
+{
+    fn init_state(&mut self, state: &mut S) -> Result<(), Error> {
+        if !state.has_metadata::<StateGraphMetadata>() {
+            state.add_metadata(StateGraphMetadata::new());
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::wrong_self_convention)]
+    fn is_interesting<EM, OT>(
+        &mut self,
+        state: &mut S,
+        _manager: &mut EM,
+        _input: &S::Input,
+        observers: &OT,
+        _exit_kind: &ExitKind,
+    ) -> Result<bool, Error>
+    where
+        EM: EventFirer<State = S>,
+        OT: ObserversTuple<S>,
+    {
+        let sequence = self.state_sequence(observers)?;
+        Ok(state.metadata::<StateGraphMetadata>()?.is_novel(&sequence))
+    }
+
+    fn append_metadata<OT>(
+        &mut self,
+        state: &mut S,
+        observers: &OT,
+        testcase: &mut Testcase<S::Input>,
+    ) -> Result<(), Error>
+    where
+        OT: ObserversTuple<S>,
+    {
+        let sequence = self.state_sequence(observers)?;
+        state
+            .metadata_mut::<StateGraphMetadata>()?
+            .add_sequence(&sequence);
+        testcase.add_metadata(StateSequenceMetadata::new(sequence));
+        Ok(())
+    }
+}
+
+impl Named for StateCoverageFeedback {
+    #[inline]
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl HasObserverName for StateCoverageFeedback {
+    #[inline]
+    fn observer_name(&self) -> &str {
+        &self.observer_name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::feedbacks::state_coverage::StateGraphMetadata;
+
+    #[test]
+    fn test_state_graph() {
+        let mut graph = StateGraphMetadata::new();
+        assert!(graph.is_novel(&[0, 220, 331]));
+        graph.add_sequence(&[0, 220, 331, 220]);
+
+        assert!(!graph.is_novel(&[0, 220]));
+        assert!(!graph.is_novel(&[0, 220, 331, 220, 331]));
+        // A known state, through a new transition
+        assert!(graph.is_novel(&[0, 331]));
+        assert!(graph.is_novel(&[0, 220, 530]));
+
+        graph.add_sequence(&[0, 220]);
+        assert_eq!(graph.states()[&220].paths, 2);
+        assert_eq!(graph.states()[&331].paths, 1);
+        assert_eq!(graph.transitions()[&(0, 220)], 2);
+        assert_eq!(
+            graph.to_dot(),
+            "digraph state_machine {\n    0 [label=\"0\\n2 paths\"];\n    220 [label=\"220\\n2 paths\"];\n    331 [label=\"331\\n1 paths\"];\n    0 -> 220 [label=\"2\"];\n    220 -> 331 [label=\"1\"];\n    331 -> 220 [label=\"1\"];\n}\n"
+        );
+    }
+}
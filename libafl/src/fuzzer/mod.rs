@@ -30,6 +30,11 @@ use crate::{
     Error,
 };
 
+#[cfg(all(feature = "std", unix))]
+pub mod threaded;
+#[cfg(all(feature = "std", unix))]
+pub use threaded::ThreadedInProcessFuzzer;
+
 /// Send a monitor update all 15 (or more) seconds
 const STATS_TIMEOUT_DEFAULT: Duration = Duration::from_secs(15);
 
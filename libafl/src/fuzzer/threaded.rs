@@ -0,0 +1,571 @@
+//! Multi-threaded in-process fuzzing, for thread-safe harnesses.
+//!
+//! The [`ThreadedInProcessFuzzer`] runs a number of worker threads in one process, all fuzzing the same state:
+//! they share the corpus, the scheduler, and the feedback state, such as the history map of a `MapFeedback`.
+//! Each worker has its mutator and observers of its own, usually a map observer on a thread-local coverage map,
+//! see `set_thread_edges_map` with the `thread_local_maps` feature of `libafl_targets`.
+//! Its other maps, such as the value profile and cmplog maps, are not thread-local, so observers on them
+//! see the runs of all workers mixed.
+//! The workers only hold the lock on the shared state to pick and mutate an input, and to evaluate its run,
+//! the harness runs in parallel.
+//!
+//! This saves the memory of one process per core, compared to the `Launcher`.
+//! When the harness crashes, the input of the faulting thread is stored as a solution, and the process exits,
+//! for the restarting event manager to respawn it. Timeouts are not detected, and incoming events are not processed.
+
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::{
+    cell::Cell,
+    ffi::c_void,
+    fmt::{self, Debug, Formatter},
+    mem::{self, transmute},
+    ptr::{self, addr_of, addr_of_mut},
+};
+use std::{
+    io::{BufWriter, Write},
+    panic,
+    sync::{Mutex, MutexGuard, Once, PoisonError},
+    thread,
+};
+
+#[cfg(not(miri))]
+use libafl_bolts::os::unix_signals::setup_signal_handler;
+use libafl_bolts::os::unix_signals::{ucontext_t, Handler, Signal};
+use libc::siginfo_t;
+
+use super::{ExecutionProcessor, HasScheduler, STATS_TIMEOUT_DEFAULT};
+use crate::{
+    corpus::Corpus,
+    events::{EventRestarter, ProgressReporter},
+    executors::ExitKind,
+    mutators::Mutator,
+    observers::ObserversTuple,
+    schedulers::Scheduler,
+    state::{HasClientPerfMonitor, HasCorpus, HasExecutions, HasLastReportTime, HasMetadata},
+    Error,
+};
+
+/// The size of the signal stack of each worker, to handle stack overflows in the harness
+const WORKER_SIGNAL_STACK_SIZE: usize = 2 << 22;
+
+/// The state all workers fuzz, behind one lock
+struct Shared<EM, S, Z> {
+    fuzzer: *mut Z,
+    state: *mut S,
+    mgr: *mut EM,
+    /// The runs left to do, or `None` to fuzz forever
+    iters_left: Option<u64>,
+    /// Set once a worker failed, so that the others stop as well
+    stopped: bool,
+}
+
+// The fuzzer, the state and the event manager are only ever used with the lock held
+unsafe impl<EM, S, Z> Send for Shared<EM, S, Z> {}
+
+impl<EM, S, Z> Shared<EM, S, Z> {
+    /// The fuzzer, the state and the event manager, while the lock is held
+    #[allow(clippy::mut_from_ref)]
+    unsafe fn parts(&self) -> (&mut Z, &mut S, &mut EM) {
+        (&mut *self.fuzzer, &mut *self.state, &mut *self.mgr)
+    }
+}
+
+/// Locks the shared state, even after a worker panicked with it
+fn lock<T>(shared: &Mutex<T>) -> MutexGuard<'_, T> {
+    shared.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The run of a worker, for the crash handler to find the input of the faulting thread
+struct WorkerRun<I, OT> {
+    input: *const I,
+    observers: *mut OT,
+}
+
+std::thread_local! {
+    /// The [`WorkerRun`] of the current thread, while it runs the harness
+    static CURRENT_RUN: Cell<*mut c_void> = const { Cell::new(ptr::null_mut()) };
+}
+
+/// Reports the crash of a [`WorkerRun`] to the shared state
+type CrashHandlerFuncPtr = unsafe fn(run: *mut c_void, shared: *const c_void);
+
+/// The crash handler of all workers
+#[derive(Debug)]
+struct ThreadedHandlerData {
+    /// The lock of the shared state
+    shared: *const c_void,
+    /// A [`CrashHandlerFuncPtr`] for the types of the current fuzzer
+    crash_handler: *const c_void,
+}
+
+static mut THREADED_HANDLER_DATA: ThreadedHandlerData = ThreadedHandlerData {
+    shared: ptr::null(),
+    crash_handler: ptr::null(),
+};
+
+impl ThreadedHandlerData {
+    /// Reports the crash of the current thread, if it was running the harness.
+    /// Returns `false` if it was not.
+    unsafe fn report_current_crash(&self) -> bool {
+        let run = CURRENT_RUN.with(|run| run.replace(ptr::null_mut()));
+        if run.is_null() || self.crash_handler.is_null() {
+            return false;
+        }
+        let func: CrashHandlerFuncPtr = transmute(self.crash_handler);
+        (func)(run, self.shared);
+        true
+    }
+}
+
+impl Handler for ThreadedHandlerData {
+    fn handle(&mut self, signal: Signal, info: siginfo_t, context: &mut ucontext_t) {
+        log::error!("Crashed with {signal} in {:?}", thread::current().id());
+        let mut bsod = Vec::new();
+        {
+            let mut writer = BufWriter::new(&mut bsod);
+            libafl_bolts::minibsod::generate_minibsod(&mut writer, signal, info, context).unwrap();
+            writer.flush().unwrap();
+        }
+        log::error!("{}", String::from_utf8_lossy(&bsod));
+        unsafe {
+            if !self.report_current_crash() {
+                log::error!(
+                    "The crash happened outside of the harness... Bug in the fuzzer? Exiting."
+                );
+            }
+            libc::_exit(128 + (signal as i32));
+        }
+    }
+
+    fn signals(&self) -> Vec<Signal> {
+        vec![
+            Signal::SigAbort,
+            Signal::SigBus,
+            Signal::SigPipe,
+            Signal::SigFloatingPointException,
+            Signal::SigIllegalInstruction,
+            Signal::SigSegmentationFault,
+            Signal::SigTrap,
+        ]
+    }
+}
+
+/// Stores the input of a crashed [`WorkerRun`] as a solution, and the state for the restart.
+/// The lock is never released again, so the other workers stop until the process exits.
+unsafe fn report_crash<EM, OT, S, Z>(run: *mut c_void, shared: *const c_void)
+where
+    EM: ProgressReporter<State = S> + EventRestarter<State = S>,
+    OT: ObserversTuple<S>,
+    S: HasCorpus + HasExecutions + HasClientPerfMonitor + HasMetadata + HasLastReportTime,
+    Z: ExecutionProcessor<OT, State = S>,
+{
+    let run = &mut *(run as *mut WorkerRun<S::Input, OT>);
+    let shared = lock(&*(shared as *const Mutex<Shared<EM, S, Z>>));
+    let (fuzzer, state, mgr) = shared.parts();
+    let input = &*run.input;
+    let observers = &mut *run.observers;
+
+    *state.executions_mut() += 1;
+    observers
+        .post_exec_all(state, input, &ExitKind::Crash)
+        .expect("Observers post_exec_all failed");
+    fuzzer
+        .process_execution(state, mgr, input.clone(), observers, &ExitKind::Crash, true)
+        .expect("Could not save the crash");
+    mgr.on_restart(state).unwrap();
+    mem::forget(shared);
+
+    log::info!("Bye!");
+}
+
+/// Reports panics of the harness like crashes
+fn setup_panic_hook() {
+    static PANIC_HOOK: Once = Once::new();
+    PANIC_HOOK.call_once(|| {
+        let old_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |panic_info| {
+            old_hook(panic_info);
+            unsafe {
+                if (*addr_of!(THREADED_HANDLER_DATA)).report_current_crash() {
+                    libc::_exit(128 + 6); // SIGABRT exit code
+                }
+            }
+        }));
+    });
+}
+
+/// Runs a thread-safe harness in a number of worker threads, all fuzzing the same state, see the [module docs](self).
+///
+/// Each worker calls the `worker_init` function with its number first, in its thread, for its mutator and observers.
+/// The observers of all workers have the same names, so that the feedbacks merge their results into the same state.
+pub struct ThreadedInProcessFuzzer<H, W> {
+    threads: usize,
+    harness: H,
+    worker_init: W,
+}
+
+impl<H, W> Debug for ThreadedInProcessFuzzer<H, W> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ThreadedInProcessFuzzer")
+            .field("threads", &self.threads)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<H, W> ThreadedInProcessFuzzer<H, W> {
+    /// Creates a new [`ThreadedInProcessFuzzer`], with `threads` workers
+    pub fn new(threads: usize, harness: H, worker_init: W) -> Self {
+        Self {
+            threads,
+            harness,
+            worker_init,
+        }
+    }
+
+    /// The number of worker threads
+    #[must_use]
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Fuzz forever (or until a worker fails)
+    pub fn fuzz_loop<EM, M, OT, S, Z>(
+        &self,
+        fuzzer: &mut Z,
+        state: &mut S,
+        mgr: &mut EM,
+    ) -> Result<(), Error>
+    where
+        H: Fn(&S::Input) -> ExitKind + Sync,
+        W: Fn(usize) -> Result<(M, OT), Error> + Sync,
+        EM: ProgressReporter<State = S> + EventRestarter<State = S>,
+        M: Mutator<S::Input, S>,
+        OT: ObserversTuple<S>,
+        S: HasCorpus + HasExecutions + HasClientPerfMonitor + HasMetadata + HasLastReportTime,
+        Z: ExecutionProcessor<OT, State = S> + HasScheduler,
+    {
+        self.fuzz(fuzzer, state, mgr, None)
+    }
+
+    /// Fuzz for `iters` runs of the harness, over all workers.
+    ///
+    /// If you use this fn in a restarting scenario to only run for `n` iterations,
+    /// before exiting, make sure you call `event_mgr.on_restart(&mut state)?;`.
+    pub fn fuzz_loop_for<EM, M, OT, S, Z>(
+        &self,
+        fuzzer: &mut Z,
+        state: &mut S,
+        mgr: &mut EM,
+        iters: u64,
+    ) -> Result<(), Error>
+    where
+        H: Fn(&S::Input) -> ExitKind + Sync,
+        W: Fn(usize) -> Result<(M, OT), Error> + Sync,
+        EM: ProgressReporter<State = S> + EventRestarter<State = S>,
+        M: Mutator<S::Input, S>,
+        OT: ObserversTuple<S>,
+        S: HasCorpus + HasExecutions + HasClientPerfMonitor + HasMetadata + HasLastReportTime,
+        Z: ExecutionProcessor<OT, State = S> + HasScheduler,
+    {
+        if iters == 0 {
+            return Err(Error::illegal_argument("Cannot fuzz for 0 iterations!"));
+        }
+        self.fuzz(fuzzer, state, mgr, Some(iters))
+    }
+
+    fn fuzz<EM, M, OT, S, Z>(
+        &self,
+        fuzzer: &mut Z,
+        state: &mut S,
+        mgr: &mut EM,
+        iters: Option<u64>,
+    ) -> Result<(), Error>
+    where
+        H: Fn(&S::Input) -> ExitKind + Sync,
+        W: Fn(usize) -> Result<(M, OT), Error> + Sync,
+        EM: ProgressReporter<State = S> + EventRestarter<State = S>,
+        M: Mutator<S::Input, S>,
+        OT: ObserversTuple<S>,
+        S: HasCorpus + HasExecutions + HasClientPerfMonitor + HasMetadata + HasLastReportTime,
+        Z: ExecutionProcessor<OT, State = S> + HasScheduler,
+    {
+        if self.threads == 0 {
+            return Err(Error::illegal_argument(
+                "ThreadedInProcessFuzzer needs at least one thread",
+            ));
+        }
+        if state.corpus().count() == 0 {
+            return Err(Error::empty("The corpus is empty, nothing to mutate"));
+        }
+
+        let shared = Mutex::new(Shared {
+            fuzzer: fuzzer as *mut Z,
+            state: state as *mut S,
+            mgr: mgr as *mut EM,
+            iters_left: iters,
+            stopped: false,
+        });
+
+        unsafe {
+            let data = addr_of_mut!(THREADED_HANDLER_DATA);
+            (*data).shared = &shared as *const _ as *const c_void;
+            (*data).crash_handler = report_crash::<EM, OT, S, Z> as *const c_void;
+            #[cfg(not(miri))]
+            setup_signal_handler(&mut *data)?;
+        }
+        setup_panic_hook();
+
+        let res = thread::scope(|scope| {
+            let workers: Vec<_> = (0..self.threads)
+                .map(|id| {
+                    let shared = &shared;
+                    thread::Builder::new()
+                        .name(format!("worker-{id}"))
+                        .spawn_scoped(scope, move || {
+                            let res = self.run_worker::<EM, M, OT, S, Z>(id, shared);
+                            if res.is_err() {
+                                lock(shared).stopped = true;
+                            }
+                            res
+                        })
+                })
+                .collect::<Result<_, _>>()?;
+
+            let mut res = Ok(());
+            for worker in workers {
+                let worker_res = worker
+                    .join()
+                    .unwrap_or_else(|_| Err(Error::unknown("A worker thread panicked")));
+                if res.is_ok() {
+                    res = worker_res;
+                }
+            }
+            res
+        });
+
+        unsafe {
+            let data = addr_of_mut!(THREADED_HANDLER_DATA);
+            (*data).crash_handler = ptr::null();
+            (*data).shared = ptr::null();
+        }
+        res
+    }
+
+    /// The fuzzing loop of one worker
+    fn run_worker<EM, M, OT, S, Z>(
+        &self,
+        id: usize,
+        shared: &Mutex<Shared<EM, S, Z>>,
+    ) -> Result<(), Error>
+    where
+        H: Fn(&S::Input) -> ExitKind,
+        W: Fn(usize) -> Result<(M, OT), Error>,
+        EM: ProgressReporter<State = S> + EventRestarter<State = S>,
+        M: Mutator<S::Input, S>,
+        OT: ObserversTuple<S>,
+        S: HasCorpus + HasExecutions + HasClientPerfMonitor + HasMetadata + HasLastReportTime,
+        Z: ExecutionProcessor<OT, State = S> + HasScheduler,
+    {
+        // The alternate signal stack is per thread
+        let mut signal_stack = vec![0_u8; WORKER_SIGNAL_STACK_SIZE];
+        let mut stack = libc::stack_t {
+            ss_sp: signal_stack.as_mut_ptr().cast(),
+            ss_flags: 0,
+            ss_size: signal_stack.len(),
+        };
+        unsafe { libc::sigaltstack(&stack, ptr::null_mut()) };
+
+        let res = (|| {
+            let (mut mutator, mut observers) = (self.worker_init)(id)?;
+            loop {
+                let input = {
+                    let mut shared = lock(shared);
+                    if shared.stopped || shared.iters_left == Some(0) {
+                        return Ok(());
+                    }
+                    if let Some(iters_left) = &mut shared.iters_left {
+                        *iters_left -= 1;
+                    }
+                    let (fuzzer, state, _) = unsafe { shared.parts() };
+
+                    let idx = fuzzer.scheduler_mut().next(state)?;
+                    let mut input = state.corpus().cloned_input_for_id(idx)?;
+                    mutator.mutate(state, &mut input, 0)?;
+                    {
+                        let mut testcase = state.corpus().get(idx)?.borrow_mut();
+                        let scheduled_count = testcase.scheduled_count();
+                        testcase.set_scheduled_count(scheduled_count + 1);
+                    }
+                    observers.pre_exec_all(state, &input)?;
+                    input
+                };
+
+                let mut run = WorkerRun {
+                    input: &input as *const S::Input,
+                    observers: &mut observers as *mut OT,
+                };
+                CURRENT_RUN.with(|current| current.set(addr_of_mut!(run).cast()));
+                let exit_kind = (self.harness)(&input);
+                CURRENT_RUN.with(|current| current.set(ptr::null_mut()));
+
+                let shared = lock(shared);
+                let (fuzzer, state, mgr) = unsafe { shared.parts() };
+                *state.executions_mut() += 1;
+                observers.post_exec_all(state, &input, &exit_kind)?;
+                let (_, corpus_idx) =
+                    fuzzer.process_execution(state, mgr, input, &observers, &exit_kind, true)?;
+                mutator.post_exec(state, 0, corpus_idx)?;
+                mgr.maybe_report_progress(state, STATS_TIMEOUT_DEFAULT)?;
+            }
+        })();
+
+        stack.ss_flags = libc::SS_DISABLE;
+        unsafe { libc::sigaltstack(&stack, ptr::null_mut()) };
+        res
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{boxed::Box, vec::Vec};
+    use core::{cell::Cell, ptr};
+
+    use libafl_bolts::{rands::StdRand, tuples::tuple_list};
+
+    use crate::{
+        corpus::{Corpus, InMemoryCorpus, Testcase},
+        events::NopEventManager,
+        executors::ExitKind,
+        feedbacks::{ConstFeedback, CrashFeedback, MaxMapFeedback},
+        fuzzer::{threaded::ThreadedInProcessFuzzer, StdFuzzer},
+        inputs::{BytesInput, HasBytesVec},
+        mutators::{havoc_mutations, StdScheduledMutator},
+        observers::StdMapObserver,
+        schedulers::{QueueScheduler, Scheduler},
+        state::{HasCorpus, HasExecutions, StdState},
+    };
+
+    std::thread_local! {
+        static MAP: Cell<*mut u8> = const { Cell::new(ptr::null_mut()) };
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_threaded_fuzzing() {
+        let harness = |input: &BytesInput| {
+            let map = MAP.with(Cell::get);
+            unsafe { *map.add(input.bytes().len().min(15)) = 1 };
+            ExitKind::Ok
+        };
+        let worker_init = |_id| {
+            let map = Box::leak(vec![0_u8; 16].into_boxed_slice());
+            MAP.with(|ptr| ptr.set(map.as_mut_ptr()));
+            let observer = unsafe { StdMapObserver::from_mut_ptr("map", map.as_mut_ptr(), 16) };
+            Ok((
+                StdScheduledMutator::new(havoc_mutations()),
+                tuple_list!(observer),
+            ))
+        };
+
+        let mut map = [0_u8; 16];
+        let observer = unsafe { StdMapObserver::from_mut_ptr("map", map.as_mut_ptr(), 16) };
+        let mut feedback = MaxMapFeedback::new(&observer);
+        let mut objective = CrashFeedback::new();
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        let mut scheduler = QueueScheduler::new();
+        let idx = state
+            .corpus_mut()
+            .add(Testcase::new(BytesInput::new(vec![0; 4])))
+            .unwrap();
+        scheduler.on_add(&mut state, idx).unwrap();
+        let mut fuzzer = StdFuzzer::new(scheduler, feedback, objective);
+
+        ThreadedInProcessFuzzer::new(4, harness, worker_init)
+            .fuzz_loop_for(&mut fuzzer, &mut state, &mut NopEventManager::new(), 1000)
+            .unwrap();
+
+        assert_eq!(*state.executions(), 1000);
+        // inputs of other lengths were found, by any of the workers
+        assert!(state.corpus().count() > 1);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn test_threaded_crash() {
+        use std::{env, fs, process};
+
+        use nix::{
+            sys::wait::{waitpid, WaitStatus},
+            unistd::{fork, ForkResult},
+        };
+
+        use crate::corpus::OnDiskCorpus;
+
+        let dir = env::temp_dir().join(format!("libafl_threaded_crash_{}", process::id()));
+
+        // the signal handlers are process wide, so crash in a child of our own
+        match unsafe { fork() }.unwrap() {
+            ForkResult::Child => {
+                let harness = |input: &BytesInput| {
+                    if input.bytes().contains(&0xff) {
+                        unsafe { libc::raise(libc::SIGSEGV) };
+                    }
+                    ExitKind::Ok
+                };
+                let worker_init =
+                    |_id| Ok((StdScheduledMutator::new(havoc_mutations()), tuple_list!()));
+
+                let mut feedback = ConstFeedback::new(false);
+                let mut objective = CrashFeedback::new();
+                let mut state = StdState::new(
+                    StdRand::with_seed(0),
+                    InMemoryCorpus::new(),
+                    OnDiskCorpus::new(&dir).unwrap(),
+                    &mut feedback,
+                    &mut objective,
+                )
+                .unwrap();
+                let mut scheduler = QueueScheduler::new();
+                let idx = state
+                    .corpus_mut()
+                    .add(Testcase::new(BytesInput::new(vec![0; 4])))
+                    .unwrap();
+                scheduler.on_add(&mut state, idx).unwrap();
+                let mut fuzzer = StdFuzzer::new(scheduler, feedback, objective);
+
+                drop(
+                    ThreadedInProcessFuzzer::new(4, harness, worker_init).fuzz_loop_for(
+                        &mut fuzzer,
+                        &mut state,
+                        &mut NopEventManager::new(),
+                        1_000_000,
+                    ),
+                );
+                // no crash at all
+                unsafe { libc::_exit(0) };
+            }
+            ForkResult::Parent { child } => {
+                assert_eq!(
+                    waitpid(child, None).unwrap(),
+                    WaitStatus::Exited(child, 128 + libc::SIGSEGV)
+                );
+                // the solution is the input of the crashing worker
+                let solutions = fs::read_dir(&dir)
+                    .unwrap()
+                    .map(|entry| entry.unwrap().path())
+                    .filter(|path| !path.file_name().unwrap().to_string_lossy().starts_with('.'))
+                    .collect::<Vec<_>>();
+                assert_eq!(solutions.len(), 1);
+                assert!(fs::read(&solutions[0]).unwrap().contains(&0xff));
+                fs::remove_dir_all(dir).unwrap();
+            }
+        }
+    }
+}
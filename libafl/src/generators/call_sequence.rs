@@ -0,0 +1,65 @@
+//! Generates random [`CallSequenceInput`]s.
+
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use libafl_bolts::{rands::Rand, HasLen};
+
+use crate::{
+    generators::Generator,
+    inputs::{Call, CallSequenceInput},
+    state::HasRand,
+    Error,
+};
+
+/// How often the [`CallSequenceGenerator`] tries to generate each call,
+/// before giving up on finding one whose resources can be bound
+const CALL_GENERATION_ATTEMPTS: usize = 16;
+
+/// Generates sequences of up to `max_calls` random calls,
+/// each taking its resources from the calls before it
+#[derive(Clone, Debug)]
+pub struct CallSequenceGenerator<C> {
+    max_calls: usize,
+    phantom: PhantomData<C>,
+}
+
+impl<C, S> Generator<CallSequenceInput<C>, S> for CallSequenceGenerator<C>
+where
+    C: Call,
+    S: HasRand,
+{
+    fn generate(&mut self, state: &mut S) -> Result<CallSequenceInput<C>, Error> {
+        let rand = state.rand_mut();
+        let len = rand.between(1, self.max_calls.max(1) as u64) as usize;
+        let mut input = CallSequenceInput::new(Vec::with_capacity(len));
+        for _ in 0..len {
+            for _ in 0..CALL_GENERATION_ATTEMPTS {
+                let call = C::generate(rand);
+                if input.insert_call(input.len(), call, rand) {
+                    break;
+                }
+            }
+        }
+        if input.len() == 0 {
+            return Err(Error::illegal_state(
+                "Could not generate any call without missing resources",
+            ));
+        }
+        Ok(input)
+    }
+}
+
+impl<C> CallSequenceGenerator<C>
+where
+    C: Call,
+{
+    /// Creates a new [`CallSequenceGenerator`], generating up to `max_calls` calls.
+    #[must_use]
+    pub fn new(max_calls: usize) -> Self {
+        Self {
+            max_calls,
+            phantom: PhantomData,
+        }
+    }
+}
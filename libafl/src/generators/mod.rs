@@ -14,6 +14,12 @@ use crate::{
 pub mod gramatron;
 pub use gramatron::*;
 
+pub mod call_sequence;
+pub use call_sequence::CallSequenceGenerator;
+
+pub mod structured;
+pub use structured::StructuredGenerator;
+
 #[cfg(feature = "nautilus")]
 pub mod nautilus;
 #[cfg(feature = "nautilus")]
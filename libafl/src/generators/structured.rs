@@ -0,0 +1,48 @@
+//! Generates random structured inputs, see [`crate::inputs::structured`].
+
+use core::marker::PhantomData;
+
+use crate::{
+    generators::Generator,
+    inputs::{FieldConstraints, Generate, Input},
+    state::HasRand,
+    Error,
+};
+
+/// Generates random inputs of a type implementing [`Generate`], usually derived
+#[derive(Clone, Debug)]
+pub struct StructuredGenerator<I> {
+    phantom: PhantomData<I>,
+}
+
+impl<I, S> Generator<I, S> for StructuredGenerator<I>
+where
+    I: Input + Generate,
+    S: HasRand,
+{
+    fn generate(&mut self, state: &mut S) -> Result<I, Error> {
+        Ok(I::generate(state.rand_mut(), &FieldConstraints::NONE))
+    }
+}
+
+impl<I> Default for StructuredGenerator<I>
+where
+    I: Input + Generate,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I> StructuredGenerator<I>
+where
+    I: Input + Generate,
+{
+    /// Creates a new [`StructuredGenerator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,368 @@
+//! An input made of a sequence of typed API calls, as in `syzkaller`.
+//!
+//! Calls may return resources, such as handles or file descriptors, that later calls of the same sequence
+//! take as arguments. The arguments referring to resources are [`ResourceRef`]s, pointing to the producing call.
+//! The harness replays the calls in order, keeping the return value of each call by its index.
+//!
+//! [`Call`] can be implemented by hand, or derived for an enum of operations with `#[derive(Call)]`:
+//!
+//! ```rust,ignore
+//! #[derive(Call, Serialize, Deserialize, Clone, Debug, Hash)]
+//! enum FileOp {
+//!     #[call(returns = "file")]
+//!     Open { path: String, flags: u32 },
+//!     Write { #[call(resource = "file")] file: ResourceRef, data: Vec<u8> },
+//!     Close(#[call(resource = "file")] ResourceRef),
+//! }
+//! ```
+
+use alloc::{rc::Rc, string::String, vec::Vec};
+use core::{
+    cell::RefCell,
+    fmt::Debug,
+    hash::{BuildHasher, Hash, Hasher},
+};
+
+use ahash::RandomState;
+use libafl_bolts::{rands::Rand, HasLen};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    inputs::{FieldConstraints, Generate, Input, Mutate},
+    mutators::MutationResult,
+};
+
+/// A reference to the resource returned by an earlier call of the same [`CallSequenceInput`]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ResourceRef {
+    call: usize,
+}
+
+impl ResourceRef {
+    /// Creates a new [`ResourceRef`] to the resource returned by the call at index `call`
+    #[must_use]
+    pub fn new(call: usize) -> Self {
+        Self { call }
+    }
+
+    /// The index of the call returning the resource
+    #[must_use]
+    pub fn call(&self) -> usize {
+        self.call
+    }
+}
+
+/// A typed argument of a [`Call`], that can be generated and mutated on its own.
+///
+/// Implemented for all structured types, see [`crate::inputs::structured`].
+pub trait CallArgument {
+    /// Generate a random value
+    fn generate<R: Rand>(rand: &mut R) -> Self;
+
+    /// Mutate the value
+    fn mutate<R: Rand>(&mut self, rand: &mut R) -> MutationResult;
+}
+
+impl<T> CallArgument for T
+where
+    T: Generate + Mutate,
+{
+    fn generate<R: Rand>(rand: &mut R) -> Self {
+        <T as Generate>::generate(rand, &FieldConstraints::NONE)
+    }
+
+    fn mutate<R: Rand>(&mut self, rand: &mut R) -> MutationResult {
+        <T as Mutate>::mutate(self, rand, &FieldConstraints::NONE)
+    }
+}
+
+/// A single call of a [`CallSequenceInput`], usually an enum with one variant per operation.
+///
+/// Can be derived with `#[derive(Call)]` from `libafl_derive`.
+pub trait Call: Clone + Debug + Hash + Serialize + DeserializeOwned {
+    /// The kind of resource this call returns, if any
+    fn returns(&self) -> Option<&'static str>;
+
+    /// The resources this call takes as arguments, with the kind each must be of
+    fn resources(&self) -> Vec<(&'static str, ResourceRef)>;
+
+    /// The resources this call takes as arguments, mutable
+    fn resources_mut(&mut self) -> Vec<(&'static str, &mut ResourceRef)>;
+
+    /// Generate a random call. Its resources are bound by the [`CallSequenceInput`] it gets inserted into.
+    fn generate<R: Rand>(rand: &mut R) -> Self;
+
+    /// Mutate one of the arguments of this call that is no resource
+    fn mutate_args<R: Rand>(&mut self, rand: &mut R) -> MutationResult;
+}
+
+/// A sequence of [`Call`]s, where each resource argument refers to an earlier call returning a resource of its kind
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+#[serde(bound = "C: Call")]
+pub struct CallSequenceInput<C> {
+    calls: Vec<C>,
+}
+
+impl<C> Input for CallSequenceInput<C>
+where
+    C: Call,
+{
+    /// Generate a name for this input
+    fn generate_name(&self, _idx: usize) -> String {
+        let mut hasher = RandomState::with_seeds(0, 0, 0, 0).build_hasher();
+        self.calls.hash(&mut hasher);
+        format!("{:016x}", hasher.finish())
+    }
+}
+
+/// Rc Ref-cell from Input
+impl<C> From<CallSequenceInput<C>> for Rc<RefCell<CallSequenceInput<C>>> {
+    fn from(input: CallSequenceInput<C>) -> Self {
+        Rc::new(RefCell::new(input))
+    }
+}
+
+/// The number of calls
+impl<C> HasLen for CallSequenceInput<C> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.calls.len()
+    }
+}
+
+impl<C> CallSequenceInput<C>
+where
+    C: Call,
+{
+    /// Creates a new [`CallSequenceInput`] from the given calls, which must refer to valid resources
+    #[must_use]
+    pub fn new(calls: Vec<C>) -> Self {
+        Self { calls }
+    }
+
+    /// The calls of this input
+    #[must_use]
+    pub fn calls(&self) -> &[C] {
+        &self.calls
+    }
+
+    /// The calls of this input, mutable.
+    /// Changing them is on the caller to keep the resource references valid, see [`Self::is_valid`].
+    pub fn calls_mut(&mut self) -> &mut Vec<C> {
+        &mut self.calls
+    }
+
+    /// Whether every resource argument refers to an earlier call returning a resource of its kind
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        self.calls.iter().enumerate().all(|(idx, call)| {
+            call.resources().iter().all(|(kind, resource)| {
+                resource.call() < idx && self.calls[resource.call()].returns() == Some(*kind)
+            })
+        })
+    }
+
+    /// The indices of the calls before `before` returning a resource of the given `kind`
+    #[must_use]
+    pub fn producers(&self, kind: &str, before: usize) -> Vec<usize> {
+        producers(&self.calls, kind, before)
+    }
+
+    /// Point each resource argument of `call` to a random producer before `position`.
+    /// Returns `false` if there is no producer for one of its kinds.
+    pub fn bind_resources<R: Rand>(&self, call: &mut C, position: usize, rand: &mut R) -> bool {
+        bind_resources(&self.calls, call, position, rand)
+    }
+
+    /// Insert `call` at `position`, binding its resources to earlier calls
+    /// and moving the references of later calls along.
+    /// Returns `false`, leaving the input untouched, if the resources of `call` can't be bound.
+    pub fn insert_call<R: Rand>(&mut self, position: usize, mut call: C, rand: &mut R) -> bool {
+        if !self.bind_resources(&mut call, position, rand) {
+            return false;
+        }
+        for later in &mut self.calls[position..] {
+            for (_, resource) in later.resources_mut() {
+                if resource.call >= position {
+                    resource.call += 1;
+                }
+            }
+        }
+        self.calls.insert(position, call);
+        true
+    }
+
+    /// Remove the call at `position`. Calls using its resource are bound to another producer.
+    /// Returns `false`, leaving the input untouched, if there is none for some of them.
+    pub fn remove_call<R: Rand>(&mut self, position: usize, rand: &mut R) -> bool {
+        let mut calls = self.calls.clone();
+        calls.remove(position);
+        let mapping: Vec<Option<usize>> = (0..self.calls.len())
+            .map(|idx| match idx.cmp(&position) {
+                core::cmp::Ordering::Less => Some(idx),
+                core::cmp::Ordering::Equal => None,
+                core::cmp::Ordering::Greater => Some(idx - 1),
+            })
+            .collect();
+        self.remap(calls, &mapping, rand)
+    }
+
+    /// Move the call at `from` to `to`, binding resources that would be used before they are returned
+    /// to other producers.
+    /// Returns `false`, leaving the input untouched, if there is none for some of them.
+    pub fn move_call<R: Rand>(&mut self, from: usize, to: usize, rand: &mut R) -> bool {
+        let mut calls = self.calls.clone();
+        let call = calls.remove(from);
+        calls.insert(to, call);
+        let mapping: Vec<Option<usize>> = (0..self.calls.len())
+            .map(|idx| {
+                Some(if idx == from {
+                    to
+                } else if from < idx && idx <= to {
+                    idx - 1
+                } else if to <= idx && idx < from {
+                    idx + 1
+                } else {
+                    idx
+                })
+            })
+            .collect();
+        self.remap(calls, &mapping, rand)
+    }
+
+    /// Point the resources of the reordered `calls` to the new indices in `mapping`,
+    /// rebinding the ones that got removed or no longer precede their user.
+    fn remap<R: Rand>(
+        &mut self,
+        mut calls: Vec<C>,
+        mapping: &[Option<usize>],
+        rand: &mut R,
+    ) -> bool {
+        for idx in 0..calls.len() {
+            let (before, rest) = calls.split_at_mut(idx);
+            for (kind, resource) in rest[0].resources_mut() {
+                match mapping[resource.call] {
+                    Some(call) if call < idx => resource.call = call,
+                    _ => {
+                        let candidates = producers(before, kind, idx);
+                        if candidates.is_empty() {
+                            return false;
+                        }
+                        resource.call = *rand.choose(&candidates);
+                    }
+                }
+            }
+        }
+        self.calls = calls;
+        true
+    }
+}
+
+/// The indices of the `calls` before `before` returning a resource of the given `kind`
+fn producers<C: Call>(calls: &[C], kind: &str, before: usize) -> Vec<usize> {
+    calls[..before.min(calls.len())]
+        .iter()
+        .enumerate()
+        .filter(|(_, call)| call.returns() == Some(kind))
+        .map(|(idx, _)| idx)
+        .collect()
+}
+
+fn bind_resources<C: Call, R: Rand>(
+    calls: &[C],
+    call: &mut C,
+    position: usize,
+    rand: &mut R,
+) -> bool {
+    for (kind, resource) in call.resources_mut() {
+        let candidates = producers(calls, kind, position);
+        if candidates.is_empty() {
+            return false;
+        }
+        resource.call = *rand.choose(&candidates);
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{string::String, vec::Vec};
+
+    use libafl_bolts::rands::{Rand, StdRand};
+    use serde::{Deserialize, Serialize};
+
+    use crate::{
+        inputs::{Call, CallArgument, CallSequenceInput, ResourceRef},
+        mutators::MutationResult,
+    };
+
+    #[derive(Serialize, Deserialize, Clone, Debug, Hash)]
+    enum FileOp {
+        Open(String),
+        Write(ResourceRef, Vec<u8>),
+    }
+
+    impl Call for FileOp {
+        fn returns(&self) -> Option<&'static str> {
+            match self {
+                FileOp::Open(_) => Some("file"),
+                FileOp::Write(..) => None,
+            }
+        }
+
+        fn resources(&self) -> Vec<(&'static str, ResourceRef)> {
+            match self {
+                FileOp::Open(_) => vec![],
+                FileOp::Write(file, _) => vec![("file", *file)],
+            }
+        }
+
+        fn resources_mut(&mut self) -> Vec<(&'static str, &mut ResourceRef)> {
+            match self {
+                FileOp::Open(_) => vec![],
+                FileOp::Write(file, _) => vec![("file", file)],
+            }
+        }
+
+        fn generate<R: Rand>(rand: &mut R) -> Self {
+            if rand.below(2) == 0 {
+                FileOp::Open(String::generate(rand))
+            } else {
+                FileOp::Write(ResourceRef::default(), Vec::generate(rand))
+            }
+        }
+
+        fn mutate_args<R: Rand>(&mut self, rand: &mut R) -> MutationResult {
+            match self {
+                FileOp::Open(path) => path.mutate(rand),
+                FileOp::Write(_, data) => data.mutate(rand),
+            }
+        }
+    }
+
+    #[test]
+    fn test_call_sequence_resources() {
+        let mut rand = StdRand::with_seed(0);
+        let mut input = CallSequenceInput::new(vec![]);
+        // Nothing to write to yet
+        assert!(!input.insert_call(0, FileOp::Write(ResourceRef::default(), vec![]), &mut rand));
+        assert!(input.insert_call(0, FileOp::Open("a".into()), &mut rand));
+        assert!(input.insert_call(1, FileOp::Write(ResourceRef::default(), vec![]), &mut rand));
+        assert!(input.insert_call(0, FileOp::Open("b".into()), &mut rand));
+        assert!(input.is_valid());
+        assert!(matches!(input.calls()[2], FileOp::Write(file, _) if file.call() == 1));
+
+        // The write can't move in front of every open
+        assert!(!input.move_call(2, 0, &mut rand));
+        assert!(input.move_call(2, 1, &mut rand));
+        assert!(input.is_valid());
+        assert!(matches!(input.calls()[1], FileOp::Write(file, _) if file.call() == 0));
+
+        // Removing the open the write uses rebinds it, if possible
+        assert!(!input.remove_call(0, &mut rand));
+        assert!(input.move_call(2, 0, &mut rand));
+        assert!(input.remove_call(1, &mut rand));
+        assert!(input.is_valid());
+        assert!(matches!(input.calls()[1], FileOp::Write(file, _) if file.call() == 0));
+    }
+}
@@ -12,6 +12,18 @@ pub use gramatron::*;
 pub mod generalized;
 pub use generalized::*;
 
+pub mod packets;
+pub use packets::PacketSequenceInput;
+
+pub mod multi;
+pub use multi::MultipartInput;
+
+pub mod structured;
+pub use structured::{FieldConstraints, Generate, Mutate, StructuredField};
+
+pub mod call_sequence;
+pub use call_sequence::{Call, CallArgument, CallSequenceInput, ResourceRef};
+
 #[cfg(feature = "nautilus")]
 pub mod nautilus;
 use alloc::{
@@ -119,6 +131,13 @@ impl HasTargetBytes for NopInput {
 pub trait HasTargetBytes {
     /// Target bytes, that can be written to a target
     fn target_bytes(&self) -> OwnedSlice<u8>;
+
+    /// The named parts of this input, for executors that deliver each part on its own,
+    /// like a [`MultipartInput`] whose parts go to separate files.
+    /// Inputs made of a single part return it, unnamed.
+    fn target_parts(&self) -> Vec<(&str, OwnedSlice<u8>)> {
+        vec![("", self.target_bytes())]
+    }
 }
 
 /// Contains an internal bytes Vector
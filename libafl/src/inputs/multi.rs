@@ -0,0 +1,243 @@
+//! An input made of several named parts, for targets that take more than one buffer,
+//! like a decoder reading an image and a config file, or a tool taking a file and command line flags.
+//! Each part can be mutated on its own, see [`crate::mutators::multi`],
+//! and delivered on its own, see [`HasTargetBytes::target_parts`].
+
+use alloc::{rc::Rc, string::String, vec::Vec};
+use core::{
+    cell::RefCell,
+    convert::From,
+    hash::{BuildHasher, Hasher},
+};
+
+use ahash::RandomState;
+use libafl_bolts::{ownedref::OwnedSlice, AsSlice, HasLen};
+use serde::{Deserialize, Serialize};
+
+use crate::inputs::{HasTargetBytes, Input};
+
+/// Generated names longer than this are hashed, to stay well below the filename limit of common filesystems
+const MAX_NAME_LEN: usize = 128;
+
+/// An input made of named parts, each of them an input on its own.
+/// Several parts may share a name, e.g. for a list of files of the same kind.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(bound = "I: Serialize + serde::de::DeserializeOwned")]
+pub struct MultipartInput<I> {
+    parts: Vec<I>,
+    names: Vec<String>,
+}
+
+impl<I> Default for MultipartInput<I> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<I> Input for MultipartInput<I>
+where
+    I: Input,
+{
+    /// Generate a name for this input, made of the names of its parts.
+    /// Characters that are not safe in filenames are replaced, and names too long for a filename are hashed.
+    fn generate_name(&self, idx: usize) -> String {
+        let name = self
+            .names
+            .iter()
+            .zip(&self.parts)
+            .map(|(name, part)| format!("{name}-{}", part.generate_name(idx)))
+            .collect::<Vec<_>>()
+            .join(",");
+        let sanitized: String = name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ',') {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        if sanitized == name && name.len() <= MAX_NAME_LEN {
+            return name;
+        }
+
+        // Keep names that only differ in replaced characters apart
+        let mut hasher = RandomState::with_seeds(0, 0, 0, 0).build_hasher();
+        hasher.write(name.as_bytes());
+        let hash = hasher.finish();
+        if sanitized.len() > MAX_NAME_LEN {
+            format!("multipart-{hash:016x}")
+        } else {
+            format!("{sanitized}-{hash:016x}")
+        }
+    }
+}
+
+/// Rc Ref-cell from Input
+impl<I> From<MultipartInput<I>> for Rc<RefCell<MultipartInput<I>>> {
+    fn from(input: MultipartInput<I>) -> Self {
+        Rc::new(RefCell::new(input))
+    }
+}
+
+impl<I, N> From<Vec<(N, I)>> for MultipartInput<I>
+where
+    N: Into<String>,
+{
+    fn from(parts: Vec<(N, I)>) -> Self {
+        let mut input = Self::new();
+        for (name, part) in parts {
+            input.add_part(name, part);
+        }
+        input
+    }
+}
+
+/// The number of parts
+impl<I> HasLen for MultipartInput<I> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.parts.len()
+    }
+}
+
+/// All parts, concatenated, for executors that can only deliver one buffer.
+/// Executors that know about parts use [`HasTargetBytes::target_parts`] instead.
+impl<I> HasTargetBytes for MultipartInput<I>
+where
+    I: HasTargetBytes,
+{
+    fn target_bytes(&self) -> OwnedSlice<u8> {
+        let mut bytes = Vec::new();
+        for part in &self.parts {
+            bytes.extend_from_slice(part.target_bytes().as_slice());
+        }
+        OwnedSlice::from(bytes)
+    }
+
+    fn target_parts(&self) -> Vec<(&str, OwnedSlice<u8>)> {
+        self.names
+            .iter()
+            .zip(&self.parts)
+            .map(|(name, part)| (name.as_str(), part.target_bytes()))
+            .collect()
+    }
+}
+
+impl<I> MultipartInput<I> {
+    /// Creates a new [`MultipartInput`] without any parts
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            parts: Vec::new(),
+            names: Vec::new(),
+        }
+    }
+
+    /// Adds a part with the given name
+    pub fn add_part<N>(&mut self, name: N, part: I)
+    where
+        N: Into<String>,
+    {
+        self.names.push(name.into());
+        self.parts.push(part);
+    }
+
+    /// The parts of this input
+    #[must_use]
+    pub fn parts(&self) -> &[I] {
+        &self.parts
+    }
+
+    /// The parts of this input, mutable
+    #[must_use]
+    pub fn parts_mut(&mut self) -> &mut [I] {
+        &mut self.parts
+    }
+
+    /// The names of the parts, in the same order as [`Self::parts`]
+    #[must_use]
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// The part at the given index, mutable, together with its name
+    #[must_use]
+    pub fn part_mut(&mut self, idx: usize) -> Option<(&str, &mut I)> {
+        let name = self.names.get(idx)?;
+        Some((name.as_str(), &mut self.parts[idx]))
+    }
+
+    /// The indices of all parts with the given name
+    pub fn idxs_with_name<'a>(&'a self, name: &'a str) -> impl Iterator<Item = usize> + 'a {
+        self.names
+            .iter()
+            .enumerate()
+            .filter(move |(_, part_name)| *part_name == name)
+            .map(|(idx, _)| idx)
+    }
+
+    /// All parts with the given name
+    pub fn parts_by_name<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a I> + 'a {
+        self.idxs_with_name(name).map(|idx| &self.parts[idx])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use libafl_bolts::{AsSlice, HasLen};
+
+    use crate::inputs::{BytesInput, HasTargetBytes, Input, MultipartInput};
+
+    #[test]
+    fn test_multipart_input() {
+        let input = MultipartInput::from(vec![
+            ("image", BytesInput::new(b"\x89PNG".to_vec())),
+            ("config", BytesInput::new(b"scale=2".to_vec())),
+            ("image", BytesInput::new(b"GIF89a".to_vec())),
+        ]);
+        assert_eq!(input.len(), 3);
+        assert_eq!(input.idxs_with_name("image").collect::<Vec<_>>(), [0, 2]);
+        assert_eq!(input.parts_by_name("config").count(), 1);
+        assert_eq!(
+            input.target_bytes().as_slice(),
+            b"\x89PNGscale=2GIF89a".as_slice()
+        );
+
+        let parts = input.target_parts();
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[1].0, "config");
+        assert_eq!(parts[1].1.as_slice(), b"scale=2");
+
+        // Renaming a part makes for a different input
+        let mut other = MultipartInput::new();
+        for (idx, part) in input.parts().iter().enumerate() {
+            other.add_part(format!("{idx}"), part.clone());
+        }
+        assert_ne!(input.generate_name(0), other.generate_name(0));
+    }
+
+    #[test]
+    fn test_multipart_name() {
+        let slash = MultipartInput::from(vec![("dir/file", BytesInput::new(vec![1]))]);
+        let underscore = MultipartInput::from(vec![("dir_file", BytesInput::new(vec![1]))]);
+        let name = slash.generate_name(0);
+        assert!(!name.contains('/'));
+        assert_ne!(name, underscore.generate_name(0));
+
+        let many = MultipartInput::from(
+            (0..64)
+                .map(|idx| (format!("part{idx}"), BytesInput::new(vec![idx])))
+                .collect::<Vec<_>>(),
+        );
+        let name = many.generate_name(0);
+        assert!(name.len() <= 128);
+        assert_ne!(
+            name,
+            MultipartInput::from(vec![("part0", BytesInput::new(vec![0]))]).generate_name(0)
+        );
+    }
+}
@@ -0,0 +1,104 @@
+//! An input made of an ordered sequence of messages, as sent to a stateful network server,
+//! see [`crate::executors::network::NetworkForkserverExecutor`].
+use alloc::{rc::Rc, string::String, vec::Vec};
+use core::{
+    cell::RefCell,
+    convert::From,
+    hash::{BuildHasher, Hasher},
+};
+
+use ahash::RandomState;
+use libafl_bolts::{ownedref::OwnedSlice, HasLen};
+use serde::{Deserialize, Serialize};
+
+use crate::inputs::{BytesInput, HasBytesVec, HasTargetBytes, Input};
+
+/// An input made of packets, each delivered to the target as a message of its own
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PacketSequenceInput {
+    /// The messages, in the order they are sent
+    packets: Vec<BytesInput>,
+}
+
+impl Input for PacketSequenceInput {
+    /// Generate a name for this input
+    fn generate_name(&self, _idx: usize) -> String {
+        let mut hasher = RandomState::with_seeds(0, 0, 0, 0).build_hasher();
+        for packet in &self.packets {
+            hasher.write_usize(packet.bytes().len());
+            hasher.write(packet.bytes());
+        }
+        format!("{:016x}", hasher.finish())
+    }
+}
+
+/// Rc Ref-cell from Input
+impl From<PacketSequenceInput> for Rc<RefCell<PacketSequenceInput>> {
+    fn from(input: PacketSequenceInput) -> Self {
+        Rc::new(RefCell::new(input))
+    }
+}
+
+impl From<Vec<Vec<u8>>> for PacketSequenceInput {
+    fn from(packets: Vec<Vec<u8>>) -> Self {
+        Self::new(packets.into_iter().map(BytesInput::new).collect())
+    }
+}
+
+/// The number of packets
+impl HasLen for PacketSequenceInput {
+    #[inline]
+    fn len(&self) -> usize {
+        self.packets.len()
+    }
+}
+
+/// All packets, concatenated, for targets that read the sequence in one go
+impl HasTargetBytes for PacketSequenceInput {
+    fn target_bytes(&self) -> OwnedSlice<u8> {
+        OwnedSlice::from(
+            self.packets
+                .iter()
+                .flat_map(|packet| packet.bytes().iter().copied())
+                .collect::<Vec<u8>>(),
+        )
+    }
+}
+
+impl PacketSequenceInput {
+    /// Creates a new packet sequence input from the given packets
+    #[must_use]
+    pub fn new(packets: Vec<BytesInput>) -> Self {
+        Self { packets }
+    }
+
+    /// The packets of this input
+    #[must_use]
+    pub fn packets(&self) -> &[BytesInput] {
+        &self.packets
+    }
+
+    /// The packets of this input, mutable
+    #[must_use]
+    pub fn packets_mut(&mut self) -> &mut Vec<BytesInput> {
+        &mut self.packets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::{AsSlice, HasLen};
+
+    use crate::inputs::{HasTargetBytes, Input, PacketSequenceInput};
+
+    #[test]
+    fn test_packet_sequence_input() {
+        let input = PacketSequenceInput::from(vec![b"USER a\r\n".to_vec(), b"PASS b\r\n".to_vec()]);
+        assert_eq!(input.len(), 2);
+        assert_eq!(input.target_bytes().as_slice(), b"USER a\r\nPASS b\r\n");
+
+        // Moving bytes between packets makes for a different input
+        let other = PacketSequenceInput::from(vec![b"USER a\r\nPASS".to_vec(), b" b\r\n".to_vec()]);
+        assert_ne!(input.generate_name(0), other.generate_name(0));
+    }
+}
@@ -0,0 +1,502 @@
+//! Structure-aware inputs: arbitrary Rust types that are generated and mutated field by field.
+//!
+//! [`Generate`] and [`Mutate`] are implemented for integers, floats, `bool`, [`String`], [`Vec`] and [`Option`],
+//! and can be derived for structs and enums composed of them with `#[derive(Generate, Mutate)]` from `libafl_derive`.
+//! The derived types also implement [`Input`](crate::inputs::Input), as long as they implement
+//! `Serialize`, `Deserialize`, `Clone` and `Debug`, so they can be fuzzed with the
+//! [`crate::mutators::structured_mutations`] and the [`crate::generators::StructuredGenerator`].
+//!
+//! ```rust,ignore
+//! #[derive(Generate, Mutate, Serialize, Deserialize, Clone, Debug)]
+//! struct Request {
+//!     #[mutate(range = 1..=9)]
+//!     version: u8,
+//!     #[mutate(max_len = 8)]
+//!     headers: Vec<String>,
+//!     body: Option<Vec<u8>>,
+//! }
+//! ```
+//!
+//! Fields take the constraints `range = lo..hi` or `lo..=hi`, for integers and floats,
+//! and `max_len = n`, for strings and vectors. The constraints of a [`Vec`] or [`Option`] also apply to its elements.
+//! Fields marked `skip` are always [`Default`] and never mutated.
+
+use alloc::{string::String, vec::Vec};
+use core::{
+    any::{Any, TypeId},
+    hash::{BuildHasher, Hasher},
+};
+
+use ahash::RandomState;
+use libafl_bolts::rands::Rand;
+use serde::Serialize;
+
+use crate::mutators::MutationResult;
+
+/// The default maximum length of generated or mutated [`String`]s and [`Vec`]s
+pub const DEFAULT_MAX_LEN: usize = 32;
+
+/// The constraints of a field, given in its `#[mutate(..)]` attribute
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FieldConstraints {
+    /// The inclusive range of an integer field
+    pub range: Option<(i128, i128)>,
+    /// The range of a float field
+    pub float_range: Option<(f64, f64)>,
+    /// The maximum length of a collection field
+    pub max_len: Option<usize>,
+}
+
+impl FieldConstraints {
+    /// No constraints at all
+    pub const NONE: Self = Self {
+        range: None,
+        float_range: None,
+        max_len: None,
+    };
+
+    /// The maximum length of a collection, [`DEFAULT_MAX_LEN`] if unconstrained
+    #[must_use]
+    pub fn max_len(&self) -> usize {
+        self.max_len.unwrap_or(DEFAULT_MAX_LEN)
+    }
+
+    /// The range of an integer of the given bounds, narrowed to the constrained range if there is one
+    #[must_use]
+    pub fn int_range(&self, min: i128, max: i128) -> (i128, i128) {
+        match self.range {
+            Some((lo, hi)) if lo.max(min) <= hi.min(max) => (lo.max(min), hi.min(max)),
+            _ => (min, max),
+        }
+    }
+}
+
+/// A field of a structured input, as visited by [`Mutate::fields`], to be replaced by another field of the same type
+/// and constraints
+pub trait StructuredField: Any {
+    /// The type of this field
+    fn field_type_id(&self) -> TypeId;
+
+    /// This field, as [`Any`]
+    fn as_any(&self) -> &dyn Any;
+
+    /// Replace this field with a clone of `donor`, if it has the same type
+    fn replace_with(&mut self, donor: &dyn Any) -> bool;
+}
+
+impl<T> StructuredField for T
+where
+    T: Any + Clone,
+{
+    fn field_type_id(&self) -> TypeId {
+        TypeId::of::<T>()
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn replace_with(&mut self, donor: &dyn Any) -> bool {
+        match donor.downcast_ref::<T>() {
+            Some(donor) => {
+                *self = donor.clone();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// A type that can be generated at random, field by field
+pub trait Generate: Sized {
+    /// Generate a random value within the `constraints`
+    fn generate<R: Rand>(rand: &mut R, constraints: &FieldConstraints) -> Self;
+}
+
+/// A type that can be mutated, field by field
+pub trait Mutate: Clone + 'static {
+    /// Mutate a random part of this value, within the `constraints`
+    fn mutate<R: Rand>(&mut self, rand: &mut R, constraints: &FieldConstraints) -> MutationResult;
+
+    /// Splice this value with `other`: keep it up to a random point, take the rest from `other`,
+    /// within the `constraints`
+    fn splice<R: Rand>(
+        &mut self,
+        other: &Self,
+        _rand: &mut R,
+        _constraints: &FieldConstraints,
+    ) -> MutationResult {
+        *self = other.clone();
+        MutationResult::Mutated
+    }
+
+    /// Visit this value and all of its fields, recursively, along with the constraints each of them is
+    /// generated and mutated within. This value itself has the given `constraints`.
+    fn fields<'a>(
+        &'a self,
+        constraints: &FieldConstraints,
+        visitor: &mut dyn FnMut(&'a dyn StructuredField, &FieldConstraints),
+    ) {
+        visitor(self, constraints);
+    }
+
+    /// Visit this value and all of its fields, recursively, mutable, see [`Mutate::fields`]
+    fn fields_mut(
+        &mut self,
+        constraints: &FieldConstraints,
+        visitor: &mut dyn FnMut(&mut dyn StructuredField, &FieldConstraints),
+    ) {
+        visitor(self, constraints);
+    }
+}
+
+/// The name of a structured input, a hash of its serialized form
+pub fn structured_input_name<T>(input: &T) -> String
+where
+    T: Serialize,
+{
+    let mut hasher = RandomState::with_seeds(0, 0, 0, 0).build_hasher();
+    hasher.write(&postcard::to_allocvec(input).unwrap());
+    format!("{:016x}", hasher.finish())
+}
+
+fn changed<T: PartialEq>(old: &T, new: &T) -> MutationResult {
+    if old == new {
+        MutationResult::Skipped
+    } else {
+        MutationResult::Mutated
+    }
+}
+
+/// A random value in the inclusive range
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+fn int_between<R: Rand>(rand: &mut R, lo: i128, hi: i128) -> i128 {
+    let span = (hi - lo) as u128;
+    if span >= u128::from(u64::MAX) {
+        lo + i128::from(rand.next())
+    } else {
+        lo + i128::from(rand.below(span as u64 + 1))
+    }
+}
+
+macro_rules! impl_structured_int {
+    ($($ty:ty),*) => {
+        $(
+            #[allow(
+                trivial_numeric_casts,
+                clippy::cast_possible_truncation,
+                clippy::cast_possible_wrap,
+                clippy::cast_sign_loss,
+                clippy::cast_lossless
+            )]
+            impl Generate for $ty {
+                fn generate<R: Rand>(rand: &mut R, constraints: &FieldConstraints) -> Self {
+                    let (lo, hi) = constraints.int_range(<$ty>::MIN as i128, <$ty>::MAX as i128);
+                    // Boundary values are likelier to trigger bugs
+                    if rand.below(4) == 0 {
+                        *rand.choose(&[lo, hi, 0.max(lo).min(hi), 1.max(lo).min(hi)]) as $ty
+                    } else {
+                        int_between(rand, lo, hi) as $ty
+                    }
+                }
+            }
+
+            #[allow(
+                trivial_numeric_casts,
+                clippy::cast_possible_truncation,
+                clippy::cast_possible_wrap,
+                clippy::cast_lossless
+            )]
+            impl Mutate for $ty {
+                fn mutate<R: Rand>(&mut self, rand: &mut R, constraints: &FieldConstraints) -> MutationResult {
+                    let old = *self;
+                    match rand.below(4) {
+                        0 => *self ^= (1 as $ty) << rand.below(u64::from(<$ty>::BITS)),
+                        1 => *self = self.wrapping_add(rand.between(1, 16) as $ty),
+                        2 => *self = self.wrapping_sub(rand.between(1, 16) as $ty),
+                        _ => *self = Self::generate(rand, constraints),
+                    }
+                    let (lo, hi) = constraints.int_range(<$ty>::MIN as i128, <$ty>::MAX as i128);
+                    if !(lo..=hi).contains(&(*self as i128)) {
+                        *self = Self::generate(rand, constraints);
+                    }
+                    changed(&old, self)
+                }
+            }
+        )*
+    };
+}
+
+impl_structured_int!(u8, u16, u32, u64, usize, i8, i16, i32, i64, isize);
+
+macro_rules! impl_structured_float {
+    ($($ty:ty),*) => {
+        $(
+            #[allow(trivial_numeric_casts, clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+            impl Generate for $ty {
+                fn generate<R: Rand>(rand: &mut R, constraints: &FieldConstraints) -> Self {
+                    let unit = rand.next() as f64 / u64::MAX as f64;
+                    if let Some((lo, hi)) = constraints.float_range {
+                        if rand.below(4) == 0 {
+                            return *rand.choose(&[lo, hi]) as $ty;
+                        }
+                        return (lo + (hi - lo) * unit) as $ty;
+                    }
+                    match rand.below(4) {
+                        0 => *rand.choose(&[
+                            0.0,
+                            -0.0,
+                            1.0,
+                            -1.0,
+                            <$ty>::MIN,
+                            <$ty>::MAX,
+                            <$ty>::EPSILON,
+                            <$ty>::INFINITY,
+                            <$ty>::NEG_INFINITY,
+                            <$ty>::NAN,
+                        ]),
+                        1 => <$ty>::from_bits(rand.next() as _),
+                        _ => ((unit - 0.5) * 2000.0) as $ty,
+                    }
+                }
+            }
+
+            #[allow(trivial_numeric_casts, clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+            impl Mutate for $ty {
+                fn mutate<R: Rand>(&mut self, rand: &mut R, constraints: &FieldConstraints) -> MutationResult {
+                    let old = self.to_bits();
+                    match rand.below(4) {
+                        0 => *self = <$ty>::from_bits(self.to_bits() ^ (1 << rand.below(8 * core::mem::size_of::<$ty>() as u64))),
+                        1 => *self *= *rand.choose(&[-1.0, 0.5, 2.0]),
+                        2 => *self += (rand.between(1, 16) as $ty) * *rand.choose(&[-1.0, 1.0]),
+                        _ => *self = Self::generate(rand, constraints),
+                    }
+                    if let Some((lo, hi)) = constraints.float_range {
+                        if !(f64::from(*self) >= lo && f64::from(*self) <= hi) {
+                            *self = Self::generate(rand, constraints);
+                        }
+                    }
+                    changed(&old, &self.to_bits())
+                }
+            }
+        )*
+    };
+}
+
+impl_structured_float!(f32, f64);
+
+impl Generate for bool {
+    fn generate<R: Rand>(rand: &mut R, _constraints: &FieldConstraints) -> Self {
+        rand.below(2) == 1
+    }
+}
+
+impl Mutate for bool {
+    fn mutate<R: Rand>(
+        &mut self,
+        _rand: &mut R,
+        _constraints: &FieldConstraints,
+    ) -> MutationResult {
+        *self = !*self;
+        MutationResult::Mutated
+    }
+}
+
+/// A random printable ASCII char
+#[allow(clippy::cast_possible_truncation)]
+fn printable_char<R: Rand>(rand: &mut R) -> char {
+    char::from(rand.between(0x20, 0x7e) as u8)
+}
+
+impl Generate for String {
+    fn generate<R: Rand>(rand: &mut R, constraints: &FieldConstraints) -> Self {
+        let len = rand.below(constraints.max_len() as u64 + 1);
+        (0..len).map(|_| printable_char(rand)).collect()
+    }
+}
+
+impl Mutate for String {
+    #[allow(clippy::cast_possible_truncation)]
+    fn mutate<R: Rand>(&mut self, rand: &mut R, constraints: &FieldConstraints) -> MutationResult {
+        let mut chars: Vec<char> = self.chars().collect();
+        let len = chars.len() as u64;
+        match rand.below(3) {
+            0 if len > 0 => chars[rand.below(len) as usize] = printable_char(rand),
+            1 if len > 0 => {
+                chars.remove(rand.below(len) as usize);
+            }
+            _ if chars.len() < constraints.max_len() => {
+                chars.insert(rand.below(len + 1) as usize, printable_char(rand));
+            }
+            _ => return MutationResult::Skipped,
+        }
+        let mutated: String = chars.into_iter().collect();
+        let result = changed(self, &mutated);
+        *self = mutated;
+        result
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn splice<R: Rand>(
+        &mut self,
+        other: &Self,
+        rand: &mut R,
+        constraints: &FieldConstraints,
+    ) -> MutationResult {
+        let chars: Vec<char> = self.chars().collect();
+        let other_chars: Vec<char> = other.chars().collect();
+        let cut = rand.below(chars.len() as u64 + 1) as usize;
+        let from = rand.below(other_chars.len() as u64 + 1) as usize;
+        let spliced: String = chars[..cut]
+            .iter()
+            .chain(&other_chars[from..])
+            .take(constraints.max_len())
+            .collect();
+        let result = changed(self, &spliced);
+        *self = spliced;
+        result
+    }
+}
+
+impl<T> Generate for Vec<T>
+where
+    T: Generate,
+{
+    fn generate<R: Rand>(rand: &mut R, constraints: &FieldConstraints) -> Self {
+        let len = rand.below(constraints.max_len() as u64 + 1);
+        (0..len).map(|_| T::generate(rand, constraints)).collect()
+    }
+}
+
+impl<T> Mutate for Vec<T>
+where
+    T: Generate + Mutate,
+{
+    #[allow(clippy::cast_possible_truncation)]
+    fn mutate<R: Rand>(&mut self, rand: &mut R, constraints: &FieldConstraints) -> MutationResult {
+        let len = self.len() as u64;
+        let full = self.len() >= constraints.max_len();
+        match rand.below(4) {
+            0 if len > 0 => {
+                let idx = rand.below(len) as usize;
+                self[idx].mutate(rand, constraints)
+            }
+            1 if len > 0 => {
+                self.remove(rand.below(len) as usize);
+                MutationResult::Mutated
+            }
+            2 if len > 0 && !full => {
+                let idx = rand.below(len) as usize;
+                self.insert(idx, self[idx].clone());
+                MutationResult::Mutated
+            }
+            _ if !full => {
+                let idx = rand.below(len + 1) as usize;
+                self.insert(idx, T::generate(rand, constraints));
+                MutationResult::Mutated
+            }
+            _ => MutationResult::Skipped,
+        }
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn splice<R: Rand>(
+        &mut self,
+        other: &Self,
+        rand: &mut R,
+        constraints: &FieldConstraints,
+    ) -> MutationResult {
+        let cut = rand.below(self.len() as u64 + 1) as usize;
+        let from = rand.below(other.len() as u64 + 1) as usize;
+        self.truncate(cut);
+        self.extend_from_slice(&other[from..]);
+        self.truncate(constraints.max_len());
+        MutationResult::Mutated
+    }
+
+    fn fields<'a>(
+        &'a self,
+        constraints: &FieldConstraints,
+        visitor: &mut dyn FnMut(&'a dyn StructuredField, &FieldConstraints),
+    ) {
+        visitor(self, constraints);
+        for item in self {
+            item.fields(constraints, visitor);
+        }
+    }
+
+    fn fields_mut(
+        &mut self,
+        constraints: &FieldConstraints,
+        visitor: &mut dyn FnMut(&mut dyn StructuredField, &FieldConstraints),
+    ) {
+        visitor(self, constraints);
+        for item in self {
+            item.fields_mut(constraints, visitor);
+        }
+    }
+}
+
+impl<T> Generate for Option<T>
+where
+    T: Generate,
+{
+    fn generate<R: Rand>(rand: &mut R, constraints: &FieldConstraints) -> Self {
+        if rand.below(4) == 0 {
+            None
+        } else {
+            Some(T::generate(rand, constraints))
+        }
+    }
+}
+
+impl<T> Mutate for Option<T>
+where
+    T: Generate + Mutate,
+{
+    fn mutate<R: Rand>(&mut self, rand: &mut R, constraints: &FieldConstraints) -> MutationResult {
+        match self {
+            None => *self = Some(T::generate(rand, constraints)),
+            Some(_) if rand.below(8) == 0 => *self = None,
+            Some(value) => return value.mutate(rand, constraints),
+        }
+        MutationResult::Mutated
+    }
+
+    fn splice<R: Rand>(
+        &mut self,
+        other: &Self,
+        rand: &mut R,
+        constraints: &FieldConstraints,
+    ) -> MutationResult {
+        match (self, other) {
+            (Some(value), Some(other)) => value.splice(other, rand, constraints),
+            (value, other) => {
+                value.clone_from(other);
+                MutationResult::Mutated
+            }
+        }
+    }
+
+    fn fields<'a>(
+        &'a self,
+        constraints: &FieldConstraints,
+        visitor: &mut dyn FnMut(&'a dyn StructuredField, &FieldConstraints),
+    ) {
+        visitor(self, constraints);
+        if let Some(value) = self {
+            value.fields(constraints, visitor);
+        }
+    }
+
+    fn fields_mut(
+        &mut self,
+        constraints: &FieldConstraints,
+        visitor: &mut dyn FnMut(&mut dyn StructuredField, &FieldConstraints),
+    ) {
+        visitor(self, constraints);
+        if let Some(value) = self {
+            value.fields_mut(constraints, visitor);
+        }
+    }
+}
@@ -0,0 +1,424 @@
+//! Loads `AFL++` custom mutators, shared libraries exporting `afl_custom_*` functions,
+//! see <https://aflplus.plus/docs/custom_mutators/>.
+//!
+//! The [`AflCustomLibrary`] is shared between the parts of the fuzzer using its hooks:
+//! `afl_custom_fuzz` is the [`AflCustomMutator`],
+//! `afl_custom_post_process` the [`crate::executors::AflCustomPostProcessExecutor`],
+//! `afl_custom_trim` the [`crate::stages::AflCustomTrimStage`], and
+//! `afl_custom_queue_new_entry` the [`crate::schedulers::AflCustomQueueScheduler`].
+
+use alloc::{rc::Rc, string::String, vec::Vec};
+use core::{
+    ffi::{c_char, c_uint, c_void, CStr},
+    ptr,
+};
+use std::{ffi::CString, os::unix::ffi::OsStrExt, path::Path};
+
+use libafl_bolts::{rands::Rand, Named};
+
+use crate::{
+    corpus::Corpus,
+    inputs::{HasBytesVec, Input},
+    mutators::{MutationResult, Mutator},
+    random_corpus_id,
+    state::{HasCorpus, HasMaxSize, HasRand},
+    Error,
+};
+
+type InitFn = unsafe extern "C" fn(afl: *mut c_void, seed: c_uint) -> *mut c_void;
+type DeinitFn = unsafe extern "C" fn(data: *mut c_void);
+type FuzzFn = unsafe extern "C" fn(
+    data: *mut c_void,
+    buf: *mut u8,
+    buf_size: usize,
+    out_buf: *mut *mut u8,
+    add_buf: *mut u8,
+    add_buf_size: usize,
+    max_size: usize,
+) -> usize;
+type PostProcessFn = unsafe extern "C" fn(
+    data: *mut c_void,
+    buf: *mut u8,
+    buf_size: usize,
+    out_buf: *mut *mut u8,
+) -> usize;
+type InitTrimFn = unsafe extern "C" fn(data: *mut c_void, buf: *mut u8, buf_size: usize) -> i32;
+type TrimFn = unsafe extern "C" fn(data: *mut c_void, out_buf: *mut *mut u8) -> usize;
+type PostTrimFn = unsafe extern "C" fn(data: *mut c_void, success: u8) -> i32;
+type QueueNewEntryFn = unsafe extern "C" fn(
+    data: *mut c_void,
+    filename_new_queue: *const c_char,
+    filename_orig_queue: *const c_char,
+) -> u8;
+
+/// Looks up the symbol `name`, which must be nul-terminated, as a function of type `F`
+unsafe fn symbol<F: Copy>(handle: *mut c_void, name: &[u8]) -> Option<F> {
+    debug_assert_eq!(name.last(), Some(&0));
+    let sym = libc::dlsym(handle, name.as_ptr().cast());
+    if sym.is_null() {
+        None
+    } else {
+        Some(core::mem::transmute_copy(&sym))
+    }
+}
+
+/// The last error of the dynamic loader
+fn dl_error() -> String {
+    unsafe {
+        let err = libc::dlerror();
+        if err.is_null() {
+            String::from("unknown error")
+        } else {
+            CStr::from_ptr(err).to_string_lossy().into_owned()
+        }
+    }
+}
+
+/// Copies the buffer of `len` bytes a custom mutator returned
+unsafe fn copy_out_buf(out_buf: *const u8, len: usize) -> Vec<u8> {
+    if out_buf.is_null() || len == 0 {
+        Vec::new()
+    } else {
+        core::slice::from_raw_parts(out_buf, len).to_vec()
+    }
+}
+
+/// An `AFL++` custom mutator library, loaded with `dlopen`.
+///
+/// Only `afl_custom_init` is required, all other hooks are optional.
+/// The `afl` state pointer `afl_custom_init` gets is always `NULL`,
+/// so libraries that need `AFL++` internals can't be used.
+#[derive(Debug)]
+pub struct AflCustomLibrary {
+    handle: *mut c_void,
+    data: *mut c_void,
+    deinit: Option<DeinitFn>,
+    fuzz: Option<FuzzFn>,
+    post_process: Option<PostProcessFn>,
+    init_trim: Option<InitTrimFn>,
+    trim: Option<TrimFn>,
+    post_trim: Option<PostTrimFn>,
+    queue_new_entry: Option<QueueNewEntryFn>,
+}
+
+impl AflCustomLibrary {
+    /// Loads the custom mutator library at `path` and initializes it with `seed`.
+    pub fn load<P>(path: P, seed: u32) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = CString::new(path.as_ref().as_os_str().as_bytes())
+            .map_err(|_| Error::illegal_argument("Custom mutator path contains a nul byte"))?;
+        unsafe {
+            let handle = libc::dlopen(path.as_ptr(), libc::RTLD_NOW);
+            if handle.is_null() {
+                return Err(Error::illegal_argument(format!(
+                    "Could not load custom mutator {path:?}: {}",
+                    dl_error()
+                )));
+            }
+            let Some(init) = symbol::<InitFn>(handle, b"afl_custom_init\0") else {
+                libc::dlclose(handle);
+                return Err(Error::illegal_argument(format!(
+                    "Custom mutator {path:?} does not export afl_custom_init"
+                )));
+            };
+            let data = init(ptr::null_mut(), seed);
+            if data.is_null() {
+                libc::dlclose(handle);
+                return Err(Error::illegal_state(format!(
+                    "afl_custom_init of custom mutator {path:?} failed"
+                )));
+            }
+
+            let init_trim = symbol(handle, b"afl_custom_init_trim\0");
+            let trim = symbol(handle, b"afl_custom_trim\0");
+            let post_trim = symbol(handle, b"afl_custom_post_trim\0");
+            // Trimming needs all three of them
+            let has_trim = init_trim.is_some() && trim.is_some() && post_trim.is_some();
+            Ok(Self {
+                handle,
+                data,
+                deinit: symbol(handle, b"afl_custom_deinit\0"),
+                fuzz: symbol(handle, b"afl_custom_fuzz\0"),
+                post_process: symbol(handle, b"afl_custom_post_process\0"),
+                init_trim: init_trim.filter(|_| has_trim),
+                trim: trim.filter(|_| has_trim),
+                post_trim: post_trim.filter(|_| has_trim),
+                queue_new_entry: symbol(handle, b"afl_custom_queue_new_entry\0"),
+            })
+        }
+    }
+
+    /// If the library exports `afl_custom_fuzz`
+    #[must_use]
+    pub fn has_fuzz(&self) -> bool {
+        self.fuzz.is_some()
+    }
+
+    /// If the library exports `afl_custom_post_process`
+    #[must_use]
+    pub fn has_post_process(&self) -> bool {
+        self.post_process.is_some()
+    }
+
+    /// If the library exports `afl_custom_init_trim`, `afl_custom_trim` and `afl_custom_post_trim`
+    #[must_use]
+    pub fn has_trim(&self) -> bool {
+        self.trim.is_some()
+    }
+
+    /// If the library exports `afl_custom_queue_new_entry`
+    #[must_use]
+    pub fn has_queue_new_entry(&self) -> bool {
+        self.queue_new_entry.is_some()
+    }
+
+    /// Mutates `buf`, optionally using `add_buf` for splicing, to at most `max_size` bytes.
+    /// Returns `None` if the library does not fuzz, or failed to.
+    pub fn fuzz(&self, buf: &mut [u8], add_buf: &mut [u8], max_size: usize) -> Option<Vec<u8>> {
+        let fuzz = self.fuzz?;
+        let mut out_buf = ptr::null_mut();
+        let (add_buf_ptr, add_buf_size) = if add_buf.is_empty() {
+            (ptr::null_mut(), 0)
+        } else {
+            (add_buf.as_mut_ptr(), add_buf.len())
+        };
+        unsafe {
+            let len = fuzz(
+                self.data,
+                buf.as_mut_ptr(),
+                buf.len(),
+                &mut out_buf,
+                add_buf_ptr,
+                add_buf_size,
+                max_size,
+            );
+            if len == 0 || out_buf.is_null() {
+                None
+            } else {
+                Some(copy_out_buf(out_buf, len.min(max_size)))
+            }
+        }
+    }
+
+    /// Processes `buf` right before it is sent to the target.
+    /// Returns `None` if the input should not be run at all.
+    /// Without a `afl_custom_post_process`, the input is returned as is.
+    pub fn post_process(&self, buf: &mut [u8]) -> Option<Vec<u8>> {
+        let Some(post_process) = self.post_process else {
+            return Some(buf.to_vec());
+        };
+        let mut out_buf = ptr::null_mut();
+        unsafe {
+            let len = post_process(self.data, buf.as_mut_ptr(), buf.len(), &mut out_buf);
+            if len == 0 {
+                None
+            } else {
+                Some(copy_out_buf(out_buf, len))
+            }
+        }
+    }
+
+    /// Starts trimming `buf`, returning the number of trimming steps
+    pub fn init_trim(&self, buf: &mut [u8]) -> i32 {
+        let Some(init_trim) = self.init_trim else {
+            return 0;
+        };
+        unsafe { init_trim(self.data, buf.as_mut_ptr(), buf.len()) }
+    }
+
+    /// The trimmed candidate of the current trimming step
+    #[must_use]
+    pub fn trim(&self) -> Option<Vec<u8>> {
+        let trim = self.trim?;
+        let mut out_buf = ptr::null_mut();
+        unsafe {
+            let len = trim(self.data, &mut out_buf);
+            if out_buf.is_null() {
+                None
+            } else {
+                Some(copy_out_buf(out_buf, len))
+            }
+        }
+    }
+
+    /// Reports whether the last trimmed candidate kept the behavior of the original,
+    /// returning the next trimming step, or a negative value on error
+    #[must_use]
+    pub fn post_trim(&self, success: bool) -> i32 {
+        let Some(post_trim) = self.post_trim else {
+            return -1;
+        };
+        unsafe { post_trim(self.data, u8::from(success)) }
+    }
+
+    /// Notifies the library of a new corpus entry stored at `new_entry`,
+    /// found while fuzzing the corpus entry stored at `orig_entry`, if any
+    pub fn queue_new_entry(
+        &self,
+        new_entry: &Path,
+        orig_entry: Option<&Path>,
+    ) -> Result<(), Error> {
+        let Some(queue_new_entry) = self.queue_new_entry else {
+            return Ok(());
+        };
+        let to_c_string = |path: &Path| {
+            CString::new(path.as_os_str().as_bytes())
+                .map_err(|_| Error::illegal_argument("Corpus path contains a nul byte"))
+        };
+        let new_entry = to_c_string(new_entry)?;
+        let orig_entry = orig_entry.map(to_c_string).transpose()?;
+        unsafe {
+            queue_new_entry(
+                self.data,
+                new_entry.as_ptr(),
+                orig_entry
+                    .as_ref()
+                    .map_or(ptr::null(), |orig| orig.as_ptr()),
+            );
+        }
+        Ok(())
+    }
+}
+
+impl Drop for AflCustomLibrary {
+    fn drop(&mut self) {
+        unsafe {
+            if let Some(deinit) = self.deinit {
+                deinit(self.data);
+            }
+            if !self.handle.is_null() {
+                libc::dlclose(self.handle);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+impl AflCustomLibrary {
+    /// A library made of hooks compiled into the tests, with nothing loaded behind it
+    pub(crate) fn from_hooks() -> Self {
+        Self {
+            handle: ptr::null_mut(),
+            data: ptr::null_mut(),
+            deinit: None,
+            fuzz: None,
+            post_process: None,
+            init_trim: None,
+            trim: None,
+            post_trim: None,
+            queue_new_entry: None,
+        }
+    }
+
+    pub(crate) fn with_post_process(mut self, post_process: PostProcessFn) -> Self {
+        self.post_process = Some(post_process);
+        self
+    }
+
+    pub(crate) fn with_trim(
+        mut self,
+        init_trim: InitTrimFn,
+        trim: TrimFn,
+        post_trim: PostTrimFn,
+    ) -> Self {
+        self.init_trim = Some(init_trim);
+        self.trim = Some(trim);
+        self.post_trim = Some(post_trim);
+        self
+    }
+
+    pub(crate) fn with_queue_new_entry(mut self, queue_new_entry: QueueNewEntryFn) -> Self {
+        self.queue_new_entry = Some(queue_new_entry);
+        self
+    }
+}
+
+/// Mutates inputs with the `afl_custom_fuzz` of an `AFL++` custom mutator library,
+/// splicing with random corpus entries
+#[derive(Debug, Clone)]
+pub struct AflCustomMutator {
+    library: Rc<AflCustomLibrary>,
+}
+
+impl<I, S> Mutator<I, S> for AflCustomMutator
+where
+    I: Input + HasBytesVec,
+    S: HasCorpus<Input = I> + HasRand + HasMaxSize,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let mut add_buf = if state.corpus().count() == 0 {
+            Vec::new()
+        } else {
+            let idx = random_corpus_id!(state.corpus(), state.rand_mut());
+            let mut other_testcase = state.corpus().get(idx)?.borrow_mut();
+            let add_buf = other_testcase.load_input(state.corpus())?.bytes().to_vec();
+            add_buf
+        };
+
+        let max_size = state.max_size();
+        match self.library.fuzz(input.bytes_mut(), &mut add_buf, max_size) {
+            Some(mutated) => {
+                *input.bytes_mut() = mutated;
+                Ok(MutationResult::Mutated)
+            }
+            None => Ok(MutationResult::Skipped),
+        }
+    }
+}
+
+impl Named for AflCustomMutator {
+    fn name(&self) -> &str {
+        "AflCustomMutator"
+    }
+}
+
+impl AflCustomMutator {
+    /// Loads the custom mutator library at `path`, see [`AflCustomLibrary::load`],
+    /// and creates a new [`AflCustomMutator`] using it.
+    pub fn new<P>(path: P, seed: u32) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let library = AflCustomLibrary::load(path, seed)?;
+        if !library.has_fuzz() {
+            return Err(Error::illegal_argument(
+                "Custom mutator does not export afl_custom_fuzz",
+            ));
+        }
+        Ok(Self::with_library(Rc::new(library)))
+    }
+
+    /// Creates a new [`AflCustomMutator`] using an already loaded library
+    #[must_use]
+    pub fn with_library(library: Rc<AflCustomLibrary>) -> Self {
+        Self { library }
+    }
+
+    /// The loaded library, to share with the other users of its hooks
+    #[must_use]
+    pub fn library(&self) -> Rc<AflCustomLibrary> {
+        self.library.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::mutators::{AflCustomLibrary, AflCustomMutator};
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    #[cfg_attr(miri, ignore)]
+    fn test_afl_custom_load() {
+        assert!(AflCustomMutator::new("/nonexistent/custom_mutator.so", 0).is_err());
+        // A library, but not a custom mutator
+        let err = AflCustomLibrary::load("libc.so.6", 0).unwrap_err();
+        assert!(format!("{err:?}").contains("afl_custom_init"));
+    }
+}
@@ -0,0 +1,359 @@
+//! Mutations for [`CallSequenceInput`]s: inserting, removing and reordering calls,
+//! or changing the arguments of a single call. Resource references stay valid throughout.
+
+use alloc::vec::Vec;
+
+use libafl_bolts::{
+    rands::Rand,
+    tuples::{tuple_list, tuple_list_type},
+    HasLen, Named,
+};
+
+use crate::{
+    inputs::{Call, CallSequenceInput, ResourceRef},
+    mutators::{MutationResult, Mutator},
+    state::HasRand,
+    Error,
+};
+
+/// The default maximum number of calls the [`CallInsertMutator`] grows a sequence to
+pub const DEFAULT_MAX_CALLS: usize = 64;
+
+/// Inserts a newly generated call at a random position of the sequence,
+/// taking its resources from the calls before it
+#[derive(Debug)]
+pub struct CallInsertMutator {
+    max_calls: usize,
+}
+
+impl<C, S> Mutator<CallSequenceInput<C>, S> for CallInsertMutator
+where
+    C: Call,
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut CallSequenceInput<C>,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        if input.len() >= self.max_calls {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let rand = state.rand_mut();
+        let call = C::generate(rand);
+        let position = rand.below(input.len() as u64 + 1) as usize;
+        if input.insert_call(position, call, rand) {
+            Ok(MutationResult::Mutated)
+        } else {
+            Ok(MutationResult::Skipped)
+        }
+    }
+}
+
+impl Named for CallInsertMutator {
+    fn name(&self) -> &str {
+        "CallInsertMutator"
+    }
+}
+
+impl Default for CallInsertMutator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CallInsertMutator {
+    /// Creates a new [`CallInsertMutator`], growing sequences up to [`DEFAULT_MAX_CALLS`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_max_calls(DEFAULT_MAX_CALLS)
+    }
+
+    /// Creates a new [`CallInsertMutator`], growing sequences up to `max_calls`.
+    #[must_use]
+    pub fn with_max_calls(max_calls: usize) -> Self {
+        Self { max_calls }
+    }
+}
+
+/// Removes a random call, as long as there are at least two of them.
+/// Later calls using its resource are bound to another call returning one of the same kind.
+#[derive(Default, Debug)]
+pub struct CallRemoveMutator;
+
+impl<C, S> Mutator<CallSequenceInput<C>, S> for CallRemoveMutator
+where
+    C: Call,
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut CallSequenceInput<C>,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        if input.len() < 2 {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let rand = state.rand_mut();
+        let position = rand.below(input.len() as u64) as usize;
+        if input.remove_call(position, rand) {
+            Ok(MutationResult::Mutated)
+        } else {
+            Ok(MutationResult::Skipped)
+        }
+    }
+}
+
+impl Named for CallRemoveMutator {
+    fn name(&self) -> &str {
+        "CallRemoveMutator"
+    }
+}
+
+impl CallRemoveMutator {
+    /// Creates a new [`CallRemoveMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Moves a random call to another position of the sequence
+#[derive(Default, Debug)]
+pub struct CallMoveMutator;
+
+impl<C, S> Mutator<CallSequenceInput<C>, S> for CallMoveMutator
+where
+    C: Call,
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut CallSequenceInput<C>,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        if input.len() < 2 {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let rand = state.rand_mut();
+        let from = rand.below(input.len() as u64) as usize;
+        let to = rand.below(input.len() as u64) as usize;
+        if from != to && input.move_call(from, to, rand) {
+            Ok(MutationResult::Mutated)
+        } else {
+            Ok(MutationResult::Skipped)
+        }
+    }
+}
+
+impl Named for CallMoveMutator {
+    fn name(&self) -> &str {
+        "CallMoveMutator"
+    }
+}
+
+impl CallMoveMutator {
+    /// Creates a new [`CallMoveMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Mutates an argument of a random call, see [`Call::mutate_args`]
+#[derive(Default, Debug)]
+pub struct CallArgsMutator;
+
+impl<C, S> Mutator<CallSequenceInput<C>, S> for CallArgsMutator
+where
+    C: Call,
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut CallSequenceInput<C>,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        if input.len() == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let rand = state.rand_mut();
+        let idx = rand.below(input.len() as u64) as usize;
+        Ok(input.calls_mut()[idx].mutate_args(rand))
+    }
+}
+
+impl Named for CallArgsMutator {
+    fn name(&self) -> &str {
+        "CallArgsMutator"
+    }
+}
+
+impl CallArgsMutator {
+    /// Creates a new [`CallArgsMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Binds a random resource argument to another earlier call returning a resource of its kind
+#[derive(Default, Debug)]
+pub struct CallResourceMutator;
+
+impl<C, S> Mutator<CallSequenceInput<C>, S> for CallResourceMutator
+where
+    C: Call,
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut CallSequenceInput<C>,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        if input.len() == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let rand = state.rand_mut();
+        let idx = rand.below(input.len() as u64) as usize;
+        let resources = input.calls()[idx].resources();
+        if resources.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        let arg = rand.below(resources.len() as u64) as usize;
+        let (kind, old) = resources[arg];
+        let candidates: Vec<usize> = input
+            .producers(kind, idx)
+            .into_iter()
+            .filter(|call| *call != old.call())
+            .collect();
+        if candidates.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        let new = *rand.choose(&candidates);
+        *input.calls_mut()[idx].resources_mut()[arg].1 = ResourceRef::new(new);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for CallResourceMutator {
+    fn name(&self) -> &str {
+        "CallResourceMutator"
+    }
+}
+
+impl CallResourceMutator {
+    /// Creates a new [`CallResourceMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Tuple type of the mutations for [`CallSequenceInput`]s
+pub type CallSequenceMutationsType = tuple_list_type!(
+    CallInsertMutator,
+    CallRemoveMutator,
+    CallMoveMutator,
+    CallArgsMutator,
+    CallResourceMutator,
+);
+
+/// Get the mutations for [`CallSequenceInput`]s
+#[must_use]
+pub fn call_sequence_mutations() -> CallSequenceMutationsType {
+    tuple_list!(
+        CallInsertMutator::new(),
+        CallRemoveMutator::new(),
+        CallMoveMutator::new(),
+        CallArgsMutator::new(),
+        CallResourceMutator::new(),
+    )
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod tests {
+    use alloc::{string::String, vec::Vec};
+
+    use libafl_bolts::{rands::StdRand, HasLen};
+    use serde::{Deserialize, Serialize};
+
+    use crate::{
+        self as libafl,
+        corpus::InMemoryCorpus,
+        feedbacks::ConstFeedback,
+        generators::{CallSequenceGenerator, Generator},
+        inputs::{Call, CallSequenceInput, ResourceRef},
+        mutators::{call_sequence_mutations, MutationResult, MutatorsTuple},
+        state::StdState,
+    };
+
+    #[derive(Call, Serialize, Deserialize, Clone, Debug, Hash)]
+    enum FileOp {
+        #[call(returns = "file")]
+        Open {
+            path: String,
+            flags: u32,
+        },
+        Write {
+            #[call(resource = "file")]
+            file: ResourceRef,
+            data: Vec<u8>,
+        },
+        Dup(#[call(resource = "file")] ResourceRef),
+        Sync,
+    }
+
+    #[test]
+    fn test_call_sequence_mutations() {
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(1337),
+            InMemoryCorpus::<CallSequenceInput<FileOp>>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+
+        let mut input: CallSequenceInput<FileOp> =
+            CallSequenceGenerator::new(8).generate(&mut state).unwrap();
+        assert!(input.is_valid());
+        assert!(FileOp::Sync.resources().is_empty());
+        assert_eq!(
+            FileOp::Open {
+                path: String::new(),
+                flags: 0
+            }
+            .returns(),
+            Some("file")
+        );
+
+        let mut mutations = call_sequence_mutations();
+        let mut mutated = 0;
+        for i in 0..1000 {
+            let idx = i % 5;
+            if mutations
+                .get_and_mutate(idx.into(), &mut state, &mut input, 0)
+                .unwrap()
+                == MutationResult::Mutated
+            {
+                mutated += 1;
+            }
+            assert!(input.is_valid());
+            assert!(input.len() > 0);
+        }
+        assert!(mutated > 0);
+    }
+}
@@ -19,6 +19,18 @@ pub mod grimoire;
 pub use grimoire::*;
 pub mod tuneable;
 pub use tuneable::*;
+pub mod packet_mutations;
+pub use packet_mutations::*;
+pub mod call_sequence_mutations;
+pub use call_sequence_mutations::*;
+pub mod structured;
+pub use structured::*;
+pub mod multi;
+pub use multi::*;
+#[cfg(all(feature = "std", unix))]
+pub mod afl_custom;
+#[cfg(all(feature = "std", unix))]
+pub use afl_custom::*;
 
 #[cfg(feature = "nautilus")]
 pub mod nautilus;
@@ -0,0 +1,252 @@
+//! Mutations for [`MultipartInput`]s: mutating a single part with the mutator routed to its name,
+//! or splicing a part with the same-named part of another corpus entry.
+
+use alloc::{string::String, vec::Vec};
+
+use libafl_bolts::{rands::Rand, HasLen, Named};
+
+use crate::{
+    corpus::{Corpus, CorpusId},
+    inputs::{HasBytesVec, Input, MultipartInput},
+    mutators::{MutationResult, Mutator, MutatorsTuple},
+    random_corpus_id,
+    state::{HasCorpus, HasMaxSize, HasRand},
+    Error,
+};
+
+/// Mutates a single, random, part of a [`MultipartInput`].
+/// The part is mutated by one of the `mutators`, picked by the part's name, see [`Self::with_route`].
+/// Parts without a route go to the first mutator.
+#[derive(Debug)]
+pub struct MultipartMutator<MT> {
+    name: String,
+    mutators: MT,
+    routes: Vec<(String, usize)>,
+}
+
+impl<I, MT, S> Mutator<MultipartInput<I>, S> for MultipartMutator<MT>
+where
+    MT: MutatorsTuple<I, S>,
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut MultipartInput<I>,
+        stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        if input.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let idx = state.rand_mut().below(input.len() as u64) as usize;
+        let (name, part) = input.part_mut(idx).unwrap();
+        let mutator_idx = self.route(name);
+        self.mutators
+            .get_and_mutate(mutator_idx.into(), state, part, stage_idx)
+    }
+
+    fn post_exec(
+        &mut self,
+        state: &mut S,
+        stage_idx: i32,
+        corpus_idx: Option<CorpusId>,
+    ) -> Result<(), Error> {
+        self.mutators.post_exec_all(state, stage_idx, corpus_idx)
+    }
+}
+
+impl<MT> Named for MultipartMutator<MT> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<MT> MultipartMutator<MT> {
+    /// Creates a new [`MultipartMutator`], mutating all parts with the first of the `mutators`
+    /// until routes are added.
+    #[must_use]
+    pub fn new(mutators: MT) -> Self {
+        Self {
+            name: String::from("MultipartMutator"),
+            mutators,
+            routes: Vec::new(),
+        }
+    }
+
+    /// Mutates parts with the given name using the mutator at `mutator_idx` of the tuple.
+    #[must_use]
+    pub fn with_route<N>(mut self, name: N, mutator_idx: usize) -> Self
+    where
+        N: Into<String>,
+    {
+        self.routes.push((name.into(), mutator_idx));
+        self
+    }
+
+    /// The index of the mutator for parts with the given name
+    #[must_use]
+    pub fn route(&self, name: &str) -> usize {
+        self.routes
+            .iter()
+            .find(|(part_name, _)| part_name == name)
+            .map_or(0, |(_, mutator_idx)| *mutator_idx)
+    }
+}
+
+/// Splices a random part of the input with a part of the same name from another corpus entry,
+/// keeping the start of the former and the end of the latter
+#[derive(Default, Debug)]
+pub struct MultipartSpliceMutator;
+
+impl<I, S> Mutator<MultipartInput<I>, S> for MultipartSpliceMutator
+where
+    I: Input + HasBytesVec,
+    S: HasCorpus<Input = MultipartInput<I>> + HasRand + HasMaxSize,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut MultipartInput<I>,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        if input.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        let other_idx = random_corpus_id!(state.corpus(), state.rand_mut());
+        if *state.corpus().current() == Some(other_idx) {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let idx = state.rand_mut().below(input.len() as u64) as usize;
+        let name = input.names()[idx].clone();
+        let donor_lens: Vec<usize> = {
+            let mut other_testcase = state.corpus().get(other_idx)?.borrow_mut();
+            let other = other_testcase.load_input(state.corpus())?;
+            other
+                .parts_by_name(&name)
+                .map(|part| part.bytes().len())
+                .collect()
+        };
+        if donor_lens.is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+        let donor = state.rand_mut().below(donor_lens.len() as u64) as usize;
+        if donor_lens[donor] == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        let from = state.rand_mut().below(donor_lens[donor] as u64) as usize;
+        let tail = {
+            let other_testcase = state.corpus().get(other_idx)?.borrow();
+            // Input will already be loaded.
+            let other = other_testcase.input().as_ref().unwrap();
+            let tail = other.parts_by_name(&name).nth(donor).unwrap().bytes()[from..].to_vec();
+            tail
+        };
+
+        let others_size: usize = input
+            .parts()
+            .iter()
+            .enumerate()
+            .filter(|(part_idx, _)| *part_idx != idx)
+            .map(|(_, part)| part.bytes().len())
+            .sum();
+        let max_len = state.max_size().saturating_sub(others_size);
+
+        let part = &mut input.parts_mut()[idx];
+        let cut = state.rand_mut().below(part.bytes().len() as u64 + 1) as usize;
+        let bytes = part.bytes_mut();
+        bytes.truncate(cut);
+        bytes.extend_from_slice(&tail);
+        bytes.truncate(max_len);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for MultipartSpliceMutator {
+    fn name(&self) -> &str {
+        "MultipartSpliceMutator"
+    }
+}
+
+impl MultipartSpliceMutator {
+    /// Creates a new [`MultipartSpliceMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::{rands::StdRand, tuples::tuple_list, HasLen};
+
+    use crate::{
+        corpus::{Corpus, InMemoryCorpus},
+        feedbacks::ConstFeedback,
+        inputs::{BytesInput, HasBytesVec, MultipartInput},
+        mutators::{
+            BytesDeleteMutator, BytesInsertMutator, MultipartMutator, MultipartSpliceMutator,
+            MutationResult, Mutator,
+        },
+        state::{HasMaxSize, StdState},
+    };
+
+    fn input(image: &[u8], config: &[u8]) -> MultipartInput<BytesInput> {
+        MultipartInput::from(vec![
+            ("image", BytesInput::new(image.to_vec())),
+            ("config", BytesInput::new(config.to_vec())),
+        ])
+    }
+
+    #[test]
+    fn test_multipart_mutations() {
+        let mut corpus = InMemoryCorpus::new();
+        corpus
+            .add(input(b"OTHERIMAGE", b"OTHERCONFIG").into())
+            .unwrap();
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(1337),
+            corpus,
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        state.set_max_size(64);
+
+        // Images only grow, configs only shrink
+        let mut mutator = MultipartMutator::new(tuple_list!(
+            BytesInsertMutator::new(),
+            BytesDeleteMutator::new()
+        ))
+        .with_route("config", 1);
+        let mut multipart = input(b"IMAGE", b"CONFIG");
+        for _ in 0..8 {
+            mutator.mutate(&mut state, &mut multipart, 0).unwrap();
+        }
+        assert!(multipart.parts()[0].bytes().len() >= 5);
+        assert!(multipart.parts()[1].bytes().len() <= 6);
+
+        let mut splice = MultipartSpliceMutator::new();
+        let mut multipart = input(b"IMAGE", b"CONFIG");
+        let mut mutated = false;
+        for _ in 0..16 {
+            mutated |=
+                splice.mutate(&mut state, &mut multipart, 0).unwrap() == MutationResult::Mutated;
+        }
+        assert!(mutated);
+        assert_eq!(multipart.len(), 2);
+        // Parts are only spliced with parts of the same name
+        assert!(!multipart.parts()[0]
+            .bytes()
+            .windows(6)
+            .any(|w| w == b"CONFIG"));
+        assert!(!multipart.parts()[1]
+            .bytes()
+            .windows(5)
+            .any(|w| w == b"IMAGE"));
+    }
+}
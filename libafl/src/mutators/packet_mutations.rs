@@ -0,0 +1,393 @@
+//! Mutations for [`PacketSequenceInput`]s: reordering whole messages of the sequence,
+//! or mutating the bytes within a single one of them.
+
+use alloc::string::String;
+
+use libafl_bolts::{
+    rands::Rand,
+    tuples::{tuple_list, tuple_list_type},
+    HasLen, Named,
+};
+
+use crate::{
+    corpus::{Corpus, CorpusId},
+    inputs::{BytesInput, HasBytesVec, PacketSequenceInput},
+    mutators::{
+        havoc_mutations_no_crossover, HavocMutationsNoCrossoverType, MutationResult, Mutator,
+        StdScheduledMutator,
+    },
+    random_corpus_id,
+    state::{HasCorpus, HasMaxSize, HasRand},
+    Error,
+};
+
+/// The total size of all packets
+fn total_size(input: &PacketSequenceInput) -> usize {
+    input
+        .packets()
+        .iter()
+        .map(|packet| packet.bytes().len())
+        .sum()
+}
+
+/// Picks another corpus entry than the one currently fuzzed, to take packets from
+fn other_corpus_id<S>(state: &mut S) -> Option<CorpusId>
+where
+    S: HasCorpus + HasRand,
+{
+    let idx = random_corpus_id!(state.corpus(), state.rand_mut());
+    if *state.corpus().current() == Some(idx) {
+        None
+    } else {
+        Some(idx)
+    }
+}
+
+/// Inserts a packet of another corpus entry at a random position of the sequence
+#[derive(Default, Debug)]
+pub struct PacketInsertMutator;
+
+impl<S> Mutator<PacketSequenceInput, S> for PacketInsertMutator
+where
+    S: HasCorpus<Input = PacketSequenceInput> + HasRand + HasMaxSize,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut PacketSequenceInput,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let Some(idx) = other_corpus_id(state) else {
+            return Ok(MutationResult::Skipped);
+        };
+
+        let other_len = {
+            let mut other_testcase = state.corpus().get(idx)?.borrow_mut();
+            other_testcase.load_input(state.corpus())?.len()
+        };
+        if other_len == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        let from = state.rand_mut().below(other_len as u64) as usize;
+        // No need to load the input again, it'll still be cached.
+        let packet = state
+            .corpus()
+            .get(idx)?
+            .borrow()
+            .input()
+            .as_ref()
+            .unwrap()
+            .packets()[from]
+            .clone();
+
+        if total_size(input) + packet.bytes().len() > state.max_size() {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let to = state.rand_mut().below(input.len() as u64 + 1) as usize;
+        input.packets_mut().insert(to, packet);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for PacketInsertMutator {
+    fn name(&self) -> &str {
+        "PacketInsertMutator"
+    }
+}
+
+impl PacketInsertMutator {
+    /// Creates a new [`PacketInsertMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Deletes a random packet, as long as there are at least two of them
+#[derive(Default, Debug)]
+pub struct PacketDeleteMutator;
+
+impl<S> Mutator<PacketSequenceInput, S> for PacketDeleteMutator
+where
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut PacketSequenceInput,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        if input.len() < 2 {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let idx = state.rand_mut().below(input.len() as u64) as usize;
+        input.packets_mut().remove(idx);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for PacketDeleteMutator {
+    fn name(&self) -> &str {
+        "PacketDeleteMutator"
+    }
+}
+
+impl PacketDeleteMutator {
+    /// Creates a new [`PacketDeleteMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Sends a random packet twice in a row
+#[derive(Default, Debug)]
+pub struct PacketDuplicateMutator;
+
+impl<S> Mutator<PacketSequenceInput, S> for PacketDuplicateMutator
+where
+    S: HasRand + HasMaxSize,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut PacketSequenceInput,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        if input.packets().is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let idx = state.rand_mut().below(input.len() as u64) as usize;
+        let packet = input.packets()[idx].clone();
+        if total_size(input) + packet.bytes().len() > state.max_size() {
+            return Ok(MutationResult::Skipped);
+        }
+        input.packets_mut().insert(idx + 1, packet);
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for PacketDuplicateMutator {
+    fn name(&self) -> &str {
+        "PacketDuplicateMutator"
+    }
+}
+
+impl PacketDuplicateMutator {
+    /// Creates a new [`PacketDuplicateMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Continues the sequence, from a random packet on, with the packets of another corpus entry
+#[derive(Default, Debug)]
+pub struct PacketSpliceMutator;
+
+impl<S> Mutator<PacketSequenceInput, S> for PacketSpliceMutator
+where
+    S: HasCorpus<Input = PacketSequenceInput> + HasRand + HasMaxSize,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut PacketSequenceInput,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let Some(idx) = other_corpus_id(state) else {
+            return Ok(MutationResult::Skipped);
+        };
+
+        let cut = state.rand_mut().below(input.len() as u64 + 1) as usize;
+        let other_len = {
+            let mut other_testcase = state.corpus().get(idx)?.borrow_mut();
+            other_testcase.load_input(state.corpus())?.len()
+        };
+        if other_len == 0 {
+            return Ok(MutationResult::Skipped);
+        }
+        let from = state.rand_mut().below(other_len as u64) as usize;
+        let tail = state
+            .corpus()
+            .get(idx)?
+            .borrow()
+            .input()
+            .as_ref()
+            .unwrap()
+            .packets()[from..]
+            .to_vec();
+
+        input.packets_mut().truncate(cut);
+        input.packets_mut().extend(tail);
+        while input.len() > 1 && total_size(input) > state.max_size() {
+            input.packets_mut().pop();
+        }
+        Ok(MutationResult::Mutated)
+    }
+}
+
+impl Named for PacketSpliceMutator {
+    fn name(&self) -> &str {
+        "PacketSpliceMutator"
+    }
+}
+
+impl PacketSpliceMutator {
+    /// Creates a new [`PacketSpliceMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Mutates the bytes of a single, random, packet with the given [`BytesInput`] mutator,
+/// for example a [`StdScheduledMutator`] of [`havoc_mutations_no_crossover`].
+#[derive(Debug)]
+pub struct PacketHavocMutator<M> {
+    name: String,
+    mutator: M,
+}
+
+impl<M, S> Mutator<PacketSequenceInput, S> for PacketHavocMutator<M>
+where
+    M: Mutator<BytesInput, S>,
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut PacketSequenceInput,
+        stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        if input.packets().is_empty() {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let idx = state.rand_mut().below(input.len() as u64) as usize;
+        self.mutator
+            .mutate(state, &mut input.packets_mut()[idx], stage_idx)
+    }
+
+    fn post_exec(
+        &mut self,
+        state: &mut S,
+        stage_idx: i32,
+        corpus_idx: Option<CorpusId>,
+    ) -> Result<(), Error> {
+        self.mutator.post_exec(state, stage_idx, corpus_idx)
+    }
+}
+
+impl<M> Named for PacketHavocMutator<M> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<M> PacketHavocMutator<M>
+where
+    M: Named,
+{
+    /// Creates a new [`PacketHavocMutator`], mutating single packets with `mutator`.
+    #[must_use]
+    pub fn new(mutator: M) -> Self {
+        Self {
+            name: format!("PacketHavocMutator[{}]", mutator.name()),
+            mutator,
+        }
+    }
+}
+
+/// Tuple type of the mutations for [`PacketSequenceInput`]s
+pub type PacketMutationsType<S> = tuple_list_type!(
+    PacketInsertMutator,
+    PacketDeleteMutator,
+    PacketDuplicateMutator,
+    PacketSpliceMutator,
+    PacketHavocMutator<StdScheduledMutator<BytesInput, HavocMutationsNoCrossoverType, S>>,
+);
+
+/// Get the mutations for [`PacketSequenceInput`]s, changing the sequence of packets,
+/// or havocing the bytes of one of them
+#[must_use]
+pub fn packet_mutations<S>() -> PacketMutationsType<S>
+where
+    S: HasRand + HasMaxSize,
+{
+    tuple_list!(
+        PacketInsertMutator::new(),
+        PacketDeleteMutator::new(),
+        PacketDuplicateMutator::new(),
+        PacketSpliceMutator::new(),
+        PacketHavocMutator::new(StdScheduledMutator::new(havoc_mutations_no_crossover())),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::{rands::StdRand, HasLen};
+
+    use crate::{
+        corpus::{Corpus, InMemoryCorpus},
+        feedbacks::ConstFeedback,
+        inputs::PacketSequenceInput,
+        mutators::{
+            packet_mutations::{
+                PacketDeleteMutator, PacketDuplicateMutator, PacketInsertMutator,
+                PacketSpliceMutator,
+            },
+            MutationResult, Mutator,
+        },
+        state::StdState,
+    };
+
+    #[test]
+    fn test_packet_mutations() {
+        let mut corpus = InMemoryCorpus::new();
+        corpus
+            .add(PacketSequenceInput::from(vec![b"HELO".to_vec(), b"QUIT".to_vec()]).into())
+            .unwrap();
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(1337),
+            corpus,
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+
+        let mut input = PacketSequenceInput::from(vec![b"USER".to_vec()]);
+
+        // Single packets are never deleted
+        let res = PacketDeleteMutator::new().mutate(&mut state, &mut input, 0);
+        assert_eq!(res.unwrap(), MutationResult::Skipped);
+
+        PacketDuplicateMutator::new()
+            .mutate(&mut state, &mut input, 0)
+            .unwrap();
+        assert_eq!(input.len(), 2);
+        assert_eq!(input.packets()[0], input.packets()[1]);
+
+        PacketInsertMutator::new()
+            .mutate(&mut state, &mut input, 0)
+            .unwrap();
+        assert_eq!(input.len(), 3);
+
+        PacketSpliceMutator::new()
+            .mutate(&mut state, &mut input, 0)
+            .unwrap();
+        assert!(!input.packets().is_empty());
+
+        while input.len() > 1 {
+            PacketDeleteMutator::new()
+                .mutate(&mut state, &mut input, 0)
+                .unwrap();
+        }
+    }
+}
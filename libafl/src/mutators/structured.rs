@@ -0,0 +1,329 @@
+//! Mutations for structured inputs, see [`crate::inputs::structured`]:
+//! mutating a single field, splicing with another corpus entry,
+//! or replacing a field with a field of the same type from another corpus entry, like `Grimoire` does for strings.
+
+use alloc::vec::Vec;
+
+use libafl_bolts::{
+    rands::Rand,
+    tuples::{tuple_list, tuple_list_type},
+    Named,
+};
+
+use crate::{
+    corpus::Corpus,
+    inputs::{FieldConstraints, Input, Mutate, StructuredField},
+    mutators::{MutationResult, Mutator},
+    random_corpus_id,
+    state::{HasCorpus, HasRand},
+    Error,
+};
+
+/// Loads a clone of another corpus entry than the one currently fuzzed
+fn other_input<S>(state: &mut S) -> Result<Option<S::Input>, Error>
+where
+    S: HasCorpus + HasRand,
+{
+    let idx = random_corpus_id!(state.corpus(), state.rand_mut());
+    if *state.corpus().current() == Some(idx) {
+        return Ok(None);
+    }
+    let mut other_testcase = state.corpus().get(idx)?.borrow_mut();
+    Ok(Some(other_testcase.load_input(state.corpus())?.clone()))
+}
+
+/// Mutates a random field of the input, see [`Mutate::mutate`]
+#[derive(Default, Debug)]
+pub struct StructuredMutator;
+
+impl<I, S> Mutator<I, S> for StructuredMutator
+where
+    I: Input + Mutate,
+    S: HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        Ok(input.mutate(state.rand_mut(), &FieldConstraints::NONE))
+    }
+}
+
+impl Named for StructuredMutator {
+    fn name(&self) -> &str {
+        "StructuredMutator"
+    }
+}
+
+impl StructuredMutator {
+    /// Creates a new [`StructuredMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Splices the input with another corpus entry, see [`Mutate::splice`]
+#[derive(Default, Debug)]
+pub struct StructuredSpliceMutator;
+
+impl<I, S> Mutator<I, S> for StructuredSpliceMutator
+where
+    I: Input + Mutate,
+    S: HasCorpus<Input = I> + HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let Some(other) = other_input(state)? else {
+            return Ok(MutationResult::Skipped);
+        };
+        Ok(input.splice(&other, state.rand_mut(), &FieldConstraints::NONE))
+    }
+}
+
+impl Named for StructuredSpliceMutator {
+    fn name(&self) -> &str {
+        "StructuredSpliceMutator"
+    }
+}
+
+impl StructuredSpliceMutator {
+    /// Creates a new [`StructuredSpliceMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Replaces a random field of the input, at any depth,
+/// with a field of the same type and constraints from another corpus entry
+#[derive(Default, Debug)]
+pub struct StructuredCrossoverMutator;
+
+impl<I, S> Mutator<I, S> for StructuredCrossoverMutator
+where
+    I: Input + Mutate,
+    S: HasCorpus<Input = I> + HasRand,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        _stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let Some(other) = other_input(state)? else {
+            return Ok(MutationResult::Skipped);
+        };
+        let mut donors: Vec<(&dyn StructuredField, FieldConstraints)> = Vec::new();
+        other.fields(&FieldConstraints::NONE, &mut |field, constraints| {
+            donors.push((field, *constraints));
+        });
+
+        let mut count = 0;
+        input.fields_mut(&FieldConstraints::NONE, &mut |_, _| count += 1);
+        // The first field is the whole input
+        if count < 2 {
+            return Ok(MutationResult::Skipped);
+        }
+
+        let rand = state.rand_mut();
+        let target = rand.between(1, count - 1);
+        let mut idx = 0;
+        let mut result = MutationResult::Skipped;
+        input.fields_mut(&FieldConstraints::NONE, &mut |field, constraints| {
+            if idx == target {
+                // Only fields generated within the same constraints keep the target field within its own.
+                // Called as functions, so they don't resolve to the impl for the reference itself
+                let candidates: Vec<&dyn StructuredField> = donors
+                    .iter()
+                    .filter(|(donor, donor_constraints)| {
+                        StructuredField::field_type_id(*donor)
+                            == StructuredField::field_type_id(field)
+                            && donor_constraints == constraints
+                    })
+                    .map(|(donor, _)| *donor)
+                    .collect();
+                if !candidates.is_empty() {
+                    let donor = StructuredField::as_any(*rand.choose(&candidates));
+                    if field.replace_with(donor) {
+                        result = MutationResult::Mutated;
+                    }
+                }
+            }
+            idx += 1;
+        });
+        Ok(result)
+    }
+}
+
+impl Named for StructuredCrossoverMutator {
+    fn name(&self) -> &str {
+        "StructuredCrossoverMutator"
+    }
+}
+
+impl StructuredCrossoverMutator {
+    /// Creates a new [`StructuredCrossoverMutator`].
+    #[must_use]
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Tuple type of the mutations for structured inputs
+pub type StructuredMutationsType = tuple_list_type!(
+    StructuredMutator,
+    StructuredSpliceMutator,
+    StructuredCrossoverMutator,
+);
+
+/// Get the mutations for structured inputs, to be scheduled by a [`crate::mutators::StdScheduledMutator`]
+#[must_use]
+pub fn structured_mutations() -> StructuredMutationsType {
+    tuple_list!(
+        StructuredMutator::new(),
+        StructuredSpliceMutator::new(),
+        StructuredCrossoverMutator::new(),
+    )
+}
+
+#[cfg(all(test, feature = "derive"))]
+mod tests {
+    use alloc::{string::String, vec::Vec};
+
+    use libafl_bolts::rands::StdRand;
+    use serde::{Deserialize, Serialize};
+
+    use crate::{
+        self as libafl,
+        corpus::{Corpus, InMemoryCorpus},
+        feedbacks::ConstFeedback,
+        generators::{Generator, StructuredGenerator},
+        inputs::{FieldConstraints, Mutate},
+        mutators::{
+            structured_mutations, MutationResult, Mutator, StdScheduledMutator,
+            StructuredCrossoverMutator,
+        },
+        state::{HasCorpus, StdState},
+    };
+
+    #[derive(Generate, Mutate, Serialize, Deserialize, Clone, Debug, PartialEq)]
+    enum Shape {
+        Circle(#[mutate(range = 0.5..2.0)] f64),
+        Polygon {
+            #[mutate(range = 3..=8)]
+            corners: u8,
+            name: Option<String>,
+        },
+        Empty,
+    }
+
+    #[derive(Generate, Mutate, Serialize, Deserialize, Clone, Debug, PartialEq)]
+    struct Scene {
+        #[mutate(range = -10..10)]
+        depth: i32,
+        #[mutate(max_len = 4)]
+        shapes: Vec<Shape>,
+        #[mutate(skip)]
+        cached: u64,
+    }
+
+    fn assert_constraints(scene: &Scene) {
+        assert!((-10..10).contains(&scene.depth));
+        assert!(scene.shapes.len() <= 4);
+        assert_eq!(scene.cached, 0);
+        for shape in &scene.shapes {
+            match shape {
+                Shape::Circle(radius) => assert!((0.5..=2.0).contains(radius)),
+                Shape::Polygon { corners, .. } => assert!((3..=8).contains(corners)),
+                Shape::Empty => {}
+            }
+        }
+    }
+
+    #[test]
+    fn test_structured_mutations() {
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(1337),
+            InMemoryCorpus::<Scene>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+
+        let mut generator = StructuredGenerator::new();
+        for _ in 0..8 {
+            let scene = generator.generate(&mut state).unwrap();
+            assert_constraints(&scene);
+            state.corpus_mut().add(scene.into()).unwrap();
+        }
+
+        let mut mutator = StdScheduledMutator::new(structured_mutations());
+        let mut input: Scene = generator.generate(&mut state).unwrap();
+        for _ in 0..1000 {
+            mutator.mutate(&mut state, &mut input, 0).unwrap();
+            assert_constraints(&input);
+        }
+
+        let mut count = 0;
+        input.fields(&FieldConstraints::NONE, &mut |_, _| count += 1);
+        // The scene, its depth and shape vector, at least
+        assert!(count >= 3);
+    }
+
+    #[derive(Generate, Mutate, Serialize, Deserialize, Clone, Debug, PartialEq)]
+    struct Window {
+        #[mutate(range = 0..=9)]
+        low: u32,
+        #[mutate(range = 100..=199)]
+        high: u32,
+        #[mutate(max_len = 2)]
+        short: String,
+        #[mutate(max_len = 16)]
+        long: String,
+    }
+
+    #[test]
+    fn test_crossover_keeps_constraints() {
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(1337),
+            InMemoryCorpus::<Window>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+
+        let mut generator = StructuredGenerator::new();
+        for _ in 0..8 {
+            let window: Window = generator.generate(&mut state).unwrap();
+            state.corpus_mut().add(window.into()).unwrap();
+        }
+
+        let mut mutator = StructuredCrossoverMutator::new();
+        let mut input: Window = generator.generate(&mut state).unwrap();
+        let mut mutated = 0;
+        for _ in 0..1000 {
+            if mutator.mutate(&mut state, &mut input, 0).unwrap() == MutationResult::Mutated {
+                mutated += 1;
+            }
+            // A field of the same type, but with other constraints, is never a donor
+            assert!(input.low <= 9);
+            assert!((100..=199).contains(&input.high));
+            assert!(input.short.chars().count() <= 2);
+            assert!(input.long.chars().count() <= 16);
+        }
+        assert!(mutated > 0);
+    }
+}
@@ -0,0 +1,15 @@
+//! The `DistanceObserver` reports how close an execution got to the targets of directed fuzzing.
+
+use crate::{inputs::UsesInput, observers::Observer};
+
+/// An [`Observer`] measuring the distance of the last execution to a set of targets,
+/// for example the `AFLGo`-style basic block distances computed from the CFG of the target.
+pub trait DistanceObserver<S>: Observer<S>
+where
+    S: UsesInput,
+{
+    /// The distance of the last execution to the targets, lower is closer.
+    ///
+    /// Returns `None` if the execution did not cover anything from which a target is reachable.
+    fn last_distance(&self) -> Option<f64>;
+}
@@ -6,6 +6,12 @@ pub use map::*;
 pub mod cmp;
 pub use cmp::*;
 
+pub mod distance;
+pub use distance::*;
+
+pub mod response;
+pub use response::ResponseCodeObserver;
+
 #[cfg(feature = "std")]
 pub mod stdio;
 #[cfg(feature = "std")]
@@ -114,6 +120,18 @@ where
     #[inline]
     #[allow(unused_variables)]
     fn observe_stderr(&mut self, stderr: &[u8]) {}
+
+    /// If this observer observes the responses of a network target
+    #[inline]
+    fn observes_responses(&self) -> bool {
+        false
+    }
+
+    /// React to the responses of a network target, one per message sent.
+    /// To use this, always return `true` from `observes_responses`
+    #[inline]
+    #[allow(unused_variables)]
+    fn observe_responses(&mut self, responses: &[Vec<u8>]) {}
 }
 
 /// Defines the observer type shared across traits of the type.
@@ -159,6 +177,17 @@ where
     fn observe_stdout(&mut self, stdout: &[u8]);
     /// Runs `observe_stderr` for all stderr observers in the list
     fn observe_stderr(&mut self, stderr: &[u8]);
+
+    /// Returns true if a responses observer was added to the list
+    #[inline]
+    fn observes_responses(&self) -> bool {
+        false
+    }
+
+    /// Runs `observe_responses` for all responses observers in the list
+    #[inline]
+    #[allow(unused_variables)]
+    fn observe_responses(&mut self, responses: &[Vec<u8>]) {}
 }
 
 impl<S> ObserversTuple<S> for ()
@@ -275,6 +304,19 @@ where
         self.0.observe_stderr(stderr);
         self.1.observe_stderr(stderr);
     }
+
+    /// Returns true if a responses observer was added to the list
+    #[inline]
+    fn observes_responses(&self) -> bool {
+        self.0.observes_responses() || self.1.observes_responses()
+    }
+
+    /// Runs `observe_responses` for all responses observers in the list
+    #[inline]
+    fn observe_responses(&mut self, responses: &[Vec<u8>]) {
+        self.0.observe_responses(responses);
+        self.1.observe_responses(responses);
+    }
 }
 
 /// A trait for [`Observer`]`s` with a hash field
@@ -283,6 +325,13 @@ pub trait ObserverWithHashField {
     fn hash(&self) -> Option<u64>;
 }
 
+/// A trait for [`Observer`]`s` with a single value per run, such as the stack depth,
+/// which a [`crate::feedbacks::MaxValueFeedback`] maximizes
+pub trait ObserverWithValue {
+    /// get the value of the last run, if any
+    fn value(&self) -> Option<u64>;
+}
+
 /// A trait for [`Observer`]`s` which observe over differential execution.
 ///
 /// Differential observers have the following flow during a single execution:
@@ -0,0 +1,161 @@
+//! The [`ResponseCodeObserver`] extracts the response codes from the replies of a network server,
+//! the states of the protocol's state machine, as in `AFLNet`.
+//! The executor must explicitly support this observer.
+//! For example, it is supported on the [`crate::executors::NetworkForkserverExecutor`].
+
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::fmt::{self, Debug, Formatter};
+
+use libafl_bolts::Named;
+use serde::{Deserialize, Serialize};
+
+use crate::{inputs::UsesInput, observers::Observer, Error};
+
+/// The function extracting the response codes from a single response
+type ResponseCodeExtractor = Box<dyn Fn(&[u8]) -> Vec<u32>>;
+
+/// An observer collecting the response codes the target sends back to each message.
+///
+/// Only the codes are serialized, the extractor stays with the fuzzer that created the observer.
+#[derive(Serialize, Deserialize)]
+pub struct ResponseCodeObserver {
+    name: String,
+    codes: Vec<u32>,
+    #[serde(skip)]
+    extractor: Option<ResponseCodeExtractor>,
+}
+
+impl Debug for ResponseCodeObserver {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResponseCodeObserver")
+            .field("name", &self.name)
+            .field("codes", &self.codes)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ResponseCodeObserver {
+    /// Creates a new [`ResponseCodeObserver`], getting the code of each response from `extractor`.
+    /// Responses the `extractor` returns `None` for don't count as a state.
+    #[must_use]
+    pub fn new<F>(name: &str, extractor: F) -> Self
+    where
+        F: Fn(&[u8]) -> Option<u32> + 'static,
+    {
+        Self::with_multiple_codes(name, move |response| {
+            extractor(response).into_iter().collect()
+        })
+    }
+
+    /// Creates a new [`ResponseCodeObserver`] for protocols that may put several codes
+    /// into a single response, such as multiline replies.
+    #[must_use]
+    pub fn with_multiple_codes<F>(name: &str, extractor: F) -> Self
+    where
+        F: Fn(&[u8]) -> Vec<u32> + 'static,
+    {
+        Self {
+            name: name.into(),
+            codes: Vec::new(),
+            extractor: Some(Box::new(extractor)),
+        }
+    }
+
+    /// Creates a new [`ResponseCodeObserver`] taking each match of the `regex` in a response as a code.
+    ///
+    /// If the `regex` has a capture group, the first one is parsed as decimal number,
+    /// otherwise the whole match. Matches that are no number are hashed to a code.
+    #[cfg(feature = "regex")]
+    pub fn with_regex(name: &str, regex: &str) -> Result<Self, Error> {
+        use core::hash::{BuildHasher, Hasher};
+
+        use ahash::RandomState;
+
+        let regex = regex::bytes::Regex::new(regex)
+            .map_err(|err| Error::illegal_argument(format!("Invalid response regex: {err}")))?;
+        Ok(Self::with_multiple_codes(name, move |response| {
+            regex
+                .captures_iter(response)
+                .filter_map(|captures| captures.get(1).or_else(|| captures.get(0)))
+                .map(|code| {
+                    core::str::from_utf8(code.as_bytes())
+                        .ok()
+                        .and_then(|code| code.trim().parse().ok())
+                        .unwrap_or_else(|| {
+                            let mut hasher = RandomState::with_seeds(0, 0, 0, 0).build_hasher();
+                            hasher.write(code.as_bytes());
+                            hasher.finish() as u32
+                        })
+                })
+                .collect()
+        }))
+    }
+
+    /// The response codes of the last execution, in the order they were received
+    #[must_use]
+    pub fn codes(&self) -> &[u32] {
+        &self.codes
+    }
+}
+
+impl<S> Observer<S> for ResponseCodeObserver
+where
+    S: UsesInput,
+{
+    fn pre_exec(&mut self, _state: &mut S, _input: &S::Input) -> Result<(), Error> {
+        self.codes.clear();
+        Ok(())
+    }
+
+    #[inline]
+    fn observes_responses(&self) -> bool {
+        true
+    }
+
+    /// React to new responses
+    fn observe_responses(&mut self, responses: &[Vec<u8>]) {
+        if let Some(extractor) = &self.extractor {
+            for response in responses {
+                self.codes.extend(extractor(response));
+            }
+        }
+    }
+}
+
+impl Named for ResponseCodeObserver {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        inputs::BytesInput,
+        observers::{Observer, ResponseCodeObserver},
+        state::NopState,
+    };
+
+    #[test]
+    fn test_response_codes() {
+        let mut observer = ResponseCodeObserver::new("codes", |response| {
+            core::str::from_utf8(response.get(..3)?).ok()?.parse().ok()
+        });
+        Observer::<NopState<BytesInput>>::observe_responses(
+            &mut observer,
+            &[b"220 ready\r\n".to_vec(), vec![], b"530 no\r\n".to_vec()],
+        );
+        assert_eq!(observer.codes(), [220, 530]);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn test_response_codes_regex() {
+        let mut observer = ResponseCodeObserver::with_regex("codes", r"(?m)^(\d{3})[ -]").unwrap();
+        Observer::<NopState<BytesInput>>::observe_responses(
+            &mut observer,
+            &[b"230-welcome\r\n230 logged in\r\n".to_vec()],
+        );
+        assert_eq!(observer.codes(), [230, 230]);
+    }
+}
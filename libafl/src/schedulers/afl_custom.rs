@@ -0,0 +1,216 @@
+//! Notifies an `AFL++` custom mutator of new corpus entries, through its `afl_custom_queue_new_entry`,
+//! see [`crate::mutators::afl_custom`].
+
+use alloc::rc::Rc;
+use core::fmt::{self, Debug, Formatter};
+
+use crate::{
+    corpus::{Corpus, CorpusId, Testcase},
+    inputs::UsesInput,
+    mutators::AflCustomLibrary,
+    observers::ObserversTuple,
+    schedulers::{RemovableScheduler, Scheduler},
+    state::{HasCorpus, UsesState},
+    Error,
+};
+
+/// Wraps a [`Scheduler`], telling the given [`AflCustomLibrary`] about each corpus entry added.
+///
+/// The library gets the file of the new entry, and the file of the entry it was found from.
+/// Entries without a file, like those in an [`crate::corpus::InMemoryCorpus`], are not reported.
+pub struct AflCustomQueueScheduler<CS> {
+    base: CS,
+    library: Rc<AflCustomLibrary>,
+}
+
+impl<CS> Debug for AflCustomQueueScheduler<CS>
+where
+    CS: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AflCustomQueueScheduler")
+            .field("base", &self.base)
+            .field("library", &self.library)
+            .finish()
+    }
+}
+
+impl<CS> UsesState for AflCustomQueueScheduler<CS>
+where
+    CS: UsesState,
+{
+    type State = CS::State;
+}
+
+impl<CS> RemovableScheduler for AflCustomQueueScheduler<CS>
+where
+    CS: RemovableScheduler,
+    CS::State: HasCorpus,
+{
+    fn on_remove(
+        &mut self,
+        state: &mut CS::State,
+        idx: CorpusId,
+        testcase: &Option<Testcase<<CS::State as UsesInput>::Input>>,
+    ) -> Result<(), Error> {
+        self.base.on_remove(state, idx, testcase)
+    }
+
+    fn on_replace(
+        &mut self,
+        state: &mut CS::State,
+        idx: CorpusId,
+        prev: &Testcase<<CS::State as UsesInput>::Input>,
+    ) -> Result<(), Error> {
+        self.base.on_replace(state, idx, prev)
+    }
+}
+
+impl<CS> Scheduler for AflCustomQueueScheduler<CS>
+where
+    CS: Scheduler,
+    CS::State: HasCorpus,
+{
+    fn on_add(&mut self, state: &mut CS::State, idx: CorpusId) -> Result<(), Error> {
+        self.base.on_add(state, idx)?;
+        if !self.library.has_queue_new_entry() {
+            return Ok(());
+        }
+
+        let (new_entry, parent_id) = {
+            let testcase = state.corpus().get(idx)?.borrow();
+            (testcase.file_path().clone(), testcase.parent_id())
+        };
+        let Some(new_entry) = new_entry else {
+            return Ok(());
+        };
+        let orig_entry = match parent_id {
+            Some(parent_id) => state.corpus().get(parent_id)?.borrow().file_path().clone(),
+            None => None,
+        };
+        self.library
+            .queue_new_entry(&new_entry, orig_entry.as_deref())
+    }
+
+    fn on_evaluation<OT>(
+        &mut self,
+        state: &mut Self::State,
+        input: &<Self::State as UsesInput>::Input,
+        observers: &OT,
+    ) -> Result<(), Error>
+    where
+        OT: ObserversTuple<Self::State>,
+    {
+        self.base.on_evaluation(state, input, observers)
+    }
+
+    fn next(&mut self, state: &mut CS::State) -> Result<CorpusId, Error> {
+        self.base.next(state)
+    }
+
+    fn set_current_scheduled(
+        &mut self,
+        state: &mut Self::State,
+        next_idx: Option<CorpusId>,
+    ) -> Result<(), Error> {
+        self.base.set_current_scheduled(state, next_idx)
+    }
+}
+
+impl<CS> AflCustomQueueScheduler<CS> {
+    /// Wraps the `base` scheduler, notifying `library` of new corpus entries
+    pub fn new(base: CS, library: Rc<AflCustomLibrary>) -> Self {
+        Self { base, library }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{
+        rc::Rc,
+        string::{String, ToString},
+        vec::Vec,
+    };
+    use core::{
+        cell::RefCell,
+        ffi::{c_char, c_void, CStr},
+    };
+    use std::{env, fs};
+
+    use libafl_bolts::rands::StdRand;
+
+    use crate::{
+        corpus::{Corpus, InMemoryCorpus, InMemoryOnDiskCorpus, Testcase},
+        feedbacks::ConstFeedback,
+        inputs::BytesInput,
+        mutators::AflCustomLibrary,
+        schedulers::{AflCustomQueueScheduler, QueueScheduler, Scheduler},
+        state::{HasCorpus, StdState},
+    };
+
+    std::thread_local! {
+        static QUEUED: RefCell<Vec<(String, Option<String>)>> = const { RefCell::new(Vec::new()) };
+    }
+
+    unsafe extern "C" fn queue_new_entry(
+        _data: *mut c_void,
+        filename_new_queue: *const c_char,
+        filename_orig_queue: *const c_char,
+    ) -> u8 {
+        let to_string = |name: *const c_char| CStr::from_ptr(name).to_string_lossy().into_owned();
+        let orig = (!filename_orig_queue.is_null()).then(|| to_string(filename_orig_queue));
+        QUEUED.with(|queued| {
+            queued
+                .borrow_mut()
+                .push((to_string(filename_new_queue), orig));
+        });
+        1
+    }
+
+    #[test]
+    fn test_afl_custom_queue_new_entry() {
+        let dir = env::temp_dir().join(format!("libafl_afl_custom_queue_{}", std::process::id()));
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryOnDiskCorpus::<BytesInput>::new(&dir).unwrap(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        let library = Rc::new(AflCustomLibrary::from_hooks().with_queue_new_entry(queue_new_entry));
+        let mut scheduler = AflCustomQueueScheduler::new(QueueScheduler::new(), library);
+
+        let seed = state
+            .corpus_mut()
+            .add(Testcase::new(BytesInput::new(b"seed".to_vec())))
+            .unwrap();
+        scheduler.on_add(&mut state, seed).unwrap();
+        // Found while fuzzing the seed
+        *state.corpus_mut().current_mut() = Some(seed);
+        let found = state
+            .corpus_mut()
+            .add(Testcase::new(BytesInput::new(b"found".to_vec())))
+            .unwrap();
+        scheduler.on_add(&mut state, found).unwrap();
+
+        let path = |idx| {
+            state
+                .corpus()
+                .get(idx)
+                .unwrap()
+                .borrow()
+                .file_path()
+                .as_ref()
+                .unwrap()
+                .to_string_lossy()
+                .to_string()
+        };
+        let expected = [(path(seed), None), (path(found), Some(path(seed)))];
+        QUEUED.with(|queued| assert_eq!(*queued.borrow(), expected));
+
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
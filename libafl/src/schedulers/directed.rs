@@ -0,0 +1,321 @@
+//! The directed corpus scheduler from `AFLGo` (`https://dl.acm.org/doi/10.1145/3133956.3134020`).
+//! Testcases closer to the targets get more energy, the longer the campaign runs.
+
+use alloc::string::{String, ToString};
+use core::{marker::PhantomData, time::Duration};
+
+use libafl_bolts::current_time;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{CorpusId, HasTestcase, Testcase},
+    inputs::UsesInput,
+    observers::{DistanceObserver, ObserversTuple},
+    schedulers::{
+        testcase_score::{CorpusPowerTestcaseScore, TestcaseScore, HAVOC_MAX_MULT},
+        RemovableScheduler, Scheduler,
+    },
+    state::{HasCorpus, HasMetadata, UsesState},
+    Error,
+};
+
+/// The maximum factor the annealing schedule multiplies or divides the energy with
+const MAX_ANNEALING_FACTOR: f64 = 32.0;
+
+/// The state metadata for [`DirectedScheduler`]
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct DirectedMetadata {
+    /// When the directed campaign started
+    start_time: Duration,
+    /// After how long the exploration phase should be mostly over
+    time_to_exploit: Duration,
+    /// The lowest distance of any corpus entry
+    min_distance: f64,
+    /// The highest distance of any corpus entry
+    max_distance: f64,
+}
+
+libafl_bolts::impl_serdeany!(DirectedMetadata);
+
+impl DirectedMetadata {
+    /// Creates a new [`struct@DirectedMetadata`], starting the annealing schedule now.
+    #[must_use]
+    pub fn new(time_to_exploit: Duration) -> Self {
+        Self {
+            start_time: current_time(),
+            time_to_exploit,
+            min_distance: f64::MAX,
+            max_distance: 0.0,
+        }
+    }
+
+    /// When the directed campaign started
+    #[must_use]
+    pub fn start_time(&self) -> Duration {
+        self.start_time
+    }
+
+    /// After how long the exploration phase should be mostly over
+    #[must_use]
+    pub fn time_to_exploit(&self) -> Duration {
+        self.time_to_exploit
+    }
+
+    /// The lowest distance of any corpus entry
+    #[must_use]
+    pub fn min_distance(&self) -> f64 {
+        self.min_distance
+    }
+
+    /// The highest distance of any corpus entry
+    #[must_use]
+    pub fn max_distance(&self) -> f64 {
+        self.max_distance
+    }
+
+    /// Account for the distance of a new corpus entry
+    pub fn update_distance(&mut self, distance: f64) {
+        self.min_distance = self.min_distance.min(distance);
+        self.max_distance = self.max_distance.max(distance);
+    }
+
+    /// The factor the energy of an entry at `distance` is multiplied with, `elapsed` into the campaign.
+    ///
+    /// The temperature cools down exponentially, reaching 0.05 at `time_to_exploit`.
+    /// While it is high, every entry gets the same energy; once it is low, the closest entries
+    /// get up to [`MAX_ANNEALING_FACTOR`] times more and the farthest up to as much less.
+    #[must_use]
+    pub fn annealing_factor(&self, distance: f64, elapsed: Duration) -> f64 {
+        let normalized_distance = if self.max_distance > self.min_distance {
+            (distance - self.min_distance) / (self.max_distance - self.min_distance)
+        } else {
+            0.0
+        };
+        let progress = elapsed.as_secs_f64() / self.time_to_exploit.as_secs_f64().max(1.0);
+        let temperature = libm::pow(20.0, -progress);
+        let power = (1.0 - normalized_distance) * (1.0 - temperature) + 0.5 * temperature;
+        libm::pow(2.0, 2.0 * libm::log2(MAX_ANNEALING_FACTOR) * (power - 0.5))
+    }
+}
+
+/// The testcase metadata for [`DirectedScheduler`]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct DirectedTestcaseMetadata {
+    /// The distance of this testcase to the targets, if it reaches any of them
+    distance: Option<f64>,
+}
+
+libafl_bolts::impl_serdeany!(DirectedTestcaseMetadata);
+
+impl DirectedTestcaseMetadata {
+    /// Creates a new [`struct@DirectedTestcaseMetadata`]
+    #[must_use]
+    pub fn new(distance: Option<f64>) -> Self {
+        Self { distance }
+    }
+
+    /// The distance of this testcase to the targets, if it reaches any of them
+    #[must_use]
+    pub fn distance(&self) -> Option<f64> {
+        self.distance
+    }
+}
+
+/// A corpus scheduler for directed fuzzing, remembering the distance of each testcase
+/// to the targets, as reported by a [`DistanceObserver`].
+///
+/// Scheduling is left to the `base` scheduler, the distances are used to assign energy
+/// by the [`DirectedTestcaseScore`].
+#[derive(Clone, Debug)]
+pub struct DirectedScheduler<CS, O> {
+    base: CS,
+    distance_observer_name: String,
+    last_distance: Option<f64>,
+    phantom: PhantomData<O>,
+}
+
+impl<CS, O> UsesState for DirectedScheduler<CS, O>
+where
+    CS: UsesState,
+{
+    type State = CS::State;
+}
+
+impl<CS, O> RemovableScheduler for DirectedScheduler<CS, O>
+where
+    CS: RemovableScheduler,
+    O: DistanceObserver<CS::State>,
+    CS::State: HasCorpus + HasMetadata + HasTestcase,
+{
+    fn on_remove(
+        &mut self,
+        state: &mut CS::State,
+        idx: CorpusId,
+        testcase: &Option<Testcase<<CS::State as UsesInput>::Input>>,
+    ) -> Result<(), Error> {
+        self.base.on_remove(state, idx, testcase)
+    }
+
+    fn on_replace(
+        &mut self,
+        state: &mut CS::State,
+        idx: CorpusId,
+        prev: &Testcase<<CS::State as UsesInput>::Input>,
+    ) -> Result<(), Error> {
+        self.base.on_replace(state, idx, prev)?;
+        // The replacing testcase has not been executed yet, keep the previous distance
+        let distance = prev
+            .metadata::<DirectedTestcaseMetadata>()
+            .ok()
+            .and_then(DirectedTestcaseMetadata::distance);
+        state
+            .testcase_mut(idx)?
+            .add_metadata(DirectedTestcaseMetadata::new(distance));
+        Ok(())
+    }
+}
+
+impl<CS, O> Scheduler for DirectedScheduler<CS, O>
+where
+    CS: Scheduler,
+    O: DistanceObserver<CS::State>,
+    CS::State: HasCorpus + HasMetadata + HasTestcase,
+{
+    /// Called when a [`Testcase`] is added to the corpus
+    fn on_add(&mut self, state: &mut CS::State, idx: CorpusId) -> Result<(), Error> {
+        self.base.on_add(state, idx)?;
+
+        if let Some(distance) = self.last_distance {
+            state
+                .metadata_mut::<DirectedMetadata>()?
+                .update_distance(distance);
+        }
+        state
+            .testcase_mut(idx)?
+            .add_metadata(DirectedTestcaseMetadata::new(self.last_distance));
+        Ok(())
+    }
+
+    fn on_evaluation<OT>(
+        &mut self,
+        state: &mut CS::State,
+        input: &<CS::State as UsesInput>::Input,
+        observers: &OT,
+    ) -> Result<(), Error>
+    where
+        OT: ObserversTuple<CS::State>,
+    {
+        self.base.on_evaluation(state, input, observers)?;
+
+        let observer = observers
+            .match_name::<O>(&self.distance_observer_name)
+            .ok_or_else(|| Error::key_not_found("DistanceObserver not found".to_string()))?;
+        self.last_distance = observer.last_distance();
+        Ok(())
+    }
+
+    fn next(&mut self, state: &mut CS::State) -> Result<CorpusId, Error> {
+        self.base.next(state)
+    }
+
+    /// Set current fuzzed corpus id and `scheduled_count`
+    fn set_current_scheduled(
+        &mut self,
+        _state: &mut Self::State,
+        _next_idx: Option<CorpusId>,
+    ) -> Result<(), Error> {
+        // We do nothing here, the inner scheduler will take care of it
+        Ok(())
+    }
+}
+
+impl<CS, O> DirectedScheduler<CS, O>
+where
+    CS: Scheduler,
+    O: DistanceObserver<CS::State>,
+    CS::State: HasCorpus + HasMetadata,
+{
+    /// Creates a new [`DirectedScheduler`] wrapping `base`.
+    ///
+    /// `time_to_exploit` is the time after which the annealing schedule mostly favors
+    /// the testcases closest to the targets, `AFLGo` suggests around 45 minutes.
+    #[must_use]
+    pub fn new(
+        state: &mut CS::State,
+        distance_observer: &O,
+        base: CS,
+        time_to_exploit: Duration,
+    ) -> Self {
+        if !state.has_metadata::<DirectedMetadata>() {
+            state.add_metadata(DirectedMetadata::new(time_to_exploit));
+        }
+        Self {
+            base,
+            distance_observer_name: distance_observer.name().to_string(),
+            last_distance: None,
+            phantom: PhantomData,
+        }
+    }
+}
+
+/// The power assigned to each corpus entry for directed fuzzing.
+/// This is the power of [`CorpusPowerTestcaseScore`], scaled by `AFLGo`'s simulated annealing
+/// schedule according to the distance recorded by the [`DirectedScheduler`].
+#[derive(Debug, Clone)]
+pub struct DirectedTestcaseScore<S> {
+    phantom: PhantomData<S>,
+}
+
+impl<S> TestcaseScore<S> for DirectedTestcaseScore<S>
+where
+    S: HasCorpus + HasMetadata,
+{
+    fn compute(state: &S, entry: &mut Testcase<S::Input>) -> Result<f64, Error> {
+        let perf_score = CorpusPowerTestcaseScore::compute(state, entry)?;
+
+        let Some(distance) = entry.metadata::<DirectedTestcaseMetadata>()?.distance() else {
+            // Without a distance, there is nothing to direct towards
+            return Ok(perf_score);
+        };
+        let meta = state.metadata::<DirectedMetadata>()?;
+        let elapsed = current_time().saturating_sub(meta.start_time());
+        let factor = meta.annealing_factor(distance, elapsed);
+
+        Ok((perf_score * factor).min(HAVOC_MAX_MULT * 100.0))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::time::Duration;
+
+    use crate::schedulers::directed::{DirectedMetadata, MAX_ANNEALING_FACTOR};
+
+    #[test]
+    fn test_annealing_factor() {
+        let mut meta = DirectedMetadata::new(Duration::from_secs(60));
+        meta.update_distance(2.0);
+        meta.update_distance(10.0);
+
+        // In the beginning, every testcase gets the same energy
+        assert!((meta.annealing_factor(2.0, Duration::ZERO) - 1.0).abs() < 1e-9);
+        assert!((meta.annealing_factor(10.0, Duration::ZERO) - 1.0).abs() < 1e-9);
+
+        // Later on, the closest ones get the most
+        let late = Duration::from_secs(60 * 60);
+        let closest = meta.annealing_factor(2.0, late);
+        let middle = meta.annealing_factor(6.0, late);
+        let farthest = meta.annealing_factor(10.0, late);
+        assert!((closest - MAX_ANNEALING_FACTOR).abs() < 1e-3);
+        assert!((middle - 1.0).abs() < 1e-3);
+        assert!((farthest - 1.0 / MAX_ANNEALING_FACTOR).abs() < 1e-3);
+    }
+}
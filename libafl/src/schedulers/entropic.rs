@@ -0,0 +1,698 @@
+//! The entropic corpus scheduler from libFuzzer (`https://mboehme.github.io/paper/FSE20.Entropy.pdf`).
+//! Testcases are chosen by the information they reveal about the rarest features of the target,
+//! estimated as the entropy of their local feature incidence.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::marker::PhantomData;
+
+use libafl_bolts::rands::Rand;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use crate::{
+    corpus::{Corpus, CorpusId, HasTestcase, SchedulerTestcaseMetadata, Testcase},
+    feedbacks::{MapFeedbackMetadata, MAPFEEDBACK_PREFIX},
+    inputs::UsesInput,
+    observers::{MapObserver, ObserversTuple},
+    random_corpus_id,
+    schedulers::{powersched::SchedulerMetadata, RemovableScheduler, Scheduler, TestcaseScore},
+    state::{HasCorpus, HasMetadata, HasNamedMetadata, HasRand, UsesState},
+    Error,
+};
+
+/// The number of rarest features libFuzzer keeps track of, by default
+pub const DEFAULT_NUMBER_OF_RAREST_FEATURES: usize = 100;
+/// The frequency from which on a feature is no longer considered rare, by default
+pub const DEFAULT_FEATURE_FREQUENCY_THRESHOLD: u16 = 0xFF;
+
+/// The state metadata for the [`EntropicScheduler`], holding the global frequencies of rare features
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct EntropicMetadata {
+    /// The map indexes currently considered rare
+    rare_features: Vec<usize>,
+    /// How often each map index was hit since it became rare, saturating
+    global_feature_freqs: Vec<u16>,
+    /// The highest global frequency of any rare feature
+    freq_of_most_abundant_rare_feature: u16,
+    /// Keep at least this many rare features
+    number_of_rarest_features: usize,
+    /// Features hit more often than this are no longer rare, if there are enough others
+    feature_frequency_threshold: u16,
+}
+
+libafl_bolts::impl_serdeany!(EntropicMetadata);
+
+impl EntropicMetadata {
+    /// Creates a new [`struct@EntropicMetadata`]
+    #[must_use]
+    pub fn new(number_of_rarest_features: usize, feature_frequency_threshold: u16) -> Self {
+        Self {
+            rare_features: vec![],
+            global_feature_freqs: vec![],
+            freq_of_most_abundant_rare_feature: 0,
+            number_of_rarest_features,
+            feature_frequency_threshold,
+        }
+    }
+
+    /// The map indexes currently considered rare
+    #[must_use]
+    pub fn rare_features(&self) -> &[usize] {
+        &self.rare_features
+    }
+
+    /// Checks if the feature at the given map index is currently rare
+    #[must_use]
+    pub fn is_rare(&self, idx: usize) -> bool {
+        self.rare_features.contains(&idx)
+    }
+
+    /// The global frequency of the feature at the given map index
+    #[must_use]
+    pub fn global_frequency(&self, idx: usize) -> u16 {
+        self.global_feature_freqs.get(idx).copied().unwrap_or(0)
+    }
+
+    /// Drops the most abundant rare features, as long as there are more than enough
+    /// and they are above the threshold. Returns the dropped map indexes.
+    fn drop_abundant_features(&mut self) -> Vec<usize> {
+        let mut dropped = vec![];
+        while self.rare_features.len() > self.number_of_rarest_features
+            && self.freq_of_most_abundant_rare_feature > self.feature_frequency_threshold
+        {
+            let mut most_abundant = (0, self.rare_features[0]);
+            let mut second_most_abundant = self.rare_features[0];
+            for (i, idx) in self.rare_features.iter().enumerate() {
+                if self.global_feature_freqs[*idx] >= self.global_feature_freqs[most_abundant.1] {
+                    second_most_abundant = most_abundant.1;
+                    most_abundant = (i, *idx);
+                }
+            }
+            dropped.push(self.rare_features.swap_remove(most_abundant.0));
+            self.freq_of_most_abundant_rare_feature =
+                self.global_feature_freqs[second_most_abundant];
+        }
+        dropped
+    }
+
+    /// Adds a newly discovered feature to the rare ones
+    fn add_rare_feature(&mut self, idx: usize) {
+        if self.global_feature_freqs.len() <= idx {
+            self.global_feature_freqs.resize(idx + 1, 0);
+        }
+        self.rare_features.push(idx);
+        self.global_feature_freqs[idx] = 0;
+    }
+
+    /// Accounts for a hit of the feature at the given map index.
+    /// Returns `true` if the feature is rare, so the local frequency should be updated as well.
+    fn update_feature_frequency(&mut self, idx: usize) -> bool {
+        if self.global_feature_freqs.len() <= idx {
+            self.global_feature_freqs.resize(idx + 1, 0);
+        }
+        let freq = self.global_feature_freqs[idx];
+        if freq == u16::MAX {
+            return false;
+        }
+        self.global_feature_freqs[idx] += 1;
+        if freq > self.freq_of_most_abundant_rare_feature || !self.is_rare(idx) {
+            return false;
+        }
+        if freq == self.freq_of_most_abundant_rare_feature {
+            self.freq_of_most_abundant_rare_feature += 1;
+        }
+        true
+    }
+}
+
+impl Default for EntropicMetadata {
+    fn default() -> Self {
+        Self::new(
+            DEFAULT_NUMBER_OF_RAREST_FEATURES,
+            DEFAULT_FEATURE_FREQUENCY_THRESHOLD,
+        )
+    }
+}
+
+/// The testcase metadata for the [`EntropicScheduler`], holding the local incidence of rare features
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct EntropicTestcaseMetadata {
+    /// How often mutations of this testcase hit each rare feature, sorted by map index
+    feature_freqs: Vec<(usize, u16)>,
+    /// How many mutations of this testcase were executed
+    num_executed_mutations: u64,
+    /// The last computed energy
+    energy: f64,
+    /// If the local frequencies changed since the energy was computed
+    needs_energy_update: bool,
+}
+
+libafl_bolts::impl_serdeany!(EntropicTestcaseMetadata);
+
+impl EntropicTestcaseMetadata {
+    /// Creates a new [`struct@EntropicTestcaseMetadata`] for a new testcase.
+    /// It gets the maximum energy possible with the given number of rare features.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn new(rare_features: usize) -> Self {
+        Self {
+            feature_freqs: vec![],
+            num_executed_mutations: 0,
+            energy: if rare_features == 0 {
+                1.0
+            } else {
+                libm::log(rare_features as f64)
+            },
+            needs_energy_update: false,
+        }
+    }
+
+    /// The last computed energy
+    #[must_use]
+    pub fn energy(&self) -> f64 {
+        self.energy
+    }
+
+    /// How many mutations of this testcase were executed
+    #[must_use]
+    pub fn num_executed_mutations(&self) -> u64 {
+        self.num_executed_mutations
+    }
+
+    /// How often mutations of this testcase hit the rare feature at the given map index
+    #[must_use]
+    pub fn local_frequency(&self, idx: usize) -> u16 {
+        self.feature_freqs
+            .binary_search_by_key(&idx, |(i, _)| *i)
+            .map_or(0, |pos| self.feature_freqs[pos].1)
+    }
+
+    /// Accounts for a hit of the rare feature at the given map index by a mutation of this testcase
+    fn update_feature_frequency(&mut self, idx: usize) {
+        self.needs_energy_update = true;
+        match self.feature_freqs.binary_search_by_key(&idx, |(i, _)| *i) {
+            Ok(pos) => {
+                self.feature_freqs[pos].1 = self.feature_freqs[pos].1.saturating_add(1);
+            }
+            Err(pos) => self.feature_freqs.insert(pos, (idx, 1)),
+        }
+    }
+
+    /// Forgets the local frequency of a feature
+    fn delete_feature_frequency(&mut self, idx: usize) {
+        if let Ok(pos) = self.feature_freqs.binary_search_by_key(&idx, |(i, _)| *i) {
+            self.feature_freqs.remove(pos);
+        }
+        self.needs_energy_update = true;
+    }
+
+    /// Computes the entropy of the local incidence of all `global_rare_features`,
+    /// with add-one smoothing and all non-rare features folded into a single abundant one.
+    #[allow(clippy::cast_precision_loss)]
+    fn update_energy(&mut self, global_rare_features: usize) {
+        let mut energy = 0.0;
+        let mut sum_incidence = 0.0;
+        for (_, freq) in &self.feature_freqs {
+            let local_incidence = f64::from(*freq) + 1.0;
+            energy -= local_incidence * libm::log(local_incidence);
+            sum_incidence += local_incidence;
+        }
+        // Locally undiscovered features have an incidence of 1, adding nothing to the energy
+        sum_incidence += global_rare_features.saturating_sub(self.feature_freqs.len()) as f64;
+
+        let abundant_incidence = self.num_executed_mutations as f64 + 1.0;
+        energy -= abundant_incidence * libm::log(abundant_incidence);
+        sum_incidence += abundant_incidence;
+
+        if sum_incidence != 0.0 {
+            energy = energy / sum_incidence + libm::log(sum_incidence);
+        }
+        self.energy = energy;
+        self.needs_energy_update = false;
+    }
+}
+
+/// The entropic energy of each corpus entry, as used by the [`EntropicScheduler`]
+#[derive(Debug, Clone)]
+pub struct EntropicTestcaseScore<S> {
+    phantom: PhantomData<S>,
+}
+
+impl<S> TestcaseScore<S> for EntropicTestcaseScore<S>
+where
+    S: HasCorpus + HasMetadata,
+{
+    fn compute(state: &S, entry: &mut Testcase<S::Input>) -> Result<f64, Error> {
+        let global_rare_features = state.metadata::<EntropicMetadata>()?.rare_features().len();
+        let tcmeta = entry.metadata_mut::<EntropicTestcaseMetadata>()?;
+        if tcmeta.needs_energy_update {
+            tcmeta.update_energy(global_rare_features);
+        }
+        Ok(tcmeta.energy())
+    }
+}
+
+/// A corpus scheduler implementing libFuzzer's entropic power schedule.
+///
+/// It keeps the global frequencies of the rarest features (map indexes) of the given map observer
+/// and, for each testcase, how often its mutations hit each of them. Testcases whose mutations
+/// reveal more information about the rare features are chosen more often.
+/// Newly discovered features are the ones not yet in the history of the `MapFeedback` tracking
+/// the same map observer.
+/// The energies of all testcases are cached, and only recomputed once their local frequencies,
+/// or the set of rare features, change.
+#[derive(Clone, Debug)]
+pub struct EntropicScheduler<O, S> {
+    map_observer_name: String,
+    map_feedback_name: String,
+    /// The cached energy of each enabled corpus entry
+    energies: Vec<(CorpusId, f64)>,
+    /// Entries whose local frequencies changed since their energy was cached
+    stale: Vec<CorpusId>,
+    /// If all energies have to be recomputed, e.g., because the rare features changed
+    needs_rebuild: bool,
+    phantom: PhantomData<(O, S)>,
+}
+
+impl<O, S> UsesState for EntropicScheduler<O, S>
+where
+    S: UsesInput,
+{
+    type State = S;
+}
+
+impl<O, S> RemovableScheduler for EntropicScheduler<O, S>
+where
+    S: HasCorpus + HasMetadata + HasNamedMetadata + HasRand + HasTestcase,
+    S::Input: PartialEq,
+    O: MapObserver,
+    O::Entry: Serialize + DeserializeOwned,
+{
+    fn on_remove(
+        &mut self,
+        _state: &mut Self::State,
+        _idx: CorpusId,
+        _testcase: &Option<Testcase<<Self::State as UsesInput>::Input>>,
+    ) -> Result<(), Error> {
+        self.needs_rebuild = true;
+        Ok(())
+    }
+
+    fn on_replace(
+        &mut self,
+        _state: &mut Self::State,
+        _idx: CorpusId,
+        _prev: &Testcase<<Self::State as UsesInput>::Input>,
+    ) -> Result<(), Error> {
+        self.needs_rebuild = true;
+        Ok(())
+    }
+}
+
+impl<O, S> Scheduler for EntropicScheduler<O, S>
+where
+    S: HasCorpus + HasMetadata + HasNamedMetadata + HasRand + HasTestcase,
+    S::Input: PartialEq,
+    O: MapObserver,
+    O::Entry: Serialize + DeserializeOwned,
+{
+    fn on_add(&mut self, state: &mut Self::State, idx: CorpusId) -> Result<(), Error> {
+        let current_idx = *state.corpus().current();
+        let depth = match current_idx {
+            Some(parent_idx) => state
+                .testcase(parent_idx)?
+                .metadata::<SchedulerTestcaseMetadata>()
+                .map_or(0, SchedulerTestcaseMetadata::depth),
+            None => 0,
+        };
+        let rare_features = state.metadata::<EntropicMetadata>()?.rare_features().len();
+
+        let mut testcase = state.testcase_mut(idx)?;
+        testcase.set_parent_id_optional(current_idx);
+        // Keep the power stages working on top of this scheduler
+        testcase.add_metadata(SchedulerTestcaseMetadata::new(depth + 1));
+        let tcmeta = EntropicTestcaseMetadata::new(rare_features);
+        self.energies.push((idx, tcmeta.energy()));
+        testcase.add_metadata(tcmeta);
+        Ok(())
+    }
+
+    fn on_evaluation<OT>(
+        &mut self,
+        state: &mut Self::State,
+        input: &<Self::State as UsesInput>::Input,
+        observers: &OT,
+    ) -> Result<(), Error>
+    where
+        OT: ObserversTuple<Self::State>,
+    {
+        let observer = observers
+            .match_name::<O>(&self.map_observer_name)
+            .ok_or_else(|| Error::key_not_found("MapObserver not found".to_string()))?;
+        let initial = observer.initial();
+
+        // The history is only updated once the input is added to the corpus,
+        // so anything not in there yet, and never counted, is a new feature.
+        let mut new_features = vec![];
+        let mut hit_features = vec![];
+        {
+            let history = state
+                .named_metadata::<MapFeedbackMetadata<O::Entry>>(&self.map_feedback_name)
+                .map(|meta| meta.history_map.as_slice())
+                .unwrap_or_default();
+            let meta = state.metadata::<EntropicMetadata>()?;
+            for i in 0..observer.usable_count() {
+                if *observer.get(i) == initial {
+                    continue;
+                }
+                if history.get(i).map_or(true, |h| *h == initial) && meta.global_frequency(i) == 0 {
+                    new_features.push(i);
+                }
+                hit_features.push(i);
+            }
+        }
+
+        if !new_features.is_empty() {
+            self.needs_rebuild = true;
+        }
+        for idx in new_features {
+            self.add_rare_feature(state, idx)?;
+        }
+
+        let meta = state.metadata_mut::<EntropicMetadata>()?;
+        hit_features.retain(|idx| meta.update_feature_frequency(*idx));
+
+        if let Some(parent_idx) = *state.corpus().current() {
+            let mut parent = state.testcase_mut(parent_idx)?;
+            // Like libFuzzer, only count mutations, not runs of the entry itself such as its calibration
+            let is_mutation = parent.input().as_ref() != Some(input);
+            let tcmeta = parent.metadata_mut::<EntropicTestcaseMetadata>()?;
+            if is_mutation {
+                tcmeta.num_executed_mutations += 1;
+            }
+            if !hit_features.is_empty() && !self.stale.contains(&parent_idx) {
+                self.stale.push(parent_idx);
+            }
+            for idx in hit_features {
+                tcmeta.update_feature_frequency(idx);
+            }
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn next(&mut self, state: &mut Self::State) -> Result<CorpusId, Error> {
+        if state.corpus().count() == 0 {
+            return Err(Error::empty(String::from("No entries in corpus")));
+        }
+
+        self.update_energies(state)?;
+        let total: f64 = self.energies.iter().map(|(_, energy)| energy).sum();
+
+        let id = if total > 0.0 {
+            let threshold = total * (state.rand_mut().next() as f64 / u64::MAX as f64);
+            let mut sum = 0.0;
+            self.energies
+                .iter()
+                .find(|(_, energy)| {
+                    sum += energy;
+                    sum >= threshold
+                })
+                .or_else(|| self.energies.last())
+                .map(|(idx, _)| *idx)
+                .unwrap()
+        } else {
+            // No energy anywhere, fall back to a uniform choice
+            random_corpus_id!(state.corpus(), state.rand_mut())
+        };
+
+        self.set_current_scheduled(state, Some(id))?;
+        Ok(id)
+    }
+}
+
+impl<O, S> EntropicScheduler<O, S>
+where
+    S: HasCorpus + HasMetadata + HasTestcase,
+    O: MapObserver,
+{
+    /// Creates a new [`EntropicScheduler`] with libFuzzer's default parameters.
+    ///
+    /// The `map_observer` has to be tracked by a `MapFeedback` as well.
+    /// Power mutational stages still work, but without any [`crate::schedulers::powersched::PowerSchedule`].
+    #[must_use]
+    pub fn new(state: &mut S, map_observer: &O) -> Self {
+        Self::with_parameters(
+            state,
+            map_observer,
+            DEFAULT_NUMBER_OF_RAREST_FEATURES,
+            DEFAULT_FEATURE_FREQUENCY_THRESHOLD,
+        )
+    }
+
+    /// Creates a new [`EntropicScheduler`], keeping at least `number_of_rarest_features` rare
+    /// features, and all that were hit at most `feature_frequency_threshold` times.
+    #[must_use]
+    pub fn with_parameters(
+        state: &mut S,
+        map_observer: &O,
+        number_of_rarest_features: usize,
+        feature_frequency_threshold: u16,
+    ) -> Self {
+        if !state.has_metadata::<SchedulerMetadata>() {
+            state.add_metadata(SchedulerMetadata::new(None));
+        }
+        if !state.has_metadata::<EntropicMetadata>() {
+            state.add_metadata(EntropicMetadata::new(
+                number_of_rarest_features,
+                feature_frequency_threshold,
+            ));
+        }
+        Self {
+            map_observer_name: map_observer.name().to_string(),
+            map_feedback_name: MAPFEEDBACK_PREFIX.to_string() + map_observer.name(),
+            energies: vec![],
+            stale: vec![],
+            needs_rebuild: true,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Brings the cached energies up to date, recomputing only what changed since the last call
+    fn update_energies(&mut self, state: &mut S) -> Result<(), Error> {
+        if self.needs_rebuild || self.energies.len() != state.corpus().count() {
+            self.energies.clear();
+            for idx in state.corpus().ids() {
+                let energy = EntropicTestcaseScore::compute(state, &mut *state.testcase_mut(idx)?)?;
+                self.energies.push((idx, energy.max(0.0)));
+            }
+            self.needs_rebuild = false;
+        } else {
+            for idx in self.stale.drain(..) {
+                let energy = EntropicTestcaseScore::compute(state, &mut *state.testcase_mut(idx)?)?;
+                if let Some(entry) = self.energies.iter_mut().find(|(id, _)| *id == idx) {
+                    entry.1 = energy.max(0.0);
+                }
+            }
+        }
+        self.stale.clear();
+        Ok(())
+    }
+
+    /// Adds a newly discovered feature to the rare ones, dropping the most abundant ones if needed
+    #[allow(clippy::unused_self)]
+    fn add_rare_feature(&self, state: &mut S, idx: usize) -> Result<(), Error> {
+        let meta = state.metadata_mut::<EntropicMetadata>()?;
+        let mut changed = meta.drop_abundant_features();
+        meta.add_rare_feature(idx);
+        changed.push(idx);
+
+        // Local frequencies of the changed features are reset for everyone
+        for id in state.corpus().ids() {
+            let mut testcase = state.testcase_mut(id)?;
+            if let Ok(tcmeta) = testcase.metadata_mut::<EntropicTestcaseMetadata>() {
+                for feature in &changed {
+                    tcmeta.delete_feature_frequency(*feature);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl_bolts::{rands::StdRand, tuples::tuple_list, AsMutSlice};
+
+    use crate::{
+        corpus::{Corpus, InMemoryCorpus, Testcase},
+        feedbacks::ConstFeedback,
+        inputs::BytesInput,
+        observers::StdMapObserver,
+        schedulers::{
+            entropic::{EntropicMetadata, EntropicScheduler, EntropicTestcaseMetadata},
+            Scheduler,
+        },
+        state::{HasCorpus, HasMetadata, StdState},
+    };
+
+    #[test]
+    fn test_rare_features() {
+        let mut meta = EntropicMetadata::new(2, 3);
+        for idx in [10, 20, 30] {
+            meta.add_rare_feature(idx);
+        }
+        for _ in 0..5 {
+            assert!(meta.update_feature_frequency(10));
+        }
+        assert!(meta.update_feature_frequency(20));
+        assert_eq!(meta.global_frequency(10), 5);
+
+        // More than 2 rare features and one above the threshold: it is dropped
+        assert_eq!(meta.drop_abundant_features(), vec![10]);
+        assert_eq!(meta.rare_features().len(), 2);
+        assert!(!meta.is_rare(10));
+        assert!(!meta.update_feature_frequency(10));
+        assert!(meta.drop_abundant_features().is_empty());
+    }
+
+    #[test]
+    fn test_energy() {
+        let mut fresh = EntropicTestcaseMetadata::new(4);
+        assert!((fresh.energy() - libm::log(4.0)).abs() < f64::EPSILON);
+
+        // A testcase hitting rare features evenly reveals more than one hitting a single one
+        let mut even = fresh.clone();
+        for idx in [1, 2, 3, 4] {
+            even.update_feature_frequency(idx);
+        }
+        for _ in 0..4 {
+            fresh.update_feature_frequency(1);
+        }
+        assert_eq!(even.local_frequency(1), 1);
+        assert_eq!(fresh.local_frequency(1), 4);
+        even.update_energy(4);
+        fresh.update_energy(4);
+        assert!(even.energy() > fresh.energy());
+
+        // Executing many mutations without finding anything rare lowers the energy
+        let before = even.energy();
+        even.num_executed_mutations = 1000;
+        even.update_energy(4);
+        assert!(even.energy() < before);
+    }
+
+    #[test]
+    fn test_cached_energies() {
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        let mut observer = StdMapObserver::owned("edges", vec![0_u8; 16]);
+        let mut scheduler = EntropicScheduler::new(&mut state, &observer);
+
+        for i in 0..2 {
+            let idx = state
+                .corpus_mut()
+                .add(Testcase::new(BytesInput::new(vec![i])))
+                .unwrap();
+            scheduler.on_add(&mut state, idx).unwrap();
+        }
+        scheduler.next(&mut state).unwrap();
+        assert_eq!(scheduler.energies.len(), 2);
+        assert!(!scheduler.needs_rebuild);
+
+        // A new rare feature changes the energy of everyone
+        let (first, before) = scheduler.energies[0];
+        *state.corpus_mut().current_mut() = Some(first);
+        observer.map_mut().as_mut_slice()[1] = 1;
+        let input = BytesInput::new(vec![]);
+        let observers = tuple_list!(observer);
+        scheduler
+            .on_evaluation(&mut state, &input, &observers)
+            .unwrap();
+        assert!(scheduler.needs_rebuild);
+        scheduler.next(&mut state).unwrap();
+        assert!(!scheduler.needs_rebuild);
+
+        // Hitting the rare feature again only makes the parent stale
+        *state.corpus_mut().current_mut() = Some(first);
+        scheduler
+            .on_evaluation(&mut state, &input, &observers)
+            .unwrap();
+        assert!(!scheduler.needs_rebuild);
+        assert_eq!(scheduler.stale, vec![first]);
+        scheduler.next(&mut state).unwrap();
+        assert!(scheduler.stale.is_empty());
+        assert!(scheduler.energies[0].1 < before);
+        assert_eq!(scheduler.energies.len(), 2);
+    }
+
+    #[test]
+    fn test_num_executed_mutations() {
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        let observer = StdMapObserver::owned("edges", vec![0_u8; 16]);
+        let mut scheduler = EntropicScheduler::new(&mut state, &observer);
+        let observers = tuple_list!(observer);
+
+        let entry = BytesInput::new(vec![0]);
+        let idx = state
+            .corpus_mut()
+            .add(Testcase::new(entry.clone()))
+            .unwrap();
+        scheduler.on_add(&mut state, idx).unwrap();
+        *state.corpus_mut().current_mut() = Some(idx);
+
+        let executed_mutations = |state: &StdState<
+            BytesInput,
+            InMemoryCorpus<BytesInput>,
+            StdRand,
+            InMemoryCorpus<BytesInput>,
+        >| {
+            state
+                .corpus()
+                .get(idx)
+                .unwrap()
+                .borrow()
+                .metadata::<EntropicTestcaseMetadata>()
+                .unwrap()
+                .num_executed_mutations()
+        };
+
+        // Runs of the entry itself, e.g., to calibrate it, are no mutations
+        scheduler
+            .on_evaluation(&mut state, &entry, &observers)
+            .unwrap();
+        assert_eq!(executed_mutations(&state), 0);
+
+        scheduler
+            .on_evaluation(&mut state, &BytesInput::new(vec![1]), &observers)
+            .unwrap();
+        assert_eq!(executed_mutations(&state), 1);
+    }
+}
@@ -0,0 +1,278 @@
+//! The rare branch scheduler from `FairFuzz` (`https://arxiv.org/abs/1709.07101`).
+//! Testcases hitting edges that only few executions have hit so far are fuzzed first,
+//! use it together with the [`crate::stages::BranchMaskMutationalStage`] to keep those edges hit.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::marker::PhantomData;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{Corpus, CorpusId, HasTestcase},
+    inputs::UsesInput,
+    observers::{MapObserver, ObserversTuple},
+    schedulers::{RemovableScheduler, Scheduler},
+    state::{HasCorpus, HasMetadata, UsesState},
+    Error,
+};
+
+/// The state metadata for [`FairFuzzScheduler`]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct FairFuzzMetadata {
+    /// How many executions hit each edge of the map
+    hit_counts: Vec<u32>,
+    /// The rare edge the current testcase got scheduled for, if any
+    target_edge: Option<usize>,
+}
+
+libafl_bolts::impl_serdeany!(FairFuzzMetadata);
+
+impl FairFuzzMetadata {
+    /// Creates a new [`struct@FairFuzzMetadata`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// How many executions hit each edge of the map
+    #[must_use]
+    pub fn hit_counts(&self) -> &[u32] {
+        &self.hit_counts
+    }
+
+    /// How many executions hit the edge at `idx`
+    #[must_use]
+    pub fn hit_count(&self, idx: usize) -> u32 {
+        self.hit_counts.get(idx).copied().unwrap_or(0)
+    }
+
+    /// The rare edge the current testcase got scheduled for, if any
+    #[must_use]
+    pub fn target_edge(&self) -> Option<usize> {
+        self.target_edge
+    }
+
+    /// Account for an execution hitting the edge at `idx`
+    pub fn record_hit(&mut self, idx: usize) {
+        if idx >= self.hit_counts.len() {
+            self.hit_counts.resize(idx + 1, 0);
+        }
+        self.hit_counts[idx] = self.hit_counts[idx].saturating_add(1);
+    }
+
+    /// Edges hit at most this often are rare: the smallest power of two not below the
+    /// hit count of the least hit edge.
+    #[must_use]
+    pub fn rare_threshold(&self) -> Option<u32> {
+        self.hit_counts
+            .iter()
+            .filter(|hits| **hits > 0)
+            .min()
+            .map(|hits| hits.checked_next_power_of_two().unwrap_or(u32::MAX))
+    }
+
+    /// The least hit of the edges in `edges` hit at most `threshold` times, see [`Self::rare_threshold`]
+    #[must_use]
+    pub fn rarest_edge(&self, edges: &[usize], threshold: u32) -> Option<usize> {
+        edges
+            .iter()
+            .copied()
+            .filter(|idx| (1..=threshold).contains(&self.hit_count(*idx)))
+            .min_by_key(|idx| self.hit_count(*idx))
+    }
+}
+
+/// The testcase metadata for [`FairFuzzScheduler`]
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct FairFuzzTestcaseMetadata {
+    /// The edges this testcase hit when it was added to the corpus
+    edges: Vec<usize>,
+}
+
+libafl_bolts::impl_serdeany!(FairFuzzTestcaseMetadata);
+
+impl FairFuzzTestcaseMetadata {
+    /// Creates a new [`struct@FairFuzzTestcaseMetadata`]
+    #[must_use]
+    pub fn new(edges: Vec<usize>) -> Self {
+        Self { edges }
+    }
+
+    /// The edges this testcase hit when it was added to the corpus
+    #[must_use]
+    pub fn edges(&self) -> &[usize] {
+        &self.edges
+    }
+}
+
+/// Walk the corpus like a queue, but only stop at testcases hitting a rare edge.
+///
+/// The hit counts of the edges are collected from the map observer on every execution.
+/// The rare edge a testcase got chosen for is stored in the [`struct@FairFuzzMetadata`],
+/// so that later stages can focus on it. If no testcase hits a rare edge, the next one
+/// in the queue is scheduled without a target.
+#[derive(Debug, Clone)]
+pub struct FairFuzzScheduler<O, S> {
+    map_observer_name: String,
+    last_edges: Vec<usize>,
+    phantom: PhantomData<(O, S)>,
+}
+
+impl<O, S> UsesState for FairFuzzScheduler<O, S>
+where
+    S: UsesInput,
+{
+    type State = S;
+}
+
+impl<O, S> RemovableScheduler for FairFuzzScheduler<O, S>
+where
+    S: HasCorpus + HasMetadata + HasTestcase,
+    O: MapObserver,
+{
+}
+
+impl<O, S> Scheduler for FairFuzzScheduler<O, S>
+where
+    S: HasCorpus + HasMetadata + HasTestcase,
+    O: MapObserver,
+{
+    fn on_add(&mut self, state: &mut Self::State, idx: CorpusId) -> Result<(), Error> {
+        let current_idx = *state.corpus().current();
+        let mut testcase = state.testcase_mut(idx)?;
+        testcase.set_parent_id_optional(current_idx);
+        testcase.add_metadata(FairFuzzTestcaseMetadata::new(core::mem::take(
+            &mut self.last_edges,
+        )));
+        Ok(())
+    }
+
+    fn on_evaluation<OT>(
+        &mut self,
+        state: &mut Self::State,
+        _input: &<Self::State as UsesInput>::Input,
+        observers: &OT,
+    ) -> Result<(), Error>
+    where
+        OT: ObserversTuple<Self::State>,
+    {
+        let observer = observers
+            .match_name::<O>(&self.map_observer_name)
+            .ok_or_else(|| Error::key_not_found("MapObserver not found".to_string()))?;
+        let initial = observer.initial();
+
+        self.last_edges.clear();
+        let meta = state.metadata_mut::<FairFuzzMetadata>()?;
+        for i in 0..observer.usable_count() {
+            if *observer.get(i) != initial {
+                meta.record_hit(i);
+                self.last_edges.push(i);
+            }
+        }
+        Ok(())
+    }
+
+    /// Gets the next entry in the queue hitting a rare edge
+    fn next(&mut self, state: &mut Self::State) -> Result<CorpusId, Error> {
+        if state.corpus().count() == 0 {
+            return Err(Error::empty(String::from("No entries in corpus")));
+        }
+
+        let start = state
+            .corpus()
+            .current()
+            .and_then(|id| state.corpus().next(id))
+            .unwrap_or_else(|| state.corpus().first().unwrap());
+
+        let meta = state.metadata::<FairFuzzMetadata>()?;
+        let threshold = meta.rare_threshold().unwrap_or(0);
+
+        let mut id = start;
+        let mut target = None;
+        loop {
+            let edge = state
+                .testcase(id)?
+                .metadata::<FairFuzzTestcaseMetadata>()
+                .ok()
+                .and_then(|tcmeta| meta.rarest_edge(tcmeta.edges(), threshold));
+            if edge.is_some() {
+                target = edge;
+                break;
+            }
+            id = state
+                .corpus()
+                .next(id)
+                .unwrap_or_else(|| state.corpus().first().unwrap());
+            if id == start {
+                // Nothing hits a rare edge, just fuzz the next one in the queue
+                break;
+            }
+        }
+
+        state.metadata_mut::<FairFuzzMetadata>()?.target_edge = target;
+        self.set_current_scheduled(state, Some(id))?;
+        Ok(id)
+    }
+}
+
+impl<O, S> FairFuzzScheduler<O, S>
+where
+    S: HasMetadata,
+    O: MapObserver,
+{
+    /// Creates a new [`FairFuzzScheduler`] counting the edge hits of `map_observer`.
+    #[must_use]
+    pub fn new(state: &mut S, map_observer: &O) -> Self {
+        if !state.has_metadata::<FairFuzzMetadata>() {
+            state.add_metadata(FairFuzzMetadata::new());
+        }
+        Self {
+            map_observer_name: map_observer.name().to_string(),
+            last_edges: Vec::new(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::schedulers::fairfuzz::FairFuzzMetadata;
+
+    #[test]
+    fn test_rarest_edge() {
+        let mut meta = FairFuzzMetadata::new();
+        assert_eq!(meta.rare_threshold(), None);
+
+        for _ in 0..100 {
+            meta.record_hit(0);
+        }
+        for _ in 0..3 {
+            meta.record_hit(1);
+        }
+        for _ in 0..4 {
+            meta.record_hit(2);
+        }
+        for _ in 0..5 {
+            meta.record_hit(3);
+        }
+
+        // The least hit edge was hit 3 times, so everything hit up to 4 times is rare
+        assert_eq!(meta.rare_threshold(), Some(4));
+        assert_eq!(meta.rarest_edge(&[0, 2, 1], 4), Some(1));
+        assert_eq!(meta.rarest_edge(&[0, 2], 4), Some(2));
+        assert_eq!(meta.rarest_edge(&[0, 3], 4), None);
+        // Edges never hit can't be rare
+        assert_eq!(meta.rarest_edge(&[0, 42], 4), None);
+    }
+}
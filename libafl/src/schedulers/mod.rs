@@ -29,6 +29,27 @@ pub use weighted::{StdWeightedScheduler, WeightedScheduler};
 pub mod ecofuzz;
 pub use ecofuzz::{EcoMetadata, EcoScheduler, EcoState, EcoTestcaseMetadata, EcoTestcaseScore};
 
+pub mod entropic;
+pub use entropic::{
+    EntropicMetadata, EntropicScheduler, EntropicTestcaseMetadata, EntropicTestcaseScore,
+};
+
+pub mod directed;
+pub use directed::{
+    DirectedMetadata, DirectedScheduler, DirectedTestcaseMetadata, DirectedTestcaseScore,
+};
+
+pub mod fairfuzz;
+pub use fairfuzz::{FairFuzzMetadata, FairFuzzScheduler, FairFuzzTestcaseMetadata};
+
+pub mod state_aware;
+pub use state_aware::StateAwareScheduler;
+
+#[cfg(all(feature = "std", unix))]
+pub mod afl_custom;
+#[cfg(all(feature = "std", unix))]
+pub use afl_custom::AflCustomQueueScheduler;
+
 pub mod tuneable;
 use libafl_bolts::rands::Rand;
 pub use tuneable::*;
@@ -0,0 +1,202 @@
+//! The state-aware corpus scheduler from `AFLNet` (`https://thuanpv.github.io/publications/AFLNet_ICST20.pdf`).
+//! It first picks a state of the protocol state machine to explore, preferring the under-explored
+//! ones, then a corpus entry reaching that state.
+
+use alloc::{string::String, vec::Vec};
+use core::marker::PhantomData;
+
+use libafl_bolts::rands::Rand;
+
+use crate::{
+    corpus::{Corpus, CorpusId, HasTestcase},
+    feedbacks::{StateGraphMetadata, StateInfo, StateSequenceMetadata},
+    inputs::UsesInput,
+    observers::ObserversTuple,
+    random_corpus_id,
+    schedulers::{RemovableScheduler, Scheduler},
+    state::{HasCorpus, HasMetadata, HasRand, UsesState},
+    Error,
+};
+
+/// The score of a state, higher for states that were rarely selected or explored,
+/// and for states that led to many new corpus entries before.
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn state_score(info: &StateInfo) -> f64 {
+    let explored = libm::log10(info.fuzzs as f64 + 1.0) * info.selected_times as f64;
+    libm::ceil(
+        1000.0
+            * libm::pow(2.0, -libm::log10(explored + 1.0))
+            * libm::pow(2.0, libm::log(info.paths_discovered as f64 + 1.0)),
+    )
+}
+
+/// A corpus scheduler choosing a state of the [`struct@StateGraphMetadata`] to explore, weighted by its [`state_score`],
+/// and then one of the corpus entries reaching it.
+///
+/// The states are discovered by a [`crate::feedbacks::StateCoverageFeedback`].
+/// Until then, corpus entries are chosen at random.
+#[derive(Debug, Clone)]
+pub struct StateAwareScheduler<S> {
+    phantom: PhantomData<S>,
+}
+
+impl<S> UsesState for StateAwareScheduler<S>
+where
+    S: UsesInput,
+{
+    type State = S;
+}
+
+impl<S> RemovableScheduler for StateAwareScheduler<S> where
+    S: HasCorpus + HasMetadata + HasRand + HasTestcase
+{
+}
+
+impl<S> Scheduler for StateAwareScheduler<S>
+where
+    S: HasCorpus + HasMetadata + HasRand + HasTestcase,
+{
+    fn on_add(&mut self, state: &mut Self::State, idx: CorpusId) -> Result<(), Error> {
+        let current_idx = *state.corpus().current();
+        state.testcase_mut(idx)?.set_parent_id_optional(current_idx);
+
+        if let Ok(graph) = state.metadata_mut::<StateGraphMetadata>() {
+            if let Some(target) = graph.target_state() {
+                graph
+                    .states_mut()
+                    .entry(target)
+                    .or_default()
+                    .paths_discovered += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn on_evaluation<OT>(
+        &mut self,
+        state: &mut Self::State,
+        _input: &<Self::State as UsesInput>::Input,
+        _observers: &OT,
+    ) -> Result<(), Error>
+    where
+        OT: ObserversTuple<Self::State>,
+    {
+        if let Ok(graph) = state.metadata_mut::<StateGraphMetadata>() {
+            if let Some(target) = graph.target_state() {
+                graph.states_mut().entry(target).or_default().fuzzs += 1;
+            }
+        }
+        Ok(())
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn next(&mut self, state: &mut Self::State) -> Result<CorpusId, Error> {
+        if state.corpus().count() == 0 {
+            return Err(Error::empty(String::from("No entries in corpus")));
+        }
+
+        let target = Self::choose_state(state);
+        let mut candidates = Vec::new();
+        if let Some(target) = target {
+            for idx in state.corpus().ids() {
+                if state
+                    .testcase(idx)?
+                    .metadata::<StateSequenceMetadata>()
+                    .map_or(false, |meta| meta.reaches(target))
+                {
+                    candidates.push(idx);
+                }
+            }
+        }
+        let id = if candidates.is_empty() {
+            random_corpus_id!(state.corpus(), state.rand_mut())
+        } else {
+            *state.rand_mut().choose(&candidates)
+        };
+
+        if let Ok(graph) = state.metadata_mut::<StateGraphMetadata>() {
+            graph.set_target_state(target);
+            if let Some(target) = target {
+                graph.states_mut().entry(target).or_default().selected_times += 1;
+            }
+        }
+        self.set_current_scheduled(state, Some(id))?;
+        Ok(id)
+    }
+}
+
+impl<S> StateAwareScheduler<S>
+where
+    S: HasMetadata + HasRand,
+{
+    /// Creates a new [`StateAwareScheduler`]
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            phantom: PhantomData,
+        }
+    }
+
+    /// Choose the state to explore next, weighted by the [`state_score`] of each
+    #[allow(clippy::cast_precision_loss)]
+    fn choose_state(state: &mut S) -> Option<u32> {
+        let graph = state.metadata::<StateGraphMetadata>().ok()?;
+        let mut scores: Vec<(u32, f64)> = graph
+            .states()
+            .iter()
+            .map(|(id, info)| (*id, state_score(info)))
+            .collect();
+        // Keep the choice reproducible for a given seed
+        scores.sort_unstable_by_key(|(id, _)| *id);
+
+        let total: f64 = scores.iter().map(|(_, score)| score).sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let threshold = total * (state.rand_mut().next() as f64 / u64::MAX as f64);
+        let mut sum = 0.0;
+        scores
+            .iter()
+            .find(|(_, score)| {
+                sum += score;
+                sum >= threshold
+            })
+            .or_else(|| scores.last())
+            .map(|(id, _)| *id)
+    }
+}
+
+impl<S> Default for StateAwareScheduler<S>
+where
+    S: HasMetadata + HasRand,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{feedbacks::StateInfo, schedulers::state_aware::state_score};
+
+    #[test]
+    fn test_state_score() {
+        let fresh = state_score(&StateInfo::default());
+        assert!((fresh - 1000.0).abs() < f64::EPSILON);
+
+        let explored = StateInfo {
+            selected_times: 50,
+            fuzzs: 10_000,
+            ..StateInfo::default()
+        };
+        assert!(state_score(&explored) < fresh);
+
+        // States that keep leading to new paths stay interesting
+        let productive = StateInfo {
+            paths_discovered: 20,
+            ..explored.clone()
+        };
+        assert!(state_score(&productive) > state_score(&explored));
+    }
+}
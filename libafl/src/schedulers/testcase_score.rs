@@ -47,7 +47,7 @@ where
 /// Constants for powerschedules
 const POWER_BETA: f64 = 1.0;
 const MAX_FACTOR: f64 = POWER_BETA * 32.0;
-const HAVOC_MAX_MULT: f64 = 64.0;
+pub(crate) const HAVOC_MAX_MULT: f64 = 64.0;
 
 /// The power assigned to each corpus entry
 /// This result is used for power scheduling
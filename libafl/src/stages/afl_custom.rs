@@ -0,0 +1,326 @@
+//! Trims corpus entries with the `afl_custom_trim` hooks of an `AFL++` custom mutator,
+//! see [`crate::mutators::afl_custom`].
+
+use alloc::{
+    rc::Rc,
+    string::{String, ToString},
+};
+use core::marker::PhantomData;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{Corpus, CorpusId},
+    executors::{Executor, HasObservers},
+    inputs::HasBytesVec,
+    mutators::AflCustomLibrary,
+    observers::MapObserver,
+    stages::{colorization::run_and_inspect_map, Stage},
+    state::{HasCorpus, HasMetadata, UsesState},
+    Error,
+};
+
+/// A testcase metadata saying the testcase was trimmed by the [`AflCustomTrimStage`] already
+#[derive(Debug, Serialize, Deserialize)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct AflCustomTrimmedMetadata {}
+
+libafl_bolts::impl_serdeany!(AflCustomTrimmedMetadata);
+
+/// A stage trimming each corpus entry once, like `AFL++` does with a custom mutator's
+/// `afl_custom_init_trim`, `afl_custom_trim` and `afl_custom_post_trim`.
+///
+/// A trimmed candidate is kept if the map of the given observer stays the same.
+#[derive(Debug)]
+pub struct AflCustomTrimStage<E, EM, O, Z> {
+    map_observer_name: String,
+    library: Rc<AflCustomLibrary>,
+    phantom: PhantomData<(E, EM, O, Z)>,
+}
+
+impl<E, EM, O, Z> UsesState for AflCustomTrimStage<E, EM, O, Z>
+where
+    E: UsesState,
+{
+    type State = E::State;
+}
+
+impl<E, EM, O, Z> Stage<E, EM, Z> for AflCustomTrimStage<E, EM, O, Z>
+where
+    EM: UsesState<State = E::State>,
+    E: HasObservers + Executor<EM, Z>,
+    E::State: HasCorpus,
+    E::Input: HasBytesVec,
+    O: MapObserver,
+    Z: UsesState<State = E::State>,
+{
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut E::State,
+        manager: &mut EM,
+        corpus_idx: CorpusId,
+    ) -> Result<(), Error> {
+        if !self.library.has_trim() {
+            return Ok(());
+        }
+        let original = {
+            let mut testcase = state.corpus().get(corpus_idx)?.borrow_mut();
+            if testcase.has_metadata::<AflCustomTrimmedMetadata>() {
+                return Ok(());
+            }
+            testcase.add_metadata(AflCustomTrimmedMetadata {});
+            state.corpus().load_input_into(&mut testcase)?;
+            testcase.input().as_ref().unwrap().clone()
+        };
+
+        let original_hash = self.map_hash(fuzzer, executor, state, manager, &original)?;
+
+        let mut trimmed = original.clone();
+        let steps = self.library.init_trim(trimmed.bytes_mut());
+        if steps < 0 {
+            return Err(Error::illegal_state(
+                "afl_custom_init_trim of the custom mutator failed",
+            ));
+        }
+        let mut step = 0;
+        while step < steps {
+            let Some(candidate) = self.library.trim() else {
+                break;
+            };
+            let mut input = original.clone();
+            *input.bytes_mut() = candidate;
+            let success = self.map_hash(fuzzer, executor, state, manager, &input)? == original_hash;
+            if success {
+                trimmed = input;
+            }
+            step = self.library.post_trim(success);
+            if step < 0 {
+                return Err(Error::illegal_state(
+                    "afl_custom_post_trim of the custom mutator failed",
+                ));
+            }
+        }
+
+        if trimmed.bytes().len() < original.bytes().len() {
+            let mut testcase = state.corpus().get(corpus_idx)?.borrow_mut();
+            testcase.set_input(trimmed);
+            state.corpus().store_input_from(&testcase)?;
+        }
+        Ok(())
+    }
+}
+
+impl<E, EM, O, Z> AflCustomTrimStage<E, EM, O, Z>
+where
+    EM: UsesState<State = E::State>,
+    E: HasObservers + Executor<EM, Z>,
+    O: MapObserver,
+    Z: UsesState<State = E::State>,
+{
+    /// Creates a new [`AflCustomTrimStage`], trimming with `library`
+    /// and comparing the maps of `map_observer`.
+    #[must_use]
+    pub fn new(map_observer: &O, library: Rc<AflCustomLibrary>) -> Self {
+        Self {
+            map_observer_name: map_observer.name().to_string(),
+            library,
+            phantom: PhantomData,
+        }
+    }
+
+    fn map_hash(
+        &self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut E::State,
+        manager: &mut EM,
+        input: &E::Input,
+    ) -> Result<u64, Error> {
+        run_and_inspect_map::<E, EM, O, Z, _, _>(
+            fuzzer,
+            executor,
+            state,
+            manager,
+            input,
+            &self.map_observer_name,
+            MapObserver::hash,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::{rc::Rc, vec::Vec};
+    use core::{cell::RefCell, ffi::c_void};
+
+    use libafl_bolts::{
+        rands::StdRand,
+        tuples::{tuple_list, tuple_list_type, MatchName},
+        AsMutSlice,
+    };
+
+    use crate::{
+        corpus::{Corpus, CorpusId, InMemoryCorpus, Testcase},
+        events::NopEventManager,
+        executors::{Executor, ExitKind, HasObservers},
+        feedbacks::ConstFeedback,
+        inputs::{BytesInput, HasBytesVec},
+        mutators::AflCustomLibrary,
+        observers::{StdMapObserver, UsesObservers},
+        stages::{AflCustomTrimStage, Stage},
+        state::{HasCorpus, StdState, UsesState},
+        Error,
+    };
+
+    type TestState =
+        StdState<BytesInput, InMemoryCorpus<BytesInput>, StdRand, InMemoryCorpus<BytesInput>>;
+
+    struct TestFuzzer;
+
+    impl UsesState for TestFuzzer {
+        type State = TestState;
+    }
+
+    /// Hits the first edge if the input contains an `x`
+    #[derive(Debug)]
+    struct EdgeExecutor {
+        observers: tuple_list_type!(StdMapObserver<'static, u8, false>),
+    }
+
+    impl UsesState for EdgeExecutor {
+        type State = TestState;
+    }
+
+    impl UsesObservers for EdgeExecutor {
+        type Observers = tuple_list_type!(StdMapObserver<'static, u8, false>);
+    }
+
+    impl HasObservers for EdgeExecutor {
+        fn observers(&self) -> &Self::Observers {
+            &self.observers
+        }
+
+        fn observers_mut(&mut self) -> &mut Self::Observers {
+            &mut self.observers
+        }
+    }
+
+    impl Executor<NopEventManager<TestState>, TestFuzzer> for EdgeExecutor {
+        fn run_target(
+            &mut self,
+            _fuzzer: &mut TestFuzzer,
+            _state: &mut TestState,
+            _mgr: &mut NopEventManager<TestState>,
+            input: &BytesInput,
+        ) -> Result<ExitKind, Error> {
+            let observer = self
+                .observers
+                .match_name_mut::<StdMapObserver<'static, u8, false>>("edges")
+                .unwrap();
+            observer.map_mut().as_mut_slice()[0] = u8::from(input.bytes().contains(&b'x'));
+            Ok(ExitKind::Ok)
+        }
+    }
+
+    std::thread_local! {
+        static TRIM_BUF: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+        static TRIM_FAILS: RefCell<bool> = const { RefCell::new(false) };
+    }
+
+    /// Trims the last byte, as long as that works
+    unsafe extern "C" fn init_trim(_data: *mut c_void, buf: *mut u8, len: usize) -> i32 {
+        TRIM_BUF.with(|trim_buf| {
+            *trim_buf.borrow_mut() = core::slice::from_raw_parts(buf, len).to_vec();
+        });
+        i32::try_from(len).unwrap()
+    }
+
+    unsafe extern "C" fn trim(_data: *mut c_void, out_buf: *mut *mut u8) -> usize {
+        TRIM_BUF.with(|trim_buf| {
+            let mut trim_buf = trim_buf.borrow_mut();
+            *out_buf = trim_buf.as_mut_ptr();
+            trim_buf.len() - 1
+        })
+    }
+
+    unsafe extern "C" fn post_trim(_data: *mut c_void, success: u8) -> i32 {
+        if TRIM_FAILS.with(|fails| *fails.borrow()) {
+            return -1;
+        }
+        TRIM_BUF.with(|trim_buf| {
+            let mut trim_buf = trim_buf.borrow_mut();
+            // Stop at the first failed candidate
+            if success == 0 {
+                return i32::MAX;
+            }
+            trim_buf.pop();
+            0
+        })
+    }
+
+    #[test]
+    fn test_afl_custom_trim() {
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        let observer = StdMapObserver::owned("edges", vec![0_u8; 4]);
+        let library = Rc::new(AflCustomLibrary::from_hooks().with_trim(init_trim, trim, post_trim));
+        let mut trim_stage = AflCustomTrimStage::<_, _, StdMapObserver<'static, u8, false>, _>::new(
+            &observer, library,
+        );
+        let mut executor = EdgeExecutor {
+            observers: tuple_list!(observer),
+        };
+        let mut fuzzer = TestFuzzer;
+        let mut mgr = NopEventManager::new();
+
+        let mut perform = |state: &mut TestState, idx: CorpusId| {
+            trim_stage.perform(&mut fuzzer, &mut executor, state, &mut mgr, idx)
+        };
+        let input = |state: &TestState, idx: CorpusId| {
+            state
+                .corpus()
+                .cloned_input_for_id(idx)
+                .unwrap()
+                .bytes()
+                .to_vec()
+        };
+
+        // Trimmed as long as the edge stays the same, and only once
+        let idx = state
+            .corpus_mut()
+            .add(Testcase::new(BytesInput::new(b"axbb".to_vec())))
+            .unwrap();
+        perform(&mut state, idx).unwrap();
+        assert_eq!(input(&state, idx), b"ax");
+        state
+            .corpus()
+            .get(idx)
+            .unwrap()
+            .borrow_mut()
+            .set_input(BytesInput::new(b"axbb".to_vec()));
+        perform(&mut state, idx).unwrap();
+        assert_eq!(input(&state, idx), b"axbb");
+
+        // A failing post_trim ends trimming with an error
+        TRIM_FAILS.with(|fails| *fails.borrow_mut() = true);
+        let idx = state
+            .corpus_mut()
+            .add(Testcase::new(BytesInput::new(b"axbb".to_vec())))
+            .unwrap();
+        assert!(perform(&mut state, idx).is_err());
+        assert_eq!(input(&state, idx), b"axbb");
+    }
+}
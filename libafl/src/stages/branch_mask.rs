@@ -0,0 +1,332 @@
+//! The branch mask mutational stage from `FairFuzz` (`https://arxiv.org/abs/1709.07101`).
+//! It finds out which bytes of a testcase can be changed without losing the rare edge the
+//! [`crate::schedulers::FairFuzzScheduler`] picked it for, and keeps the mutations to those.
+
+use alloc::{
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::marker::PhantomData;
+
+use libafl_bolts::rands::Rand;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{Corpus, CorpusId},
+    executors::{Executor, HasObservers},
+    fuzzer::Evaluator,
+    inputs::HasBytesVec,
+    mutators::{MutationResult, Mutator},
+    observers::MapObserver,
+    schedulers::FairFuzzMetadata,
+    stages::{
+        colorization::run_and_inspect_map, mutational::DEFAULT_MUTATIONAL_MAX_ITERATIONS, Stage,
+    },
+    state::{HasCorpus, HasMetadata, HasRand, UsesState},
+    Error,
+};
+
+/// The byte at this position can be overwritten
+pub const BRANCH_MASK_OVERWRITE: u8 = 1;
+/// The byte at this position can be deleted
+pub const BRANCH_MASK_DELETE: u8 = 2;
+/// Bytes can be inserted at this position
+pub const BRANCH_MASK_INSERT: u8 = 4;
+
+/// The branch mask of a testcase, for the rare edge it was computed for
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+pub struct BranchMaskMetadata {
+    edge: usize,
+    /// One entry per byte, and one more for appending at the end
+    mask: Vec<u8>,
+}
+
+libafl_bolts::impl_serdeany!(BranchMaskMetadata);
+
+impl BranchMaskMetadata {
+    /// Creates a new [`struct@BranchMaskMetadata`] for `edge`.
+    ///
+    /// The `mask` has an entry of `BRANCH_MASK_*` flags for every byte of the input,
+    /// and one more for inserting at its end.
+    #[must_use]
+    pub fn new(edge: usize, mask: Vec<u8>) -> Self {
+        Self { edge, mask }
+    }
+
+    /// The edge this mask keeps hit
+    #[must_use]
+    pub fn edge(&self) -> usize {
+        self.edge
+    }
+
+    /// The mask, with an entry of `BRANCH_MASK_*` flags per position
+    #[must_use]
+    pub fn mask(&self) -> &[u8] {
+        &self.mask
+    }
+
+    fn allows(&self, idx: usize, flag: u8) -> bool {
+        self.mask.get(idx).map_or(false, |entry| entry & flag != 0)
+    }
+
+    /// Restricts the changes `mutated` makes to `original` to what the mask allows.
+    ///
+    /// Overwritten bytes that must be kept are restored. As the exact mutation is unknown
+    /// for inputs changing in size, the changed region must be deletable, or insertable
+    /// and overwritable, as a whole.
+    /// Returns `false` if nothing is left of the mutation.
+    pub fn restrict(&self, original: &[u8], mutated: &mut [u8]) -> bool {
+        if mutated.len() == original.len() {
+            for (idx, (byte, orig)) in mutated.iter_mut().zip(original).enumerate() {
+                if byte != orig && !self.allows(idx, BRANCH_MASK_OVERWRITE) {
+                    *byte = *orig;
+                }
+            }
+            return mutated != original;
+        }
+
+        let prefix = mutated
+            .iter()
+            .zip(original)
+            .take_while(|(byte, orig)| byte == orig)
+            .count();
+        let suffix = mutated[prefix..]
+            .iter()
+            .rev()
+            .zip(original[prefix..].iter().rev())
+            .take_while(|(byte, orig)| byte == orig)
+            .count();
+        let mut changed = prefix..(original.len() - suffix);
+
+        if mutated.len() < original.len() {
+            changed.all(|idx| self.allows(idx, BRANCH_MASK_DELETE))
+        } else {
+            self.allows(prefix, BRANCH_MASK_INSERT)
+                && changed.all(|idx| self.allows(idx, BRANCH_MASK_OVERWRITE))
+        }
+    }
+}
+
+/// A mutational stage keeping the mutations of the [`StdScheduledMutator`](crate::mutators::StdScheduledMutator)
+/// (or any other `mutator`) to the bytes that don't affect the rare edge
+/// the [`crate::schedulers::FairFuzzScheduler`] chose the current testcase for.
+///
+/// The branch mask is computed once per testcase and rare edge, by trying to overwrite,
+/// delete, and insert at each position of the input, see [`struct@BranchMaskMetadata`].
+/// Without a rare edge to target, the mutations are not restricted.
+#[derive(Clone, Debug)]
+pub struct BranchMaskMutationalStage<E, EM, M, O, Z> {
+    map_observer_name: String,
+    mutator: M,
+    max_iterations: u64,
+    #[allow(clippy::type_complexity)]
+    phantom: PhantomData<(E, EM, O, Z)>,
+}
+
+impl<E, EM, M, O, Z> UsesState for BranchMaskMutationalStage<E, EM, M, O, Z>
+where
+    E: UsesState,
+{
+    type State = E::State;
+}
+
+impl<E, EM, M, O, Z> Stage<E, EM, Z> for BranchMaskMutationalStage<E, EM, M, O, Z>
+where
+    EM: UsesState<State = E::State>,
+    E: HasObservers + Executor<EM, Z>,
+    E::State: HasCorpus + HasMetadata + HasRand,
+    E::Input: HasBytesVec,
+    M: Mutator<E::Input, E::State>,
+    O: MapObserver,
+    Z: Evaluator<E, EM, State = E::State>,
+{
+    #[allow(clippy::cast_possible_wrap)] // more than i32 stages on 32 bit system - highly unlikely...
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut E::State,
+        manager: &mut EM,
+        corpus_idx: CorpusId,
+    ) -> Result<(), Error> {
+        let original = {
+            let mut testcase = state.corpus().get(corpus_idx)?.borrow_mut();
+            state.corpus().load_input_into(&mut testcase)?;
+            testcase.input().as_ref().unwrap().clone()
+        };
+
+        let target_edge = state
+            .metadata::<FairFuzzMetadata>()
+            .ok()
+            .and_then(FairFuzzMetadata::target_edge);
+        let mask = match target_edge {
+            Some(edge) => self.branch_mask(
+                fuzzer, executor, state, manager, corpus_idx, &original, edge,
+            )?,
+            None => None,
+        };
+
+        let num = 1 + state.rand_mut().below(self.max_iterations);
+        for i in 0..num {
+            let mut input = original.clone();
+            if self.mutator.mutate(state, &mut input, i as i32)? == MutationResult::Skipped {
+                continue;
+            }
+            if let Some(mask) = &mask {
+                if !mask.restrict(original.bytes(), input.bytes_mut()) {
+                    continue;
+                }
+            }
+
+            let (_, corpus_idx) = fuzzer.evaluate_input(state, executor, manager, input)?;
+            self.mutator.post_exec(state, i as i32, corpus_idx)?;
+        }
+        Ok(())
+    }
+}
+
+impl<E, EM, M, O, Z> BranchMaskMutationalStage<E, EM, M, O, Z>
+where
+    EM: UsesState<State = E::State>,
+    E: HasObservers + Executor<EM, Z>,
+    E::State: HasCorpus + HasMetadata + HasRand,
+    E::Input: HasBytesVec,
+    O: MapObserver,
+    Z: UsesState<State = E::State>,
+{
+    /// Creates a new [`BranchMaskMutationalStage`], checking the rare edges in the map of `map_observer`.
+    #[must_use]
+    pub fn new(map_observer: &O, mutator: M) -> Self {
+        Self::with_max_iterations(map_observer, mutator, DEFAULT_MUTATIONAL_MAX_ITERATIONS)
+    }
+
+    /// Creates a new [`BranchMaskMutationalStage`] with a custom maximum number of mutations per testcase
+    #[must_use]
+    pub fn with_max_iterations(map_observer: &O, mutator: M, max_iterations: u64) -> Self {
+        Self {
+            map_observer_name: map_observer.name().to_string(),
+            mutator,
+            max_iterations,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Gets the branch mask of the testcase for `edge`, computing it if it is not known yet.
+    /// Returns `None` if the testcase does not even hit `edge` itself.
+    #[allow(clippy::too_many_arguments, clippy::needless_range_loop)]
+    fn branch_mask(
+        &self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut E::State,
+        manager: &mut EM,
+        corpus_idx: CorpusId,
+        original: &E::Input,
+        edge: usize,
+    ) -> Result<Option<BranchMaskMetadata>, Error> {
+        if let Ok(meta) = state
+            .corpus()
+            .get(corpus_idx)?
+            .borrow()
+            .metadata::<BranchMaskMetadata>()
+        {
+            if meta.edge() == edge {
+                return Ok(Some(meta.clone()));
+            }
+        }
+
+        if !self.hits_edge(fuzzer, executor, state, manager, original, edge)? {
+            return Ok(None);
+        }
+
+        let len = original.bytes().len();
+        let mut mask = vec![0; len + 1];
+        let mut input = original.clone();
+        for idx in 0..=len {
+            if idx < len {
+                input.bytes_mut()[idx] ^= 0xff;
+                if self.hits_edge(fuzzer, executor, state, manager, &input, edge)? {
+                    mask[idx] |= BRANCH_MASK_OVERWRITE;
+                }
+                input.bytes_mut()[idx] ^= 0xff;
+
+                let removed = input.bytes_mut().remove(idx);
+                if self.hits_edge(fuzzer, executor, state, manager, &input, edge)? {
+                    mask[idx] |= BRANCH_MASK_DELETE;
+                }
+                input.bytes_mut().insert(idx, removed);
+            }
+
+            let inserted = state.rand_mut().below(256) as u8;
+            input.bytes_mut().insert(idx, inserted);
+            if self.hits_edge(fuzzer, executor, state, manager, &input, edge)? {
+                mask[idx] |= BRANCH_MASK_INSERT;
+            }
+            input.bytes_mut().remove(idx);
+        }
+
+        let meta = BranchMaskMetadata::new(edge, mask);
+        state
+            .corpus()
+            .get(corpus_idx)?
+            .borrow_mut()
+            .add_metadata(meta.clone());
+        Ok(Some(meta))
+    }
+
+    fn hits_edge(
+        &self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut E::State,
+        manager: &mut EM,
+        input: &E::Input,
+        edge: usize,
+    ) -> Result<bool, Error> {
+        run_and_inspect_map::<E, EM, O, Z, _, _>(
+            fuzzer,
+            executor,
+            state,
+            manager,
+            input,
+            &self.map_observer_name,
+            |observer| edge < observer.usable_count() && *observer.get(edge) != observer.initial(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stages::branch_mask::{
+        BranchMaskMetadata, BRANCH_MASK_DELETE, BRANCH_MASK_INSERT, BRANCH_MASK_OVERWRITE,
+    };
+
+    #[test]
+    fn test_restrict() {
+        let all = BRANCH_MASK_OVERWRITE | BRANCH_MASK_DELETE | BRANCH_MASK_INSERT;
+        // The second byte has to stay as it is, and nothing can be inserted before it
+        let meta = BranchMaskMetadata::new(0, vec![all, 0, all, all]);
+        let original = b"abc";
+
+        let mut mutated = *b"xyz";
+        assert!(meta.restrict(original, &mut mutated));
+        assert_eq!(&mutated, b"xbz");
+
+        let mut mutated = *b"ayc";
+        assert!(!meta.restrict(original, &mut mutated));
+        assert_eq!(&mutated, original);
+
+        // Deletions
+        assert!(meta.restrict(original, &mut b"bc".to_vec()));
+        assert!(!meta.restrict(original, &mut b"ac".to_vec()));
+
+        // Insertions
+        assert!(meta.restrict(original, &mut b"abcd".to_vec()));
+        assert!(meta.restrict(original, &mut b"abxc".to_vec()));
+        assert!(!meta.restrict(original, &mut b"axbc".to_vec()));
+    }
+}
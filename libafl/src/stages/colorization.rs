@@ -101,6 +101,46 @@ where
     }
 }
 
+/// Run the target and inspect the map observer called `name`, before any `post_exec` (e.g., hitcounts's) is applied.
+///
+/// The execution is not reported to the fuzzer, so it won't end up in the corpus nor in the stats.
+pub(crate) fn run_and_inspect_map<E, EM, O, Z, F, R>(
+    fuzzer: &mut Z,
+    executor: &mut E,
+    state: &mut E::State,
+    manager: &mut EM,
+    input: &E::Input,
+    name: &str,
+    inspect: F,
+) -> Result<R, Error>
+where
+    EM: UsesState<State = E::State>,
+    E: HasObservers + Executor<EM, Z>,
+    O: MapObserver,
+    Z: UsesState<State = E::State>,
+    F: FnOnce(&O) -> R,
+{
+    executor.observers_mut().pre_exec_all(state, input)?;
+
+    let exit_kind = executor.run_target(fuzzer, state, manager, input)?;
+
+    let observer = executor
+        .observers()
+        .match_name::<O>(name)
+        .ok_or_else(|| Error::key_not_found("MapObserver not found".to_string()))?;
+
+    let res = inspect(observer);
+
+    executor
+        .observers_mut()
+        .post_exec_all(state, input, &exit_kind)?;
+
+    // let observers = executor.observers();
+    // fuzzer.process_execution(state, manager, input, observers, &exit_kind, true)?;
+
+    Ok(res)
+}
+
 /// Store the taint and the input
 #[derive(Debug, Serialize, Deserialize)]
 #[cfg_attr(
@@ -304,25 +344,15 @@ where
         input: E::Input,
         name: &str,
     ) -> Result<usize, Error> {
-        executor.observers_mut().pre_exec_all(state, &input)?;
-
-        let exit_kind = executor.run_target(fuzzer, state, manager, &input)?;
-
-        let observer = executor
-            .observers()
-            .match_name::<O>(name)
-            .ok_or_else(|| Error::key_not_found("MapObserver not found".to_string()))?;
-
-        let hash = observer.hash() as usize;
-
-        executor
-            .observers_mut()
-            .post_exec_all(state, &input, &exit_kind)?;
-
-        // let observers = executor.observers();
-        // fuzzer.process_execution(state, manager, input, observers, &exit_kind, true)?;
-
-        Ok(hash)
+        run_and_inspect_map::<E, EM, O, Z, _, _>(
+            fuzzer,
+            executor,
+            state,
+            manager,
+            &input,
+            name,
+            |observer| observer.hash() as usize,
+        )
     }
 
     /// Replace bytes with random values but following certain rules
@@ -0,0 +1,691 @@
+//! The deterministic stage, walking each new corpus entry once with the bitflip, arithmetic and
+//! interesting value passes of AFL's `fuzz_one()`.
+//!
+//! Progress is kept in [`DeterministicStageMetadata`] on the [`crate::corpus::Testcase`], so a walk
+//! interrupted by a restart (for example of a [`crate::events::LlmpRestartingEventManager`]) can
+//! continue where it crashed instead of starting over.
+
+use alloc::{
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::marker::PhantomData;
+
+use libafl_bolts::{impl_serdeany, tuples::MatchName};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    corpus::{Corpus, CorpusId},
+    executors::{Executor, HasObservers},
+    fuzzer::Evaluator,
+    inputs::HasBytesVec,
+    mutators::mutations::{ARITH_MAX, INTERESTING_16, INTERESTING_32, INTERESTING_8},
+    observers::{MapObserver, ObserversTuple},
+    stages::Stage,
+    state::{HasClientPerfMonitor, HasCorpus, HasMetadata, UsesState},
+    Error,
+};
+
+/// Inputs shorter than this are not worth an effector map (AFL's `EFF_MIN_LEN`)
+const EFF_MIN_LEN: usize = 128;
+/// If more than this percentage of the input is effective, the whole input is considered effective (AFL's `EFF_MAX_PERC`)
+const EFF_MAX_PERC: usize = 90;
+
+/// The passes of the deterministic walk, in the order they are performed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeterministicPhase {
+    /// Flip a single bit, walking one bit at a time
+    BitFlip1,
+    /// Flip two adjacent bits, walking one bit at a time
+    BitFlip2,
+    /// Flip four adjacent bits, walking one bit at a time
+    BitFlip4,
+    /// Flip a whole byte, walking one byte at a time. This pass builds the effector map.
+    ByteFlip8,
+    /// Flip two adjacent bytes, walking one byte at a time
+    ByteFlip16,
+    /// Flip four adjacent bytes, walking one byte at a time
+    ByteFlip32,
+    /// Add and subtract small values to a byte
+    Arith8,
+    /// Add and subtract small values to a word, in both endiannesses
+    Arith16,
+    /// Add and subtract small values to a dword, in both endiannesses
+    Arith32,
+    /// Replace a byte with interesting values
+    Interest8,
+    /// Replace a word with interesting values, in both endiannesses
+    Interest16,
+    /// Replace a dword with interesting values, in both endiannesses
+    Interest32,
+    /// The walk is finished
+    Done,
+}
+
+impl DeterministicPhase {
+    /// The phase following this one
+    #[must_use]
+    pub fn next(self) -> Self {
+        match self {
+            Self::BitFlip1 => Self::BitFlip2,
+            Self::BitFlip2 => Self::BitFlip4,
+            Self::BitFlip4 => Self::ByteFlip8,
+            Self::ByteFlip8 => Self::ByteFlip16,
+            Self::ByteFlip16 => Self::ByteFlip32,
+            Self::ByteFlip32 => Self::Arith8,
+            Self::Arith8 => Self::Arith16,
+            Self::Arith16 => Self::Arith32,
+            Self::Arith32 => Self::Interest8,
+            Self::Interest8 => Self::Interest16,
+            Self::Interest16 => Self::Interest32,
+            Self::Interest32 | Self::Done => Self::Done,
+        }
+    }
+
+    /// The number of steps this phase takes for an input of `len` bytes.
+    /// Each step results in at most one execution.
+    #[must_use]
+    pub fn steps(self, len: usize) -> usize {
+        let arith = ARITH_MAX as usize;
+        match self {
+            Self::BitFlip1 => len * 8,
+            Self::BitFlip2 => (len * 8).saturating_sub(1),
+            Self::BitFlip4 => (len * 8).saturating_sub(3),
+            Self::ByteFlip8 => len,
+            Self::ByteFlip16 => len.saturating_sub(1),
+            Self::ByteFlip32 => len.saturating_sub(3),
+            Self::Arith8 => len * 2 * arith,
+            Self::Arith16 => len.saturating_sub(1) * 4 * arith,
+            Self::Arith32 => len.saturating_sub(3) * 4 * arith,
+            Self::Interest8 => len * INTERESTING_8.len(),
+            Self::Interest16 => len.saturating_sub(1) * 2 * INTERESTING_16.len(),
+            Self::Interest32 => len.saturating_sub(3) * 2 * INTERESTING_32.len(),
+            Self::Done => 0,
+        }
+    }
+
+    /// Applies the given `step` of this phase to `bytes`.
+    ///
+    /// Returns `false` if the step was skipped, either because the effector map marks the bytes
+    /// as ineffective, or because an earlier phase already produced the same input.
+    /// An empty `effector` map considers every byte effective.
+    #[must_use]
+    #[allow(clippy::cast_sign_loss, clippy::too_many_lines)]
+    pub fn apply(self, step: usize, bytes: &mut [u8], effector: &[bool]) -> bool {
+        let arith = ARITH_MAX as usize;
+        match self {
+            Self::BitFlip1 | Self::BitFlip2 | Self::BitFlip4 => {
+                let width = match self {
+                    Self::BitFlip1 => 1,
+                    Self::BitFlip2 => 2,
+                    _ => 4,
+                };
+                for bit in step..step + width {
+                    bytes[bit >> 3] ^= 128 >> (bit & 7);
+                }
+                true
+            }
+            Self::ByteFlip8 => {
+                bytes[step] ^= 0xff;
+                true
+            }
+            Self::ByteFlip16 | Self::ByteFlip32 => {
+                let width = if self == Self::ByteFlip16 { 2 } else { 4 };
+                if !is_effective(effector, step, width) {
+                    return false;
+                }
+                for byte in &mut bytes[step..step + width] {
+                    *byte ^= 0xff;
+                }
+                true
+            }
+            Self::Arith8 => {
+                let pos = step / (2 * arith);
+                let variant = step % (2 * arith);
+                if !is_effective(effector, pos, 1) {
+                    return false;
+                }
+                let orig = bytes[pos];
+                let amount = (variant / 2 + 1) as u8;
+                let new = if variant % 2 == 0 {
+                    orig.wrapping_add(amount)
+                } else {
+                    orig.wrapping_sub(amount)
+                };
+                if could_be_bitflip(u32::from(orig ^ new)) {
+                    return false;
+                }
+                bytes[pos] = new;
+                true
+            }
+            Self::Arith16 | Self::Arith32 => {
+                let width = if self == Self::Arith16 { 2 } else { 4 };
+                let pos = step / (4 * arith);
+                let variant = step % (4 * arith);
+                if !is_effective(effector, pos, width) {
+                    return false;
+                }
+                let amount = (variant % arith + 1) as u32;
+                let (mask, low_mask) = if width == 2 {
+                    (0xffff, 0xff)
+                } else {
+                    (0xffff_ffff, 0xffff)
+                };
+                let orig = read_le(bytes, pos, width);
+                // Only try values that affect more than the lower half, the rest was done before.
+                let new = match variant / arith {
+                    0 if (orig & low_mask) + amount > low_mask => orig.wrapping_add(amount) & mask,
+                    1 if (orig & low_mask) < amount => orig.wrapping_sub(amount) & mask,
+                    2 if (swap(orig, width) & low_mask) + amount > low_mask => {
+                        swap(swap(orig, width).wrapping_add(amount) & mask, width)
+                    }
+                    3 if (swap(orig, width) & low_mask) < amount => {
+                        swap(swap(orig, width).wrapping_sub(amount) & mask, width)
+                    }
+                    _ => return false,
+                };
+                if could_be_bitflip(orig ^ new) {
+                    return false;
+                }
+                write_le(bytes, pos, width, new);
+                true
+            }
+            Self::Interest8 => {
+                let pos = step / INTERESTING_8.len();
+                if !is_effective(effector, pos, 1) {
+                    return false;
+                }
+                let orig = u32::from(bytes[pos]);
+                let new = u32::from(INTERESTING_8[step % INTERESTING_8.len()] as u8);
+                if could_be_bitflip(orig ^ new) || could_be_arith(orig, new, 1) {
+                    return false;
+                }
+                bytes[pos] = new as u8;
+                true
+            }
+            Self::Interest16 | Self::Interest32 => {
+                let (width, count) = if self == Self::Interest16 {
+                    (2, INTERESTING_16.len())
+                } else {
+                    (4, INTERESTING_32.len())
+                };
+                let pos = step / (2 * count);
+                let variant = step % (2 * count);
+                if !is_effective(effector, pos, width) {
+                    return false;
+                }
+                let value = if width == 2 {
+                    u32::from(INTERESTING_16[variant / 2] as u16)
+                } else {
+                    INTERESTING_32[variant / 2] as u32
+                };
+                let big_endian = variant % 2 == 1;
+                let new = if big_endian {
+                    let swapped = swap(value, width);
+                    if swapped == value {
+                        return false;
+                    }
+                    swapped
+                } else {
+                    value
+                };
+                let orig = read_le(bytes, pos, width);
+                if could_be_bitflip(orig ^ new)
+                    || could_be_arith(orig, new, width)
+                    || could_be_interest(orig, new, width, big_endian)
+                {
+                    return false;
+                }
+                write_le(bytes, pos, width, new);
+                true
+            }
+            Self::Done => false,
+        }
+    }
+}
+
+/// Checks whether any of the `width` bytes at `pos` are marked in the effector map
+fn is_effective(effector: &[bool], pos: usize, width: usize) -> bool {
+    effector.is_empty() || effector[pos..pos + width].iter().any(|e| *e)
+}
+
+fn read_le(bytes: &[u8], pos: usize, width: usize) -> u32 {
+    bytes[pos..pos + width]
+        .iter()
+        .rev()
+        .fold(0, |acc, byte| (acc << 8) | u32::from(*byte))
+}
+
+fn write_le(bytes: &mut [u8], pos: usize, width: usize, val: u32) {
+    for (i, byte) in bytes[pos..pos + width].iter_mut().enumerate() {
+        *byte = (val >> (8 * i)) as u8;
+    }
+}
+
+fn swap(val: u32, width: usize) -> u32 {
+    if width == 2 {
+        u32::from((val as u16).swap_bytes())
+    } else {
+        val.swap_bytes()
+    }
+}
+
+/// Whether the difference `xor_val` could have been produced by one of the bitflip phases
+fn could_be_bitflip(mut xor_val: u32) -> bool {
+    if xor_val == 0 {
+        return true;
+    }
+    let shift = xor_val.trailing_zeros();
+    xor_val >>= shift;
+
+    // 1-, 2-, and 4-bit patterns are OK anywhere
+    if xor_val == 1 || xor_val == 3 || xor_val == 15 {
+        return true;
+    }
+    // 8-, 16-, and 32-bit patterns only at byte boundaries
+    shift % 8 == 0 && (xor_val == 0xff || xor_val == 0xffff || xor_val == 0xffff_ffff)
+}
+
+/// Whether `new_val` could have been produced from `old_val` by one of the arith phases
+fn could_be_arith(old_val: u32, new_val: u32, width: usize) -> bool {
+    let arith = ARITH_MAX as u32;
+    if old_val == new_val {
+        return true;
+    }
+
+    // one-byte adjustments
+    let mut diffs = 0;
+    let (mut ov, mut nv) = (0, 0);
+    for i in 0..width {
+        let (a, b) = ((old_val >> (8 * i)) as u8, (new_val >> (8 * i)) as u8);
+        if a != b {
+            diffs += 1;
+            ov = a;
+            nv = b;
+        }
+    }
+    if diffs == 1
+        && (u32::from(ov.wrapping_sub(nv)) <= arith || u32::from(nv.wrapping_sub(ov)) <= arith)
+    {
+        return true;
+    }
+    if width == 1 {
+        return false;
+    }
+
+    // two-byte adjustments
+    let mut diffs = 0;
+    let (mut ov, mut nv) = (0_u16, 0_u16);
+    for i in 0..width / 2 {
+        let (a, b) = ((old_val >> (16 * i)) as u16, (new_val >> (16 * i)) as u16);
+        if a != b {
+            diffs += 1;
+            ov = a;
+            nv = b;
+        }
+    }
+    if diffs == 1 {
+        if u32::from(ov.wrapping_sub(nv)) <= arith || u32::from(nv.wrapping_sub(ov)) <= arith {
+            return true;
+        }
+        let (ov, nv) = (ov.swap_bytes(), nv.swap_bytes());
+        if u32::from(ov.wrapping_sub(nv)) <= arith || u32::from(nv.wrapping_sub(ov)) <= arith {
+            return true;
+        }
+    }
+
+    // four-byte adjustments
+    if width == 4 {
+        if old_val.wrapping_sub(new_val) <= arith || new_val.wrapping_sub(old_val) <= arith {
+            return true;
+        }
+        let (ov, nv) = (old_val.swap_bytes(), new_val.swap_bytes());
+        if ov.wrapping_sub(nv) <= arith || nv.wrapping_sub(ov) <= arith {
+            return true;
+        }
+    }
+    false
+}
+
+/// Whether `new_val` could have been produced from `old_val` by an earlier interesting value phase.
+/// With `check_le`, also consider the little endian insertions of the current width.
+#[allow(clippy::cast_sign_loss)]
+fn could_be_interest(old_val: u32, new_val: u32, width: usize, check_le: bool) -> bool {
+    if old_val == new_val {
+        return true;
+    }
+
+    // one-byte insertions
+    for i in 0..width {
+        for interesting in INTERESTING_8 {
+            let tval = (old_val & !(0xff << (i * 8))) | (u32::from(interesting as u8) << (i * 8));
+            if new_val == tval {
+                return true;
+            }
+        }
+    }
+    if width == 2 && !check_le {
+        return false;
+    }
+
+    // two-byte insertions
+    for i in 0..width - 1 {
+        for interesting in INTERESTING_16 {
+            let interesting = interesting as u16;
+            let tval = (old_val & !(0xffff << (i * 8))) | (u32::from(interesting) << (i * 8));
+            if new_val == tval {
+                return true;
+            }
+            if width > 2 {
+                let tval = (old_val & !(0xffff << (i * 8)))
+                    | (u32::from(interesting.swap_bytes()) << (i * 8));
+                if new_val == tval {
+                    return true;
+                }
+            }
+        }
+    }
+
+    // four-byte insertions, little endian only
+    width == 4 && check_le && INTERESTING_32.iter().any(|i| new_val == *i as u32)
+}
+
+/// Marks the first and last byte as effective, and the whole input if the map is too small or too dense
+fn finalize_effector(effector: &mut [bool]) {
+    let len = effector.len();
+    if len == 0 {
+        return;
+    }
+    effector[0] = true;
+    effector[len - 1] = true;
+    let effective = effector.iter().filter(|e| **e).count();
+    if len < EFF_MIN_LEN || effective * 100 / len > EFF_MAX_PERC {
+        effector.fill(true);
+    }
+}
+
+/// The progress of the [`DeterministicStage`] on a [`crate::corpus::Testcase`]
+#[cfg_attr(
+    any(not(feature = "serdeany_autoreg"), miri),
+    allow(clippy::unsafe_derive_deserialize)
+)] // for SerdeAny
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeterministicStageMetadata {
+    phase: DeterministicPhase,
+    step: usize,
+    effector: Vec<bool>,
+}
+
+impl_serdeany!(DeterministicStageMetadata);
+
+impl DeterministicStageMetadata {
+    /// Create a new [`struct@DeterministicStageMetadata`] at the start of the walk
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            phase: DeterministicPhase::BitFlip1,
+            step: 0,
+            effector: vec![],
+        }
+    }
+
+    /// The phase the walk is currently in
+    #[must_use]
+    pub fn phase(&self) -> DeterministicPhase {
+        self.phase
+    }
+
+    /// The next step to perform in the current phase
+    #[must_use]
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    /// The effector map, marking the bytes which changed the coverage when flipped.
+    /// Empty until the [`DeterministicPhase::ByteFlip8`] phase starts.
+    #[must_use]
+    pub fn effector(&self) -> &[bool] {
+        &self.effector
+    }
+
+    /// Whether the walk is finished
+    #[must_use]
+    pub fn is_done(&self) -> bool {
+        self.phase == DeterministicPhase::Done
+    }
+}
+
+impl Default for DeterministicStageMetadata {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The deterministic stage performs AFL's deterministic walk on each new corpus entry, once.
+///
+/// The map observer is used to build the effector map: bytes for which flipping the whole byte
+/// does not change the map are skipped in the later, more expensive, phases.
+#[derive(Clone, Debug)]
+pub struct DeterministicStage<E, EM, O, Z> {
+    map_observer_name: String,
+    resume: bool,
+    #[allow(clippy::type_complexity)]
+    phantom: PhantomData<(E, EM, O, Z)>,
+}
+
+impl<E, EM, O, Z> UsesState for DeterministicStage<E, EM, O, Z>
+where
+    E: UsesState,
+{
+    type State = E::State;
+}
+
+impl<E, EM, O, Z> Stage<E, EM, Z> for DeterministicStage<E, EM, O, Z>
+where
+    E: Executor<EM, Z> + HasObservers,
+    EM: UsesState<State = E::State>,
+    E::State: HasCorpus + HasClientPerfMonitor,
+    E::Input: HasBytesVec,
+    O: MapObserver,
+    Z: Evaluator<E, EM, State = E::State>,
+{
+    fn perform(
+        &mut self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut E::State,
+        manager: &mut EM,
+        corpus_idx: CorpusId,
+    ) -> Result<(), Error> {
+        let (original, mut meta) = {
+            let mut testcase = state.corpus().get(corpus_idx)?.borrow_mut();
+            let meta = if let Ok(meta) = testcase.metadata::<DeterministicStageMetadata>() {
+                if meta.is_done() {
+                    return Ok(());
+                }
+                if !self.resume {
+                    // A previous walk on this entry got interrupted, and we don't want to pick it up again
+                    testcase.metadata_mut::<DeterministicStageMetadata>()?.phase =
+                        DeterministicPhase::Done;
+                    return Ok(());
+                }
+                meta.clone()
+            } else {
+                let meta = DeterministicStageMetadata::new();
+                testcase.add_metadata(meta.clone());
+                meta
+            };
+            (testcase.load_input(state.corpus())?.clone(), meta)
+        };
+
+        let len = original.bytes().len();
+        let mut orig_hash = None;
+
+        while !meta.is_done() {
+            if meta.step >= meta.phase.steps(len) {
+                if meta.phase == DeterministicPhase::ByteFlip8 {
+                    finalize_effector(&mut meta.effector);
+                }
+                meta.phase = meta.phase.next();
+                meta.step = 0;
+                Self::update_metadata(state, corpus_idx, |m| *m = meta.clone())?;
+                continue;
+            }
+
+            if meta.phase == DeterministicPhase::ByteFlip8 {
+                if meta.effector.len() != len {
+                    meta.effector = vec![false; len];
+                    Self::update_metadata(state, corpus_idx, |m| {
+                        m.effector.clone_from(&meta.effector);
+                    })?;
+                }
+                if orig_hash.is_none() {
+                    orig_hash =
+                        Some(self.run_and_hash(fuzzer, executor, state, manager, &original)?);
+                }
+            }
+
+            // Advance before executing, so that a step crashing the target is not repeated after a restart
+            let step = meta.step;
+            meta.step += 1;
+            Self::update_metadata(state, corpus_idx, |m| m.step = meta.step)?;
+
+            let mut input = original.clone();
+            if !meta.phase.apply(step, input.bytes_mut(), &meta.effector) {
+                continue;
+            }
+
+            fuzzer.evaluate_input(state, executor, manager, input)?;
+
+            if meta.phase == DeterministicPhase::ByteFlip8
+                && orig_hash != Some(self.map_hash(executor)?)
+            {
+                meta.effector[step] = true;
+                Self::update_metadata(state, corpus_idx, |m| m.effector[step] = true)?;
+            }
+        }
+
+        #[cfg(feature = "introspection")]
+        state.introspection_monitor_mut().finish_stage();
+
+        Ok(())
+    }
+}
+
+impl<E, EM, O, Z> DeterministicStage<E, EM, O, Z>
+where
+    E: Executor<EM, Z> + HasObservers,
+    EM: UsesState<State = E::State>,
+    E::State: HasCorpus,
+    O: MapObserver,
+    Z: UsesState<State = E::State>,
+{
+    /// Creates a new [`DeterministicStage`], resuming interrupted walks
+    #[must_use]
+    pub fn new(map_observer: &O) -> Self {
+        Self {
+            map_observer_name: map_observer.name().to_string(),
+            resume: true,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Creates a new [`DeterministicStage`] that gives up on corpus entries whose walk got
+    /// interrupted, for example by a crash and the following restart
+    #[must_use]
+    pub fn without_resume(map_observer: &O) -> Self {
+        Self {
+            map_observer_name: map_observer.name().to_string(),
+            resume: false,
+            phantom: PhantomData,
+        }
+    }
+
+    fn update_metadata<F>(state: &E::State, corpus_idx: CorpusId, update: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut DeterministicStageMetadata),
+    {
+        let mut testcase = state.corpus().get(corpus_idx)?.borrow_mut();
+        update(testcase.metadata_mut::<DeterministicStageMetadata>()?);
+        Ok(())
+    }
+
+    fn map_hash(&self, executor: &E) -> Result<u64, Error> {
+        Ok(executor
+            .observers()
+            .match_name::<O>(&self.map_observer_name)
+            .ok_or_else(|| Error::key_not_found("MapObserver not found".to_string()))?
+            .hash())
+    }
+
+    // Run the unmodified input, to know the map hash the effector map is compared against
+    fn run_and_hash(
+        &self,
+        fuzzer: &mut Z,
+        executor: &mut E,
+        state: &mut E::State,
+        manager: &mut EM,
+        input: &E::Input,
+    ) -> Result<u64, Error> {
+        executor.observers_mut().pre_exec_all(state, input)?;
+        let exit_kind = executor.run_target(fuzzer, state, manager, input)?;
+        executor
+            .observers_mut()
+            .post_exec_all(state, input, &exit_kind)?;
+        self.map_hash(executor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{could_be_arith, could_be_bitflip, DeterministicPhase};
+
+    #[test]
+    fn test_bitflip_walk() {
+        let mut bytes = [0_u8; 2];
+        assert!(DeterministicPhase::BitFlip1.apply(0, &mut bytes, &[]));
+        assert_eq!(bytes, [0x80, 0]);
+
+        let mut bytes = [0_u8; 2];
+        assert!(DeterministicPhase::BitFlip4.apply(6, &mut bytes, &[]));
+        assert_eq!(bytes, [0x03, 0xc0]);
+
+        assert!(could_be_bitflip(0x0f << 3));
+        assert!(could_be_bitflip(0xff00));
+        assert!(!could_be_bitflip(0xff << 4));
+    }
+
+    #[test]
+    fn test_skips_redundant_steps() {
+        // 0 + 1 is a single bitflip, already done
+        let mut bytes = [0_u8];
+        assert!(!DeterministicPhase::Arith8.apply(0, &mut bytes, &[]));
+        // 0 - 1 is 0xff, a byte flip
+        assert!(!DeterministicPhase::Arith8.apply(1, &mut bytes, &[]));
+        // 0 + 5 is new
+        assert!(DeterministicPhase::Arith8.apply(8, &mut bytes, &[]));
+        assert_eq!(bytes, [5]);
+
+        // ineffective bytes are skipped
+        let mut bytes = [0x10_u8, 0x10];
+        assert!(!DeterministicPhase::Arith8.apply(8, &mut bytes, &[false, true]));
+        assert!(DeterministicPhase::ByteFlip16.apply(0, &mut bytes, &[false, true]));
+        assert!(!DeterministicPhase::ByteFlip16.apply(0, &mut bytes, &[false, false]));
+
+        assert!(could_be_arith(0x1000, 0x1020, 2));
+        assert!(!could_be_arith(0x1000, 0x5000, 2));
+    }
+
+    #[test]
+    fn test_phase_steps() {
+        let mut phase = DeterministicPhase::BitFlip1;
+        let mut total = 0;
+        while phase != DeterministicPhase::Done {
+            total += phase.steps(1);
+            phase = phase.next();
+        }
+        // 8 + 7 + 5 bitflips, one byte flip, 70 arith and 9 interesting values
+        assert_eq!(total, 8 + 7 + 5 + 1 + 70 + 9);
+    }
+}
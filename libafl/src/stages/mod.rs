@@ -39,6 +39,17 @@ pub use tuneable::*;
 pub mod colorization;
 pub use colorization::*;
 
+pub mod deterministic;
+pub use deterministic::*;
+
+pub mod branch_mask;
+pub use branch_mask::*;
+
+#[cfg(all(feature = "std", unix))]
+pub mod afl_custom;
+#[cfg(all(feature = "std", unix))]
+pub use afl_custom::{AflCustomTrimStage, AflCustomTrimmedMetadata};
+
 #[cfg(feature = "std")]
 pub mod concolic;
 #[cfg(feature = "std")]
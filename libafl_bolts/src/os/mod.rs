@@ -16,6 +16,9 @@ pub mod unix_signals;
 #[cfg(all(unix, feature = "std"))]
 pub mod pipes;
 
+#[cfg(all(target_os = "linux", feature = "std"))]
+pub mod procfs;
+
 #[cfg(all(unix, feature = "std"))]
 use std::ffi::CString;
 
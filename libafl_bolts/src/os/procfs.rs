@@ -0,0 +1,278 @@
+//! Helpers to inspect and track the memory and file descriptors of the current process through Linux `procfs`.
+
+use alloc::{string::String, vec::Vec};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::Write,
+    os::unix::{fs::FileExt, io::RawFd},
+};
+
+use crate::Error;
+
+/// Bit in a `/proc/self/pagemap` entry signalling that the page was written since the last soft-dirty reset
+const PAGEMAP_SOFT_DIRTY: u64 = 1 << 55;
+
+/// A single mapping of the current process, as listed in `/proc/self/maps`
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryMapping {
+    /// The first address of the mapping
+    pub start: usize,
+    /// The address right after the end of the mapping
+    pub end: usize,
+    /// If the mapping is readable
+    pub readable: bool,
+    /// If the mapping is writable
+    pub writable: bool,
+    /// If the mapping is executable
+    pub executable: bool,
+    /// If the mapping is private (copy-on-write), as opposed to shared
+    pub private: bool,
+    /// The offset into the backing file
+    pub offset: usize,
+    /// The backing file or pseudo path, such as `[heap]`, if any
+    pub path: Option<String>,
+}
+
+impl MemoryMapping {
+    /// Parse a single line of `/proc/self/maps`
+    pub fn parse(line: &str) -> Result<Self, Error> {
+        let invalid = || Error::illegal_argument(format!("Invalid mapping line: {line}"));
+        let mut fields = line.splitn(6, ' ');
+        let (range, perms, offset) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(range), Some(perms), Some(offset)) => (range, perms.as_bytes(), offset),
+            _ => return Err(invalid()),
+        };
+        let (start, end) = range.split_once('-').ok_or_else(invalid)?;
+        if perms.len() < 4 {
+            return Err(invalid());
+        }
+        // Skip device and inode, the rest is the (possibly empty) path
+        let path = fields.nth(2).map(str::trim).filter(|p| !p.is_empty());
+
+        Ok(Self {
+            start: usize::from_str_radix(start, 16).map_err(|_| invalid())?,
+            end: usize::from_str_radix(end, 16).map_err(|_| invalid())?,
+            readable: perms[0] == b'r',
+            writable: perms[1] == b'w',
+            executable: perms[2] == b'x',
+            private: perms[3] == b'p',
+            offset: usize::from_str_radix(offset, 16).map_err(|_| invalid())?,
+            path: path.map(String::from),
+        })
+    }
+
+    /// The length of this mapping, in bytes
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// If this mapping is empty
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.end == self.start
+    }
+
+    /// If this mapping is the `brk` heap
+    #[must_use]
+    pub fn is_heap(&self) -> bool {
+        self.path.as_deref() == Some("[heap]")
+    }
+
+    /// If this mapping is a kernel-provided pseudo mapping, like `[stack]`, `[vvar]` or `[vdso]`
+    #[must_use]
+    pub fn is_pseudo(&self) -> bool {
+        !self.is_heap() && self.path.as_deref().is_some_and(|p| p.starts_with('['))
+    }
+}
+
+/// Get all current mappings of this process
+pub fn mappings() -> Result<Vec<MemoryMapping>, Error> {
+    fs::read_to_string("/proc/self/maps")?
+        .lines()
+        .map(MemoryMapping::parse)
+        .collect()
+}
+
+/// The size of a memory page on this system
+#[must_use]
+pub fn page_size() -> usize {
+    // # Safety
+    // `sysconf` has no preconditions
+    let size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    usize::try_from(size).unwrap_or(4096)
+}
+
+/// Reset the soft-dirty bits of all pages of this process.
+///
+/// Subsequent writes will set the bit again for the written pages, see [`PageMap::dirty_pages`].
+pub fn clear_soft_dirty() -> Result<(), Error> {
+    OpenOptions::new()
+        .write(true)
+        .open("/proc/self/clear_refs")?
+        .write_all(b"4")?;
+    Ok(())
+}
+
+/// Check if the running kernel tracks soft-dirty bits.
+///
+/// This needs `CONFIG_MEM_SOFT_DIRTY`, which not all kernels are built with.
+/// Resets the soft-dirty bits of all pages as a side effect.
+#[must_use]
+pub fn soft_dirty_supported() -> bool {
+    let page_size = page_size();
+    // # Safety
+    // We map, write to and unmap a fresh anonymous page nobody else knows about.
+    unsafe {
+        let page = libc::mmap(
+            core::ptr::null_mut(),
+            page_size,
+            libc::PROT_READ | libc::PROT_WRITE,
+            libc::MAP_PRIVATE | libc::MAP_ANONYMOUS,
+            -1,
+            0,
+        );
+        if page == libc::MAP_FAILED {
+            return false;
+        }
+        // Fault the page in first, so that the reset has something to clear
+        core::ptr::write_volatile(page.cast::<u8>(), 1);
+
+        let supported = clear_soft_dirty().is_ok() && {
+            core::ptr::write_volatile(page.cast::<u8>(), 2);
+            PageMap::new().is_ok_and(|mut pagemap| {
+                pagemap
+                    .dirty_pages(page as usize, page as usize + page_size)
+                    .is_ok_and(|mut dirty| dirty.next().is_some())
+            })
+        };
+
+        libc::munmap(page, page_size);
+        supported
+    }
+}
+
+/// A reader for `/proc/self/pagemap`, to find out which pages were written to
+#[derive(Debug)]
+pub struct PageMap {
+    file: File,
+    page_size: usize,
+    entries: Vec<u8>,
+}
+
+impl PageMap {
+    /// Open the pagemap of the current process
+    pub fn new() -> Result<Self, Error> {
+        Ok(Self {
+            file: File::open("/proc/self/pagemap")?,
+            page_size: page_size(),
+            entries: Vec::new(),
+        })
+    }
+
+    /// Pre-allocate the internal buffer to query up to `pages` pages at once without allocating.
+    pub fn reserve(&mut self, pages: usize) {
+        self.entries.reserve(pages * 8);
+    }
+
+    /// Iterate over the start address of each page in `start..end` that has its soft-dirty bit set.
+    ///
+    /// Soft-dirty bits are reset with [`clear_soft_dirty`].
+    pub fn dirty_pages(
+        &mut self,
+        start: usize,
+        end: usize,
+    ) -> Result<impl Iterator<Item = usize> + '_, Error> {
+        let page_size = self.page_size;
+        let first = start / page_size;
+        let count = (end - first * page_size + page_size - 1) / page_size;
+
+        self.entries.resize(count * 8, 0);
+        self.file
+            .read_exact_at(&mut self.entries, (first * 8) as u64)?;
+
+        Ok(self
+            .entries
+            .chunks_exact(8)
+            .enumerate()
+            .filter(|(_, entry)| {
+                u64::from_ne_bytes((*entry).try_into().unwrap()) & PAGEMAP_SOFT_DIRTY != 0
+            })
+            .map(move |(idx, _)| (first + idx) * page_size))
+    }
+}
+
+/// The current program break, i.e., the end of the `brk` heap
+#[must_use]
+pub fn program_break() -> usize {
+    // # Safety
+    // Growing the break by 0 only queries it
+    unsafe { libc::sbrk(0) as usize }
+}
+
+/// Move the program break, growing or shrinking the `brk` heap.
+///
+/// # Safety
+/// Memory above the new break is released, and memory below it may be uninitialized.
+/// The allocator needs to be in a state that matches the new break.
+pub unsafe fn set_program_break(addr: usize) -> Result<(), Error> {
+    if libc::brk(addr as *mut libc::c_void) == 0 {
+        Ok(())
+    } else {
+        Err(Error::unknown(format!(
+            "Could not move the program break to {addr:#x}: {}",
+            std::io::Error::last_os_error()
+        )))
+    }
+}
+
+/// List the file descriptors this process currently has open
+pub fn open_fds() -> Result<Vec<RawFd>, Error> {
+    let mut fds = fs::read_dir("/proc/self/fd")?
+        .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+        .collect::<Vec<RawFd>>();
+    // The directory iterator held a file descriptor of its own, which is closed by now
+    // # Safety
+    // Querying the flags of a file descriptor has no side effects
+    fds.retain(|fd| unsafe { libc::fcntl(*fd, libc::F_GETFD) } != -1);
+    Ok(fds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{mappings, open_fds, MemoryMapping};
+
+    #[test]
+    fn test_parse_mapping() {
+        let mapping = MemoryMapping::parse(
+            "7f1c2a000000-7f1c2a021000 rw-p 00001000 08:01 1234                       /usr/lib/libfoo.so",
+        )
+        .unwrap();
+        assert_eq!(mapping.start, 0x7f1c_2a00_0000);
+        assert_eq!(mapping.len(), 0x21000);
+        assert!(mapping.readable && mapping.writable && mapping.private);
+        assert!(!mapping.executable);
+        assert_eq!(mapping.offset, 0x1000);
+        assert_eq!(mapping.path.as_deref(), Some("/usr/lib/libfoo.so"));
+
+        let anon =
+            MemoryMapping::parse("7f1c2a021000-7f1c2a022000 rw-s 00000000 00:00 0 ").unwrap();
+        assert!(!anon.private);
+        assert_eq!(anon.path, None);
+
+        let stack =
+            MemoryMapping::parse("7ffd000000-7ffd021000 rw-p 00000000 00:00 0    [stack]").unwrap();
+        assert!(stack.is_pseudo());
+        assert!(!stack.is_heap());
+
+        assert!(MemoryMapping::parse("garbage").is_err());
+    }
+
+    #[test]
+    fn test_procfs() {
+        assert!(mappings().unwrap().iter().any(MemoryMapping::is_pseudo));
+        let fds = open_fds().unwrap();
+        assert!(fds.contains(&0) || fds.contains(&1) || fds.contains(&2));
+    }
+}
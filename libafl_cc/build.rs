@@ -213,6 +213,74 @@ fn build_pass(
     }
 }
 
+/// Builds the `LibAFL` GCC plugin, if the plugin headers of `gcc` are installed,
+/// and writes the constants of the `GccWrapper`.
+fn build_gcc_plugin(out_dir: &Path, src_dir: &Path) {
+    println!("cargo:rerun-if-env-changed=LIBAFL_GCC");
+    println!("cargo:rerun-if-env-changed=LIBAFL_GXX");
+    println!("cargo:rerun-if-changed=src/gcc-plugin.cc");
+
+    let gcc = env::var("LIBAFL_GCC").unwrap_or_else(|_| "gcc".to_string());
+    let gxx = env::var("LIBAFL_GXX").unwrap_or_else(|_| "g++".to_string());
+
+    let plugin_path = out_dir.join(format!("libafl_gcc_plugin.{}", dll_extension()));
+
+    let plugin_include = Command::new(&gcc)
+        .arg("-print-file-name=plugin")
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| {
+            PathBuf::from(String::from_utf8_lossy(&output.stdout).trim()).join("include")
+        });
+
+    let built = match plugin_include {
+        Some(include) if cfg!(unix) && include.join("gcc-plugin.h").exists() => {
+            let edges_map_size: usize = option_env!("LIBAFL_EDGES_MAP_SIZE")
+                .map_or(Ok(65536), str::parse)
+                .expect("Could not parse LIBAFL_EDGES_MAP_SIZE");
+
+            let status = Command::new(&gxx)
+                .args(["-shared", "-fPIC", "-fno-rtti", "-O2"])
+                .arg(format!("-I{}", include.display()))
+                .arg(format!("-DLIBAFL_EDGES_MAP_SIZE={edges_map_size}"))
+                .arg(src_dir.join("gcc-plugin.cc"))
+                .arg("-o")
+                .arg(&plugin_path)
+                .status();
+
+            if matches!(status, Ok(s) if s.success()) {
+                true
+            } else {
+                println!("cargo:warning=Skipping src/gcc-plugin.cc");
+                false
+            }
+        }
+        _ => {
+            println!(
+                "cargo:warning=Failed to find the GCC plugin headers, we will not build the GCC plugin. If you need it, install the plugin development package of your gcc."
+            );
+            false
+        }
+    };
+
+    let mut gcc_constants_file =
+        File::create(out_dir.join("gcc_constants.rs")).expect("Could not create file");
+    write!(
+        gcc_constants_file,
+        "// These constants are autogenerated by build.rs
+
+/// The path to the `gcc` executable
+pub const GCC_PATH: &str = {gcc:?};
+/// The path to the `g++` executable
+pub const GXX_PATH: &str = {gxx:?};
+/// If the `LibAFL` GCC plugin was built
+pub const LIBAFL_CC_GCC_PLUGIN: bool = {built};
+"
+    )
+    .expect("Could not write file");
+}
+
 #[allow(clippy::single_element_loop)]
 #[allow(clippy::too_many_lines)]
 fn main() {
@@ -220,6 +288,16 @@ fn main() {
     let out_dir = Path::new(&out_dir);
     let src_dir = Path::new("src");
 
+    build_gcc_plugin(out_dir, src_dir);
+
+    // The DataFlowSanitizer ABI list of `Configuration::DataFlowSanitizer`
+    println!("cargo:rerun-if-changed=src/dfsan_abilist.txt");
+    std::fs::copy(
+        src_dir.join("dfsan_abilist.txt"),
+        out_dir.join("dfsan_abilist.txt"),
+    )
+    .expect("Could not copy the DataFlowSanitizer ABI list");
+
     let dest_path = Path::new(&out_dir).join("clang_constants.rs");
     let mut clang_constants_file = File::create(dest_path).expect("Could not create file");
 
@@ -231,6 +309,7 @@ fn main() {
     println!("cargo:rerun-if-env-changed=LIBAFL_EDGES_MAP_SIZE");
     println!("cargo:rerun-if-env-changed=LIBAFL_ACCOUNTING_MAP_SIZE");
     println!("cargo:rerun-if-changed=src/common-llvm.h");
+    println!("cargo:rerun-if-changed=src/instrument-list.h");
     println!("cargo:rerun-if-changed=build.rs");
 
     let llvm_bindir = env::var("LLVM_BINDIR");
@@ -394,9 +473,14 @@ pub const LIBAFL_CC_LLVM_VERSION: Option<usize> = None;
 
     for pass in &[
         "cmplog-routines-pass.cc",
+        "cmplog-instructions-pass.cc",
         "afl-coverage-pass.cc",
         "autotokens-pass.cc",
         "coverage-accounting-pass.cc",
+        "compare-transform-pass.cc",
+        "split-switches-pass.cc",
+        "split-compares-pass.cc",
+        "lto-coverage-pass.cc",
     ] {
         build_pass(
             bindir_path,
@@ -424,6 +508,23 @@ pub const LIBAFL_CC_LLVM_VERSION: Option<usize> = None;
         );
     }
 
+    // The instrumented part of the `dfsan` feature of `libafl_targets`,
+    // linked into the targets built with `Configuration::DataFlowSanitizer`
+    println!("cargo:rerun-if-changed=src/dfsan-driver.c");
+    let dfsan_driver = Command::new(&clang)
+        .args(["-c", "-O2", "-fPIC", "-fsanitize=dataflow"])
+        .arg(format!(
+            "-fsanitize-ignorelist={}",
+            out_dir.join("dfsan_abilist.txt").display()
+        ))
+        .arg(src_dir.join("dfsan-driver.c"))
+        .arg("-o")
+        .arg(out_dir.join("dfsan-driver.o"))
+        .status();
+    if !matches!(dfsan_driver, Ok(s) if s.success()) {
+        println!("cargo:warning=Skipping src/dfsan-driver.c");
+    }
+
     cc::Build::new()
         .file(src_dir.join("no-link-rt.c"))
         .compile("no-link-rt");
@@ -27,6 +27,7 @@
  */
 
 #include "common-llvm.h"
+#include "instrument-list.h"
 
 #include <time.h>
 #include <stdio.h>
@@ -43,7 +44,9 @@
 #include <ctype.h>
 
 #include <list>
+#include <set>
 #include <string>
+#include <vector>
 #include <fstream>
 
 #include "llvm/Support/CommandLine.h"
@@ -124,7 +127,7 @@ class AFLCoverage : public ModulePass {
   AFLCoverage() : ModulePass(ID) {
 #endif
 
-    // initInstrumentList();
+    initInstrumentList();
   }
 
 #ifdef USE_NEW_PM
@@ -134,10 +137,12 @@ class AFLCoverage : public ModulePass {
 #endif
 
  protected:
-  uint32_t                          map_size = MAP_SIZE;
-  uint32_t                          function_minimum_size = 1;
-  DenseMap<BasicBlock *, int32_t>   bb_to_cur_loc;
-  DenseMap<StringRef, BasicBlock *> entry_bb;
+  uint32_t                                       map_size = MAP_SIZE;
+  uint32_t                                       function_minimum_size = 1;
+  DenseMap<BasicBlock *, int32_t>                bb_to_cur_loc;
+  DenseMap<StringRef, BasicBlock *>              entry_bb;
+  DenseMap<BasicBlock *, std::set<std::string>>  bb_to_src_locs;
+  DenseMap<BasicBlock *, std::vector<StringRef>> bb_to_callees;
 };
 
 }  // namespace
@@ -440,7 +445,7 @@ bool AFLCoverage::runOnModule(Module &M) {
       fprintf(stderr, "FUNCTION: %s (%zu)\n", F.getName().str().c_str(),
               F.size());
 
-    // if (!isInInstrumentList(&F)) { continue; }
+    if (!isInInstrumentList(&F)) { continue; }
 
     if (F.size() < function_minimum_size) { continue; }
     if (DumpCFG) { entry_bb[F.getName()] = &F.getEntryBlock(); }
@@ -532,7 +537,25 @@ bool AFLCoverage::runOnModule(Module &M) {
 
       // cur_loc++;
       cur_loc = RandBelow(map_size);
-      if (DumpCFG) { bb_to_cur_loc[&BB] = cur_loc; }
+      if (DumpCFG) {
+        bb_to_cur_loc[&BB] = cur_loc;
+        // Remember the source lines and direct callees of this block, they
+        // are needed to compute distances to targets for directed fuzzing.
+        for (auto &IN : BB) {
+          if (DILocation *Loc = IN.getDebugLoc()) {
+            if (Loc->getLine() != 0) {
+              bb_to_src_locs[&BB].insert(
+                  formatv("{0}:{1}", Loc->getFilename(), Loc->getLine()));
+            }
+          }
+          if (auto *Call = dyn_cast<CallBase>(&IN)) {
+            Function *Callee = Call->getCalledFunction();
+            if (Callee && !Callee->isIntrinsic()) {
+              bb_to_callees[&BB].push_back(Callee->getName());
+            }
+          }
+        }
+      }
 /* There is a problem with Ubuntu 18.04 and llvm 6.0 (see issue #63).
    The inline function successors() is not inlined and also not found at runtime
    :-( As I am unable to detect Ubuntu18.04 heree, the next best thing is to
@@ -811,6 +834,12 @@ bool AFLCoverage::runOnModule(Module &M) {
         cfg += "%%__";
       auto current_cur_loc = record->getSecond();
       cfg += formatv("+{0}\n", current_cur_loc);
+      for (auto &src_loc : bb_to_src_locs[current_bb]) {
+        cfg += formatv("@@{0}\n", src_loc).str();
+      }
+      for (auto &callee : bb_to_callees[current_bb]) {
+        cfg += formatv("##{0}\n", callee).str();
+      }
       for (auto bb_successor = succ_begin(current_bb);
            bb_successor != succ_end(current_bb); bb_successor++) {
         cfg += formatv("->{0}\n", bb_to_cur_loc[*bb_successor]).str();
@@ -80,6 +80,8 @@
                                   \
   } while (0)
 
+#include "instrument-list.h"
+
 using namespace llvm;
 
 namespace {
@@ -154,6 +156,7 @@ class AutoTokensPass : public ModulePass {
 
   AutoTokensPass() : ModulePass(ID) {
 #endif
+    initInstrumentList();
   }
 
 #if USE_NEW_PM
@@ -183,6 +186,14 @@ llvmGetPassPluginInfo() {
                 [](ModulePassManager &MPM, OptimizationLevel OL) {
                   MPM.addPass(AutoTokensPass());
                 });
+  #if LLVM_VERSION_MAJOR >= 15
+            /* when loaded by the linker, collect the tokens of the whole
+             * program */
+            PB.registerFullLinkTimeOptimizationLastEPCallback(
+                [](ModulePassManager &MPM, OptimizationLevel OL) {
+                  MPM.addPass(AutoTokensPass());
+                });
+  #endif
           }};
 }
 #else
@@ -257,7 +268,7 @@ bool AutoTokensPass::runOnModule(Module &M) {
   /* Instrument all the things! */
 
   for (auto &F : M) {
-    if (isIgnoreFunction(&F)) { continue; }
+    if (isIgnoreFunction(&F) || !isInInstrumentList(&F)) { continue; }
 
     /*  Some implementation notes.
      *
@@ -698,5 +709,9 @@ static RegisterStandardPasses RegisterAutoTokensPass(
 
 static RegisterStandardPasses RegisterAutoTokensPass0(
     PassManagerBuilder::EP_EnabledOnOptLevel0, registerAutoTokensPass);
+
+static RegisterStandardPasses RegisterAutoTokensPassLTO(
+    PassManagerBuilder::EP_FullLinkTimeOptimizationLast,
+    registerAutoTokensPass);
   #endif
 #endif
@@ -1,7 +1,7 @@
 //! LLVM style control flow graph with information of AFL-style index of the each
 //! edges, use together with ``AFLCoverage`` pass having --dump-afl-cfg flag enabled.
 use std::{
-    collections::{BinaryHeap, HashMap, HashSet},
+    collections::{BinaryHeap, HashMap, HashSet, VecDeque},
     marker::PhantomData,
 };
 
@@ -73,8 +73,42 @@ impl EntryBasicBlockInfo {
     }
 }
 
+/// A basic block of the CFG together with its debug information.
+#[derive(Debug, Clone, Default)]
+pub struct BasicBlockInfo {
+    /// Name of the function that contains such basic block. For anonymous function, it is "__".
+    pub calling_func: String,
+    /// The node's index (i.e., ``cur_loc``).
+    pub node_loc: usize,
+    /// Indexes of successor blocks.
+    pub successors: Vec<usize>,
+    /// Source locations (``file:line``) of the instructions in this block.
+    pub source_locations: Vec<String>,
+    /// Names of the functions directly called from this block.
+    pub callees: Vec<String>,
+}
+
+/// The factor `AFLGo` multiplies call graph distances with, when lifting them to basic blocks.
+pub const CALL_DISTANCE_FACTOR: f64 = 10.0;
+
+/// Checks if a ``file:line`` source location is the given ``file:line`` target.
+///
+/// The target file may omit leading directories, i.e. ``foo.c:7`` matches ``src/foo.c:7``.
+fn source_location_matches(location: &str, target: &str) -> bool {
+    let (Some((loc_file, loc_line)), Some((target_file, target_line))) =
+        (location.rsplit_once(':'), target.rsplit_once(':'))
+    else {
+        return false;
+    };
+    loc_line == target_line
+        && (loc_file == target_file
+            || loc_file
+                .strip_suffix(target_file)
+                .map_or(false, |prefix| prefix.ends_with('/')))
+}
+
 /// An LLVM style control flow graph.
-/// Note: This does not track across functions.
+/// Note: Edges do not track across functions, only the basic blocks know their callees.
 #[derive(Debug)]
 pub struct ControlFlowGraph<T>
 where
@@ -86,6 +120,8 @@ where
     edges: Vec<Option<CfgEdge<T>>>,
     /// Mapping each function's name to its corresponding entry basic block information.
     func_to_entry_bb: HashMap<String, EntryBasicBlockInfo>,
+    /// Mapping each basic block's index to its information.
+    basic_blocks: HashMap<usize, BasicBlockInfo>,
 }
 
 impl<T> ControlFlowGraph<T>
@@ -101,6 +137,7 @@ where
         Self {
             edges: (0..map_size).map(|_| None).collect(),
             func_to_entry_bb: HashMap::default(),
+            basic_blocks: HashMap::default(),
         }
     }
 
@@ -125,6 +162,8 @@ where
     current_bb: usize,
     bb_to_func: HashMap<usize, String>,
     bb_to_successors: HashMap<usize, Vec<usize>>,
+    bb_to_source_locations: HashMap<usize, Vec<String>>,
+    bb_to_callees: HashMap<usize, Vec<String>>,
     func_to_entry_bb: HashMap<String, usize>,
     phantom: PhantomData<T>,
 }
@@ -138,6 +177,8 @@ where
             current_bb: 0,
             bb_to_func: HashMap::default(),
             bb_to_successors: HashMap::default(),
+            bb_to_source_locations: HashMap::default(),
+            bb_to_callees: HashMap::default(),
             func_to_entry_bb: HashMap::default(),
             phantom: PhantomData,
         }
@@ -172,6 +213,20 @@ where
                 self.current_bb = splitter.next().expect(FAILED_TO_PARSE).parse().expect("");
                 self.bb_to_func.insert(self.current_bb, func_name);
             }
+            "@@" => {
+                // "@@{file}:{line}": The current basic block contains code of this source line.
+                self.bb_to_source_locations
+                    .entry(self.current_bb)
+                    .or_default()
+                    .push(line_content.into());
+            }
+            "##" => {
+                // "##{function name}": The current basic block calls {function name}.
+                self.bb_to_callees
+                    .entry(self.current_bb)
+                    .or_default()
+                    .push(line_content.into());
+            }
             "$$" => {
                 // "$${function name}+{index}": Function {function name}'s entry block is {index}.
                 let mut splitter = line_content.split('+');
@@ -207,6 +262,25 @@ where
             cfg.create_func_entry(func_name, entry);
         }
 
+        for (bb_loc, func_name) in &self.bb_to_func {
+            let info = BasicBlockInfo {
+                calling_func: func_name.clone(),
+                node_loc: *bb_loc,
+                successors: self
+                    .bb_to_successors
+                    .get(bb_loc)
+                    .cloned()
+                    .unwrap_or_default(),
+                source_locations: self
+                    .bb_to_source_locations
+                    .get(bb_loc)
+                    .cloned()
+                    .unwrap_or_default(),
+                callees: self.bb_to_callees.get(bb_loc).cloned().unwrap_or_default(),
+            };
+            cfg.basic_blocks.insert(*bb_loc, info);
+        }
+
         // Insert edges from zero to entry basic blocks.
         let mut bb_to_successors_with_zero = self.bb_to_successors.clone();
         if !entry_bb_locs.is_empty() {
@@ -290,6 +364,12 @@ where
         self.func_to_entry_bb.get_mut(func_name)
     }
 
+    /// Get the information of a basic block by its index (i.e., ``cur_loc``).
+    #[must_use]
+    pub fn get_basic_block(&self, node_loc: usize) -> Option<&BasicBlockInfo> {
+        self.basic_blocks.get(&node_loc)
+    }
+
     /// Calculate shortest distance from start edge to all other edges
     /// in the function containing such ``start``.
     ///
@@ -328,6 +408,161 @@ where
         }
         distances
     }
+
+    /// Calculate the `AFLGo` distance of every function to the functions containing targets.
+    ///
+    /// The distance of a function is the harmonic mean of its call graph distances to all
+    /// reachable target functions, target functions themselves have a distance of `0`.
+    fn calculate_function_distances<'a>(
+        &'a self,
+        target_funcs: &HashSet<&'a str>,
+    ) -> HashMap<&'a str, f64> {
+        let mut callers: HashMap<&str, HashSet<&str>> = HashMap::new();
+        for bb in self.basic_blocks.values() {
+            for callee in &bb.callees {
+                callers
+                    .entry(callee.as_str())
+                    .or_default()
+                    .insert(bb.calling_func.as_str());
+            }
+        }
+
+        let mut inverse_sums: HashMap<&str, (f64, u32)> = HashMap::new();
+        for target in target_funcs {
+            let mut visited = HashSet::from([*target]);
+            let mut to_visit = VecDeque::from([(*target, 0_u32)]);
+            while let Some((func, distance)) = to_visit.pop_front() {
+                if distance > 0 {
+                    let (sum, count) = inverse_sums.entry(func).or_default();
+                    *sum += 1.0 / f64::from(distance);
+                    *count += 1;
+                }
+                for caller in callers.get(func).into_iter().flatten() {
+                    if visited.insert(caller) {
+                        to_visit.push_back((caller, distance + 1));
+                    }
+                }
+            }
+        }
+
+        let mut distances: HashMap<&str, f64> = inverse_sums
+            .into_iter()
+            .map(|(func, (sum, count))| (func, f64::from(count) / sum))
+            .collect();
+        for target in target_funcs {
+            distances.insert(target, 0.0);
+        }
+        distances
+    }
+
+    /// Calculate the `AFLGo` distance of every basic block to the given target source locations.
+    ///
+    /// Targets are given as ``file:line``, as emitted by the ``AFLCoverage`` pass with debug info.
+    /// Blocks containing a target have a distance of `0`, blocks calling a function ``f`` that
+    /// reaches a target are [`CALL_DISTANCE_FACTOR`] * (``d(f)`` + 1) away. All other blocks get
+    /// the harmonic mean of the (hop count + distance) to those blocks within their function.
+    ///
+    /// Blocks that cannot reach any target would not be inserted in the returned hash map.
+    #[must_use]
+    pub fn calculate_bb_distances_to_targets(&self, targets: &[&str]) -> HashMap<usize, f64> {
+        let target_bbs: HashSet<usize> = self
+            .basic_blocks
+            .values()
+            .filter(|bb| {
+                bb.source_locations.iter().any(|location| {
+                    targets
+                        .iter()
+                        .any(|target| source_location_matches(location, target))
+                })
+            })
+            .map(|bb| bb.node_loc)
+            .collect();
+        let target_funcs: HashSet<&str> = target_bbs
+            .iter()
+            .map(|bb| self.basic_blocks[bb].calling_func.as_str())
+            .collect();
+        let func_distances = self.calculate_function_distances(&target_funcs);
+
+        // Blocks from which the target distance is known without looking at the CFG.
+        let mut anchors: Vec<(usize, f64)> = vec![];
+        for bb in self.basic_blocks.values() {
+            if target_bbs.contains(&bb.node_loc) {
+                anchors.push((bb.node_loc, 0.0));
+            } else if let Some(distance) = bb
+                .callees
+                .iter()
+                .filter_map(|callee| func_distances.get(callee.as_str()))
+                .min_by(|a, b| a.total_cmp(b))
+            {
+                anchors.push((bb.node_loc, CALL_DISTANCE_FACTOR * (distance + 1.0)));
+            }
+        }
+
+        let mut predecessors: HashMap<usize, Vec<usize>> = HashMap::new();
+        for bb in self.basic_blocks.values() {
+            for successor in &bb.successors {
+                predecessors
+                    .entry(*successor)
+                    .or_default()
+                    .push(bb.node_loc);
+            }
+        }
+
+        let mut inverse_sums: HashMap<usize, (f64, u32)> = HashMap::new();
+        let mut reaching_targets: HashSet<usize> = HashSet::new();
+        for (anchor, anchor_distance) in anchors {
+            let func = &self.basic_blocks[&anchor].calling_func;
+            let mut visited = HashSet::from([anchor]);
+            let mut to_visit = VecDeque::from([(anchor, 0_u32)]);
+            while let Some((bb, hops)) = to_visit.pop_front() {
+                let distance = f64::from(hops) + anchor_distance;
+                if distance == 0.0 {
+                    reaching_targets.insert(bb);
+                } else {
+                    let (sum, count) = inverse_sums.entry(bb).or_default();
+                    *sum += 1.0 / distance;
+                    *count += 1;
+                }
+                for predecessor in predecessors.get(&bb).into_iter().flatten() {
+                    let same_func = self
+                        .basic_blocks
+                        .get(predecessor)
+                        .map_or(false, |info| &info.calling_func == func);
+                    if same_func && visited.insert(*predecessor) {
+                        to_visit.push_back((*predecessor, hops + 1));
+                    }
+                }
+            }
+        }
+
+        let mut distances: HashMap<usize, f64> = inverse_sums
+            .into_iter()
+            .map(|(bb, (sum, count))| (bb, f64::from(count) / sum))
+            .collect();
+        for bb in reaching_targets {
+            distances.insert(bb, 0.0);
+        }
+        distances
+    }
+
+    /// Calculate the `AFLGo` distance of every edge to the given target source locations.
+    ///
+    /// The returned hash map is keyed by the index of the coverage map AFL inserts to, and the
+    /// distance of an edge is the one of the basic block it leads to.
+    /// See [`ControlFlowGraph::calculate_bb_distances_to_targets`].
+    #[must_use]
+    pub fn calculate_distances_to_targets(&self, targets: &[&str]) -> HashMap<usize, f64> {
+        let bb_distances = self.calculate_bb_distances_to_targets(targets);
+        self.edges
+            .iter()
+            .flatten()
+            .filter_map(|edge| {
+                bb_distances
+                    .get(&edge.bottom_node_loc)
+                    .map(|distance| (edge.xored_loc, *distance))
+            })
+            .collect()
+    }
 }
 
 impl<T> Default for ControlFlowGraph<T>
@@ -398,4 +633,40 @@ mod tests {
         assert_eq!(*distances.get(&((26911 >> 1) ^ 41925)).unwrap(), 2);
         assert!(distances.get(&((41864 >> 1) ^ 52706)).is_none());
     }
+
+    // main (1) ──► main (2), calls foo ──► main (4)
+    //    │                                     ▲
+    //    └───────► main (3), calls foo ────────┘
+    //
+    // foo (10) ──► foo (11), the target
+    const TEST_DIRECTED_GRAPH_STR: &str = "$$main+1\n$$foo+10\n%%main+1\n@@main.c:3\n->2\n->3\n%%main+2\n@@main.c:4\n##foo\n->4\n%%main+3\n@@main.c:6\n##foo\n##printf\n->4\n%%main+4\n@@main.c:8\n%%foo+10\n@@src/foo.c:2\n->11\n%%foo+11\n@@src/foo.c:7\n";
+
+    #[test]
+    #[cfg_attr(miri, ignore)] // Testcase takes long in miri.
+    #[allow(clippy::float_cmp)] // All distances here are exact
+    fn test_distances_to_targets() {
+        let cfg: ControlFlowGraph<TestMetadata> =
+            ControlFlowGraph::from_content(TEST_DIRECTED_GRAPH_STR);
+        let bb = cfg.get_basic_block(3).unwrap();
+        assert_eq!(bb.source_locations, vec!["main.c:6"]);
+        assert_eq!(bb.callees, vec!["foo", "printf"]);
+
+        let distances = cfg.calculate_bb_distances_to_targets(&["foo.c:7"]);
+        assert_eq!(distances[&11], 0.0);
+        assert_eq!(distances[&10], 1.0);
+        assert_eq!(distances[&2], 10.0);
+        assert_eq!(distances[&3], 10.0);
+        // Harmonic mean of the two paths of length 11 through the calls to `foo`
+        assert_eq!(distances[&1], 11.0);
+        assert!(distances.get(&4).is_none());
+
+        let edge_distances = cfg.calculate_distances_to_targets(&["foo.c:7"]);
+        assert_eq!(edge_distances[&((10 >> 1) ^ 11)], 0.0);
+        assert_eq!(edge_distances[&((1 >> 1) ^ 3)], 10.0);
+        assert!(edge_distances.get(&((2 >> 1) ^ 4)).is_none());
+
+        assert!(cfg
+            .calculate_bb_distances_to_targets(&["oo.c:7"])
+            .is_empty());
+    }
 }
@@ -1,8 +1,12 @@
 //! LLVM compiler Wrapper from `LibAFL`
 
 use std::{
+    collections::hash_map::DefaultHasher,
     convert::Into,
     env,
+    ffi::OsString,
+    fs,
+    hash::{Hash, Hasher},
     path::{Path, PathBuf},
     str::FromStr,
     string::String,
@@ -26,11 +30,117 @@ fn dll_extension<'a>() -> &'a str {
 
 include!(concat!(env!("OUT_DIR"), "/clang_constants.rs"));
 
+/// The env var naming the file that lists what to instrument, in the format of `AFL++`
+pub const ALLOWLIST_ENV: &str = "AFL_LLVM_ALLOWLIST";
+/// The env var naming the file that lists what not to instrument, in the format of `AFL++`
+pub const DENYLIST_ENV: &str = "AFL_LLVM_DENYLIST";
+
+/// Converts an instrumentation list in the `AFL++` format into a `clang` special case list,
+/// as taken by `-fsanitize-coverage-allowlist` and `-fsanitize-coverage-ignorelist`.
+///
+/// `clang` only instruments a function if both its source file and its name are allowed,
+/// so an allowlist with functions and files works as an intersection, not as a union.
+fn sancov_special_case_list(list: &str, allow: bool) -> Result<String, Error> {
+    let mut files = vec![];
+    let mut functions = vec![];
+    for line in list.lines() {
+        let line: String = line
+            .split('#')
+            .next()
+            .unwrap()
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .collect();
+        let (is_file, pattern) = if let Some(pattern) = line
+            .strip_prefix("fun:")
+            .or_else(|| line.strip_prefix("function:"))
+        {
+            (false, pattern)
+        } else {
+            let pattern = line
+                .strip_prefix("src:")
+                .or_else(|| line.strip_prefix("source:"))
+                .unwrap_or(&line);
+            (true, pattern)
+        };
+        if pattern.contains(':') {
+            return Err(Error::InvalidArguments(format!(
+                "Invalid line in instrumentation list: {line}"
+            )));
+        }
+        if pattern.is_empty() {
+            continue;
+        }
+        if is_file {
+            files.push(pattern.to_string());
+        } else {
+            functions.push(pattern.to_string());
+        }
+    }
+
+    if allow {
+        // An empty section would match nothing
+        if files.is_empty() {
+            files.push("*".to_string());
+        }
+        if functions.is_empty() {
+            functions.push("*".to_string());
+        }
+    }
+
+    // Like in the `libafl_cc` passes, files match the whole path or its end after a `/`,
+    // so that `main.c` does not match `domain.c`, and functions match the whole name
+    Ok(files
+        .iter()
+        .map(|file| {
+            if file.starts_with(['/', '*']) {
+                format!("src:{file}\n")
+            } else {
+                format!("src:{file}\nsrc:*/{file}\n")
+            }
+        })
+        .chain(functions.iter().map(|function| format!("fun:{function}\n")))
+        .collect())
+}
+
+/// If `configuration` is, or is made up of, [`crate::Configuration::DataFlowSanitizer`]
+fn uses_dfsan(configuration: &crate::Configuration) -> bool {
+    match configuration {
+        crate::Configuration::DataFlowSanitizer => true,
+        crate::Configuration::Compound(configurations) => configurations.iter().any(uses_dfsan),
+        _ => false,
+    }
+}
+
+/// Writes the `clang` special case list for the instrumentation list at `path`
+/// to the temp dir, and returns its path.
+fn write_sancov_special_case_list(path: &Path, allow: bool) -> Result<PathBuf, Error> {
+    let list = fs::read_to_string(path).map_err(Error::Io)?;
+    let special_case_list = sancov_special_case_list(&list, allow)?;
+
+    // Named after the content, as the wrapper runs for each compilation unit, maybe in parallel
+    let mut hasher = DefaultHasher::new();
+    special_case_list.hash(&mut hasher);
+    let kind = if allow { "allowlist" } else { "ignorelist" };
+    let out = env::temp_dir().join(format!(
+        "libafl_cc_sancov_{kind}_{:016x}.txt",
+        hasher.finish()
+    ));
+    if !out.exists() {
+        let tmp = out.with_extension(format!("{}.tmp", std::process::id()));
+        fs::write(&tmp, special_case_list).map_err(Error::Io)?;
+        fs::rename(&tmp, &out).map_err(Error::Io)?;
+    }
+    Ok(out)
+}
+
 /// The supported LLVM passes
 #[allow(clippy::upper_case_acronyms)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum LLVMPasses {
-    //CmpLogIns,
+    /// The `CmpLog` instructions pass, logging the operands of `icmp`, `fcmp` and `switch`
+    /// in the `AFL++` cmplog map layout
+    CmpLogIns,
     /// The CmpLog pass
     CmpLogRtn,
     /// The AFL coverage pass
@@ -41,6 +151,17 @@ pub enum LLVMPasses {
     CoverageAccounting,
     /// The dump cfg pass
     DumpCfg,
+    /// The laf-intel pass turning `strcmp`, `memcmp` and friends with a constant string into
+    /// byte-wise compares
+    CompareTransform,
+    /// The laf-intel pass turning `switch` statements into chains of compares
+    SplitSwitches,
+    /// The laf-intel pass turning multi-byte integer compares into byte-wise compares
+    SplitCompares,
+    /// The link-time edge coverage pass, giving each edge of the whole program its own
+    /// map index and storing the map size to `__afl_final_loc`.
+    /// Runs at link time only, see [`ClangWrapper::add_lto_pass`].
+    LtoCoverage,
 }
 
 impl LLVMPasses {
@@ -48,6 +169,8 @@ impl LLVMPasses {
     #[must_use]
     pub fn path(&self) -> PathBuf {
         match self {
+            LLVMPasses::CmpLogIns => PathBuf::from(env!("OUT_DIR"))
+                .join(format!("cmplog-instructions-pass.{}", dll_extension())),
             LLVMPasses::CmpLogRtn => PathBuf::from(env!("OUT_DIR"))
                 .join(format!("cmplog-routines-pass.{}", dll_extension())),
             LLVMPasses::AFLCoverage => PathBuf::from(env!("OUT_DIR"))
@@ -60,6 +183,14 @@ impl LLVMPasses {
             LLVMPasses::DumpCfg => {
                 PathBuf::from(env!("OUT_DIR")).join(format!("dump-cfg-pass.{}", dll_extension()))
             }
+            LLVMPasses::CompareTransform => PathBuf::from(env!("OUT_DIR"))
+                .join(format!("compare-transform-pass.{}", dll_extension())),
+            LLVMPasses::SplitSwitches => PathBuf::from(env!("OUT_DIR"))
+                .join(format!("split-switches-pass.{}", dll_extension())),
+            LLVMPasses::SplitCompares => PathBuf::from(env!("OUT_DIR"))
+                .join(format!("split-compares-pass.{}", dll_extension())),
+            LLVMPasses::LtoCoverage => PathBuf::from(env!("OUT_DIR"))
+                .join(format!("lto-coverage-pass.{}", dll_extension())),
         }
     }
 }
@@ -94,6 +225,9 @@ pub struct ClangWrapper {
     passes: Vec<LLVMPasses>,
     passes_args: Vec<String>,
     passes_linking_args: Vec<String>,
+    lto_passes: Vec<LLVMPasses>,
+    allowlist: Option<PathBuf>,
+    denylist: Option<PathBuf>,
 }
 
 #[allow(clippy::match_same_arms)] // for the linking = false wip for "shared"
@@ -428,6 +562,35 @@ impl ToolWrapper for ClangWrapper {
                 args.push(passes_arg.into());
             }
         }
+        if !self.lto_passes.is_empty() {
+            args.push("-flto".into());
+        }
+        if args
+            .iter()
+            .chain(&self.cc_args)
+            .any(|arg| arg.starts_with("-fsanitize-coverage="))
+        {
+            let (allowlist_flag, denylist_flag) = if matches!(LIBAFL_CC_LLVM_VERSION, Some(ver) if ver < 13)
+            {
+                (
+                    "-fsanitize-coverage-whitelist",
+                    "-fsanitize-coverage-blacklist",
+                )
+            } else {
+                (
+                    "-fsanitize-coverage-allowlist",
+                    "-fsanitize-coverage-ignorelist",
+                )
+            };
+            if let Some(allowlist) = &self.allowlist {
+                let list = write_sancov_special_case_list(allowlist, true)?;
+                args.push(format!("{allowlist_flag}={}", list.display()));
+            }
+            if let Some(denylist) = &self.denylist {
+                let list = write_sancov_special_case_list(denylist, false)?;
+                args.push(format!("{denylist_flag}={}", list.display()));
+            }
+        }
         if self.linking {
             if self.x_set {
                 args.push("-x".into());
@@ -436,10 +599,38 @@ impl ToolWrapper for ClangWrapper {
 
             args.extend_from_slice(self.link_args.as_slice());
 
+            if uses_dfsan(&configuration) {
+                let driver = Path::new(concat!(env!("OUT_DIR"), "/dfsan-driver.o"));
+                if !driver.exists() {
+                    return Err(Error::Unknown(
+                        "The dfsan driver of libafl_cc was not built, it needs a clang with DataFlowSanitizer".into(),
+                    ));
+                }
+                args.push(driver.display().to_string());
+            }
+
             if use_pass {
                 args.extend_from_slice(self.passes_linking_args.as_slice());
             }
 
+            if !self.lto_passes.is_empty() {
+                args.push("-fuse-ld=lld".into());
+                // lld only loads new PM plugins from LLVM 15 on
+                let legacy_lto = matches!(LIBAFL_CC_LLVM_VERSION, Some(ver) if ver < 15);
+                if matches!(LIBAFL_CC_LLVM_VERSION, Some(13 | 14)) {
+                    // The new PM is the LTO default there
+                    args.push("-Wl,--lto-legacy-pass-manager".into());
+                }
+                for pass in &self.lto_passes {
+                    let path = pass.path().into_os_string().into_string().unwrap();
+                    if legacy_lto {
+                        args.push(format!("-Wl,-mllvm=-load={path}"));
+                    } else {
+                        args.push(format!("-Wl,--load-pass-plugin={path}"));
+                    }
+                }
+            }
+
             if cfg!(unix) {
                 args.push("-pthread".into());
                 args.push("-ldl".into());
@@ -471,6 +662,17 @@ impl ToolWrapper for ClangWrapper {
     fn is_silent(&self) -> bool {
         self.is_silent
     }
+
+    fn envs(&self) -> Vec<(&'static str, OsString)> {
+        let mut envs = vec![];
+        if let Some(allowlist) = &self.allowlist {
+            envs.push((ALLOWLIST_ENV, allowlist.clone().into_os_string()));
+        }
+        if let Some(denylist) = &self.denylist {
+            envs.push((DENYLIST_ENV, denylist.clone().into_os_string()));
+        }
+        envs
+    }
 }
 
 impl CompilerWrapper for ClangWrapper {
@@ -560,6 +762,9 @@ impl ClangWrapper {
             passes: vec![],
             passes_args: vec![],
             passes_linking_args: vec![],
+            lto_passes: vec![],
+            allowlist: env::var_os(ALLOWLIST_ENV).map(PathBuf::from),
+            denylist: env::var_os(DENYLIST_ENV).map(PathBuf::from),
             is_silent: false,
         }
     }
@@ -594,6 +799,39 @@ impl ClangWrapper {
         self
     }
 
+    /// Add an LLVM pass to run at link time, on the whole program.
+    /// This builds with `-flto` and links with `lld`.
+    pub fn add_lto_pass(&mut self, pass: LLVMPasses) -> &'_ mut Self {
+        self.lto_passes.push(pass);
+        self
+    }
+
+    /// Only instrument the functions and source files listed in the file at `path`,
+    /// in the `AFL_LLVM_ALLOWLIST` format of `AFL++`.
+    ///
+    /// The `libafl_cc` passes read it from the [`ALLOWLIST_ENV`] env var, which is set
+    /// for the wrapped compiler only. It is also handed to `-fsanitize-coverage`, if used.
+    pub fn allowlist<P>(&mut self, path: P) -> &'_ mut Self
+    where
+        P: AsRef<Path>,
+    {
+        self.allowlist = Some(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Don't instrument the functions and source files listed in the file at `path`,
+    /// in the `AFL_LLVM_DENYLIST` format of `AFL++`.
+    ///
+    /// The `libafl_cc` passes read it from the [`DENYLIST_ENV`] env var, which is set
+    /// for the wrapped compiler only. It is also handed to `-fsanitize-coverage`, if used.
+    pub fn denylist<P>(&mut self, path: P) -> &'_ mut Self
+    where
+        P: AsRef<Path>,
+    {
+        self.denylist = Some(path.as_ref().to_path_buf());
+        self
+    }
+
     /// Add LLVM pass arguments
     pub fn add_passes_arg<S>(&mut self, arg: S) -> &'_ mut Self
     where
@@ -629,11 +867,53 @@ impl ClangWrapper {
         self.use_new_pm = value;
         self
     }
+
+    /// Set if `strcmp`, `memcmp` and friends with a constant string are turned into byte-wise
+    /// compares, see [`LLVMPasses::CompareTransform`]
+    pub fn transform_compares(&mut self, value: bool) -> &'_ mut Self {
+        self.laf_pass(LLVMPasses::CompareTransform, value)
+    }
+
+    /// Set if `switch` statements are turned into chains of compares,
+    /// see [`LLVMPasses::SplitSwitches`]
+    pub fn split_switches(&mut self, value: bool) -> &'_ mut Self {
+        self.laf_pass(LLVMPasses::SplitSwitches, value)
+    }
+
+    /// Set if multi-byte integer compares are turned into byte-wise compares,
+    /// see [`LLVMPasses::SplitCompares`]
+    pub fn split_compares(&mut self, value: bool) -> &'_ mut Self {
+        self.laf_pass(LLVMPasses::SplitCompares, value)
+    }
+
+    /// Adds or removes a laf-intel pass.
+    /// These go before all other passes, and in this order, so that the compares
+    /// created by transforming calls and switches get split as well.
+    fn laf_pass(&mut self, pass: LLVMPasses, value: bool) -> &'_ mut Self {
+        const LAF_PASSES: [LLVMPasses; 3] = [
+            LLVMPasses::CompareTransform,
+            LLVMPasses::SplitSwitches,
+            LLVMPasses::SplitCompares,
+        ];
+
+        self.passes.retain(|p| *p != pass);
+        if value {
+            let earlier = &LAF_PASSES[..LAF_PASSES.iter().position(|p| *p == pass).unwrap()];
+            let idx = self
+                .passes
+                .iter()
+                .position(|p| !earlier.contains(p))
+                .unwrap_or(self.passes.len());
+            self.passes.insert(idx, pass);
+        }
+        self
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{ClangWrapper, ToolWrapper};
+    use super::sancov_special_case_list;
+    use crate::{ClangWrapper, LLVMPasses, ToolWrapper};
 
     #[test]
     #[cfg_attr(miri, ignore)]
@@ -646,4 +926,72 @@ mod tests {
             println!("Ignored error {res:?} - clang is probably not installed.");
         }
     }
+
+    #[test]
+    fn test_laf_passes_order() {
+        let mut cc = ClangWrapper::new();
+        cc.add_pass(LLVMPasses::AFLCoverage)
+            .split_compares(true)
+            .transform_compares(true)
+            .split_switches(true);
+        assert_eq!(
+            cc.passes,
+            [
+                LLVMPasses::CompareTransform,
+                LLVMPasses::SplitSwitches,
+                LLVMPasses::SplitCompares,
+                LLVMPasses::AFLCoverage
+            ]
+        );
+
+        cc.split_switches(false);
+        assert_eq!(
+            cc.passes,
+            [
+                LLVMPasses::CompareTransform,
+                LLVMPasses::SplitCompares,
+                LLVMPasses::AFLCoverage
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sancov_special_case_list() {
+        let list = "# vendored code\nsrc:vendor/*.c\nfun: parse_* # parsers\nmain.c\n\n";
+        assert_eq!(
+            sancov_special_case_list(list, false).unwrap(),
+            "src:vendor/*.c\nsrc:*/vendor/*.c\nsrc:main.c\nsrc:*/main.c\nfun:parse_*\n"
+        );
+        assert_eq!(
+            sancov_special_case_list("function:parse_*", true).unwrap(),
+            "src:*\nfun:parse_*\n"
+        );
+        assert_eq!(
+            sancov_special_case_list("/abs/main.c", true).unwrap(),
+            "src:/abs/main.c\nfun:*\n"
+        );
+        assert!(sancov_special_case_list("fun:a:b", true).is_err());
+    }
+
+    #[test]
+    fn test_lto_passes_link_args() {
+        let mut cc = ClangWrapper::new();
+        cc.add_lto_pass(LLVMPasses::LtoCoverage)
+            .parse_args(&["my-clang", "-c", "main.c", "-o", "main.o"])
+            .unwrap();
+        let args = cc.command().unwrap();
+        assert!(args.iter().any(|arg| arg == "-flto"));
+        assert!(!args.iter().any(|arg| arg == "-fuse-ld=lld"));
+
+        let mut cc = ClangWrapper::new();
+        cc.add_lto_pass(LLVMPasses::LtoCoverage)
+            .parse_args(&["my-clang", "main.o", "-o", "main"])
+            .unwrap();
+        let args = cc.command().unwrap();
+        assert!(args.iter().any(|arg| arg == "-flto"));
+        assert!(args.iter().any(|arg| arg == "-fuse-ld=lld"));
+        assert!(args
+            .iter()
+            .any(|arg| arg.starts_with("-Wl,") && arg.contains("lto-coverage-pass")));
+    }
 }
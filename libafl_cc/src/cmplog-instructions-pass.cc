@@ -0,0 +1,508 @@
+/*
+   american fuzzy lop++ - LLVM CmpLog instrumentation
+   --------------------------------------------------
+
+   Written by Andrea Fioraldi <andreafioraldi@gmail.com>
+
+   Copyright 2015, 2016 Google Inc. All rights reserved.
+   Copyright 2019-2020 AFLplusplus Project. All rights reserved.
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at:
+
+     http://www.apache.org/licenses/LICENSE-2.0
+
+*/
+
+#include <stdio.h>
+#include <stdlib.h>
+#ifndef _WIN32
+  #include <unistd.h>
+  #include <sys/time.h>
+#endif
+
+#include <list>
+#include <string>
+#include <fstream>
+#include "llvm/Config/llvm-config.h"
+
+#if USE_NEW_PM
+  #include "llvm/Passes/PassPlugin.h"
+  #include "llvm/Passes/PassBuilder.h"
+  #include "llvm/IR/PassManager.h"
+#else
+  #include "llvm/IR/LegacyPassManager.h"
+#endif
+
+#include "llvm/ADT/Statistic.h"
+#include "llvm/IR/IRBuilder.h"
+#include "llvm/IR/Module.h"
+#include "llvm/Support/Debug.h"
+#include "llvm/Support/raw_ostream.h"
+#if LLVM_VERSION_MAJOR < 11
+  #include "llvm/Transforms/IPO/PassManagerBuilder.h"
+#endif
+#include "llvm/Transforms/Utils/BasicBlockUtils.h"
+#include "llvm/Pass.h"
+#include "llvm/Analysis/ValueTracking.h"
+
+#if LLVM_VERSION_MAJOR > 3 || \
+    (LLVM_VERSION_MAJOR == 3 && LLVM_VERSION_MINOR > 4)
+  #include "llvm/IR/Verifier.h"
+  #include "llvm/IR/DebugInfo.h"
+#else
+  #include "llvm/Analysis/Verifier.h"
+  #include "llvm/DebugInfo.h"
+  #define nullptr 0
+#endif
+
+#include <set>
+
+#include "instrument-list.h"
+
+using namespace llvm;
+
+/* The attributes of a comparison, as in AFL++'s cmplog.h */
+#define CMP_IS_EQUAL 1
+#define CMP_IS_GREATER 2
+#define CMP_IS_LESSER 4
+#define CMP_IS_FP 8
+
+namespace {
+
+/* Function that we never instrument or analyze */
+/* Note: this ignore check is also called in isInInstrumentList() */
+bool isIgnoreFunction(const llvm::Function *F) {
+  // Starting from "LLVMFuzzer" these are functions used in libfuzzer based
+  // fuzzing campaign installations, e.g. oss-fuzz
+
+  static constexpr const char *ignoreList[] = {
+
+      "asan.",
+      "llvm.",
+      "sancov.",
+      "__ubsan",
+      "ign.",
+      "__afl",
+      "_fini",
+      "__libc_",
+      "__asan",
+      "__msan",
+      "__cmplog",
+      "__sancov",
+      "__san",
+      "__cxx_",
+      "__decide_deferred",
+      "_GLOBAL",
+      "_ZZN6__asan",
+      "_ZZN6__lsan",
+      "msan.",
+      "LLVMFuzzerM",
+      "LLVMFuzzerC",
+      "LLVMFuzzerI",
+      "maybe_duplicate_stderr",
+      "discard_output",
+      "close_stdout",
+      "dup_and_close_stderr",
+      "maybe_close_fd_mask",
+      "ExecuteFilesOnyByOne"
+
+  };
+
+  for (auto const &ignoreListFunc : ignoreList) {
+    if (F->getName().startswith(ignoreListFunc)) { return true; }
+  }
+
+  static constexpr const char *ignoreSubstringList[] = {
+
+      "__asan",       "__msan",     "__ubsan", "__lsan",
+      "__san",        "__sanitize", "__cxx",   "_GLOBAL__",
+      "DebugCounter", "DwarfDebug", "DebugLoc"
+
+  };
+
+  for (auto const &ignoreListFunc : ignoreSubstringList) {
+    // hexcoder: F->getName().contains() not avaiilable in llvm 3.8.0
+    if (StringRef::npos != F->getName().find(ignoreListFunc)) { return true; }
+  }
+
+  return false;
+}
+
+#if USE_NEW_PM
+class CmpLogInstructions : public PassInfoMixin<CmpLogInstructions> {
+ public:
+  CmpLogInstructions() {
+#else
+
+class CmpLogInstructions : public ModulePass {
+ public:
+  static char ID;
+  CmpLogInstructions() : ModulePass(ID) {
+#endif
+    initInstrumentList();
+  }
+
+#if USE_NEW_PM
+  PreservedAnalyses run(Module &M, ModuleAnalysisManager &MAM);
+#else
+  bool        runOnModule(Module &M) override;
+
+  #if LLVM_VERSION_MAJOR < 4
+  const char *getPassName() const override {
+  #else
+  StringRef getPassName() const override {
+  #endif
+    return "cmplog instructions";
+  }
+#endif
+
+ private:
+  bool hookInstrs(Module &M);
+};
+
+}  // namespace
+
+#if USE_NEW_PM
+extern "C" ::llvm::PassPluginLibraryInfo LLVM_ATTRIBUTE_WEAK
+llvmGetPassPluginInfo() {
+  return {LLVM_PLUGIN_API_VERSION, "CmpLogInstructions", "v0.1",
+          [](PassBuilder &PB) {
+  #if LLVM_VERSION_MAJOR <= 13
+            using OptimizationLevel = typename PassBuilder::OptimizationLevel;
+  #endif
+            PB.registerOptimizerLastEPCallback(
+                [](ModulePassManager &MPM, OptimizationLevel OL) {
+                  MPM.addPass(CmpLogInstructions());
+                });
+          }};
+}
+#else
+char CmpLogInstructions::ID = 0;
+#endif
+
+/* The AFL++ attribute of a comparison predicate, -1 if it is not logged */
+static int predicateAttribute(CmpInst::Predicate pred) {
+  switch (pred) {
+    case CmpInst::ICMP_EQ:
+    case CmpInst::FCMP_OEQ:
+    case CmpInst::FCMP_UEQ:
+      return CMP_IS_EQUAL;
+    case CmpInst::ICMP_NE:
+    case CmpInst::FCMP_ONE:
+    case CmpInst::FCMP_UNE:
+      return 0;
+    case CmpInst::ICMP_UGT:
+    case CmpInst::ICMP_SGT:
+    case CmpInst::FCMP_OGT:
+    case CmpInst::FCMP_UGT:
+      return CMP_IS_GREATER;
+    case CmpInst::ICMP_UGE:
+    case CmpInst::ICMP_SGE:
+    case CmpInst::FCMP_OGE:
+    case CmpInst::FCMP_UGE:
+      return CMP_IS_GREATER | CMP_IS_EQUAL;
+    case CmpInst::ICMP_ULT:
+    case CmpInst::ICMP_SLT:
+    case CmpInst::FCMP_OLT:
+    case CmpInst::FCMP_ULT:
+      return CMP_IS_LESSER;
+    case CmpInst::ICMP_ULE:
+    case CmpInst::ICMP_SLE:
+    case CmpInst::FCMP_OLE:
+    case CmpInst::FCMP_ULE:
+      return CMP_IS_LESSER | CMP_IS_EQUAL;
+    default:
+      return -1;
+  }
+}
+
+/* The width in bits of a scalar int or floating point operand, 0 otherwise */
+static unsigned operandWidth(Type *ty) {
+  if (ty->isIntegerTy()) { return ty->getIntegerBitWidth(); }
+  if (ty->isHalfTy()) { return 16; }
+  if (ty->isFloatTy()) { return 32; }
+  if (ty->isDoubleTy()) { return 64; }
+  if (ty->isX86_FP80Ty()) { return 80; }
+  if (ty->isFP128Ty()) { return 128; }
+  return 0;
+}
+
+bool CmpLogInstructions::hookInstrs(Module &M) {
+  std::vector<CmpInst *>    cmps;
+  std::vector<SwitchInst *> switches;
+  LLVMContext              &C = M.getContext();
+
+  Type        *VoidTy = Type::getVoidTy(C);
+  IntegerType *Int8Ty = IntegerType::getInt8Ty(C);
+  IntegerType *Int16Ty = IntegerType::getInt16Ty(C);
+  IntegerType *Int32Ty = IntegerType::getInt32Ty(C);
+  IntegerType *Int64Ty = IntegerType::getInt64Ty(C);
+  IntegerType *Int128Ty = IntegerType::getInt128Ty(C);
+
+#if LLVM_VERSION_MAJOR < 9
+  Constant *
+#else
+  FunctionCallee
+#endif
+      c1 = M.getOrInsertFunction("__cmplog_ins_hook1", VoidTy, Int8Ty, Int8Ty,
+                                 Int8Ty
+#if LLVM_VERSION_MAJOR < 5
+                                 ,
+                                 NULL
+#endif
+      );
+#if LLVM_VERSION_MAJOR < 9
+  Function *cmplogHookIns1 = cast<Function>(c1);
+#else
+  FunctionCallee cmplogHookIns1 = c1;
+#endif
+
+#if LLVM_VERSION_MAJOR < 9
+  Constant *
+#else
+  FunctionCallee
+#endif
+      c2 = M.getOrInsertFunction("__cmplog_ins_hook2", VoidTy, Int16Ty,
+                                 Int16Ty, Int8Ty
+#if LLVM_VERSION_MAJOR < 5
+                                 ,
+                                 NULL
+#endif
+      );
+#if LLVM_VERSION_MAJOR < 9
+  Function *cmplogHookIns2 = cast<Function>(c2);
+#else
+  FunctionCallee cmplogHookIns2 = c2;
+#endif
+
+#if LLVM_VERSION_MAJOR < 9
+  Constant *
+#else
+  FunctionCallee
+#endif
+      c4 = M.getOrInsertFunction("__cmplog_ins_hook4", VoidTy, Int32Ty,
+                                 Int32Ty, Int8Ty
+#if LLVM_VERSION_MAJOR < 5
+                                 ,
+                                 NULL
+#endif
+      );
+#if LLVM_VERSION_MAJOR < 9
+  Function *cmplogHookIns4 = cast<Function>(c4);
+#else
+  FunctionCallee cmplogHookIns4 = c4;
+#endif
+
+#if LLVM_VERSION_MAJOR < 9
+  Constant *
+#else
+  FunctionCallee
+#endif
+      c8 = M.getOrInsertFunction("__cmplog_ins_hook8", VoidTy, Int64Ty,
+                                 Int64Ty, Int8Ty
+#if LLVM_VERSION_MAJOR < 5
+                                 ,
+                                 NULL
+#endif
+      );
+#if LLVM_VERSION_MAJOR < 9
+  Function *cmplogHookIns8 = cast<Function>(c8);
+#else
+  FunctionCallee cmplogHookIns8 = c8;
+#endif
+
+#if LLVM_VERSION_MAJOR < 9
+  Constant *
+#else
+  FunctionCallee
+#endif
+      c16 = M.getOrInsertFunction("__cmplog_ins_hook16", VoidTy, Int128Ty,
+                                  Int128Ty, Int8Ty
+#if LLVM_VERSION_MAJOR < 5
+                                  ,
+                                  NULL
+#endif
+      );
+#if LLVM_VERSION_MAJOR < 9
+  Function *cmplogHookIns16 = cast<Function>(c16);
+#else
+  FunctionCallee cmplogHookIns16 = c16;
+#endif
+
+#if LLVM_VERSION_MAJOR < 9
+  Constant *
+#else
+  FunctionCallee
+#endif
+      cN = M.getOrInsertFunction("__cmplog_ins_hookN", VoidTy, Int128Ty,
+                                 Int128Ty, Int8Ty, Int8Ty
+#if LLVM_VERSION_MAJOR < 5
+                                 ,
+                                 NULL
+#endif
+      );
+#if LLVM_VERSION_MAJOR < 9
+  Function *cmplogHookInsN = cast<Function>(cN);
+#else
+  FunctionCallee cmplogHookInsN = cN;
+#endif
+
+  /* iterate over all functions, bbs and instruction and add suitable calls */
+  for (auto &F : M) {
+    if (isIgnoreFunction(&F) || !isInInstrumentList(&F)) { continue; }
+
+    for (auto &BB : F) {
+      for (auto &IN : BB) {
+        CmpInst    *cmpInst = nullptr;
+        SwitchInst *switchInst = nullptr;
+
+        if ((cmpInst = dyn_cast<CmpInst>(&IN))) {
+          if (predicateAttribute(cmpInst->getPredicate()) >= 0) {
+            cmps.push_back(cmpInst);
+          }
+        } else if ((switchInst = dyn_cast<SwitchInst>(&IN))) {
+          if (switchInst->getNumCases() > 0) { switches.push_back(switchInst); }
+        }
+      }
+    }
+  }
+
+  if (!cmps.size() && !switches.size()) { return false; }
+
+  /* Calls the hook matching the width (in bits) of the operands.
+     The operands are already integers of that width. */
+  auto logValues = [&](IRBuilder<> &IRB, Value *v0, Value *v1,
+                       unsigned width, unsigned char attr) {
+    std::vector<Value *> args;
+    switch (width) {
+      case 8:
+        args = {v0, v1, ConstantInt::get(Int8Ty, attr)};
+        IRB.CreateCall(cmplogHookIns1, args);
+        break;
+      case 16:
+        args = {v0, v1, ConstantInt::get(Int8Ty, attr)};
+        IRB.CreateCall(cmplogHookIns2, args);
+        break;
+      case 32:
+        args = {v0, v1, ConstantInt::get(Int8Ty, attr)};
+        IRB.CreateCall(cmplogHookIns4, args);
+        break;
+      case 64:
+        args = {v0, v1, ConstantInt::get(Int8Ty, attr)};
+        IRB.CreateCall(cmplogHookIns8, args);
+        break;
+      case 128:
+        args = {v0, v1, ConstantInt::get(Int8Ty, attr)};
+        IRB.CreateCall(cmplogHookIns16, args);
+        break;
+      default:
+        args = {IRB.CreateZExt(v0, Int128Ty), IRB.CreateZExt(v1, Int128Ty),
+                ConstantInt::get(Int8Ty, attr),
+                ConstantInt::get(Int8Ty, width / 8)};
+        IRB.CreateCall(cmplogHookInsN, args);
+        break;
+    }
+  };
+
+  for (auto &cmpInst : cmps) {
+    Value *op0 = cmpInst->getOperand(0);
+    Value *op1 = cmpInst->getOperand(1);
+
+    // Vector compares are not logged
+    unsigned width = operandWidth(op0->getType());
+    if (!width || width != operandWidth(op1->getType())) { continue; }
+    // Boolean compares carry nothing worth solving
+    if (width < 8) { continue; }
+
+    unsigned char attr = predicateAttribute(cmpInst->getPredicate());
+    bool          isFp = op0->getType()->isFloatingPointTy();
+    if (isFp) { attr |= CMP_IS_FP; }
+
+    IRBuilder<> IRB(cmpInst->getParent());
+    IRB.SetInsertPoint(cmpInst);
+
+    if (isFp) {
+      IntegerType *intTy = IntegerType::get(C, width);
+      op0 = IRB.CreateBitCast(op0, intTy);
+      op1 = IRB.CreateBitCast(op1, intTy);
+    }
+
+    // Round up to whole bytes, and clamp to what the map can hold
+    unsigned logWidth = width;
+    if (logWidth % 8) { logWidth = (logWidth / 8 + 1) * 8; }
+    if (logWidth > 128) { logWidth = 128; }
+    if (logWidth != width) {
+      IntegerType *logTy = IntegerType::get(C, logWidth);
+      op0 = IRB.CreateZExtOrTrunc(op0, logTy);
+      op1 = IRB.CreateZExtOrTrunc(op1, logTy);
+    }
+
+    logValues(IRB, op0, op1, logWidth, attr);
+  }
+
+  for (auto &switchInst : switches) {
+    Value   *cond = switchInst->getCondition();
+    unsigned width = operandWidth(cond->getType());
+    if (width < 8 || width > 128) { continue; }
+
+    IRBuilder<> IRB(switchInst->getParent());
+    IRB.SetInsertPoint(switchInst);
+
+    unsigned logWidth = width;
+    if (logWidth % 8) { logWidth = (logWidth / 8 + 1) * 8; }
+    IntegerType *logTy = IntegerType::get(C, logWidth);
+    Value       *condLogged = IRB.CreateZExtOrTrunc(cond, logTy);
+
+    // Each case is an equality comparison with the condition
+    for (auto &caseIt : switchInst->cases()) {
+      Value *caseLogged = IRB.CreateZExtOrTrunc(caseIt.getCaseValue(), logTy);
+      logValues(IRB, condLogged, caseLogged, logWidth, CMP_IS_EQUAL);
+    }
+  }
+
+  return true;
+}
+
+#if USE_NEW_PM
+PreservedAnalyses CmpLogInstructions::run(Module                &M,
+                                          ModuleAnalysisManager &MAM) {
+#else
+bool CmpLogInstructions::runOnModule(Module &M) {
+#endif
+  hookInstrs(M);
+
+#if USE_NEW_PM
+  auto PA = PreservedAnalyses::all();
+#endif
+  verifyModule(M);
+
+#if USE_NEW_PM
+  return PA;
+#else
+  return true;
+#endif
+}
+
+#if USE_NEW_PM
+#else
+  #if LLVM_VERSION_MAJOR < 11 /* use old pass manager */
+static void registerCmpLogInstructionsPass(const PassManagerBuilder &,
+                                           legacy::PassManagerBase &PM) {
+  auto p = new CmpLogInstructions();
+  PM.add(p);
+}
+
+static RegisterStandardPasses RegisterCmpLogInstructionsPass(
+    PassManagerBuilder::EP_OptimizerLast, registerCmpLogInstructionsPass);
+
+static RegisterStandardPasses RegisterCmpLogInstructionsPass0(
+    PassManagerBuilder::EP_EnabledOnOptLevel0, registerCmpLogInstructionsPass);
+
+static RegisterStandardPasses RegisterCmpLogInstructionsPassLTO(
+    PassManagerBuilder::EP_FullLinkTimeOptimizationLast,
+    registerCmpLogInstructionsPass);
+
+  #endif
+#endif
@@ -59,6 +59,8 @@
 
 #include <set>
 
+#include "instrument-list.h"
+
 using namespace llvm;
 
 namespace {
@@ -133,6 +135,7 @@ class CmpLogRoutines : public ModulePass {
   static char ID;
   CmpLogRoutines() : ModulePass(ID) {
 #endif
+    initInstrumentList();
   }
 
 #if USE_NEW_PM
@@ -273,7 +276,7 @@ bool CmpLogRoutines::hookRtns(Module &M) {
 
   /* iterate over all functions, bbs and instruction and add suitable calls */
   for (auto &F : M) {
-    if (isIgnoreFunction(&F)) { continue; }
+    if (isIgnoreFunction(&F) || !isInInstrumentList(&F)) { continue; }
 
     for (auto &BB : F) {
       for (auto &IN : BB) {
@@ -0,0 +1,388 @@
+/*
+   american fuzzy lop++ - LLVM LaF compare transformation
+   ------------------------------------------------------
+
+   Based on the laf-intel passes, as shipped with AFL++.
+   Strcmp, strncmp, memcmp, bcmp, strcasecmp and strncasecmp calls with one constant
+   string are transformed into byte-wise compare chains.
+
+   Copyright 2016 laf-intel. All rights reserved.
+   Copyright 2019-2020 AFLplusplus Project. All rights reserved.
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at:
+
+     http://www.apache.org/licenses/LICENSE-2.0
+
+*/
+
+#include <stdio.h>
+#include <stdlib.h>
+#include <ctype.h>
+#ifndef _WIN32
+  #include <unistd.h>
+  #include <sys/time.h>
+#endif
+
+#include <list>
+#include <string>
+#include <fstream>
+#include "llvm/Config/llvm-config.h"
+
+#if USE_NEW_PM
+  #include "llvm/Passes/PassPlugin.h"
+  #include "llvm/Passes/PassBuilder.h"
+  #include "llvm/IR/PassManager.h"
+#else
+  #include "llvm/IR/LegacyPassManager.h"
+#endif
+
+#include "llvm/ADT/Statistic.h"
+#include "llvm/IR/IRBuilder.h"
+#include "llvm/IR/Module.h"
+#include "llvm/Support/Debug.h"
+#include "llvm/Support/raw_ostream.h"
+#if LLVM_VERSION_MAJOR < 11
+  #include "llvm/Transforms/IPO/PassManagerBuilder.h"
+#endif
+#include "llvm/Transforms/Utils/BasicBlockUtils.h"
+#include "llvm/Pass.h"
+#include "llvm/Analysis/ValueTracking.h"
+
+#if LLVM_VERSION_MAJOR > 3 || \
+    (LLVM_VERSION_MAJOR == 3 && LLVM_VERSION_MINOR > 4)
+  #include "llvm/IR/Verifier.h"
+  #include "llvm/IR/DebugInfo.h"
+#else
+  #include "llvm/Analysis/Verifier.h"
+  #include "llvm/DebugInfo.h"
+  #define nullptr 0
+#endif
+
+#include <set>
+
+#include "instrument-list.h"
+
+using namespace llvm;
+
+namespace {
+
+/* Function that we never instrument or analyze */
+/* Note: this ignore check is also called in isInInstrumentList() */
+bool isIgnoreFunction(const llvm::Function *F) {
+  // Starting from "LLVMFuzzer" these are functions used in libfuzzer based
+  // fuzzing campaign installations, e.g. oss-fuzz
+
+  static constexpr const char *ignoreList[] = {
+
+      "asan.",
+      "llvm.",
+      "sancov.",
+      "__ubsan",
+      "ign.",
+      "__afl",
+      "_fini",
+      "__libc_",
+      "__asan",
+      "__msan",
+      "__cmplog",
+      "__sancov",
+      "__san",
+      "__cxx_",
+      "__decide_deferred",
+      "_GLOBAL",
+      "_ZZN6__asan",
+      "_ZZN6__lsan",
+      "msan.",
+      "LLVMFuzzerM",
+      "LLVMFuzzerC",
+      "LLVMFuzzerI",
+      "maybe_duplicate_stderr",
+      "discard_output",
+      "close_stdout",
+      "dup_and_close_stderr",
+      "maybe_close_fd_mask",
+      "ExecuteFilesOnyByOne"
+
+  };
+
+  for (auto const &ignoreListFunc : ignoreList) {
+    if (F->getName().startswith(ignoreListFunc)) { return true; }
+  }
+
+  static constexpr const char *ignoreSubstringList[] = {
+
+      "__asan",       "__msan",     "__ubsan", "__lsan",
+      "__san",        "__sanitize", "__cxx",   "_GLOBAL__",
+      "DebugCounter", "DwarfDebug", "DebugLoc"
+
+  };
+
+  for (auto const &ignoreListFunc : ignoreSubstringList) {
+    // hexcoder: F->getName().contains() not avaiilable in llvm 3.8.0
+    if (StringRef::npos != F->getName().find(ignoreListFunc)) { return true; }
+  }
+
+  return false;
+}
+
+#if USE_NEW_PM
+class CompareTransform : public PassInfoMixin<CompareTransform> {
+ public:
+  CompareTransform() {
+#else
+
+class CompareTransform : public ModulePass {
+ public:
+  static char ID;
+  CompareTransform() : ModulePass(ID) {
+#endif
+    initInstrumentList();
+  }
+
+#if USE_NEW_PM
+  PreservedAnalyses run(Module &M, ModuleAnalysisManager &MAM);
+#else
+  bool        runOnModule(Module &M) override;
+
+  #if LLVM_VERSION_MAJOR < 4
+  const char *getPassName() const override {
+  #else
+  StringRef getPassName() const override {
+  #endif
+    return "transforms compare functions";
+  }
+#endif
+
+ private:
+  bool transformCmps(Module &M);
+};
+
+}  // namespace
+
+#if USE_NEW_PM
+extern "C" ::llvm::PassPluginLibraryInfo LLVM_ATTRIBUTE_WEAK
+llvmGetPassPluginInfo() {
+  return {LLVM_PLUGIN_API_VERSION, "CompareTransform", "v0.1",
+          [](PassBuilder &PB) {
+  #if LLVM_VERSION_MAJOR <= 13
+            using OptimizationLevel = typename PassBuilder::OptimizationLevel;
+  #endif
+            PB.registerOptimizerLastEPCallback(
+                [](ModulePassManager &MPM, OptimizationLevel OL) {
+                  MPM.addPass(CompareTransform());
+                });
+          }};
+}
+#else
+char CompareTransform::ID = 0;
+#endif
+
+/* A call to one of the compare functions, with one constant string operand */
+struct ConstCmpCall {
+  CallInst   *call;
+  Value      *varStr;
+  std::string constStr;
+  bool        constIsFirst;
+  bool        caseInsensitive;
+};
+
+bool CompareTransform::transformCmps(Module &M) {
+  std::vector<ConstCmpCall> calls;
+  LLVMContext              &C = M.getContext();
+
+  IntegerType *Int8Ty = IntegerType::getInt8Ty(C);
+  IntegerType *Int32Ty = IntegerType::getInt32Ty(C);
+  PointerType *i8PtrTy = PointerType::get(Int8Ty, 0);
+
+#if LLVM_VERSION_MAJOR < 9
+  Constant *
+#else
+  FunctionCallee
+#endif
+      c = M.getOrInsertFunction("tolower", Int32Ty, Int32Ty
+#if LLVM_VERSION_MAJOR < 5
+                                ,
+                                NULL
+#endif
+      );
+#if LLVM_VERSION_MAJOR < 9
+  Function *tolowerFn = cast<Function>(c);
+#else
+  FunctionCallee tolowerFn = c;
+#endif
+
+  /* iterate over all functions, bbs and instructions and collect the calls */
+  for (auto &F : M) {
+    if (isIgnoreFunction(&F) || !isInInstrumentList(&F)) { continue; }
+
+    for (auto &BB : F) {
+      for (auto &IN : BB) {
+        CallInst *callInst = nullptr;
+
+        if (!(callInst = dyn_cast<CallInst>(&IN))) { continue; }
+
+        Function *Callee = callInst->getCalledFunction();
+        if (!Callee) { continue; }
+        if (callInst->getCallingConv() != llvm::CallingConv::C) { continue; }
+
+        StringRef name = Callee->getName();
+        bool      isStrcmp = name == "strcmp";
+        bool      isStrncmp = name == "strncmp";
+        bool      isMemcmp = name == "memcmp" || name == "bcmp";
+        bool      isStrcasecmp = name == "strcasecmp";
+        bool      isStrncasecmp = name == "strncasecmp";
+        if (!isStrcmp && !isStrncmp && !isMemcmp && !isStrcasecmp &&
+            !isStrncasecmp) {
+          continue;
+        }
+
+        bool          hasLen = isStrncmp || isMemcmp || isStrncasecmp;
+        FunctionType *FT = Callee->getFunctionType();
+        if (FT->getNumParams() != (hasLen ? 3u : 2u) ||
+            !FT->getReturnType()->isIntegerTy() ||
+            !FT->getParamType(0)->isPointerTy() ||
+            !FT->getParamType(1)->isPointerTy()) {
+          continue;
+        }
+
+        Value    *str1P = callInst->getArgOperand(0);
+        Value    *str2P = callInst->getArgOperand(1);
+        StringRef str1, str2;
+        // memcmp may compare past a null byte, so keep the whole constant
+        bool trimAtNul = !isMemcmp;
+#if LLVM_VERSION_MAJOR >= 17
+        bool isConst1 = getConstantStringInfo(str1P, str1, trimAtNul);
+        bool isConst2 = getConstantStringInfo(str2P, str2, trimAtNul);
+#else
+        bool isConst1 = getConstantStringInfo(str1P, str1, 0, trimAtNul);
+        bool isConst2 = getConstantStringInfo(str2P, str2, 0, trimAtNul);
+#endif
+
+        // Nothing to learn if both or none of the strings are known
+        if (isConst1 == isConst2) { continue; }
+
+        std::string constStr = (isConst1 ? str1 : str2).str();
+        uint64_t    cmpLen;
+        if (hasLen) {
+          ConstantInt *lenArg =
+              dyn_cast<ConstantInt>(callInst->getArgOperand(2));
+          if (!lenArg) { continue; }
+          cmpLen = lenArg->getZExtValue();
+          if (isMemcmp) {
+            if (cmpLen > constStr.size()) { continue; }
+          } else {
+            cmpLen = std::min<uint64_t>(cmpLen, constStr.size() + 1);
+          }
+        } else {
+          cmpLen = constStr.size() + 1;
+        }
+        if (!cmpLen) { continue; }
+
+        // The trailing null byte is compared as well for the str* functions
+        constStr.resize(cmpLen, '\0');
+
+        calls.push_back({callInst, isConst1 ? str2P : str1P, constStr,
+                         isConst1, isStrcasecmp || isStrncasecmp});
+      }
+    }
+  }
+
+  if (!calls.size()) { return false; }
+
+  for (auto &cmpCall : calls) {
+    CallInst   *callInst = cmpCall.call;
+    BasicBlock *bb = callInst->getParent();
+    Function   *F = bb->getParent();
+    Type       *retTy = callInst->getType();
+
+    /* The call is replaced by a chain of blocks, each comparing one byte.
+       The first differing byte jumps to the end, with the difference. */
+    BasicBlock *endBB = bb->splitBasicBlock(BasicBlock::iterator(callInst));
+    bb->getTerminator()->eraseFromParent();
+
+    PHINode *PN =
+        PHINode::Create(retTy, cmpCall.constStr.size(), "", &endBB->front());
+
+    BasicBlock *curBB = bb;
+    for (uint64_t i = 0; i < cmpCall.constStr.size(); i++) {
+      IRBuilder<> IRB(curBB);
+
+      Value *varPtr = IRB.CreatePointerCast(cmpCall.varStr, i8PtrTy);
+      Value *varChar = IRB.CreateLoad(
+#if LLVM_VERSION_MAJOR >= 14
+          Int8Ty,
+#endif
+          IRB.CreateConstInBoundsGEP1_64(Int8Ty, varPtr, i));
+      Value *varInt = IRB.CreateZExt(varChar, Int32Ty);
+
+      unsigned char constChar = cmpCall.constStr[i];
+      if (cmpCall.caseInsensitive) {
+        varInt = IRB.CreateCall(tolowerFn, {varInt});
+        constChar = tolower(constChar);
+      }
+      Value *constInt = ConstantInt::get(Int32Ty, constChar);
+
+      Value *diff = cmpCall.constIsFirst ? IRB.CreateSub(constInt, varInt)
+                                         : IRB.CreateSub(varInt, constInt);
+      PN->addIncoming(IRB.CreateSExtOrTrunc(diff, retTy), curBB);
+
+      if (i + 1 == cmpCall.constStr.size()) {
+        IRB.CreateBr(endBB);
+      } else {
+        BasicBlock *nextBB =
+            BasicBlock::Create(C, "cmp_transform_byte", F, endBB);
+        Value *isDiff = IRB.CreateICmpNE(diff, ConstantInt::get(Int32Ty, 0));
+        IRB.CreateCondBr(isDiff, endBB, nextBB);
+        curBB = nextBB;
+      }
+    }
+
+    callInst->replaceAllUsesWith(PN);
+    callInst->eraseFromParent();
+  }
+
+  return true;
+}
+
+#if USE_NEW_PM
+PreservedAnalyses CompareTransform::run(Module &M, ModuleAnalysisManager &MAM) {
+#else
+bool CompareTransform::runOnModule(Module &M) {
+#endif
+  bool modified = transformCmps(M);
+
+#if USE_NEW_PM
+  auto PA = modified ? PreservedAnalyses::none() : PreservedAnalyses::all();
+#endif
+  verifyModule(M);
+
+#if USE_NEW_PM
+  return PA;
+#else
+  return modified;
+#endif
+}
+
+#if USE_NEW_PM
+#else
+  #if LLVM_VERSION_MAJOR < 11 /* use old pass manager */
+static void registerCompareTransformPass(const PassManagerBuilder &,
+                                         legacy::PassManagerBase &PM) {
+  auto p = new CompareTransform();
+  PM.add(p);
+}
+
+static RegisterStandardPasses RegisterCompareTransformPass(
+    PassManagerBuilder::EP_OptimizerLast, registerCompareTransformPass);
+
+static RegisterStandardPasses RegisterCompareTransformPass0(
+    PassManagerBuilder::EP_EnabledOnOptLevel0, registerCompareTransformPass);
+
+static RegisterStandardPasses RegisterCompareTransformPassLTO(
+    PassManagerBuilder::EP_FullLinkTimeOptimizationLast,
+    registerCompareTransformPass);
+
+  #endif
+#endif
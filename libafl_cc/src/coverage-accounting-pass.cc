@@ -15,6 +15,7 @@
 */
 
 #include "common-llvm.h"
+#include "instrument-list.h"
 
 #include <time.h>
 
@@ -192,7 +193,7 @@ class AFLCoverage : public ModulePass {
                       .Case("BB", BB_GRAN)
                       .Case("FUNC", FUNC_GRAN)
                       .Default(UKNOWN_GRAN);
-    // initInstrumentList();
+    initInstrumentList();
   }
 
 #ifdef USE_NEW_PM
@@ -298,7 +299,7 @@ bool AFLCoverage::runOnModule(Module &M) {
       fprintf(stderr, "FUNCTION: %s (%zu)\n", F.getName().str().c_str(),
               F.size());
 
-    // if (!isInInstrumentList(&F)) { continue; }
+    if (!isInInstrumentList(&F)) { continue; }
 
     if (F.size() < function_minimum_size) { continue; }
 
@@ -0,0 +1,42 @@
+// The instrumented part of the DataFlowSanitizer taint tracking of the
+// `dfsan` feature of libafl_targets.
+//
+// libafl_cc builds this with DFSan and links it into the targets built with
+// its `DataFlowSanitizer` configuration. The uninstrumented runtime in
+// libafl_targets calls libafl_dfsan_run for each window of input bytes,
+// which labels the bytes and runs the instrumented harness, so the harness
+// itself is instrumented like the rest of the target.
+
+#include <stddef.h>
+#include <stdint.h>
+
+#include <sanitizer/dfsan_interface.h>
+
+int LLVMFuzzerTestOneInput(const uint8_t *data, size_t size);
+
+// Handed over in globals, which are never labeled, as an instrumented
+// function called from uninstrumented code would read stale argument labels
+static uint8_t *window_data;
+static size_t   window_len;
+static size_t   window_start;
+static size_t   window_size;
+
+__attribute__((noinline)) static void run_window(void) {
+  dfsan_set_label(0, window_data, window_len);
+  for (size_t i = 0; i < window_size; i++) {
+    dfsan_set_label((dfsan_label)(1 << i), window_data + window_start + i, 1);
+  }
+
+  LLVMFuzzerTestOneInput(window_data, window_len);
+}
+
+// Labels the `size` bytes of `data` from `start` on, one label per byte,
+// and runs the harness on the `len` bytes of `data`.
+// Uninstrumented, see dfsan_abilist.txt.
+void libafl_dfsan_run(uint8_t *data, size_t len, size_t start, size_t size) {
+  window_data = data;
+  window_len = len;
+  window_start = start;
+  window_size = size;
+  run_window();
+}
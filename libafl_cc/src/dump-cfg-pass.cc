@@ -71,6 +71,8 @@
                                   \
   } while (0)
 
+#include "instrument-list.h"
+
 using namespace llvm;
 
 namespace {
@@ -86,6 +88,7 @@ class DumpCfgPass : public ModulePass {
 
   DumpCfgPass() : ModulePass(ID) {
 #endif
+    initInstrumentList();
   }
 
 #if USE_NEW_PM
@@ -142,6 +145,8 @@ bool DumpCfgPass::runOnModule(Module &M) {
   auto         moduleName = M.getName();
 
   for (auto &F : M) {
+    if (!isInInstrumentList(&F)) { continue; }
+
     unsigned bb_cnt = 0;
     entry_bb[F.getName()] = &F.getEntryBlock();
     for (auto &BB : F) {
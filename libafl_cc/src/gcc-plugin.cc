@@ -0,0 +1,381 @@
+/*
+   LibAFL - GCC plugin for edge coverage and CmpLog
+   ------------------------------------------------
+
+   Based on the GCC plugins of AFL++ (afl-gcc-pass.so.cc and
+   afl-gcc-cmplog-pass.so.cc),
+   Copyright 2019-2020 AFLplusplus Project. All rights reserved.
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at:
+
+     http://www.apache.org/licenses/LICENSE-2.0
+
+   This plugin is loaded into GCC by the GccWrapper of libafl_cc, through
+   -fplugin. It emits the same calls as clang does for the runtime of
+   libafl_targets:
+
+     -fplugin-arg-libafl_gcc_plugin-coverage
+
+       Each basic block, after splitting the critical edges, gets a static
+       guard holding its index in the edges map and calls
+       __sanitizer_cov_trace_pc_guard with it, as -fsanitize-coverage=trace-pc-guard
+       does. The guards are set at compile time, from a hash of the source
+       file and of a per file counter, so no guard init call is needed.
+
+     -fplugin-arg-libafl_gcc_plugin-cmplog
+
+       Integer comparisons and switch cases of 1, 2, 4 and 8 bytes call
+       __cmplog_ins_hook{1,2,4,8}, like the cmplog-instructions-pass.
+
+*/
+
+#include "gcc-plugin.h"
+#include "plugin-version.h"
+#include "tree.h"
+#include "tree-pass.h"
+#include "context.h"
+#include "function.h"
+#include "basic-block.h"
+#include "cfghooks.h"
+#include "tree-ssa-alias.h"
+#include "gimple-expr.h"
+#include "gimple.h"
+#include "gimple-iterator.h"
+#include "stringpool.h"
+#include "cgraph.h"
+#include "stor-layout.h"
+#include "diagnostic.h"
+
+#include <stdio.h>
+#include <string.h>
+
+#ifndef LIBAFL_EDGES_MAP_SIZE
+  #define LIBAFL_EDGES_MAP_SIZE 65536
+#endif
+
+/* Attributes of the comparisons, as in cmplog.h of libafl_targets */
+#define CMP_TYPE_INS 0
+#define CMP_EQ 1
+#define CMP_GT 2
+#define CMP_LT 4
+
+int plugin_is_GPL_compatible = 1;
+
+static struct plugin_info libafl_plugin_info = {
+    "0.1", "LibAFL edge coverage and CmpLog for GCC"};
+
+static bool enable_coverage = false;
+static bool enable_cmplog = false;
+
+/* Counts the guards of this compilation unit */
+static unsigned int guard_counter = 0;
+
+static bool isIgnoreFunction(const char *name) {
+  // Starting from "LLVMFuzzer" these are functions used in libfuzzer based
+  // fuzzing campaign installations, e.g. oss-fuzz
+  static const char *ignoreList[] = {
+
+      "asan.",
+      "llvm.",
+      "sancov.",
+      "__ubsan",
+      "ign.",
+      "__afl",
+      "_fini",
+      "__libc_",
+      "__asan",
+      "__msan",
+      "__cmplog",
+      "__sancov",
+      "__san",
+      "__cxx_",
+      "__decide_deferred",
+      "_GLOBAL",
+      "_ZZN6__asan",
+      "_ZZN6__lsan",
+      "msan.",
+      "LLVMFuzzerM",
+      "LLVMFuzzerC",
+      "LLVMFuzzerI",
+      "maybe_duplicate_stderr",
+      "discard_output",
+      "close_fd_mask",
+      "ExecuteFilesOnyByOne"
+
+  };
+
+  for (auto const &ignoreListFunc : ignoreList) {
+    if (!strncmp(name, ignoreListFunc, strlen(ignoreListFunc))) { return true; }
+  }
+
+  return false;
+}
+
+/* FNV-1a, to spread the guards of different compilation units */
+static unsigned int hashGuard(const char *file, unsigned int counter) {
+  unsigned int hash = 2166136261u;
+  for (const char *c = file; *c; c++) {
+    hash ^= (unsigned char)*c;
+    hash *= 16777619u;
+  }
+  for (int i = 0; i < 4; i++) {
+    hash ^= (counter >> (8 * i)) & 0xff;
+    hash *= 16777619u;
+  }
+  return hash;
+}
+
+static tree getTracePcGuard() {
+  static tree decl = NULL_TREE;
+  if (decl == NULL_TREE) {
+    tree fntype = build_function_type_list(
+        void_type_node, build_pointer_type(unsigned_type_node), NULL_TREE);
+    decl = build_fn_decl("__sanitizer_cov_trace_pc_guard", fntype);
+    TREE_NOTHROW(decl) = 1;
+  }
+  return decl;
+}
+
+/* __cmplog_ins_hook{1,2,4,8}, by the size of the operands in bytes */
+static tree getCmplogHook(unsigned int size) {
+  static tree decls[4] = {NULL_TREE, NULL_TREE, NULL_TREE, NULL_TREE};
+  unsigned int idx = exact_log2(size);
+  if (decls[idx] == NULL_TREE) {
+    char name[32];
+    snprintf(name, sizeof(name), "__cmplog_ins_hook%u", size);
+    tree type = build_nonstandard_integer_type(size * 8, 1);
+    tree fntype = build_function_type_list(void_type_node, type, type,
+                                           unsigned_char_type_node, NULL_TREE);
+    decls[idx] = build_fn_decl(name, fntype);
+    TREE_NOTHROW(decls[idx]) = 1;
+  }
+  return decls[idx];
+}
+
+/* A static guard, initialized to its index in the edges map */
+static tree buildGuard() {
+  char name[32];
+  snprintf(name, sizeof(name), "__libafl_guard_%u", guard_counter);
+
+  unsigned int id =
+      hashGuard(main_input_filename ? main_input_filename : "", guard_counter) %
+      LIBAFL_EDGES_MAP_SIZE;
+  guard_counter++;
+
+  tree guard = build_decl(UNKNOWN_LOCATION, VAR_DECL, get_identifier(name),
+                          unsigned_type_node);
+  TREE_STATIC(guard) = 1;
+  TREE_PUBLIC(guard) = 0;
+  TREE_USED(guard) = 1;
+  TREE_ADDRESSABLE(guard) = 1;
+  DECL_ARTIFICIAL(guard) = 1;
+  DECL_INITIAL(guard) = build_int_cst(unsigned_type_node, id);
+  varpool_node::finalize_decl(guard);
+
+  return guard;
+}
+
+/* The cmplog attribute of a comparison, or -1 for those not logged */
+static int cmpAttribute(enum tree_code code) {
+  switch (code) {
+    case EQ_EXPR:
+      return CMP_EQ;
+    case NE_EXPR:
+      return CMP_TYPE_INS;
+    case GT_EXPR:
+      return CMP_GT;
+    case GE_EXPR:
+      return CMP_GT | CMP_EQ;
+    case LT_EXPR:
+      return CMP_LT;
+    case LE_EXPR:
+      return CMP_LT | CMP_EQ;
+    default:
+      return -1;
+  }
+}
+
+/* The size in bytes of a comparison operand, or 0 if it is not logged */
+static unsigned int cmpOperandSize(tree op) {
+  tree type = TREE_TYPE(op);
+  if (TREE_CODE(type) == BOOLEAN_TYPE) { return 0; }
+  if (!INTEGRAL_TYPE_P(type) && !POINTER_TYPE_P(type)) { return 0; }
+  if (!TYPE_SIZE_UNIT(type) || !tree_fits_uhwi_p(TYPE_SIZE_UNIT(type))) {
+    return 0;
+  }
+
+  unsigned HOST_WIDE_INT size = tree_to_uhwi(TYPE_SIZE_UNIT(type));
+  switch (size) {
+    case 1:
+    case 2:
+    case 4:
+    case 8:
+      return size;
+    default:
+      return 0;
+  }
+}
+
+/* Converts an operand to the parameter type of the hook */
+static tree convertOperand(gimple_stmt_iterator *gsi, tree op, tree type) {
+  if (TREE_CODE(op) == INTEGER_CST) { return fold_convert(type, op); }
+
+  tree tmp = create_tmp_var(type, "libafl_cmp");
+  gimple *conv = gimple_build_assign(tmp, NOP_EXPR, op);
+  gsi_insert_before(gsi, conv, GSI_SAME_STMT);
+  return tmp;
+}
+
+/* Calls the hook before the statement at gsi */
+static void insertCmplogCall(gimple_stmt_iterator *gsi, tree lhs, tree rhs,
+                             int attr) {
+  unsigned int size = cmpOperandSize(lhs);
+  if (!size || size != cmpOperandSize(rhs)) { return; }
+
+  tree hook = getCmplogHook(size);
+  tree type = TREE_VALUE(TYPE_ARG_TYPES(TREE_TYPE(hook)));
+
+  tree arg1 = convertOperand(gsi, lhs, type);
+  tree arg2 = convertOperand(gsi, rhs, type);
+  gcall *call = gimple_build_call(hook, 3, arg1, arg2,
+                                  build_int_cst(unsigned_char_type_node, attr));
+  gsi_insert_before(gsi, call, GSI_SAME_STMT);
+}
+
+static void instrumentCmplog(function *fun) {
+  basic_block bb;
+  FOR_EACH_BB_FN(bb, fun) {
+    for (gimple_stmt_iterator gsi = gsi_start_bb(bb); !gsi_end_p(gsi);
+         gsi_next(&gsi)) {
+      gimple *stmt = gsi_stmt(gsi);
+
+      switch (gimple_code(stmt)) {
+        case GIMPLE_COND: {
+          int attr = cmpAttribute(gimple_cond_code(stmt));
+          if (attr < 0) { break; }
+          insertCmplogCall(&gsi, gimple_cond_lhs(stmt), gimple_cond_rhs(stmt),
+                           attr);
+          break;
+        }
+
+        case GIMPLE_ASSIGN: {
+          enum tree_code code = gimple_assign_rhs_code(stmt);
+          if (TREE_CODE_CLASS(code) != tcc_comparison) { break; }
+          int attr = cmpAttribute(code);
+          if (attr < 0) { break; }
+          insertCmplogCall(&gsi, gimple_assign_rhs1(stmt),
+                           gimple_assign_rhs2(stmt), attr);
+          break;
+        }
+
+        case GIMPLE_SWITCH: {
+          gswitch *sw = as_a<gswitch *>(stmt);
+          tree     index = gimple_switch_index(sw);
+          // label 0 is the default one
+          for (unsigned int i = 1; i < gimple_switch_num_labels(sw); i++) {
+            tree label = gimple_switch_label(sw, i);
+            tree low = CASE_LOW(label);
+            if (!low) { continue; }
+            insertCmplogCall(&gsi, index, fold_convert(TREE_TYPE(index), low),
+                             CMP_EQ);
+          }
+          break;
+        }
+
+        default:
+          break;
+      }
+    }
+  }
+}
+
+static void instrumentCoverage(function *fun) {
+  basic_block bb;
+
+  // Split the critical edges, so that each edge gets a block of its own
+  auto_vec<edge> critical;
+  FOR_EACH_BB_FN(bb, fun) {
+    edge          e;
+    edge_iterator ei;
+    FOR_EACH_EDGE(e, ei, bb->succs) {
+      if (EDGE_CRITICAL_P(e) && !(e->flags & EDGE_ABNORMAL)) {
+        critical.safe_push(e);
+      }
+    }
+  }
+  unsigned int i;
+  edge         e;
+  FOR_EACH_VEC_ELT(critical, i, e) {
+    split_edge(e);
+  }
+
+  FOR_EACH_BB_FN(bb, fun) {
+    tree   guard = buildGuard();
+    gcall *call = gimple_build_call(getTracePcGuard(), 1,
+                                    build_fold_addr_expr(guard));
+
+    gimple_stmt_iterator gsi = gsi_after_labels(bb);
+    gsi_insert_before(&gsi, call, GSI_SAME_STMT);
+  }
+}
+
+static const pass_data libafl_pass_data = {
+    GIMPLE_PASS,   /* type */
+    "libafl",      /* name */
+    OPTGROUP_NONE, /* optinfo_flags */
+    TV_NONE,       /* tv_id */
+    PROP_cfg,      /* properties_required */
+    0,             /* properties_provided */
+    0,             /* properties_destroyed */
+    0,             /* todo_flags_start */
+    0,             /* todo_flags_finish */
+};
+
+/* Runs right before the SSA form is built, on the lowered GIMPLE and CFG */
+class LibAFLPass : public gimple_opt_pass {
+ public:
+  LibAFLPass(gcc::context *ctxt) : gimple_opt_pass(libafl_pass_data, ctxt) {
+  }
+
+  unsigned int execute(function *fun) override {
+    if (isIgnoreFunction(function_name(fun))) { return 0; }
+
+    if (enable_cmplog) { instrumentCmplog(fun); }
+    if (enable_coverage) { instrumentCoverage(fun); }
+
+    return 0;
+  }
+};
+
+int plugin_init(struct plugin_name_args   *info,
+                struct plugin_gcc_version *version) {
+  if (!plugin_default_version_check(version, &gcc_version)) {
+    error("the LibAFL GCC plugin was built for GCC %s", gcc_version.basever);
+    return 1;
+  }
+
+  for (int i = 0; i < info->argc; i++) {
+    if (!strcmp(info->argv[i].key, "coverage")) {
+      enable_coverage = true;
+    } else if (!strcmp(info->argv[i].key, "cmplog")) {
+      enable_cmplog = true;
+    } else {
+      error("unknown argument %s of the LibAFL GCC plugin", info->argv[i].key);
+      return 1;
+    }
+  }
+
+  struct register_pass_info pass_info;
+  pass_info.pass = new LibAFLPass(g);
+  pass_info.reference_pass_name = "ssa";
+  pass_info.ref_pass_instance_number = 1;
+  pass_info.pos_op = PASS_POS_INSERT_BEFORE;
+
+  register_callback(info->base_name, PLUGIN_INFO, NULL, &libafl_plugin_info);
+  register_callback(info->base_name, PLUGIN_PASS_MANAGER_SETUP, NULL,
+                    &pass_info);
+
+  return 0;
+}
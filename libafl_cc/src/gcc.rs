@@ -0,0 +1,484 @@
+//! GCC compiler Wrapper from `LibAFL`
+
+use std::{
+    convert::Into,
+    path::{Path, PathBuf},
+    str::FromStr,
+    string::String,
+    vec::Vec,
+};
+
+use crate::{CompilerWrapper, Error, ToolWrapper, LIB_EXT, LIB_PREFIX};
+
+include!(concat!(env!("OUT_DIR"), "/gcc_constants.rs"));
+
+/// The name `gcc` knows the `LibAFL` plugin by, used in its `-fplugin-arg-` args
+const GCC_PLUGIN_NAME: &str = "libafl_gcc_plugin";
+
+/// Wrap GCC, instrumenting through the `LibAFL` GCC plugin.
+///
+/// The plugin calls the same runtime as `clang` does, `__sanitizer_cov_trace_pc_guard` for
+/// the edge coverage and `__cmplog_ins_hook{1,2,4,8}` for the `CmpLog` instructions,
+/// so the targets link against `libafl_targets` as if built by the [`crate::ClangWrapper`].
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Debug)]
+pub struct GccWrapper {
+    is_silent: bool,
+    optimize: bool,
+    wrapped_cc: String,
+    wrapped_cxx: String,
+
+    name: String,
+    is_cpp: bool,
+    linking: bool,
+    shared: bool,
+    x_set: bool,
+    need_libafl_arg: bool,
+    has_libafl_arg: bool,
+    coverage: bool,
+    cmplog: bool,
+
+    output: Option<PathBuf>,
+    configurations: Vec<crate::Configuration>,
+    ignoring_configurations: bool,
+    parse_args_called: bool,
+    base_args: Vec<String>,
+    cc_args: Vec<String>,
+    link_args: Vec<String>,
+}
+
+#[allow(clippy::match_same_arms)] // for the linking = false wip for "shared"
+impl ToolWrapper for GccWrapper {
+    #[allow(clippy::too_many_lines)]
+    fn parse_args<S>(&mut self, args: &[S]) -> Result<&'_ mut Self, Error>
+    where
+        S: AsRef<str>,
+    {
+        let mut new_args: Vec<String> = vec![];
+        if args.is_empty() {
+            return Err(Error::InvalidArguments(
+                "The number of arguments cannot be 0".to_string(),
+            ));
+        }
+
+        if self.parse_args_called {
+            return Err(Error::Unknown(
+                "ToolWrapper::parse_args cannot be called twice on the same instance".to_string(),
+            ));
+        }
+        self.parse_args_called = true;
+
+        if args.len() == 1 {
+            return Err(Error::InvalidArguments(
+                "LibAFL Tool wrapper - no commands specified. Use me as compiler.".to_string(),
+            ));
+        }
+
+        self.name = args[0].as_ref().to_string();
+        // Detect C++ compiler looking at the wrapper name
+        self.is_cpp = self.is_cpp || self.name.ends_with("++");
+
+        let mut linking = true;
+        let mut shared = false;
+        // Detect stray -v calls from ./configure scripts.
+        if args.len() > 1 && args[1].as_ref() == "-v" {
+            if args.len() == 2 {
+                self.base_args.push(args[1].as_ref().into());
+                return Ok(self);
+            }
+            linking = false;
+        }
+
+        let mut suppress_linking = 0;
+        let mut i = 1;
+        while i < args.len() {
+            match args[i].as_ref() {
+                "--libafl-no-link" => {
+                    suppress_linking += 1;
+                    self.has_libafl_arg = true;
+                    i += 1;
+                    continue;
+                }
+                "--libafl" => {
+                    suppress_linking += 1337;
+                    self.has_libafl_arg = true;
+                    i += 1;
+                    continue;
+                }
+                "-Wl,-z,defs" | "-Wl,--no-undefined" | "--no-undefined" => {
+                    i += 1;
+                    continue;
+                }
+                "-z" | "-Wl,-z" => {
+                    if i + 1 < args.len()
+                        && (args[i + 1].as_ref() == "defs" || args[i + 1].as_ref() == "-Wl,defs")
+                    {
+                        i += 2;
+                        continue;
+                    }
+                }
+                "--libafl-ignore-configurations" => {
+                    self.ignoring_configurations = true;
+                    i += 1;
+                    continue;
+                }
+                "--libafl-configurations" => {
+                    if i + 1 < args.len() {
+                        self.configurations.extend(
+                            args[i + 1]
+                                .as_ref()
+                                .split(',')
+                                .map(|x| crate::Configuration::from_str(x).unwrap()),
+                        );
+                        i += 2;
+                        continue;
+                    }
+                }
+                "-o" => {
+                    if i + 1 < args.len() {
+                        self.output = Some(PathBuf::from(args[i + 1].as_ref()));
+                        i += 2;
+                        continue;
+                    }
+                }
+                "-x" => self.x_set = true,
+                "-c" | "-S" | "-E" => linking = false,
+                "-shared" => {
+                    linking = false;
+                    shared = true;
+                }
+                _ => (),
+            };
+            new_args.push(args[i].as_ref().to_string());
+            i += 1;
+        }
+        if linking
+            && (suppress_linking > 0 || (self.has_libafl_arg && suppress_linking == 0))
+            && suppress_linking < 1337
+        {
+            linking = false;
+            new_args.push(
+                PathBuf::from(env!("OUT_DIR"))
+                    .join(format!("{LIB_PREFIX}no-link-rt.{LIB_EXT}"))
+                    .into_os_string()
+                    .into_string()
+                    .unwrap(),
+            );
+        }
+
+        self.linking = linking;
+        self.shared = shared;
+
+        if self.optimize {
+            new_args.push("-g".into());
+            new_args.push("-O3".into());
+            new_args.push("-funroll-loops".into());
+        }
+
+        // Fuzzing define common among tools
+        new_args.push("-DFUZZING_BUILD_MODE_UNSAFE_FOR_PRODUCTION=1".into());
+
+        // required by timer API (timer_create, timer_settime)
+        #[cfg(target_os = "linux")]
+        if linking {
+            new_args.push("-lrt".into());
+        }
+
+        self.base_args.extend(new_args);
+        Ok(self)
+    }
+
+    fn add_arg<S>(&mut self, arg: S) -> &'_ mut Self
+    where
+        S: AsRef<str>,
+    {
+        self.base_args.push(arg.as_ref().to_string());
+        self
+    }
+
+    fn add_configuration(&mut self, configuration: crate::Configuration) -> &'_ mut Self {
+        self.configurations.push(configuration);
+        self
+    }
+
+    fn configurations(&self) -> Result<Vec<crate::Configuration>, Error> {
+        let mut configs = self.configurations.clone();
+        configs.reverse();
+        Ok(configs)
+    }
+
+    fn ignore_configurations(&self) -> Result<bool, Error> {
+        Ok(self.ignoring_configurations)
+    }
+
+    fn command(&mut self) -> Result<Vec<String>, Error> {
+        self.command_for_configuration(crate::Configuration::Default)
+    }
+
+    fn command_for_configuration(
+        &mut self,
+        configuration: crate::Configuration,
+    ) -> Result<Vec<String>, Error> {
+        let mut args = vec![];
+
+        if self.is_cpp {
+            args.push(self.wrapped_cxx.clone());
+        } else {
+            args.push(self.wrapped_cc.clone());
+        }
+
+        let rename = |arg: &String, extensions: &[&str]| {
+            let arg_as_path = PathBuf::from(arg);
+            if arg.ends_with('.') {
+                return arg.clone();
+            }
+            match arg_as_path.extension() {
+                Some(extension)
+                    if extensions.contains(&&extension.to_string_lossy().to_lowercase()[..]) =>
+                {
+                    configuration.replace_extension(&arg_as_path)
+                }
+                _ => arg_as_path,
+            }
+            .into_os_string()
+            .into_string()
+            .unwrap()
+        };
+
+        let base_args = self
+            .base_args
+            .iter()
+            .map(|arg| rename(arg, &["a", "la"]))
+            .collect::<Vec<_>>();
+
+        if let Some(output) = self.output.clone() {
+            let output = configuration.replace_extension(&output);
+            let new_filename = output.into_os_string().into_string().unwrap();
+            args.push("-o".to_string());
+            args.push(new_filename);
+            args.extend(base_args);
+        } else {
+            // No output specified, we need to rewrite the single .c file's name.
+            args.extend(
+                base_args
+                    .iter()
+                    .map(|arg| rename(arg, &["c", "cc", "cxx", "cpp"])),
+            );
+        }
+
+        args.extend_from_slice(&configuration.to_flags()?);
+
+        if self.need_libafl_arg && !self.has_libafl_arg {
+            return Ok(args);
+        }
+
+        if self.coverage || self.cmplog {
+            if !LIBAFL_CC_GCC_PLUGIN {
+                return Err(Error::Unknown(
+                    "The LibAFL GCC plugin was not built, install the GCC plugin headers and rebuild libafl_cc".to_string(),
+                ));
+            }
+            args.push(format!("-fplugin={}", Self::plugin_path().display()));
+            if self.coverage {
+                args.push(format!("-fplugin-arg-{GCC_PLUGIN_NAME}-coverage"));
+            }
+            if self.cmplog {
+                args.push(format!("-fplugin-arg-{GCC_PLUGIN_NAME}-cmplog"));
+            }
+        }
+
+        if self.linking {
+            if self.x_set {
+                args.push("-x".into());
+                args.push("none".into());
+            }
+
+            args.extend_from_slice(self.link_args.as_slice());
+
+            if cfg!(unix) {
+                args.push("-pthread".into());
+                args.push("-ldl".into());
+                args.push("-lm".into());
+            }
+        } else {
+            args.extend_from_slice(self.cc_args.as_slice());
+        }
+
+        Ok(args)
+    }
+
+    fn is_linking(&self) -> bool {
+        self.linking
+    }
+
+    fn filter(&self, args: &mut Vec<String>) {
+        args.retain(|x| x != "-Werror");
+    }
+
+    fn silence(&mut self, value: bool) -> &'_ mut Self {
+        self.is_silent = value;
+        self
+    }
+
+    fn is_silent(&self) -> bool {
+        self.is_silent
+    }
+}
+
+impl CompilerWrapper for GccWrapper {
+    fn add_cc_arg<S>(&mut self, arg: S) -> &'_ mut Self
+    where
+        S: AsRef<str>,
+    {
+        self.cc_args.push(arg.as_ref().to_string());
+        self
+    }
+
+    fn add_link_arg<S>(&mut self, arg: S) -> &'_ mut Self
+    where
+        S: AsRef<str>,
+    {
+        self.link_args.push(arg.as_ref().to_string());
+        self
+    }
+
+    fn link_staticlib<S>(&mut self, dir: &Path, name: S) -> &'_ mut Self
+    where
+        S: AsRef<str>,
+    {
+        let lib_file = dir
+            .join(format!("{LIB_PREFIX}{}.{LIB_EXT}", name.as_ref()))
+            .into_os_string()
+            .into_string()
+            .unwrap();
+
+        self.add_link_arg("-Wl,--whole-archive")
+            .add_link_arg(lib_file)
+            .add_link_arg("-Wl,--no-whole-archive")
+    }
+}
+
+impl Default for GccWrapper {
+    /// Create a new GCC Wrapper
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GccWrapper {
+    /// Create a new GCC Wrapper
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            optimize: true,
+            wrapped_cc: GCC_PATH.into(),
+            wrapped_cxx: GXX_PATH.into(),
+            name: String::new(),
+            is_cpp: false,
+            linking: false,
+            shared: false,
+            x_set: false,
+            need_libafl_arg: false,
+            has_libafl_arg: false,
+            coverage: false,
+            cmplog: false,
+            output: None,
+            configurations: vec![crate::Configuration::Default],
+            ignoring_configurations: false,
+            parse_args_called: false,
+            base_args: vec![],
+            cc_args: vec![],
+            link_args: vec![],
+            is_silent: false,
+        }
+    }
+
+    /// The path of the `LibAFL` GCC plugin, built if the GCC plugin headers were found
+    #[must_use]
+    pub fn plugin_path() -> PathBuf {
+        PathBuf::from(env!("OUT_DIR")).join(format!("{GCC_PLUGIN_NAME}.so"))
+    }
+
+    /// Sets the wrapped `cc` compiler
+    pub fn wrapped_cc(&mut self, cc: String) -> &'_ mut Self {
+        self.wrapped_cc = cc;
+        self
+    }
+
+    /// Sets the wrapped `cxx` compiler
+    pub fn wrapped_cxx(&mut self, cxx: String) -> &'_ mut Self {
+        self.wrapped_cxx = cxx;
+        self
+    }
+
+    /// Disable optimizations
+    pub fn dont_optimize(&mut self) -> &'_ mut Self {
+        self.optimize = false;
+        self
+    }
+
+    /// Set cpp mode
+    pub fn cpp(&mut self, value: bool) -> &'_ mut Self {
+        self.is_cpp = value;
+        self
+    }
+
+    /// Set if linking
+    pub fn linking(&mut self, value: bool) -> &'_ mut Self {
+        self.linking = value;
+        self
+    }
+
+    /// Set if it needs the --libafl arg to add the custom arguments to gcc
+    pub fn need_libafl_arg(&mut self, value: bool) -> &'_ mut Self {
+        self.need_libafl_arg = value;
+        self
+    }
+
+    /// Set if the plugin adds the edge coverage, calling `__sanitizer_cov_trace_pc_guard`
+    pub fn coverage(&mut self, value: bool) -> &'_ mut Self {
+        self.coverage = value;
+        self
+    }
+
+    /// Set if the plugin logs the integer compares, calling `__cmplog_ins_hook{1,2,4,8}`
+    pub fn cmplog(&mut self, value: bool) -> &'_ mut Self {
+        self.cmplog = value;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LIBAFL_CC_GCC_PLUGIN;
+    use crate::{GccWrapper, ToolWrapper};
+
+    #[test]
+    fn test_gcc_plugin_args() {
+        let mut cc = GccWrapper::new();
+        cc.parse_args(&["my-gcc", "-c", "main.c", "-o", "main.o"])
+            .unwrap();
+        let args = cc.command().unwrap();
+        assert!(!args.iter().any(|arg| arg.starts_with("-fplugin")));
+
+        let mut cc = GccWrapper::new();
+        cc.coverage(true)
+            .cmplog(true)
+            .parse_args(&["my-gcc", "-c", "main.c", "-o", "main.o"])
+            .unwrap();
+        if !LIBAFL_CC_GCC_PLUGIN {
+            assert!(cc.command().is_err());
+            return;
+        }
+        let args = cc.command().unwrap();
+        assert!(args
+            .iter()
+            .any(|arg| arg.starts_with("-fplugin=") && arg.ends_with("libafl_gcc_plugin.so")));
+        assert!(args
+            .iter()
+            .any(|arg| arg == "-fplugin-arg-libafl_gcc_plugin-coverage"));
+        assert!(args
+            .iter()
+            .any(|arg| arg == "-fplugin-arg-libafl_gcc_plugin-cmplog"));
+    }
+}
@@ -0,0 +1,173 @@
+/*
+   LibAFL - allowlist and denylist for the instrumentation
+   -------------------------------------------------------
+
+   Based on the instrument list support of AFL++ (afl-llvm-common.cc),
+   Copyright 2019-2020 AFLplusplus Project. All rights reserved.
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at:
+
+     http://www.apache.org/licenses/LICENSE-2.0
+
+   The lists are read from the files named by AFL_LLVM_ALLOWLIST and
+   AFL_LLVM_DENYLIST, in the format of AFL++:
+
+     # a comment
+     fun:parse_*        a function, also as "function:"
+     src:vendor/*.c     a source file, also as "source:"
+     foo.c              a source file, without prefix
+
+   Patterns are globs. Function patterns match the whole function name,
+   source file patterns the whole path or its end after a '/', so that
+   main.c matches src/main.c, but not src/domain.c. With an allowlist, only the listed functions and
+   the functions of the listed files are instrumented. With a denylist, all
+   but those are.
+
+*/
+
+#ifndef LIBAFL_INSTRUMENT_LIST_H
+#define LIBAFL_INSTRUMENT_LIST_H
+
+#include <stdlib.h>
+
+#include <algorithm>
+#include <fstream>
+#include <list>
+#include <string>
+
+#include "llvm/Config/llvm-config.h"
+#include "llvm/ADT/Twine.h"
+#include "llvm/IR/DebugInfoMetadata.h"
+#include "llvm/IR/Function.h"
+#include "llvm/IR/Module.h"
+#include "llvm/Support/ErrorHandling.h"
+#include "llvm/Support/GlobPattern.h"
+
+static std::list<std::string> allowListFiles;
+static std::list<std::string> allowListFunctions;
+static std::list<std::string> denyListFiles;
+static std::list<std::string> denyListFunctions;
+
+/* Reads the entries of one list file */
+static void readInstrumentList(const char *env, const char *path,
+                               std::list<std::string> &files,
+                               std::list<std::string> &functions) {
+  std::ifstream fileStream(path);
+  if (!fileStream) {
+    llvm::report_fatal_error(llvm::Twine("Unable to open ") + env + " " +
+                             path);
+  }
+
+  std::string line;
+  while (std::getline(fileStream, line)) {
+    std::string original_line = line;
+
+    std::size_t npos = line.find('#');
+    if (npos != std::string::npos) { line = line.substr(0, npos); }
+    line.erase(std::remove_if(line.begin(), line.end(), ::isspace),
+               line.end());
+
+    bool is_file = true;
+    if (line.compare(0, 4, "fun:") == 0) {
+      is_file = false;
+      line = line.substr(4);
+    } else if (line.compare(0, 9, "function:") == 0) {
+      is_file = false;
+      line = line.substr(9);
+    } else if (line.compare(0, 4, "src:") == 0) {
+      line = line.substr(4);
+    } else if (line.compare(0, 7, "source:") == 0) {
+      line = line.substr(7);
+    }
+
+    if (line.find(':') != std::string::npos) {
+      llvm::report_fatal_error(llvm::Twine("Invalid line in ") + env + ": " +
+                               original_line);
+    }
+    if (line.empty()) { continue; }
+
+    if (is_file) {
+      files.push_back(line);
+    } else {
+      functions.push_back(line);
+    }
+  }
+}
+
+/* Reads the allowlist and the denylist, once for all passes of this module */
+static void initInstrumentList() {
+  static bool initialized = false;
+  if (initialized) { return; }
+  initialized = true;
+
+  const char *allowlist = getenv("AFL_LLVM_ALLOWLIST");
+  const char *denylist = getenv("AFL_LLVM_DENYLIST");
+
+  if (allowlist) {
+    readInstrumentList("AFL_LLVM_ALLOWLIST", allowlist, allowListFiles,
+                       allowListFunctions);
+  }
+  if (denylist) {
+    readInstrumentList("AFL_LLVM_DENYLIST", denylist, denyListFiles,
+                       denyListFunctions);
+  }
+}
+
+/* If one of the glob patterns matches, the whole name, or with asPath, the
+   end of the name after a '/' as well */
+static bool matchesInstrumentList(const std::list<std::string> &patterns,
+                                  llvm::StringRef name, bool asPath) {
+  for (auto &pattern : patterns) {
+    auto glob = llvm::GlobPattern::create(pattern);
+    if (!glob) {
+      llvm::consumeError(glob.takeError());
+      continue;
+    }
+    if (glob->match(name)) { return true; }
+    if (!asPath) { continue; }
+
+    auto pathGlob = llvm::GlobPattern::create("*/" + pattern);
+    if (!pathGlob) {
+      llvm::consumeError(pathGlob.takeError());
+      continue;
+    }
+    if (pathGlob->match(name)) { return true; }
+  }
+
+  return false;
+}
+
+/* The source file of a function, from the debug info if there is some */
+static std::string getSourceName(llvm::Function *F) {
+  if (llvm::DISubprogram *SP = F->getSubprogram()) {
+    std::string filename = SP->getFilename().str();
+    if (!filename.empty()) { return filename; }
+  }
+
+  return F->getParent()->getSourceFileName();
+}
+
+/* If a function is to be instrumented, according to the lists */
+static bool isInInstrumentList(llvm::Function *F) {
+  std::string functionName = F->getName().str();
+
+  if (!denyListFiles.empty() || !denyListFunctions.empty()) {
+    if (matchesInstrumentList(denyListFunctions, functionName, false)) {
+      return false;
+    }
+    if (matchesInstrumentList(denyListFiles, getSourceName(F), true)) {
+      return false;
+    }
+  }
+
+  if (!allowListFiles.empty() || !allowListFunctions.empty()) {
+    return matchesInstrumentList(allowListFunctions, functionName, false) ||
+           matchesInstrumentList(allowListFiles, getSourceName(F), true);
+  }
+
+  return true;
+}
+
+#endif  // LIBAFL_INSTRUMENT_LIST_H
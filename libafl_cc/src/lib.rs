@@ -58,7 +58,7 @@
     )
 )]
 
-use std::{convert::Into, path::Path, process::Command, string::String, vec::Vec};
+use std::{convert::Into, ffi::OsString, path::Path, process::Command, string::String, vec::Vec};
 
 pub mod ar;
 pub use ar::ArWrapper;
@@ -66,6 +66,8 @@ pub mod cfg;
 pub use cfg::{CfgEdge, ControlFlowGraph, EntryBasicBlockInfo, HasWeight};
 pub mod clang;
 pub use clang::{ClangWrapper, LLVMPasses};
+pub mod gcc;
+pub use gcc::GccWrapper;
 pub mod libtool;
 pub use libtool::LibtoolWrapper;
 
@@ -95,6 +97,10 @@ pub enum Configuration {
     GenerateCoverageProfile,
     /// Instrumenting for cmplog/redqueen
     CmpLog,
+    /// Tracking the data flow from the input bytes to the compares with `DataFlowSanitizer`,
+    /// for the `dfsan` feature of `libafl_targets`. Needs LLVM 13 or newer.
+    /// Linking adds the `dfsan` driver of `libafl_cc`, which labels the input and runs the harness.
+    DataFlowSanitizer,
     /// A compound `Configuration`, made up of a list of other `Configuration`s
     Compound(Vec<Self>),
 }
@@ -120,6 +126,14 @@ impl Configuration {
                 vec!["-fsanitize-coverage=trace-pc-guard".to_string()]
             }
             Configuration::CmpLog => vec!["-fsanitize-coverage=trace-cmp".to_string()],
+            Configuration::DataFlowSanitizer => vec![
+                "-fsanitize=dataflow".to_string(),
+                "-fsanitize-coverage=trace-cmp".to_string(),
+                format!(
+                    "-fsanitize-ignorelist={}",
+                    concat!(env!("OUT_DIR"), "/dfsan_abilist.txt")
+                ),
+            ],
             Configuration::GenerateCoverageProfile => {
                 vec![
                     "-fprofile-instr-generate".to_string(),
@@ -171,6 +185,7 @@ impl std::str::FromStr for Configuration {
             "coverage" => Configuration::GenerateCoverageMap,
             "llvm-cov" => Configuration::GenerateCoverageProfile,
             "cmplog" => Configuration::CmpLog,
+            "dfsan" => Configuration::DataFlowSanitizer,
             _ => Configuration::Default,
         })
     }
@@ -185,6 +200,7 @@ impl std::fmt::Display for Configuration {
             Configuration::GenerateCoverageMap => write!(f, "coverage"),
             Configuration::GenerateCoverageProfile => write!(f, "llvm-cov"),
             Configuration::CmpLog => write!(f, "cmplog"),
+            Configuration::DataFlowSanitizer => write!(f, "dfsan"),
             Configuration::Compound(configurations) => {
                 let mut result: Vec<String> = vec![];
                 for configuration in configurations {
@@ -266,6 +282,11 @@ pub trait ToolWrapper {
     /// Returns `true` if `silence` was called with `true`
     fn is_silent(&self) -> bool;
 
+    /// Env vars to set for the wrapped tool, but not for the wrapper itself
+    fn envs(&self) -> Vec<(&'static str, OsString)> {
+        vec![]
+    }
+
     /// Run the tool
     fn run(&mut self) -> Result<Option<i32>, Error> {
         let mut last_status = Ok(None);
@@ -287,7 +308,11 @@ pub trait ToolWrapper {
                 ));
                 continue;
             }
-            let status = match Command::new(&args[0]).args(&args[1..]).status() {
+            let status = match Command::new(&args[0])
+                .args(&args[1..])
+                .envs(self.envs())
+                .status()
+            {
                 Ok(s) => s,
                 Err(e) => {
                     last_status = Err(Error::Io(e));
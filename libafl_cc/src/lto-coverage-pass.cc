@@ -0,0 +1,274 @@
+/*
+   LibAFL - LTO-mode edge coverage instrumentation
+   -----------------------------------------------
+
+   Based on the afl-coverage pass and on the LTO mode of AFL++,
+   Copyright 2019-2020 AFLplusplus Project. All rights reserved.
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at:
+
+     http://www.apache.org/licenses/LICENSE-2.0
+
+   This pass runs once, at link time, on the merged module of the whole
+   program. Every basic block, after splitting the critical edges, gets its
+   own map index, counting up from 1, so that no two edges ever collide.
+   The size of the map that is actually used is stored to __afl_final_loc
+   by a constructor, for the runtime to pick it up.
+
+*/
+
+#include <stdio.h>
+#include <stdlib.h>
+
+#include "llvm/Config/llvm-config.h"
+
+/* lld loads new PM plugins for its LTO pipeline since LLVM 15,
+   before that, we go through the legacy PM. */
+#if LLVM_VERSION_MAJOR >= 15
+  #define LTO_NEW_PM 1
+#endif
+
+#if LTO_NEW_PM
+  #include "llvm/Passes/PassPlugin.h"
+  #include "llvm/Passes/PassBuilder.h"
+  #include "llvm/IR/PassManager.h"
+#else
+  #include "llvm/IR/LegacyPassManager.h"
+  #include "llvm/Transforms/IPO/PassManagerBuilder.h"
+#endif
+
+#include "llvm/IR/IRBuilder.h"
+#include "llvm/IR/Module.h"
+#include "llvm/Support/raw_ostream.h"
+#include "llvm/Transforms/Utils/BasicBlockUtils.h"
+#include "llvm/Transforms/Utils/ModuleUtils.h"
+#include "llvm/Pass.h"
+#include "llvm/IR/Verifier.h"
+
+#include <vector>
+
+#include "instrument-list.h"
+
+using namespace llvm;
+
+namespace {
+
+/* Function that we never instrument or analyze */
+/* Note: this ignore check is also called in isInInstrumentList() */
+bool isIgnoreFunction(const llvm::Function *F) {
+  // Starting from "LLVMFuzzer" these are functions used in libfuzzer based
+  // fuzzing campaign installations, e.g. oss-fuzz
+
+  static constexpr const char *ignoreList[] = {
+
+      "asan.",
+      "llvm.",
+      "sancov.",
+      "__ubsan",
+      "ign.",
+      "__afl",
+      "_fini",
+      "__libc_",
+      "__asan",
+      "__msan",
+      "__cmplog",
+      "__sancov",
+      "__san",
+      "__cxx_",
+      "__decide_deferred",
+      "_GLOBAL",
+      "_ZZN6__asan",
+      "_ZZN6__lsan",
+      "msan.",
+      "LLVMFuzzerM",
+      "LLVMFuzzerC",
+      "LLVMFuzzerI",
+      "maybe_duplicate_stderr",
+      "discard_output",
+      "close_stdout",
+      "dup_and_close_stderr",
+      "maybe_close_fd_mask",
+      "ExecuteFilesOnyByOne"
+
+  };
+
+  for (auto const &ignoreListFunc : ignoreList) {
+    if (F->getName().startswith(ignoreListFunc)) { return true; }
+  }
+
+  static constexpr const char *ignoreSubstringList[] = {
+
+      "__asan",       "__msan",     "__ubsan", "__lsan",
+      "__san",        "__sanitize", "__cxx",   "_GLOBAL__",
+      "DebugCounter", "DwarfDebug", "DebugLoc"
+
+  };
+
+  for (auto const &ignoreListFunc : ignoreSubstringList) {
+    // hexcoder: F->getName().contains() not avaiilable in llvm 3.8.0
+    if (StringRef::npos != F->getName().find(ignoreListFunc)) { return true; }
+  }
+
+  return false;
+}
+
+
+#if LTO_NEW_PM
+class LtoCoverage : public PassInfoMixin<LtoCoverage> {
+ public:
+  LtoCoverage() {
+#else
+
+class LtoCoverage : public ModulePass {
+ public:
+  static char ID;
+  LtoCoverage() : ModulePass(ID) {
+#endif
+    initInstrumentList();
+  }
+
+#if LTO_NEW_PM
+  PreservedAnalyses run(Module &M, ModuleAnalysisManager &MAM);
+#else
+  bool      runOnModule(Module &M) override;
+  StringRef getPassName() const override {
+    return "lto coverage";
+  }
+#endif
+
+ private:
+  uint32_t instrumentModule(Module &M);
+};
+
+}  // namespace
+
+#if LTO_NEW_PM
+extern "C" ::llvm::PassPluginLibraryInfo LLVM_ATTRIBUTE_WEAK
+llvmGetPassPluginInfo() {
+  return {LLVM_PLUGIN_API_VERSION, "LtoCoverage", "v0.1",
+          [](PassBuilder &PB) {
+            PB.registerFullLinkTimeOptimizationLastEPCallback(
+                [](ModulePassManager &MPM, OptimizationLevel OL) {
+                  MPM.addPass(LtoCoverage());
+                });
+          }};
+}
+#else
+char LtoCoverage::ID = 0;
+#endif
+
+/* Instruments every basic block of the module, returns the map size used */
+uint32_t LtoCoverage::instrumentModule(Module &M) {
+  LLVMContext &C = M.getContext();
+  IntegerType *Int8Ty = IntegerType::getInt8Ty(C);
+  IntegerType *Int32Ty = IntegerType::getInt32Ty(C);
+  PointerType *Int8PtrTy = PointerType::get(Int8Ty, 0);
+  ConstantInt *One = ConstantInt::get(Int8Ty, 1);
+  ConstantInt *Zero = ConstantInt::get(Int8Ty, 0);
+  MDNode      *NoSanitize = MDNode::get(C, {});
+  unsigned     NoSanitizeKind = M.getMDKindID("nosanitize");
+
+  Constant *AFLMapPtr = M.getOrInsertGlobal("__afl_area_ptr", Int8PtrTy);
+
+  /* Index 0 is written by the forkserver, so we start at 1 */
+  uint32_t cur_loc = 1;
+
+  for (auto &F : M) {
+    if (F.isDeclaration() || isIgnoreFunction(&F) || !isInInstrumentList(&F)) {
+      continue;
+    }
+
+    /* A block on each critical edge, so that block coverage is edge coverage
+     */
+    SplitAllCriticalEdges(F);
+
+    std::vector<BasicBlock *> blocks;
+    for (auto &BB : F) {
+      blocks.push_back(&BB);
+    }
+
+    for (auto BB : blocks) {
+      BasicBlock::iterator IP = BB->getFirstInsertionPt();
+      if (IP == BB->end()) { continue; }
+      IRBuilder<> IRB(&(*IP));
+
+      LoadInst *MapPtr = IRB.CreateLoad(
+#if LLVM_VERSION_MAJOR >= 14
+          Int8PtrTy,
+#endif
+          AFLMapPtr);
+      MapPtr->setMetadata(NoSanitizeKind, NoSanitize);
+
+      Value *MapPtrIdx = IRB.CreateGEP(
+#if LLVM_VERSION_MAJOR >= 14
+          Int8Ty,
+#endif
+          MapPtr, ConstantInt::get(Int32Ty, cur_loc));
+
+      LoadInst *Counter = IRB.CreateLoad(
+#if LLVM_VERSION_MAJOR >= 14
+          Int8Ty,
+#endif
+          MapPtrIdx);
+      Counter->setMetadata(NoSanitizeKind, NoSanitize);
+
+      /* Never zero: the counter skips 0 when overflowing */
+      Value *Incr = IRB.CreateAdd(Counter, One);
+      Incr = IRB.CreateAdd(
+          Incr, IRB.CreateZExt(IRB.CreateICmpEQ(Incr, Zero), Int8Ty));
+
+      IRB.CreateStore(Incr, MapPtrIdx)->setMetadata(NoSanitizeKind, NoSanitize);
+
+      cur_loc++;
+    }
+  }
+
+  return cur_loc;
+}
+
+#if LTO_NEW_PM
+PreservedAnalyses LtoCoverage::run(Module &M, ModuleAnalysisManager &MAM) {
+#else
+bool LtoCoverage::runOnModule(Module &M) {
+#endif
+  LLVMContext &C = M.getContext();
+  IntegerType *Int32Ty = IntegerType::getInt32Ty(C);
+
+  uint32_t map_size = instrumentModule(M);
+
+  /* Tell the runtime how large the map is, before anything else runs */
+  Constant *AFLFinalLoc = M.getOrInsertGlobal("__afl_final_loc", Int32Ty);
+
+  FunctionType *CtorTy = FunctionType::get(Type::getVoidTy(C), false);
+  Function     *Ctor = Function::Create(CtorTy, GlobalValue::InternalLinkage,
+                                        "__afl_lto_set_map_size", &M);
+  IRBuilder<>   IRB(BasicBlock::Create(C, "entry", Ctor));
+  IRB.CreateStore(ConstantInt::get(Int32Ty, map_size), AFLFinalLoc);
+  IRB.CreateRetVoid();
+  appendToGlobalCtors(M, Ctor, 0);
+
+  verifyModule(M);
+
+#if LTO_NEW_PM
+  return PreservedAnalyses::none();
+#else
+  return true;
+#endif
+}
+
+#if !LTO_NEW_PM
+static void registerLtoCoveragePass(const PassManagerBuilder &,
+                                    legacy::PassManagerBase &PM) {
+  PM.add(new LtoCoverage());
+}
+
+static RegisterStandardPasses RegisterLtoCoveragePass(
+    PassManagerBuilder::EP_FullLinkTimeOptimizationLast,
+    registerLtoCoveragePass);
+
+static RegisterPass<LtoCoverage> X("lto-coverage",
+                                   "LTO-mode edge coverage pass", false,
+                                   false);
+#endif
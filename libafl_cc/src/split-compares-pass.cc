@@ -0,0 +1,348 @@
+/*
+   american fuzzy lop++ - LLVM LaF split compares
+   ----------------------------------------------
+
+   Based on the laf-intel passes, as shipped with AFL++.
+   Multi-byte integer compares are transformed into byte-wise compare chains.
+
+   Copyright 2016 laf-intel. All rights reserved.
+   Copyright 2019-2020 AFLplusplus Project. All rights reserved.
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at:
+
+     http://www.apache.org/licenses/LICENSE-2.0
+
+*/
+
+#include <stdio.h>
+#include <stdlib.h>
+#ifndef _WIN32
+  #include <unistd.h>
+  #include <sys/time.h>
+#endif
+
+#include <list>
+#include <string>
+#include <fstream>
+#include "llvm/Config/llvm-config.h"
+
+#if USE_NEW_PM
+  #include "llvm/Passes/PassPlugin.h"
+  #include "llvm/Passes/PassBuilder.h"
+  #include "llvm/IR/PassManager.h"
+#else
+  #include "llvm/IR/LegacyPassManager.h"
+#endif
+
+#include "llvm/ADT/Statistic.h"
+#include "llvm/IR/IRBuilder.h"
+#include "llvm/IR/Module.h"
+#include "llvm/Support/Debug.h"
+#include "llvm/Support/raw_ostream.h"
+#if LLVM_VERSION_MAJOR < 11
+  #include "llvm/Transforms/IPO/PassManagerBuilder.h"
+#endif
+#include "llvm/Transforms/Utils/BasicBlockUtils.h"
+#include "llvm/Pass.h"
+#include "llvm/Analysis/ValueTracking.h"
+
+#if LLVM_VERSION_MAJOR > 3 || \
+    (LLVM_VERSION_MAJOR == 3 && LLVM_VERSION_MINOR > 4)
+  #include "llvm/IR/Verifier.h"
+  #include "llvm/IR/DebugInfo.h"
+#else
+  #include "llvm/Analysis/Verifier.h"
+  #include "llvm/DebugInfo.h"
+  #define nullptr 0
+#endif
+
+#include <set>
+
+#include "instrument-list.h"
+
+using namespace llvm;
+
+namespace {
+
+/* Function that we never instrument or analyze */
+/* Note: this ignore check is also called in isInInstrumentList() */
+bool isIgnoreFunction(const llvm::Function *F) {
+  // Starting from "LLVMFuzzer" these are functions used in libfuzzer based
+  // fuzzing campaign installations, e.g. oss-fuzz
+
+  static constexpr const char *ignoreList[] = {
+
+      "asan.",
+      "llvm.",
+      "sancov.",
+      "__ubsan",
+      "ign.",
+      "__afl",
+      "_fini",
+      "__libc_",
+      "__asan",
+      "__msan",
+      "__cmplog",
+      "__sancov",
+      "__san",
+      "__cxx_",
+      "__decide_deferred",
+      "_GLOBAL",
+      "_ZZN6__asan",
+      "_ZZN6__lsan",
+      "msan.",
+      "LLVMFuzzerM",
+      "LLVMFuzzerC",
+      "LLVMFuzzerI",
+      "maybe_duplicate_stderr",
+      "discard_output",
+      "close_stdout",
+      "dup_and_close_stderr",
+      "maybe_close_fd_mask",
+      "ExecuteFilesOnyByOne"
+
+  };
+
+  for (auto const &ignoreListFunc : ignoreList) {
+    if (F->getName().startswith(ignoreListFunc)) { return true; }
+  }
+
+  static constexpr const char *ignoreSubstringList[] = {
+
+      "__asan",       "__msan",     "__ubsan", "__lsan",
+      "__san",        "__sanitize", "__cxx",   "_GLOBAL__",
+      "DebugCounter", "DwarfDebug", "DebugLoc"
+
+  };
+
+  for (auto const &ignoreListFunc : ignoreSubstringList) {
+    // hexcoder: F->getName().contains() not avaiilable in llvm 3.8.0
+    if (StringRef::npos != F->getName().find(ignoreListFunc)) { return true; }
+  }
+
+  return false;
+}
+
+#if USE_NEW_PM
+class SplitComparesTransform : public PassInfoMixin<SplitComparesTransform> {
+ public:
+  SplitComparesTransform() {
+#else
+
+class SplitComparesTransform : public ModulePass {
+ public:
+  static char ID;
+  SplitComparesTransform() : ModulePass(ID) {
+#endif
+    initInstrumentList();
+  }
+
+#if USE_NEW_PM
+  PreservedAnalyses run(Module &M, ModuleAnalysisManager &MAM);
+#else
+  bool        runOnModule(Module &M) override;
+
+  #if LLVM_VERSION_MAJOR < 4
+  const char *getPassName() const override {
+  #else
+  StringRef getPassName() const override {
+  #endif
+    return "splits integer compares";
+  }
+#endif
+
+ private:
+  bool splitCompares(Module &M);
+};
+
+}  // namespace
+
+#if USE_NEW_PM
+extern "C" ::llvm::PassPluginLibraryInfo LLVM_ATTRIBUTE_WEAK
+llvmGetPassPluginInfo() {
+  return {LLVM_PLUGIN_API_VERSION, "SplitComparesTransform", "v0.1",
+          [](PassBuilder &PB) {
+  #if LLVM_VERSION_MAJOR <= 13
+            using OptimizationLevel = typename PassBuilder::OptimizationLevel;
+  #endif
+            PB.registerOptimizerLastEPCallback(
+                [](ModulePassManager &MPM, OptimizationLevel OL) {
+                  MPM.addPass(SplitComparesTransform());
+                });
+          }};
+}
+#else
+char SplitComparesTransform::ID = 0;
+#endif
+
+/* The predicate deciding a comparison once all higher bytes are equal */
+static CmpInst::Predicate lastBytePredicate(CmpInst::Predicate pred) {
+  switch (pred) {
+    case CmpInst::ICMP_SGT:
+      return CmpInst::ICMP_UGT;
+    case CmpInst::ICMP_SGE:
+      return CmpInst::ICMP_UGE;
+    case CmpInst::ICMP_SLT:
+      return CmpInst::ICMP_ULT;
+    case CmpInst::ICMP_SLE:
+      return CmpInst::ICMP_ULE;
+    default:
+      return pred;
+  }
+}
+
+/* The predicate deciding a comparison at the first differing byte.
+   Only the most significant byte carries the sign. */
+static CmpInst::Predicate differingBytePredicate(CmpInst::Predicate pred,
+                                                 bool isMostSignificant) {
+  switch (pred) {
+    case CmpInst::ICMP_SGT:
+    case CmpInst::ICMP_SGE:
+      return isMostSignificant ? CmpInst::ICMP_SGT : CmpInst::ICMP_UGT;
+    case CmpInst::ICMP_SLT:
+    case CmpInst::ICMP_SLE:
+      return isMostSignificant ? CmpInst::ICMP_SLT : CmpInst::ICMP_ULT;
+    case CmpInst::ICMP_UGT:
+    case CmpInst::ICMP_UGE:
+      return CmpInst::ICMP_UGT;
+    case CmpInst::ICMP_ULT:
+    case CmpInst::ICMP_ULE:
+      return CmpInst::ICMP_ULT;
+    default:
+      return pred;
+  }
+}
+
+bool SplitComparesTransform::splitCompares(Module &M) {
+  std::vector<ICmpInst *> cmps;
+  LLVMContext            &C = M.getContext();
+
+  IntegerType *Int1Ty = IntegerType::getInt1Ty(C);
+  IntegerType *Int8Ty = IntegerType::getInt8Ty(C);
+
+  /* iterate over all functions, bbs and instructions and collect compares */
+  for (auto &F : M) {
+    if (isIgnoreFunction(&F) || !isInInstrumentList(&F)) { continue; }
+
+    for (auto &BB : F) {
+      for (auto &IN : BB) {
+        ICmpInst *cmpInst = nullptr;
+
+        if (!(cmpInst = dyn_cast<ICmpInst>(&IN))) { continue; }
+
+        // Pointers and vectors are left alone
+        IntegerType *intTy =
+            dyn_cast<IntegerType>(cmpInst->getOperand(0)->getType());
+        if (!intTy) { continue; }
+
+        unsigned width = intTy->getBitWidth();
+        if (width <= 8 || width > 128 || width % 8) { continue; }
+
+        if (isa<Constant>(cmpInst->getOperand(0)) &&
+            isa<Constant>(cmpInst->getOperand(1))) {
+          continue;
+        }
+
+        cmps.push_back(cmpInst);
+      }
+    }
+  }
+
+  if (!cmps.size()) { return false; }
+
+  for (auto &cmpInst : cmps) {
+    BasicBlock        *bb = cmpInst->getParent();
+    Function          *F = bb->getParent();
+    Value             *op0 = cmpInst->getOperand(0);
+    Value             *op1 = cmpInst->getOperand(1);
+    CmpInst::Predicate pred = cmpInst->getPredicate();
+    unsigned           bytes = op0->getType()->getIntegerBitWidth() / 8;
+
+    /* The compare is replaced by a chain of blocks, one per byte, starting
+       at the most significant one. A differing byte decides the result,
+       an equal one moves on to the next block. */
+    BasicBlock *endBB = bb->splitBasicBlock(BasicBlock::iterator(cmpInst));
+    bb->getTerminator()->eraseFromParent();
+
+    PHINode *PN = PHINode::Create(Int1Ty, bytes, "", &endBB->front());
+
+    BasicBlock *curBB = bb;
+    for (unsigned i = bytes; i-- > 0;) {
+      IRBuilder<> IRB(curBB);
+
+      Value *byte0 = IRB.CreateTrunc(IRB.CreateLShr(op0, i * 8), Int8Ty);
+      Value *byte1 = IRB.CreateTrunc(IRB.CreateLShr(op1, i * 8), Int8Ty);
+
+      if (i == 0) {
+        PN->addIncoming(IRB.CreateICmp(lastBytePredicate(pred), byte0, byte1),
+                        curBB);
+        IRB.CreateBr(endBB);
+        break;
+      }
+
+      Value *result;
+      if (pred == CmpInst::ICMP_EQ) {
+        result = ConstantInt::getFalse(C);
+      } else if (pred == CmpInst::ICMP_NE) {
+        result = ConstantInt::getTrue(C);
+      } else {
+        result = IRB.CreateICmp(
+            differingBytePredicate(pred, i == bytes - 1), byte0, byte1);
+      }
+      PN->addIncoming(result, curBB);
+
+      BasicBlock *nextBB = BasicBlock::Create(C, "split_cmp_byte", F, endBB);
+      IRB.CreateCondBr(IRB.CreateICmpNE(byte0, byte1), endBB, nextBB);
+      curBB = nextBB;
+    }
+
+    cmpInst->replaceAllUsesWith(PN);
+    cmpInst->eraseFromParent();
+  }
+
+  return true;
+}
+
+#if USE_NEW_PM
+PreservedAnalyses SplitComparesTransform::run(Module                &M,
+                                              ModuleAnalysisManager &MAM) {
+#else
+bool SplitComparesTransform::runOnModule(Module &M) {
+#endif
+  bool modified = splitCompares(M);
+
+#if USE_NEW_PM
+  auto PA = modified ? PreservedAnalyses::none() : PreservedAnalyses::all();
+#endif
+  verifyModule(M);
+
+#if USE_NEW_PM
+  return PA;
+#else
+  return modified;
+#endif
+}
+
+#if USE_NEW_PM
+#else
+  #if LLVM_VERSION_MAJOR < 11 /* use old pass manager */
+static void registerSplitComparesPass(const PassManagerBuilder &,
+                                      legacy::PassManagerBase &PM) {
+  auto p = new SplitComparesTransform();
+  PM.add(p);
+}
+
+static RegisterStandardPasses RegisterSplitComparesPass(
+    PassManagerBuilder::EP_OptimizerLast, registerSplitComparesPass);
+
+static RegisterStandardPasses RegisterSplitComparesPass0(
+    PassManagerBuilder::EP_EnabledOnOptLevel0, registerSplitComparesPass);
+
+static RegisterStandardPasses RegisterSplitComparesPassLTO(
+    PassManagerBuilder::EP_FullLinkTimeOptimizationLast,
+    registerSplitComparesPass);
+
+  #endif
+#endif
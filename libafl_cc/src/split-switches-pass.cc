@@ -0,0 +1,302 @@
+/*
+   american fuzzy lop++ - LLVM LaF split switches
+   ----------------------------------------------
+
+   Based on the laf-intel passes, as shipped with AFL++.
+   Switch statements are transformed into chains of compares, one per case.
+
+   Copyright 2016 laf-intel. All rights reserved.
+   Copyright 2019-2020 AFLplusplus Project. All rights reserved.
+
+   Licensed under the Apache License, Version 2.0 (the "License");
+   you may not use this file except in compliance with the License.
+   You may obtain a copy of the License at:
+
+     http://www.apache.org/licenses/LICENSE-2.0
+
+*/
+
+#include <stdio.h>
+#include <stdlib.h>
+#ifndef _WIN32
+  #include <unistd.h>
+  #include <sys/time.h>
+#endif
+
+#include <list>
+#include <string>
+#include <fstream>
+#include "llvm/Config/llvm-config.h"
+
+#if USE_NEW_PM
+  #include "llvm/Passes/PassPlugin.h"
+  #include "llvm/Passes/PassBuilder.h"
+  #include "llvm/IR/PassManager.h"
+#else
+  #include "llvm/IR/LegacyPassManager.h"
+#endif
+
+#include "llvm/ADT/Statistic.h"
+#include "llvm/IR/IRBuilder.h"
+#include "llvm/IR/Module.h"
+#include "llvm/Support/Debug.h"
+#include "llvm/Support/raw_ostream.h"
+#if LLVM_VERSION_MAJOR < 11
+  #include "llvm/Transforms/IPO/PassManagerBuilder.h"
+#endif
+#include "llvm/Transforms/Utils/BasicBlockUtils.h"
+#include "llvm/Pass.h"
+#include "llvm/Analysis/ValueTracking.h"
+
+#if LLVM_VERSION_MAJOR > 3 || \
+    (LLVM_VERSION_MAJOR == 3 && LLVM_VERSION_MINOR > 4)
+  #include "llvm/IR/Verifier.h"
+  #include "llvm/IR/DebugInfo.h"
+#else
+  #include "llvm/Analysis/Verifier.h"
+  #include "llvm/DebugInfo.h"
+  #define nullptr 0
+#endif
+
+#include <set>
+
+#include "instrument-list.h"
+
+using namespace llvm;
+
+namespace {
+
+/* Function that we never instrument or analyze */
+/* Note: this ignore check is also called in isInInstrumentList() */
+bool isIgnoreFunction(const llvm::Function *F) {
+  // Starting from "LLVMFuzzer" these are functions used in libfuzzer based
+  // fuzzing campaign installations, e.g. oss-fuzz
+
+  static constexpr const char *ignoreList[] = {
+
+      "asan.",
+      "llvm.",
+      "sancov.",
+      "__ubsan",
+      "ign.",
+      "__afl",
+      "_fini",
+      "__libc_",
+      "__asan",
+      "__msan",
+      "__cmplog",
+      "__sancov",
+      "__san",
+      "__cxx_",
+      "__decide_deferred",
+      "_GLOBAL",
+      "_ZZN6__asan",
+      "_ZZN6__lsan",
+      "msan.",
+      "LLVMFuzzerM",
+      "LLVMFuzzerC",
+      "LLVMFuzzerI",
+      "maybe_duplicate_stderr",
+      "discard_output",
+      "close_stdout",
+      "dup_and_close_stderr",
+      "maybe_close_fd_mask",
+      "ExecuteFilesOnyByOne"
+
+  };
+
+  for (auto const &ignoreListFunc : ignoreList) {
+    if (F->getName().startswith(ignoreListFunc)) { return true; }
+  }
+
+  static constexpr const char *ignoreSubstringList[] = {
+
+      "__asan",       "__msan",     "__ubsan", "__lsan",
+      "__san",        "__sanitize", "__cxx",   "_GLOBAL__",
+      "DebugCounter", "DwarfDebug", "DebugLoc"
+
+  };
+
+  for (auto const &ignoreListFunc : ignoreSubstringList) {
+    // hexcoder: F->getName().contains() not avaiilable in llvm 3.8.0
+    if (StringRef::npos != F->getName().find(ignoreListFunc)) { return true; }
+  }
+
+  return false;
+}
+
+#if USE_NEW_PM
+class SplitSwitches : public PassInfoMixin<SplitSwitches> {
+ public:
+  SplitSwitches() {
+#else
+
+class SplitSwitches : public ModulePass {
+ public:
+  static char ID;
+  SplitSwitches() : ModulePass(ID) {
+#endif
+    initInstrumentList();
+  }
+
+#if USE_NEW_PM
+  PreservedAnalyses run(Module &M, ModuleAnalysisManager &MAM);
+#else
+  bool        runOnModule(Module &M) override;
+
+  #if LLVM_VERSION_MAJOR < 4
+  const char *getPassName() const override {
+  #else
+  StringRef getPassName() const override {
+  #endif
+    return "splits switch constructs";
+  }
+#endif
+
+ private:
+  bool splitSwitches(Module &M);
+};
+
+}  // namespace
+
+#if USE_NEW_PM
+extern "C" ::llvm::PassPluginLibraryInfo LLVM_ATTRIBUTE_WEAK
+llvmGetPassPluginInfo() {
+  return {LLVM_PLUGIN_API_VERSION, "SplitSwitches", "v0.1",
+          [](PassBuilder &PB) {
+  #if LLVM_VERSION_MAJOR <= 13
+            using OptimizationLevel = typename PassBuilder::OptimizationLevel;
+  #endif
+            PB.registerOptimizerLastEPCallback(
+                [](ModulePassManager &MPM, OptimizationLevel OL) {
+                  MPM.addPass(SplitSwitches());
+                });
+          }};
+}
+#else
+char SplitSwitches::ID = 0;
+#endif
+
+bool SplitSwitches::splitSwitches(Module &M) {
+  std::vector<SwitchInst *> switches;
+  LLVMContext              &C = M.getContext();
+
+  /* iterate over all functions, bbs and instructions and collect switches */
+  for (auto &F : M) {
+    if (isIgnoreFunction(&F) || !isInInstrumentList(&F)) { continue; }
+
+    for (auto &BB : F) {
+      SwitchInst *switchInst = nullptr;
+
+      if ((switchInst = dyn_cast<SwitchInst>(BB.getTerminator()))) {
+        // A single byte is solved by the coverage of the switch already
+        if (switchInst->getCondition()->getType()->getIntegerBitWidth() <= 8) {
+          continue;
+        }
+        if (switchInst->getNumCases() < 1) { continue; }
+        switches.push_back(switchInst);
+      }
+    }
+  }
+
+  if (!switches.size()) { return false; }
+
+  for (auto &switchInst : switches) {
+    BasicBlock *origBB = switchInst->getParent();
+    Function   *F = origBB->getParent();
+    Value      *cond = switchInst->getCondition();
+    BasicBlock *defaultBB = switchInst->getDefaultDest();
+
+    std::vector<std::pair<ConstantInt *, BasicBlock *>> cases;
+    for (auto &caseIt : switchInst->cases()) {
+      cases.push_back({caseIt.getCaseValue(), caseIt.getCaseSuccessor()});
+    }
+
+    /* The incoming values of the successors, before the switch goes away */
+    std::vector<std::pair<PHINode *, Value *>> phis;
+    std::set<BasicBlock *>                     succs;
+    for (unsigned i = 0; i < switchInst->getNumSuccessors(); i++) {
+      succs.insert(switchInst->getSuccessor(i));
+    }
+    for (auto succ : succs) {
+      for (auto &phi : succ->phis()) {
+        phis.push_back({&phi, phi.getIncomingValueForBlock(origBB)});
+        while (phi.getBasicBlockIndex(origBB) >= 0) {
+          phi.removeIncomingValue(origBB, false);
+        }
+      }
+    }
+
+    switchInst->eraseFromParent();
+
+    /* Each case becomes an equality check of its own, falling through to the
+       next case, and to the default after the last one */
+    std::vector<std::pair<BasicBlock *, BasicBlock *>> edges;
+    BasicBlock                                        *curBB = origBB;
+    for (size_t i = 0; i < cases.size(); i++) {
+      BasicBlock *nextBB =
+          i + 1 == cases.size()
+              ? defaultBB
+              : BasicBlock::Create(C, "switch_case", F, defaultBB);
+
+      IRBuilder<> IRB(curBB);
+      Value      *isCase = IRB.CreateICmpEQ(cond, cases[i].first);
+      IRB.CreateCondBr(isCase, cases[i].second, nextBB);
+      edges.push_back({curBB, cases[i].second});
+      edges.push_back({curBB, nextBB});
+
+      curBB = nextBB;
+    }
+
+    for (auto &phi : phis) {
+      for (auto &edge : edges) {
+        if (edge.second == phi.first->getParent()) {
+          phi.first->addIncoming(phi.second, edge.first);
+        }
+      }
+    }
+  }
+
+  return true;
+}
+
+#if USE_NEW_PM
+PreservedAnalyses SplitSwitches::run(Module &M, ModuleAnalysisManager &MAM) {
+#else
+bool SplitSwitches::runOnModule(Module &M) {
+#endif
+  bool modified = splitSwitches(M);
+
+#if USE_NEW_PM
+  auto PA = modified ? PreservedAnalyses::none() : PreservedAnalyses::all();
+#endif
+  verifyModule(M);
+
+#if USE_NEW_PM
+  return PA;
+#else
+  return modified;
+#endif
+}
+
+#if USE_NEW_PM
+#else
+  #if LLVM_VERSION_MAJOR < 11 /* use old pass manager */
+static void registerSplitSwitchesPass(const PassManagerBuilder &,
+                                      legacy::PassManagerBase &PM) {
+  auto p = new SplitSwitches();
+  PM.add(p);
+}
+
+static RegisterStandardPasses RegisterSplitSwitchesPass(
+    PassManagerBuilder::EP_OptimizerLast, registerSplitSwitchesPass);
+
+static RegisterStandardPasses RegisterSplitSwitchesPass0(
+    PassManagerBuilder::EP_EnabledOnOptLevel0, registerSplitSwitchesPass);
+
+static RegisterStandardPasses RegisterSplitSwitchesPassLTO(
+    PassManagerBuilder::EP_FullLinkTimeOptimizationLast,
+    registerSplitSwitchesPass);
+
+  #endif
+#endif
@@ -59,9 +59,17 @@
     )
 )]
 
+extern crate alloc;
+
+use alloc::vec::Vec;
+
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{parse_macro_input, DeriveInput};
+use proc_macro2::{Span, TokenStream as TokenStream2};
+use quote::{format_ident, quote};
+use syn::{
+    parse_macro_input, parse_quote, spanned::Spanned, Attribute, Data, DeriveInput, Error, Expr,
+    ExprRange, Fields, LitStr, Member, RangeLimits,
+};
 
 /// Derive macro to implement `SerdeAny`, to use a type in a `SerdeAnyMap`
 #[proc_macro_derive(SerdeAny)]
@@ -71,3 +79,549 @@ pub fn libafl_serdeany_derive(input: TokenStream) -> TokenStream {
         libafl_bolts::impl_serdeany!(#name);
     })
 }
+
+/// Derive macro to implement `libafl::inputs::Call` for an enum of operations,
+/// to fuzz sequences of them as `CallSequenceInput`.
+///
+/// Each field is an argument, generated and mutated through its `CallArgument` implementation.
+/// Variants returning a resource are marked with `#[call(returns = "kind")]`,
+/// fields taking a resource are `ResourceRef`s marked with `#[call(resource = "kind")]`.
+#[proc_macro_derive(Call, attributes(call))]
+pub fn libafl_call_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    call_derive(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+/// Parse the `#[call(key = "value")]` attributes, returning the value of `key`, if given
+fn call_attribute(attrs: &[Attribute], key: &str) -> Result<Option<LitStr>, Error> {
+    let mut value = None;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("call")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("returns") || meta.path.is_ident("resource") {
+                let lit: LitStr = meta.value()?.parse()?;
+                if meta.path.is_ident(key) {
+                    value = Some(lit);
+                }
+                Ok(())
+            } else {
+                Err(meta.error("expected `returns` or `resource`"))
+            }
+        })?;
+    }
+    Ok(value)
+}
+
+#[allow(clippy::too_many_lines)]
+fn call_derive(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let Data::Enum(data) = &input.data else {
+        return Err(Error::new(
+            Span::call_site(),
+            "Call can only be derived for enums, with one variant per operation",
+        ));
+    };
+    if data.variants.is_empty() {
+        return Err(Error::new(
+            input.ident.span(),
+            "Call can not be derived for an enum without operations",
+        ));
+    }
+
+    let mut returns_arms = Vec::new();
+    let mut resources_arms = Vec::new();
+    let mut resources_mut_arms = Vec::new();
+    let mut generate_arms = Vec::new();
+    let mut mutate_arms = Vec::new();
+
+    for (variant_idx, variant) in data.variants.iter().enumerate() {
+        let variant_ident = &variant.ident;
+        let returns = if let Some(kind) = call_attribute(&variant.attrs, "returns")? {
+            quote! { ::core::option::Option::Some(#kind) }
+        } else {
+            quote! { ::core::option::Option::None }
+        };
+        returns_arms.push(quote! { Self::#variant_ident { .. } => #returns });
+
+        let fields: Vec<_> = match &variant.fields {
+            Fields::Named(fields) => fields.named.iter().collect(),
+            Fields::Unnamed(fields) => fields.unnamed.iter().collect(),
+            Fields::Unit => Vec::new(),
+        };
+
+        let mut resource_members = Vec::new();
+        let mut resource_bindings = Vec::new();
+        let mut resource_kinds = Vec::new();
+        let mut arg_members = Vec::new();
+        let mut arg_bindings = Vec::new();
+        let mut generated = Vec::new();
+
+        for (field_idx, field) in fields.into_iter().enumerate() {
+            let member = match &field.ident {
+                Some(ident) => Member::Named(ident.clone()),
+                None => Member::Unnamed(syn::Index {
+                    index: field_idx as u32,
+                    span: field.span(),
+                }),
+            };
+            let binding = format_ident!("__field{}", field_idx);
+            let ty = &field.ty;
+            if let Some(kind) = call_attribute(&field.attrs, "resource")? {
+                generated.push(quote! {
+                    #member: ::core::default::Default::default()
+                });
+                resource_members.push(member);
+                resource_bindings.push(binding);
+                resource_kinds.push(kind);
+            } else {
+                generated.push(quote! {
+                    #member: <#ty as libafl::inputs::CallArgument>::generate(rand)
+                });
+                arg_members.push(member);
+                arg_bindings.push(binding);
+            }
+        }
+
+        resources_arms.push(quote! {
+            Self::#variant_ident { #(#resource_members: #resource_bindings,)* .. } => {
+                libafl::alloc::vec![#((#resource_kinds, *#resource_bindings)),*]
+            }
+        });
+        resources_mut_arms.push(quote! {
+            Self::#variant_ident { #(#resource_members: #resource_bindings,)* .. } => {
+                libafl::alloc::vec![#((#resource_kinds, #resource_bindings)),*]
+            }
+        });
+
+        let variant_idx = variant_idx as u64;
+        generate_arms.push(quote! {
+            #variant_idx => Self::#variant_ident { #(#generated,)* }
+        });
+
+        let arg_count = arg_bindings.len() as u64;
+        let arg_indices = 0..arg_count;
+        mutate_arms.push(if arg_bindings.is_empty() {
+            quote! {
+                Self::#variant_ident { .. } => libafl::mutators::MutationResult::Skipped
+            }
+        } else {
+            quote! {
+                Self::#variant_ident { #(#arg_members: #arg_bindings,)* .. } => {
+                    match libafl_bolts::rands::Rand::below(rand, #arg_count) {
+                        #(#arg_indices => libafl::inputs::CallArgument::mutate(#arg_bindings, rand),)*
+                        _ => unreachable!(),
+                    }
+                }
+            }
+        });
+    }
+
+    let name = &input.ident;
+    let variant_count = data.variants.len() as u64;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics libafl::inputs::Call for #name #ty_generics #where_clause {
+            fn returns(&self) -> ::core::option::Option<&'static str> {
+                match self {
+                    #(#returns_arms,)*
+                }
+            }
+
+            fn resources(&self) -> libafl::alloc::vec::Vec<(&'static str, libafl::inputs::ResourceRef)> {
+                match self {
+                    #(#resources_arms,)*
+                }
+            }
+
+            fn resources_mut(
+                &mut self,
+            ) -> libafl::alloc::vec::Vec<(&'static str, &mut libafl::inputs::ResourceRef)> {
+                match self {
+                    #(#resources_mut_arms,)*
+                }
+            }
+
+            fn generate<R: libafl_bolts::rands::Rand>(rand: &mut R) -> Self {
+                match libafl_bolts::rands::Rand::below(rand, #variant_count) {
+                    #(#generate_arms,)*
+                    _ => unreachable!(),
+                }
+            }
+
+            fn mutate_args<R: libafl_bolts::rands::Rand>(
+                &mut self,
+                rand: &mut R,
+            ) -> libafl::mutators::MutationResult {
+                match self {
+                    #(#mutate_arms,)*
+                }
+            }
+        }
+    })
+}
+
+/// A field of a struct, or of an enum variant, deriving `Generate` or `Mutate`
+struct StructuredField {
+    member: Member,
+    binding: proc_macro2::Ident,
+    ty: syn::Type,
+    constraints: TokenStream2,
+    skip: bool,
+}
+
+/// A struct, or an enum variant, deriving `Generate` or `Mutate`
+struct StructuredVariant {
+    path: TokenStream2,
+    fields: Vec<StructuredField>,
+}
+
+impl StructuredVariant {
+    /// The pattern binding the fields that are not skipped
+    fn pattern(&self, prefix: &str) -> TokenStream2 {
+        let path = &self.path;
+        let bindings = self.fields.iter().filter(|field| !field.skip).map(|field| {
+            let member = &field.member;
+            let binding = format_ident!("{}{}", prefix, field.binding);
+            quote! { #member: #binding }
+        });
+        quote! { #path { #(#bindings,)* .. } }
+    }
+
+    fn mutable_fields(&self) -> impl Iterator<Item = &StructuredField> {
+        self.fields.iter().filter(|field| !field.skip)
+    }
+}
+
+/// Parse the `#[mutate(..)]` attributes of a field into `FieldConstraints`, and whether it's skipped
+fn field_constraints(attrs: &[Attribute]) -> Result<(TokenStream2, bool), Error> {
+    let mut range = quote! { ::core::option::Option::None };
+    let mut float_range = quote! { ::core::option::Option::None };
+    let mut max_len = quote! { ::core::option::Option::None };
+    let mut skip = false;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("mutate")) {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("range") {
+                let ExprRange {
+                    start, limits, end, ..
+                } = meta.value()?.parse()?;
+                let (Some(start), Some(end)) = (start, end) else {
+                    return Err(meta.error("expected a range with both bounds"));
+                };
+                let int_end: Expr = match limits {
+                    RangeLimits::HalfOpen(_) => parse_quote! { (#end) as i128 - 1 },
+                    RangeLimits::Closed(_) => parse_quote! { (#end) as i128 },
+                };
+                range = quote! { ::core::option::Option::Some(((#start) as i128, #int_end)) };
+                float_range =
+                    quote! { ::core::option::Option::Some(((#start) as f64, (#end) as f64)) };
+                Ok(())
+            } else if meta.path.is_ident("max_len") {
+                let len: Expr = meta.value()?.parse()?;
+                max_len = quote! { ::core::option::Option::Some(#len) };
+                Ok(())
+            } else if meta.path.is_ident("skip") {
+                skip = true;
+                Ok(())
+            } else {
+                Err(meta.error("expected `range`, `max_len` or `skip`"))
+            }
+        })?;
+    }
+    Ok((
+        quote! {
+            libafl::inputs::FieldConstraints {
+                range: #range,
+                float_range: #float_range,
+                max_len: #max_len,
+            }
+        },
+        skip,
+    ))
+}
+
+/// The struct, or the variants of the enum, deriving `Generate` or `Mutate`
+fn structured_variants(input: &DeriveInput) -> Result<Vec<StructuredVariant>, Error> {
+    let variants: Vec<(TokenStream2, &Fields)> = match &input.data {
+        Data::Struct(data) => alloc::vec![(quote! { Self }, &data.fields)],
+        Data::Enum(data) => data
+            .variants
+            .iter()
+            .map(|variant| {
+                let ident = &variant.ident;
+                (quote! { Self::#ident }, &variant.fields)
+            })
+            .collect(),
+        Data::Union(_) => {
+            return Err(Error::new(
+                Span::call_site(),
+                "Generate and Mutate can not be derived for unions",
+            ))
+        }
+    };
+    if variants.is_empty() {
+        return Err(Error::new(
+            input.ident.span(),
+            "Generate and Mutate can not be derived for an enum without variants",
+        ));
+    }
+
+    variants
+        .into_iter()
+        .map(|(path, fields)| {
+            let fields = fields
+                .iter()
+                .enumerate()
+                .map(|(field_idx, field)| {
+                    let (constraints, skip) = field_constraints(&field.attrs)?;
+                    Ok(StructuredField {
+                        member: match &field.ident {
+                            Some(ident) => Member::Named(ident.clone()),
+                            None => Member::Unnamed(syn::Index {
+                                index: field_idx as u32,
+                                span: field.span(),
+                            }),
+                        },
+                        binding: format_ident!("field{}", field_idx),
+                        ty: field.ty.clone(),
+                        constraints,
+                        skip,
+                    })
+                })
+                .collect::<Result<_, Error>>()?;
+            Ok(StructuredVariant { path, fields })
+        })
+        .collect()
+}
+
+/// Add the `bound` to each type parameter
+fn add_trait_bounds(mut generics: syn::Generics, bound: &TokenStream2) -> syn::Generics {
+    for param in &mut generics.params {
+        if let syn::GenericParam::Type(ty) = param {
+            ty.bounds.push(parse_quote! { #bound });
+        }
+    }
+    generics
+}
+
+/// Derive macro to implement `libafl::inputs::Generate`, generating random values field by field.
+///
+/// Fields take the constraints `#[mutate(range = lo..=hi)]`, `#[mutate(max_len = n)]` and `#[mutate(skip)]`,
+/// see `libafl::inputs::structured`.
+#[proc_macro_derive(Generate, attributes(mutate))]
+pub fn libafl_generate_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    generate_derive(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+fn generate_derive(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let variants = structured_variants(input)?;
+    let arms = variants.iter().enumerate().map(|(variant_idx, variant)| {
+        let path = &variant.path;
+        let fields = variant.fields.iter().map(|field| {
+            let StructuredField {
+                member,
+                ty,
+                constraints,
+                skip,
+                ..
+            } = field;
+            if *skip {
+                quote! { #member: ::core::default::Default::default() }
+            } else {
+                quote! { #member: <#ty as libafl::inputs::Generate>::generate(rand, &#constraints) }
+            }
+        });
+        let variant_idx = variant_idx as u64;
+        quote! { #variant_idx => #path { #(#fields,)* } }
+    });
+
+    let name = &input.ident;
+    let variant_count = variants.len() as u64;
+    let generics = add_trait_bounds(input.generics.clone(), &quote! { libafl::inputs::Generate });
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    Ok(quote! {
+        impl #impl_generics libafl::inputs::Generate for #name #ty_generics #where_clause {
+            fn generate<R: libafl_bolts::rands::Rand>(
+                rand: &mut R,
+                _constraints: &libafl::inputs::FieldConstraints,
+            ) -> Self {
+                match libafl_bolts::rands::Rand::below(rand, #variant_count) {
+                    #(#arms,)*
+                    _ => unreachable!(),
+                }
+            }
+        }
+    })
+}
+
+/// Derive macro to implement `libafl::inputs::Mutate`, mutating values field by field,
+/// as well as `libafl::inputs::Input`, to fuzz them with the structured mutations.
+/// Types with generic parameters implement `Input` by hand.
+///
+/// Enums need to derive `Generate` as well, to switch between variants.
+/// Fields take the constraints `#[mutate(range = lo..=hi)]`, `#[mutate(max_len = n)]` and `#[mutate(skip)]`,
+/// see `libafl::inputs::structured`.
+#[proc_macro_derive(Mutate, attributes(mutate))]
+pub fn libafl_mutate_derive(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    mutate_derive(&input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[allow(clippy::too_many_lines)]
+fn mutate_derive(input: &DeriveInput) -> Result<TokenStream2, Error> {
+    let variants = structured_variants(input)?;
+    let is_enum = matches!(input.data, Data::Enum(_));
+
+    let mut mutate_arms = Vec::new();
+    let mut splice_arms = Vec::new();
+    let mut fields_arms = Vec::new();
+    let mut fields_mut_arms = Vec::new();
+    for variant in &variants {
+        let pattern = variant.pattern("");
+        let other_pattern = variant.pattern("other_");
+        let bindings: Vec<_> = variant
+            .mutable_fields()
+            .map(|field| &field.binding)
+            .collect();
+        let other_bindings: Vec<_> = bindings
+            .iter()
+            .map(|binding| format_ident!("other_{}", binding))
+            .collect();
+        let constraints: Vec<_> = variant
+            .mutable_fields()
+            .map(|field| &field.constraints)
+            .collect();
+        let count = bindings.len() as u64;
+        let indices: Vec<_> = (0..count).collect();
+
+        mutate_arms.push(if bindings.is_empty() {
+            quote! { #pattern => libafl::mutators::MutationResult::Skipped }
+        } else {
+            quote! {
+                #pattern => match libafl_bolts::rands::Rand::below(rand, #count) {
+                    #(#indices => libafl::inputs::Mutate::mutate(#bindings, rand, &#constraints),)*
+                    _ => unreachable!(),
+                }
+            }
+        });
+
+        // Splice within a random field, and take all fields after it from `other`
+        splice_arms.push(if bindings.is_empty() {
+            quote! { (#pattern, #other_pattern) => libafl::mutators::MutationResult::Skipped }
+        } else {
+            quote! {
+                (#pattern, #other_pattern) => {
+                    let cut = libafl_bolts::rands::Rand::below(rand, #count);
+                    let mut idx = 0;
+                    #(
+                        if idx == cut {
+                            libafl::inputs::Mutate::splice(#bindings, #other_bindings, rand, &#constraints);
+                        } else if idx > cut {
+                            ::core::clone::Clone::clone_from(#bindings, #other_bindings);
+                        }
+                        idx += 1;
+                    )*
+                    libafl::mutators::MutationResult::Mutated
+                }
+            }
+        });
+
+        fields_arms.push(quote! {
+            #pattern => {
+                #(libafl::inputs::Mutate::fields(#bindings, &#constraints, visitor);)*
+            }
+        });
+        fields_mut_arms.push(quote! {
+            #pattern => {
+                #(libafl::inputs::Mutate::fields_mut(#bindings, &#constraints, visitor);)*
+            }
+        });
+    }
+    if is_enum {
+        splice_arms.push(quote! { _ => libafl::mutators::MutationResult::Skipped });
+    }
+
+    let switch_variant = if is_enum && variants.len() > 1 {
+        quote! {
+            if libafl_bolts::rands::Rand::below(rand, 8) == 0 {
+                *self = <Self as libafl::inputs::Generate>::generate(rand, constraints);
+                return libafl::mutators::MutationResult::Mutated;
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    let name = &input.ident;
+    let generics = add_trait_bounds(input.generics.clone(), &quote! { libafl::inputs::Mutate });
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+    // Generic types need bounds on their parameters this derive doesn't know of
+    let input_impl = if input.generics.params.is_empty() {
+        quote! {
+            impl libafl::inputs::Input for #name {
+                fn generate_name(&self, _idx: usize) -> libafl::alloc::string::String {
+                    libafl::inputs::structured::structured_input_name(self)
+                }
+            }
+        }
+    } else {
+        quote! {}
+    };
+    Ok(quote! {
+        impl #impl_generics libafl::inputs::Mutate for #name #ty_generics #where_clause {
+            #[allow(unused_variables)]
+            fn mutate<R: libafl_bolts::rands::Rand>(
+                &mut self,
+                rand: &mut R,
+                constraints: &libafl::inputs::FieldConstraints,
+            ) -> libafl::mutators::MutationResult {
+                #switch_variant
+                match self {
+                    #(#mutate_arms,)*
+                }
+            }
+
+            #[allow(unused_variables, unused_assignments)]
+            fn splice<R: libafl_bolts::rands::Rand>(
+                &mut self,
+                other: &Self,
+                rand: &mut R,
+                _constraints: &libafl::inputs::FieldConstraints,
+            ) -> libafl::mutators::MutationResult {
+                match (self, other) {
+                    #(#splice_arms,)*
+                }
+            }
+
+            #[allow(unused_variables)]
+            fn fields<'a>(
+                &'a self,
+                constraints: &libafl::inputs::FieldConstraints,
+                visitor: &mut dyn FnMut(&'a dyn libafl::inputs::StructuredField, &libafl::inputs::FieldConstraints),
+            ) {
+                visitor(self, constraints);
+                match self {
+                    #(#fields_arms,)*
+                }
+            }
+
+            #[allow(unused_variables)]
+            fn fields_mut(
+                &mut self,
+                constraints: &libafl::inputs::FieldConstraints,
+                visitor: &mut dyn FnMut(&mut dyn libafl::inputs::StructuredField, &libafl::inputs::FieldConstraints),
+            ) {
+                visitor(self, constraints);
+                match self {
+                    #(#fields_mut_arms,)*
+                }
+            }
+        }
+
+        #input_impl
+    })
+}
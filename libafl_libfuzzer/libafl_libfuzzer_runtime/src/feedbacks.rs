@@ -78,13 +78,18 @@ impl LibfuzzerCrashCauseMetadata {
 #[derive(Debug)]
 pub struct LibfuzzerCrashCauseFeedback {
     artifact_prefix: Option<ArtifactPrefix>,
+    exact_artifact_path: Option<PathBuf>,
     exit_kind: ExitKind,
 }
 
 impl LibfuzzerCrashCauseFeedback {
-    pub fn new(artifact_prefix: Option<ArtifactPrefix>) -> Self {
+    pub fn new(
+        artifact_prefix: Option<ArtifactPrefix>,
+        exact_artifact_path: Option<PathBuf>,
+    ) -> Self {
         Self {
             artifact_prefix,
+            exact_artifact_path,
             exit_kind: ExitKind::Ok,
         }
     }
@@ -104,7 +109,10 @@ impl LibfuzzerCrashCauseFeedback {
             let name = testcase.input().as_ref().unwrap().generate_name(0);
             name
         };
-        let file_path = if let Some(artifact_prefix) = self.artifact_prefix.as_ref() {
+        let file_path = if let Some(exact_artifact_path) = self.exact_artifact_path.as_ref() {
+            // like libFuzzer, -exact_artifact_path overrides -artifact_prefix
+            exact_artifact_path.clone()
+        } else if let Some(artifact_prefix) = self.artifact_prefix.as_ref() {
             if let Some(filename_prefix) = artifact_prefix.filename_prefix() {
                 artifact_prefix
                     .dir()
@@ -179,3 +187,29 @@ where
 }
 
 pub type ShrinkMapFeedback<O, S, T> = MinMapFeedback<MappedEdgeMapObserver<O, T>, S, usize>;
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use libafl::{corpus::Testcase, inputs::BytesInput};
+
+    use super::LibfuzzerCrashCauseFeedback;
+
+    #[test]
+    fn test_exact_artifact_path() {
+        let exact = PathBuf::from("/tmp/exact-crash");
+        let feedback = LibfuzzerCrashCauseFeedback::new(None, Some(exact.clone()));
+        let mut testcase =
+            Testcase::with_filename(BytesInput::new(b"crash".to_vec()), "input".into());
+        feedback.set_filename("crash", &mut testcase);
+        assert_eq!(testcase.file_path().as_ref(), Some(&exact));
+
+        let feedback = LibfuzzerCrashCauseFeedback::new(None, None);
+        feedback.set_filename("timeout", &mut testcase);
+        assert_eq!(
+            testcase.file_path().as_ref(),
+            Some(&PathBuf::from("timeout-input"))
+        );
+    }
+}
@@ -3,7 +3,7 @@ use std::{
     fmt::Debug,
     fs::File,
     net::TcpListener,
-    time::{SystemTime, UNIX_EPOCH},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
 #[cfg(unix)]
 use std::{
@@ -14,7 +14,7 @@ use std::{
 use libafl::{
     corpus::Corpus,
     events::{
-        launcher::Launcher, EventConfig, ProgressReporter, SimpleEventManager,
+        launcher::Launcher, EventConfig, EventRestarter, ProgressReporter, SimpleEventManager,
         SimpleRestartingEventManager,
     },
     executors::ExitKind,
@@ -25,18 +25,75 @@ use libafl::{
     },
     stages::StagesTuple,
     state::{
-        HasClientPerfMonitor, HasExecutions, HasLastReportTime, HasMetadata, HasSolutions,
-        UsesState,
+        HasClientPerfMonitor, HasCorpus, HasExecutions, HasLastReportTime, HasMetadata,
+        HasSolutions, HasStartTime, UsesState,
     },
     Error, Fuzzer,
 };
 use libafl_bolts::{
     core_affinity::Cores,
+    current_time, impl_serdeany,
     shmem::{ShMemProvider, StdShMemProvider},
 };
+use serde::{Deserialize, Serialize};
 
 use crate::{feedbacks::LibfuzzerCrashCauseMetadata, fuzz_with, options::LibfuzzerOptions};
 
+/// How often the progress is reported, as in `fuzz_loop`
+const STATS_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// The size of the corpus when the fuzzing started, for `-print_final_stats`
+#[derive(Deserialize, Serialize, Debug)]
+pub(crate) struct InitialCorpusMetadata {
+    count: usize,
+}
+
+impl_serdeany!(InitialCorpusMetadata);
+
+/// The peak resident set size of this process, in megabytes
+#[cfg(unix)]
+fn peak_rss_mb() -> usize {
+    let mut usage: libc::rusage = unsafe { core::mem::zeroed() };
+    if unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) } != 0 {
+        return 0;
+    }
+    // in bytes on apple, in kilobytes elsewhere
+    #[cfg(target_vendor = "apple")]
+    let kib = usage.ru_maxrss as usize >> 10;
+    #[cfg(not(target_vendor = "apple"))]
+    let kib = usage.ru_maxrss as usize;
+    kib >> 10
+}
+
+#[cfg(not(unix))]
+fn peak_rss_mb() -> usize {
+    0
+}
+
+/// Prints the statistics of `-print_final_stats`, in the format of libFuzzer
+fn print_final_stats<S>(state: &S)
+where
+    S: HasExecutions + HasStartTime + HasCorpus + HasMetadata,
+{
+    let executions = *state.executions();
+    let elapsed = current_time()
+        .saturating_sub(*state.start_time())
+        .as_secs()
+        .max(1);
+    let new_units = state
+        .metadata::<InitialCorpusMetadata>()
+        .map_or(0, |initial| {
+            state.corpus().count().saturating_sub(initial.count)
+        });
+    eprintln!("stat::number_of_executed_units: {executions}");
+    eprintln!(
+        "stat::average_exec_per_sec:     {}",
+        executions as u64 / elapsed
+    );
+    eprintln!("stat::new_units_added:          {new_units}");
+    eprintln!("stat::peak_rss_mb:              {}", peak_rss_mb());
+}
+
 fn do_fuzz<F, ST, E, S, EM>(
     options: &LibfuzzerOptions,
     fuzzer: &mut F,
@@ -51,12 +108,21 @@ where
         + HasMetadata
         + HasExecutions
         + UsesInput
+        + HasCorpus
         + HasSolutions
+        + HasStartTime
         + HasLastReportTime,
     E: UsesState<State = S>,
-    EM: ProgressReporter<State = S>,
+    EM: ProgressReporter<State = S> + EventRestarter<State = S>,
     ST: StagesTuple<E, EM, S, F>,
 {
+    // the start of the whole campaign, kept in the state across restarts
+    if state.start_time().is_zero() {
+        *state.start_time_mut() = current_time();
+        let count = state.corpus().count();
+        state.add_metadata(InitialCorpusMetadata { count });
+    }
+
     if let Some(solution) = state.solutions().last() {
         let kind = state
             .solutions()
@@ -76,12 +142,36 @@ where
             }
         }
         if halt {
+            if options.print_final_stats() {
+                print_final_stats(state);
+            }
             log::info!("Halting; the error on the next line is actually okay. :)");
             return Err(Error::shutting_down());
         }
     }
-    fuzzer.fuzz_loop(stages, executor, state, mgr)?;
-    Ok(())
+
+    let Some(max_total_time) = options.max_total_time() else {
+        fuzzer.fuzz_loop(stages, executor, state, mgr)?;
+        return Ok(());
+    };
+
+    // like libFuzzer, -max_total_time counts from the start of the campaign, not of this process
+    while current_time().saturating_sub(*state.start_time()) < max_total_time {
+        mgr.maybe_report_progress(state, STATS_TIMEOUT)?;
+        fuzzer.fuzz_one(stages, executor, state, mgr)?;
+    }
+    eprintln!(
+        "Done {} runs in {} second(s)",
+        state.executions(),
+        current_time().saturating_sub(*state.start_time()).as_secs()
+    );
+    if options.print_final_stats() {
+        print_final_stats(state);
+    }
+
+    // no need to restart us any longer
+    mgr.send_exiting()?;
+    Err(Error::shutting_down())
 }
 
 fn fuzz_single_forking<M>(
@@ -222,3 +312,65 @@ pub fn fuzz(
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use core::marker::PhantomData;
+    use std::time::{Duration, Instant};
+
+    use libafl::{
+        corpus::{Corpus, InMemoryCorpus, Testcase},
+        events::NopEventManager,
+        feedbacks::ConstFeedback,
+        inputs::{BytesInput, UsesInput},
+        schedulers::QueueScheduler,
+        state::{HasCorpus, StdState, UsesState},
+        Error, StdFuzzer,
+    };
+    use libafl_bolts::rands::StdRand;
+
+    use super::do_fuzz;
+    use crate::options::LibfuzzerOptions;
+
+    #[derive(Debug)]
+    struct TestExecutor<S>(PhantomData<S>);
+
+    impl<S> UsesState for TestExecutor<S>
+    where
+        S: UsesInput,
+    {
+        type State = S;
+    }
+
+    #[test]
+    fn test_max_total_time() {
+        let options = LibfuzzerOptions::new(["fuzzer", "-max_total_time=1"].into_iter()).unwrap();
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+        state
+            .corpus_mut()
+            .add(Testcase::new(BytesInput::new(b"seed".to_vec())))
+            .unwrap();
+        let mut fuzzer = StdFuzzer::new(QueueScheduler::new(), feedback, objective);
+
+        let start = Instant::now();
+        let result = do_fuzz(
+            &options,
+            &mut fuzzer,
+            &mut (),
+            &mut TestExecutor(PhantomData),
+            &mut state,
+            &mut NopEventManager::new(),
+        );
+        assert!(matches!(result, Err(Error::ShuttingDown)));
+        assert!(start.elapsed() >= Duration::from_secs(1));
+    }
+}
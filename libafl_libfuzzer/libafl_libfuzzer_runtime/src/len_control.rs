@@ -0,0 +1,172 @@
+use core::marker::PhantomData;
+
+use libafl::{
+    corpus::{Corpus, CorpusId},
+    inputs::UsesInput,
+    stages::Stage,
+    state::{HasCorpus, HasExecutions, HasMaxSize, HasMetadata, UsesState},
+    Error,
+};
+use libafl_bolts::{impl_serdeany, HasLen};
+use serde::{Deserialize, Serialize};
+
+/// The default `-max_len` when there is no corpus, as in libFuzzer
+const MIN_DEFAULT_MAX_LEN: usize = 4096;
+/// The largest default `-max_len`, as in libFuzzer
+const MAX_SANE_LEN: usize = 1 << 20;
+
+/// The `-len_control` state, kept in the fuzzer state across restarts.
+/// The current length limit of the mutations is the max size of the state.
+#[derive(Deserialize, Serialize, Debug)]
+pub(crate) struct LenControlMetadata {
+    max_len: usize,
+    last_corpus_count: usize,
+    last_update_executions: usize,
+}
+
+impl_serdeany!(LenControlMetadata);
+
+/// `floor(log2(x))`, libFuzzer's `Log`
+fn log(x: usize) -> usize {
+    x.checked_ilog2().unwrap_or(0) as usize
+}
+
+/// The next length limit, if it grows: after `len_control * log(limit)` runs without new inputs,
+/// the limit grows by `log(limit)`, up to `max_len`
+fn next_len_limit(
+    limit: usize,
+    max_len: usize,
+    len_control: usize,
+    runs_without_new_inputs: usize,
+) -> Option<usize> {
+    (limit < max_len && runs_without_new_inputs > len_control * log(limit))
+        .then(|| max_len.min(limit + log(limit)))
+}
+
+/// Sets up the `-max_len` and `-len_control` of a new state, once its initial corpus is loaded.
+///
+/// Like libFuzzer, without `-max_len` the maximum length is the largest input of the corpus,
+/// but at least 4096 bytes. With `-len_control`, mutations start at the largest input of the
+/// corpus, or 4 bytes, and grow as the coverage stalls; `-len_control=0` starts at `max_len`.
+pub(crate) fn init_len_control<S>(
+    state: &mut S,
+    max_len: Option<usize>,
+    len_control: usize,
+) -> Result<(), Error>
+where
+    S: HasCorpus + HasExecutions + HasMaxSize + HasMetadata + UsesInput,
+    S::Input: HasLen,
+{
+    let mut largest_input = 0;
+    for id in state.corpus().ids() {
+        let len = state
+            .corpus()
+            .get(id)?
+            .borrow_mut()
+            .load_len(state.corpus())?;
+        largest_input = largest_input.max(len);
+    }
+
+    let max_len = max_len
+        .unwrap_or_else(|| largest_input.clamp(MIN_DEFAULT_MAX_LEN, MAX_SANE_LEN))
+        .max(1);
+    let limit = if len_control == 0 {
+        max_len
+    } else {
+        max_len.min(largest_input.max(4))
+    };
+    state.set_max_size(limit);
+
+    let meta = LenControlMetadata {
+        max_len,
+        last_corpus_count: state.corpus().count(),
+        last_update_executions: *state.executions(),
+    };
+    state.add_metadata(meta);
+    Ok(())
+}
+
+/// Grows the length limit of the mutations for `-len_control`, as libFuzzer does between runs
+#[derive(Debug)]
+pub(crate) struct LenControlStage<E, EM, Z> {
+    len_control: usize,
+    phantom: PhantomData<(E, EM, Z)>,
+}
+
+impl<E, EM, Z> LenControlStage<E, EM, Z> {
+    pub fn new(len_control: usize) -> Self {
+        Self {
+            len_control,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<E, EM, Z> UsesState for LenControlStage<E, EM, Z>
+where
+    E: UsesState,
+{
+    type State = E::State;
+}
+
+impl<E, EM, Z> Stage<E, EM, Z> for LenControlStage<E, EM, Z>
+where
+    E: UsesState,
+    EM: UsesState<State = E::State>,
+    Z: UsesState<State = E::State>,
+    E::State: HasCorpus + HasExecutions + HasMaxSize + HasMetadata,
+{
+    fn perform(
+        &mut self,
+        _fuzzer: &mut Z,
+        _executor: &mut E,
+        state: &mut E::State,
+        _manager: &mut EM,
+        _corpus_idx: CorpusId,
+    ) -> Result<(), Error> {
+        if self.len_control == 0 {
+            return Ok(());
+        }
+
+        let executions = *state.executions();
+        let corpus_count = state.corpus().count();
+        let limit = state.max_size();
+        let Some(meta) = state.metadata_map_mut().get_mut::<LenControlMetadata>() else {
+            return Ok(());
+        };
+
+        if corpus_count != meta.last_corpus_count {
+            meta.last_corpus_count = corpus_count;
+            meta.last_update_executions = executions;
+            return Ok(());
+        }
+
+        let next = next_len_limit(
+            limit,
+            meta.max_len,
+            self.len_control,
+            executions.saturating_sub(meta.last_update_executions),
+        );
+        if let Some(next) = next {
+            meta.last_update_executions = executions;
+            state.set_max_size(next);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::next_len_limit;
+
+    #[test]
+    fn test_next_len_limit() {
+        // with the default -len_control=100, a limit of 4 grows after 200 runs without new inputs
+        assert_eq!(next_len_limit(4, 4096, 100, 200), None);
+        assert_eq!(next_len_limit(4, 4096, 100, 201), Some(6));
+        // by log2 of the limit, up to max_len
+        assert_eq!(next_len_limit(1024, 4096, 100, 1001), Some(1034));
+        assert_eq!(next_len_limit(4090, 4096, 1, 100), Some(4096));
+        assert_eq!(next_len_limit(4096, 4096, 1, 100), None);
+    }
+}
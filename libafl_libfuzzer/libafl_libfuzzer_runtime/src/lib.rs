@@ -83,8 +83,10 @@ use crate::options::{LibfuzzerMode, LibfuzzerOptions};
 
 mod feedbacks;
 mod fuzz;
+mod len_control;
 mod merge;
 mod misc;
+mod mutators;
 mod observers;
 mod options;
 mod report;
@@ -155,7 +157,7 @@ macro_rules! fuzz_with {
             executors::{ExitKind, InProcessExecutor, TimeoutExecutor},
             feedback_and_fast, feedback_not, feedback_or, feedback_or_fast,
             feedbacks::{ConstFeedback, CrashFeedback, MaxMapFeedback, NewHashFeedback, TimeFeedback, TimeoutFeedback},
-            generators::RandBytesGenerator,
+            generators::{RandBytesGenerator, RandPrintablesGenerator},
             inputs::{BytesInput, HasTargetBytes},
             mutators::{
                 GrimoireExtensionMutator, GrimoireRecursiveReplacementMutator, GrimoireRandomDeleteMutator,
@@ -164,27 +166,32 @@ macro_rules! fuzz_with {
             },
             observers::{stacktrace::BacktraceObserver, TimeObserver},
             schedulers::{
-                IndexesLenTimeMinimizerScheduler, powersched::PowerSchedule, PowerQueueScheduler,
+                EntropicScheduler, IndexesLenTimeMinimizerScheduler, powersched::PowerSchedule, PowerQueueScheduler,
             },
             stages::{
                 CalibrationStage, GeneralizationStage, IfStage, StdMutationalStage,
                 StdPowerMutationalStage, TracingStage,
             },
-            state::{HasCorpus, StdState},
+            state::{HasCorpus, HasMetadata, StdState},
             StdFuzzer,
         };
-        use libafl_targets::{CmpLogObserver, LLVMCustomMutator, OomFeedback, OomObserver};
+        use libafl_targets::{CmpLogObserver, LLVMCustomMutator, OomFeedback, OomObserver, CMP_MAP};
         use rand::{thread_rng, RngCore};
         use std::{env::temp_dir, fs::create_dir, path::PathBuf};
 
         use crate::{BACKTRACE, CustomMutationStatus};
         use crate::feedbacks::{LibfuzzerCrashCauseFeedback, LibfuzzerKeepFeedback, ShrinkMapFeedback};
+        use crate::len_control::{init_len_control, LenControlMetadata, LenControlStage};
         use crate::misc::should_use_grimoire;
+        use crate::mutators::AsciiMutator;
         use crate::observers::{MappedEdgeMapObserver, SizeValueObserver};
+        use crate::schedulers::FuzzScheduler;
 
         let edge_maker = &$edge_maker;
 
-        let closure = |mut state: Option<_>, mut mgr, _cpu_id| {
+        let closure = |mut state: Option<_>, mut mgr, cpu_id| {
+            // the index of this client, to give each fork its own seed
+            let client_id: usize = Into::into(cpu_id);
             let mutator_status = CustomMutationStatus::new();
             let grimoire_metadata = should_use_grimoire(&mut state, &$options, &mutator_status)?;
             let grimoire = grimoire_metadata.should();
@@ -201,6 +208,14 @@ macro_rules! fuzz_with {
             // Create an OOM observer to monitor if an OOM has occurred
             let oom_observer = OomObserver::new($options.rss_limit(), $options.malloc_limit());
 
+            // Create an observer on the compared values, for -use_value_profile;
+            // without it, the observer has an empty map so it costs nothing per execution
+            let value_profile_observer = if $options.use_value_profile() {
+                unsafe { StdMapObserver::new("value_profile", &mut CMP_MAP) }
+            } else {
+                StdMapObserver::owned("value_profile", Vec::new())
+            };
+
             // Create the Cmp observer
             let cmplog_observer = CmpLogObserver::new("cmplog", true);
 
@@ -214,6 +229,7 @@ macro_rules! fuzz_with {
             // New maximization map feedback linked to the edges observer
             let map_feedback = MaxMapFeedback::tracking(&edges_observer, true, true);
             let shrinking_map_feedback = ShrinkMapFeedback::tracking(&size_edges_observer, false, false);
+            let value_profile_feedback = MaxMapFeedback::new(&value_profile_observer);
 
             // Set up a generalization stage for grimoire
             let generalization = GeneralizationStage::new(&edges_observer);
@@ -235,6 +251,7 @@ macro_rules! fuzz_with {
                 feedback_or!(
                     map_feedback,
                     feedback_and_fast!(ConstFeedback::new($options.shrink()), shrinking_map_feedback),
+                    value_profile_feedback,
                     // Time feedback, this one does not need a feedback state
                     TimeFeedback::with_observer(&time_observer)
                 )
@@ -242,7 +259,7 @@ macro_rules! fuzz_with {
 
             // A feedback to choose if an input is a solution or not
             let mut objective = feedback_or_fast!(
-                LibfuzzerCrashCauseFeedback::new($options.artifact_prefix().cloned()),
+                LibfuzzerCrashCauseFeedback::new($options.artifact_prefix().cloned(), $options.exact_artifact_path().cloned()),
                 OomFeedback,
                 feedback_and_fast!(
                     CrashFeedback::new(),
@@ -268,7 +285,10 @@ macro_rules! fuzz_with {
                 dir
             };
 
-            let crash_corpus = if let Some(prefix) = $options.artifact_prefix() {
+            let crash_corpus = if let Some(path) = $options.exact_artifact_path() {
+                OnDiskCorpus::with_meta_format_and_prefix(path.parent().unwrap(), None, None, false)
+                    .unwrap()
+            } else if let Some(prefix) = $options.artifact_prefix() {
                 OnDiskCorpus::with_meta_format_and_prefix(prefix.dir(), None, prefix.filename_prefix().clone(), false)
                     .unwrap()
             } else {
@@ -278,9 +298,11 @@ macro_rules! fuzz_with {
 
             // If not restarting, create a State from scratch
             let mut state = state.unwrap_or_else(|| {
+                let seed = $options.seed().unwrap_or_else(current_nanos).wrapping_add(client_id as u64);
+                eprintln!("INFO: Seed: {seed}");
                 StdState::new(
                     // RNG
-                    StdRand::with_seed(current_nanos()),
+                    StdRand::with_seed(seed),
                     // Corpus that will be evolved, we keep it in memory for performance
                     CachedOnDiskCorpus::with_meta_format_and_prefix(corpus_dir.clone(), 4096, None, None, true).unwrap(),
                     // Corpus in which we store solutions (crashes in this example),
@@ -313,18 +335,26 @@ macro_rules! fuzz_with {
             }
 
             // Setup a randomic Input2State stage, conditionally within a custom mutator
-            let i2s =
-                StdMutationalStage::new(StdScheduledMutator::new(tuple_list!(I2SRandReplace::new())));
+            let i2s = StdMutationalStage::new(AsciiMutator::new(
+                StdScheduledMutator::new(tuple_list!(I2SRandReplace::new())),
+                $options.only_ascii(),
+            ));
             let i2s = IfStage::new(|_, _, _, _, _| Ok((!mutator_status.custom_mutation).into()), (i2s, ()));
-            let cm_i2s = StdMutationalStage::new(unsafe {
-                LLVMCustomMutator::mutate_unchecked(StdScheduledMutator::new(tuple_list!(
-                    I2SRandReplace::new()
-                )))
-            });
+            let cm_i2s = StdMutationalStage::new(AsciiMutator::new(
+                unsafe {
+                    LLVMCustomMutator::mutate_unchecked(StdScheduledMutator::new(tuple_list!(
+                        I2SRandReplace::new()
+                    )))
+                },
+                $options.only_ascii(),
+            ));
             let cm_i2s = IfStage::new(|_, _, _, _, _| Ok(mutator_status.custom_mutation.into()), (cm_i2s, ()));
 
             // TODO configure with mutation stacking options from libfuzzer
-            let std_mutator = StdScheduledMutator::new(havoc_mutations().merge(tokens_mutations()));
+            let std_mutator = AsciiMutator::new(
+                StdScheduledMutator::new(havoc_mutations().merge(tokens_mutations())),
+                $options.only_ascii(),
+            );
 
             let std_power = StdPowerMutationalStage::new(std_mutator);
             let std_power = IfStage::new(|_, _, _, _, _| Ok(mutator_status.std_mutational.into()), (std_power, ()));
@@ -342,10 +372,16 @@ macro_rules! fuzz_with {
             // without performing the custom mutator's preprocessing beforehand
             // we opt not to use crossover in the LLVMFuzzerMutate and instead have a second crossover pass,
             // though it is likely an error for fuzzers to provide custom mutators but not custom crossovers
-            let custom_mutator = unsafe {
-                LLVMCustomMutator::mutate_unchecked(StdScheduledMutator::new(havoc_mutations_no_crossover().merge(tokens_mutations())))
-            };
-            let std_mutator_no_mutate = StdScheduledMutator::with_max_stack_pow(havoc_crossover(), 3);
+            let custom_mutator = AsciiMutator::new(
+                unsafe {
+                    LLVMCustomMutator::mutate_unchecked(StdScheduledMutator::new(havoc_mutations_no_crossover().merge(tokens_mutations())))
+                },
+                $options.only_ascii(),
+            );
+            let std_mutator_no_mutate = AsciiMutator::new(
+                StdScheduledMutator::with_max_stack_pow(havoc_crossover(), 3),
+                $options.only_ascii(),
+            );
 
             let cm_power = StdPowerMutationalStage::new(custom_mutator);
             let cm_power = IfStage::new(|_, _, _, _, _| Ok(mutator_status.custom_mutation.into()), (cm_power, ()));
@@ -356,13 +392,19 @@ macro_rules! fuzz_with {
             // a custom crossover is defined
             // while the scenario that a custom crossover is defined without a custom mutator is unlikely
             // we handle it here explicitly anyways
-            let custom_crossover = unsafe {
-                LLVMCustomMutator::crossover_unchecked(StdScheduledMutator::with_max_stack_pow(
-                    havoc_mutations_no_crossover().merge(tokens_mutations()),
-                    3,
-                ))
-            };
-            let std_mutator_no_crossover = StdScheduledMutator::new(havoc_mutations_no_crossover().merge(tokens_mutations()));
+            let custom_crossover = AsciiMutator::new(
+                unsafe {
+                    LLVMCustomMutator::crossover_unchecked(StdScheduledMutator::with_max_stack_pow(
+                        havoc_mutations_no_crossover().merge(tokens_mutations()),
+                        3,
+                    ))
+                },
+                $options.only_ascii(),
+            );
+            let std_mutator_no_crossover = AsciiMutator::new(
+                StdScheduledMutator::new(havoc_mutations_no_crossover().merge(tokens_mutations())),
+                $options.only_ascii(),
+            );
 
             let cc_power = StdMutationalStage::new(custom_crossover);
             let cc_power = IfStage::new(|_, _, _, _, _| Ok(mutator_status.custom_crossover.into()), (cc_power, ()));
@@ -383,8 +425,17 @@ macro_rules! fuzz_with {
             );
             let grimoire = IfStage::new(|_, _, _, _, _| Ok(grimoire.into()), (StdMutationalStage::transforming(grimoire_mutator), ()));
 
-            // A minimization+queue policy to get testcasess from the corpus
-            let scheduler = IndexesLenTimeMinimizerScheduler::new(PowerQueueScheduler::new(&mut state, &edges_observer, PowerSchedule::FAST));
+            // Entropic scheduling like libFuzzer, or a power queue, both with a minimization policy to get testcasess from the corpus
+            let scheduler = if $options.entropic() {
+                FuzzScheduler::Entropic(IndexesLenTimeMinimizerScheduler::new(EntropicScheduler::with_parameters(
+                    &mut state,
+                    &edges_observer,
+                    $options.entropic_number_of_rarest_features(),
+                    $options.entropic_feature_frequency_threshold(),
+                )))
+            } else {
+                FuzzScheduler::Power(IndexesLenTimeMinimizerScheduler::new(PowerQueueScheduler::new(&mut state, &edges_observer, PowerSchedule::FAST)))
+            };
 
             // A fuzzer with feedbacks and a corpus scheduler
             let mut fuzzer = StdFuzzer::new(scheduler, feedback, objective);
@@ -392,7 +443,11 @@ macro_rules! fuzz_with {
             // The wrapped harness function, calling out to the LLVM-style harness
             let mut harness = |input: &BytesInput| {
                 let target = input.target_bytes();
-                let buf = target.as_slice();
+                let mut buf = target.as_slice();
+                // like libFuzzer, inputs loaded from disk are truncated to -max_len
+                if let Some(max_len) = $options.max_len() {
+                    buf = &buf[..buf.len().min(max_len)];
+                }
 
                 let result = unsafe { crate::libafl_libfuzzer_test_one_input(Some(*$harness), buf.as_ptr(), buf.len()) };
                 match result {
@@ -410,7 +465,7 @@ macro_rules! fuzz_with {
             let mut executor = TimeoutExecutor::new(
                 InProcessExecutor::new(
                     &mut harness,
-                    tuple_list!(edges_observer, size_edges_observer, time_observer, backtrace_observer, oom_observer),
+                    tuple_list!(edges_observer, size_edges_observer, value_profile_observer, time_observer, backtrace_observer, oom_observer),
                     &mut fuzzer,
                     &mut state,
                     &mut mgr,
@@ -429,20 +484,42 @@ macro_rules! fuzz_with {
                         });
                     println!("We imported {} inputs from disk.", state.corpus().count());
                 }
+                if !$options.seed_inputs().is_empty() {
+                    state
+                        .load_initial_inputs_by_filenames_forced(&mut fuzzer, &mut executor, &mut mgr, $options.seed_inputs())
+                        .unwrap_or_else(|e| {
+                            panic!("Failed to load the seed inputs {:?}: {}", $options.seed_inputs(), e)
+                        });
+                    println!("We imported {} inputs from the seed inputs.", state.corpus().count());
+                }
                 if state.corpus().count() < 1 {
-                    // Generator of bytearrays of max size 64
-                    let mut generator = RandBytesGenerator::from(RandBytesGenerator::new(64));
+                    // Generator of bytearrays of max size 64, or -max_len if smaller
+                    let size = $options.max_len().map_or(64, |max_len| max_len.min(64));
 
                     // Generate 1024 initial inputs
-                    state
-                        .generate_initial_inputs(
-                            &mut fuzzer,
-                            &mut executor,
-                            &mut generator,
-                            &mut mgr,
-                            1 << 10,
-                        )
-                        .expect("Failed to generate the initial corpus");
+                    if $options.only_ascii() {
+                        let mut generator = RandPrintablesGenerator::new(size);
+                        state
+                            .generate_initial_inputs(
+                                &mut fuzzer,
+                                &mut executor,
+                                &mut generator,
+                                &mut mgr,
+                                1 << 10,
+                            )
+                            .expect("Failed to generate the initial corpus");
+                    } else {
+                        let mut generator = RandBytesGenerator::new(size);
+                        state
+                            .generate_initial_inputs(
+                                &mut fuzzer,
+                                &mut executor,
+                                &mut generator,
+                                &mut mgr,
+                                1 << 10,
+                            )
+                            .expect("Failed to generate the initial corpus");
+                    }
                     println!(
                         "We imported {} inputs from the generator.",
                         state.corpus().count()
@@ -451,6 +528,11 @@ macro_rules! fuzz_with {
             }
 
 
+            // The length limit of the mutations, once the initial corpus is known
+            if !state.has_metadata::<LenControlMetadata>() {
+                init_len_control(&mut state, $options.max_len(), $options.len_control())?;
+            }
+
             // Setup a tracing stage in which we log comparisons
             let tracing = IfStage::new(|_, _, _, _, _| Ok(!$options.skip_tracing()), (TracingStage::new(InProcessExecutor::new(
                 &mut tracing_harness,
@@ -462,6 +544,7 @@ macro_rules! fuzz_with {
 
             // The order of the stages matter!
             let mut stages = tuple_list!(
+                LenControlStage::new($options.len_control()),
                 calibration,
                 generalization,
                 tracing,
@@ -44,7 +44,9 @@ pub fn merge(
         return Err(Error::illegal_argument("Missing corpora to minimize; you should provide one directory to minimize into and one-to-many from which the inputs are loaded."));
     }
 
-    let crash_corpus = if let Some(prefix) = options.artifact_prefix() {
+    let crash_corpus = if let Some(path) = options.exact_artifact_path() {
+        OnDiskCorpus::with_meta_format_and_prefix(path.parent().unwrap(), None, None, true).unwrap()
+    } else if let Some(prefix) = options.artifact_prefix() {
         OnDiskCorpus::with_meta_format_and_prefix(
             prefix.dir(),
             None,
@@ -130,7 +132,10 @@ pub fn merge(
 
     // A feedback to choose if an input is a solution or not
     let mut objective = feedback_or_fast!(
-        LibfuzzerCrashCauseFeedback::new(options.artifact_prefix().cloned()),
+        LibfuzzerCrashCauseFeedback::new(
+            options.artifact_prefix().cloned(),
+            options.exact_artifact_path().cloned()
+        ),
         OomFeedback,
         CrashFeedback::new(),
         TimeoutFeedback::new()
@@ -0,0 +1,117 @@
+use libafl::{
+    corpus::CorpusId,
+    inputs::HasBytesVec,
+    mutators::{MutationResult, Mutator},
+    Error,
+};
+use libafl_bolts::Named;
+
+/// Makes `data` printable ASCII like libFuzzer's `ToASCII`: drops the high bit and turns
+/// non-printable, non-whitespace bytes into spaces
+pub fn to_ascii(data: &mut [u8]) {
+    for byte in data {
+        let ascii = *byte & 0x7f;
+        *byte = if ascii.is_ascii_graphic() || matches!(ascii, b' ' | b'\t'..=b'\r') {
+            ascii
+        } else {
+            b' '
+        };
+    }
+}
+
+/// A mutator whose mutations stay ASCII for `-only_ascii`, as libFuzzer's `MutationDispatcher` does
+#[derive(Debug)]
+pub struct AsciiMutator<M> {
+    inner: M,
+    only_ascii: bool,
+}
+
+impl<M> AsciiMutator<M> {
+    /// Wraps `inner`, converting its mutations to ASCII if `only_ascii` is set
+    pub fn new(inner: M, only_ascii: bool) -> Self {
+        Self { inner, only_ascii }
+    }
+}
+
+impl<M> Named for AsciiMutator<M>
+where
+    M: Named,
+{
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+}
+
+impl<I, M, S> Mutator<I, S> for AsciiMutator<M>
+where
+    I: HasBytesVec,
+    M: Mutator<I, S>,
+{
+    fn mutate(
+        &mut self,
+        state: &mut S,
+        input: &mut I,
+        stage_idx: i32,
+    ) -> Result<MutationResult, Error> {
+        let result = self.inner.mutate(state, input, stage_idx)?;
+        if self.only_ascii && result == MutationResult::Mutated {
+            to_ascii(input.bytes_mut());
+        }
+        Ok(result)
+    }
+
+    fn post_exec(
+        &mut self,
+        state: &mut S,
+        stage_idx: i32,
+        corpus_idx: Option<CorpusId>,
+    ) -> Result<(), Error> {
+        self.inner.post_exec(state, stage_idx, corpus_idx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use libafl::{
+        corpus::InMemoryCorpus,
+        feedbacks::ConstFeedback,
+        inputs::{BytesInput, HasBytesVec},
+        mutators::{havoc_mutations_no_crossover, Mutator, StdScheduledMutator},
+        state::StdState,
+    };
+    use libafl_bolts::rands::StdRand;
+
+    use super::{to_ascii, AsciiMutator};
+
+    #[test]
+    fn test_to_ascii() {
+        let mut data = *b"ok\t\n\x00\x7f\xc1\xff";
+        to_ascii(&mut data);
+        // 0xc1 keeps its low bits ('A'), 0xff becomes DEL and then a space
+        assert_eq!(&data, b"ok\t\n  A ");
+    }
+
+    #[test]
+    fn test_ascii_mutator() {
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            &mut ConstFeedback::new(false),
+            &mut ConstFeedback::new(false),
+        )
+        .unwrap();
+        let mut mutator = AsciiMutator::new(
+            StdScheduledMutator::new(havoc_mutations_no_crossover()),
+            true,
+        );
+        let mut input = BytesInput::new(b"seed".to_vec());
+        for _ in 0..1000 {
+            mutator.mutate(&mut state, &mut input, 0).unwrap();
+            assert!(input
+                .bytes()
+                .iter()
+                .all(|&b| b.is_ascii_graphic() || matches!(b, b' ' | b'\t'..=b'\r')));
+        }
+    }
+}
@@ -1,7 +1,12 @@
 use core::fmt::{Display, Formatter};
 use std::{path::PathBuf, time::Duration};
 
-use libafl::mutators::Tokens;
+use libafl::{
+    mutators::Tokens,
+    schedulers::entropic::{
+        DEFAULT_FEATURE_FREQUENCY_THRESHOLD, DEFAULT_NUMBER_OF_RAREST_FEATURES,
+    },
+};
 
 use crate::options::RawOption::{Directory, Flag};
 
@@ -111,6 +116,19 @@ pub struct LibfuzzerOptions {
     tui: bool,
     runs: usize,
     close_fd_mask: u8,
+    entropic: bool,
+    entropic_number_of_rarest_features: usize,
+    entropic_feature_frequency_threshold: u16,
+    max_len: Option<usize>,
+    max_total_time: Option<Duration>,
+    seed: Option<u64>,
+    only_ascii: bool,
+    use_value_profile: bool,
+    exact_artifact_path: Option<PathBuf>,
+    print_final_stats: bool,
+    seed_inputs: Vec<PathBuf>,
+    len_control: usize,
+    minimize_crash_internal_step: bool,
     unknown: Vec<String>,
 }
 
@@ -208,11 +226,80 @@ impl LibfuzzerOptions {
         self.close_fd_mask
     }
 
+    pub fn entropic(&self) -> bool {
+        self.entropic
+    }
+
+    pub fn entropic_number_of_rarest_features(&self) -> usize {
+        self.entropic_number_of_rarest_features
+    }
+
+    pub fn entropic_feature_frequency_threshold(&self) -> u16 {
+        self.entropic_feature_frequency_threshold
+    }
+
+    pub fn max_len(&self) -> Option<usize> {
+        self.max_len
+    }
+
+    pub fn max_total_time(&self) -> Option<Duration> {
+        self.max_total_time
+    }
+
+    pub fn seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn only_ascii(&self) -> bool {
+        self.only_ascii
+    }
+
+    pub fn use_value_profile(&self) -> bool {
+        self.use_value_profile
+    }
+
+    pub fn exact_artifact_path(&self) -> Option<&PathBuf> {
+        self.exact_artifact_path.as_ref()
+    }
+
+    pub fn print_final_stats(&self) -> bool {
+        self.print_final_stats
+    }
+
+    pub fn seed_inputs(&self) -> &[PathBuf] {
+        &self.seed_inputs
+    }
+
+    pub fn len_control(&self) -> usize {
+        self.len_control
+    }
+
+    pub fn minimize_crash_internal_step(&self) -> bool {
+        self.minimize_crash_internal_step
+    }
+
     pub fn unknown(&self) -> &[String] {
         &self.unknown
     }
 }
 
+/// The seed inputs of `-seed_inputs`: a comma-separated list of files, or `@` followed by a file
+/// containing such a list
+fn parse_seed_inputs(value: &str) -> Result<Vec<PathBuf>, OptionsParseError<'_>> {
+    let list = if let Some(list_file) = value.strip_prefix('@') {
+        std::fs::read_to_string(list_file)
+            .map_err(|_| OptionsParseError::OptionValueParseFailed("seed_inputs", value))?
+    } else {
+        value.to_string()
+    };
+    Ok(list
+        .split(',')
+        .map(str::trim)
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
 #[derive(Debug, Default)]
 #[allow(clippy::struct_excessive_bools)]
 struct LibfuzzerOptionsBuilder<'a> {
@@ -235,6 +322,19 @@ struct LibfuzzerOptionsBuilder<'a> {
     tui: bool,
     runs: usize,
     close_fd_mask: u8,
+    entropic: Option<bool>,
+    entropic_number_of_rarest_features: Option<usize>,
+    entropic_feature_frequency_threshold: Option<u16>,
+    max_len: Option<usize>,
+    max_total_time: Option<u64>,
+    seed: Option<u64>,
+    only_ascii: bool,
+    use_value_profile: bool,
+    exact_artifact_path: Option<&'a str>,
+    print_final_stats: bool,
+    seed_inputs: Vec<PathBuf>,
+    len_control: Option<usize>,
+    minimize_crash_internal_step: bool,
     unknown: Vec<&'a str>,
 }
 
@@ -273,6 +373,16 @@ impl<'a> LibfuzzerOptionsBuilder<'a> {
                                 return Err(OptionsParseError::MultipleModesSelected);
                             }
                         }
+                        "minimize_crash_internal_step" => {
+                            self.minimize_crash_internal_step =
+                                parse_or_bail!(name, value, u64) > 0;
+                            if self.minimize_crash_internal_step
+                                && *self.mode.get_or_insert(LibfuzzerMode::Tmin)
+                                    != LibfuzzerMode::Tmin
+                            {
+                                return Err(OptionsParseError::MultipleModesSelected);
+                            }
+                        }
                         "report" => {
                             if parse_or_bail!(name, value, u64) > 0
                                 && *self.mode.get_or_insert(LibfuzzerMode::Report)
@@ -317,6 +427,32 @@ impl<'a> LibfuzzerOptionsBuilder<'a> {
                         "tui" => self.tui = parse_or_bail!(name, value, u64) > 0,
                         "runs" => self.runs = parse_or_bail!(name, value, usize),
                         "close_fd_mask" => self.close_fd_mask = parse_or_bail!(name, value, u8),
+                        "entropic" => self.entropic = Some(parse_or_bail!(name, value, u64) > 0),
+                        "entropic_number_of_rarest_features" => {
+                            self.entropic_number_of_rarest_features =
+                                Some(parse_or_bail!(name, value, usize));
+                        }
+                        "entropic_feature_frequency_threshold" => {
+                            self.entropic_feature_frequency_threshold =
+                                Some(parse_or_bail!(name, value, u16));
+                        }
+                        "max_len" => self.max_len = Some(parse_or_bail!(name, value, usize)),
+                        "max_total_time" => {
+                            self.max_total_time = Some(parse_or_bail!(name, value, u64));
+                        }
+                        "seed" => self.seed = Some(parse_or_bail!(name, value, u64)),
+                        "only_ascii" => self.only_ascii = parse_or_bail!(name, value, u64) > 0,
+                        "use_value_profile" => {
+                            self.use_value_profile = parse_or_bail!(name, value, u64) > 0;
+                        }
+                        "exact_artifact_path" => self.exact_artifact_path = Some(value),
+                        "print_final_stats" => {
+                            self.print_final_stats = parse_or_bail!(name, value, u64) > 0;
+                        }
+                        "seed_inputs" => self.seed_inputs = parse_seed_inputs(value)?,
+                        "len_control" => {
+                            self.len_control = Some(parse_or_bail!(name, value, usize))
+                        }
                         _ => {
                             self.unknown.push(arg);
                         }
@@ -358,6 +494,33 @@ impl<'a> LibfuzzerOptionsBuilder<'a> {
             tui: self.tui,
             runs: self.runs,
             close_fd_mask: self.close_fd_mask,
+            // like libFuzzer, entropic scheduling is enabled by default
+            entropic: self.entropic.unwrap_or(true),
+            entropic_number_of_rarest_features: self
+                .entropic_number_of_rarest_features
+                .unwrap_or(DEFAULT_NUMBER_OF_RAREST_FEATURES),
+            entropic_feature_frequency_threshold: self
+                .entropic_feature_frequency_threshold
+                .unwrap_or(DEFAULT_FEATURE_FREQUENCY_THRESHOLD),
+            // like libFuzzer, 0 means no limit, or the default for the length
+            max_len: self.max_len.filter(|&max_len| max_len > 0),
+            max_total_time: self
+                .max_total_time
+                .filter(|&secs| secs > 0)
+                .map(Duration::from_secs),
+            seed: self.seed.filter(|&seed| seed > 0),
+            only_ascii: self.only_ascii,
+            use_value_profile: self.use_value_profile,
+            // absolute, as the artifacts go in the directory of the path
+            exact_artifact_path: self.exact_artifact_path.map(|path| {
+                std::env::current_dir()
+                    .expect("Couldn't get the current directory")
+                    .join(path)
+            }),
+            print_final_stats: self.print_final_stats,
+            seed_inputs: self.seed_inputs,
+            len_control: self.len_control.unwrap_or(100),
+            minimize_crash_internal_step: self.minimize_crash_internal_step,
             unknown: self
                 .unknown
                 .into_iter()
@@ -366,3 +529,111 @@ impl<'a> LibfuzzerOptionsBuilder<'a> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::Duration};
+
+    use super::{LibfuzzerMode, LibfuzzerOptions, OptionsParseError};
+
+    fn parse<'a>(args: &[&'a str]) -> Result<LibfuzzerOptions, OptionsParseError<'a>> {
+        LibfuzzerOptions::new(core::iter::once("fuzzer").chain(args.iter().copied()))
+    }
+
+    #[test]
+    fn test_defaults() {
+        let options = parse(&[]).unwrap();
+        assert_eq!(*options.mode(), LibfuzzerMode::Fuzz);
+        assert_eq!(options.max_len(), None);
+        assert_eq!(options.max_total_time(), None);
+        assert_eq!(options.seed(), None);
+        assert!(!options.only_ascii());
+        assert!(!options.use_value_profile());
+        assert!(options.exact_artifact_path().is_none());
+        assert!(!options.print_final_stats());
+        assert!(options.seed_inputs().is_empty());
+        assert_eq!(options.len_control(), 100);
+        assert!(!options.minimize_crash_internal_step());
+    }
+
+    #[test]
+    fn test_flags() {
+        let options = parse(&[
+            "-max_len=128",
+            "-max_total_time=60",
+            "-seed=1337",
+            "-only_ascii=1",
+            "-use_value_profile=1",
+            "-exact_artifact_path=crash.bin",
+            "-print_final_stats=1",
+            "-seed_inputs=a,b",
+            "-len_control=0",
+            "corpus",
+        ])
+        .unwrap();
+        assert!(options.unknown().is_empty());
+        assert_eq!(options.max_len(), Some(128));
+        assert_eq!(options.max_total_time(), Some(Duration::from_secs(60)));
+        assert_eq!(options.seed(), Some(1337));
+        assert!(options.only_ascii());
+        assert!(options.use_value_profile());
+        assert_eq!(
+            options.exact_artifact_path(),
+            Some(&std::env::current_dir().unwrap().join("crash.bin"))
+        );
+        assert!(options.print_final_stats());
+        assert_eq!(
+            options.seed_inputs(),
+            [PathBuf::from("a"), PathBuf::from("b")]
+        );
+        assert_eq!(options.len_control(), 0);
+        assert_eq!(options.dirs(), [PathBuf::from("corpus")]);
+    }
+
+    #[test]
+    fn test_zero_means_unset() {
+        // like libFuzzer, a zero max_len, max_total_time or seed picks the default
+        let options = parse(&["-max_len=0", "-max_total_time=0", "-seed=0"]).unwrap();
+        assert_eq!(options.max_len(), None);
+        assert_eq!(options.max_total_time(), None);
+        assert_eq!(options.seed(), None);
+    }
+
+    #[test]
+    fn test_seed_inputs_file() {
+        let list = std::env::temp_dir().join("libafl_libfuzzer_seed_inputs_test");
+        std::fs::write(&list, "x,y/z,\n").unwrap();
+        let arg = format!("-seed_inputs=@{}", list.display());
+        let options = parse(&[&arg]).unwrap();
+        assert_eq!(
+            options.seed_inputs(),
+            [PathBuf::from("x"), PathBuf::from("y/z")]
+        );
+        std::fs::remove_file(list).unwrap();
+
+        assert!(matches!(
+            parse(&["-seed_inputs=@/nonexistent/seed_inputs"]),
+            Err(OptionsParseError::OptionValueParseFailed("seed_inputs", _))
+        ));
+    }
+
+    #[test]
+    fn test_minimize_crash_internal_step() {
+        let options = parse(&["-minimize_crash_internal_step=1", "crash"]).unwrap();
+        assert_eq!(*options.mode(), LibfuzzerMode::Tmin);
+        assert!(options.minimize_crash_internal_step());
+
+        assert!(matches!(
+            parse(&["-minimize_crash_internal_step=1", "-merge=1"]),
+            Err(OptionsParseError::MultipleModesSelected)
+        ));
+    }
+
+    #[test]
+    fn test_invalid_value() {
+        assert!(matches!(
+            parse(&["-max_len=lots"]),
+            Err(OptionsParseError::OptionValueParseFailed("max_len", "lots"))
+        ));
+    }
+}
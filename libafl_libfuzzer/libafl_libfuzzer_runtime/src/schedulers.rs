@@ -7,6 +7,7 @@ use libafl::{
     corpus::{Corpus, CorpusId, Testcase},
     feedbacks::MapNoveltiesMetadata,
     inputs::UsesInput,
+    observers::ObserversTuple,
     schedulers::{RemovableScheduler, Scheduler},
     state::{HasCorpus, HasMetadata, UsesState},
     Error,
@@ -80,3 +81,95 @@ impl<S> MergeScheduler<S> {
         &self.all
     }
 }
+
+/// The scheduler used for fuzzing: entropic like libFuzzer (the default), or power scheduled
+#[derive(Clone, Debug)]
+pub enum FuzzScheduler<E, P> {
+    Entropic(E),
+    Power(P),
+}
+
+impl<E, P> UsesState for FuzzScheduler<E, P>
+where
+    E: UsesState,
+{
+    type State = E::State;
+}
+
+impl<E, P> RemovableScheduler for FuzzScheduler<E, P>
+where
+    E: RemovableScheduler,
+    P: RemovableScheduler<State = E::State>,
+    E::State: HasCorpus,
+{
+    fn on_remove(
+        &mut self,
+        state: &mut Self::State,
+        idx: CorpusId,
+        testcase: &Option<Testcase<<Self::State as UsesInput>::Input>>,
+    ) -> Result<(), Error> {
+        match self {
+            Self::Entropic(scheduler) => scheduler.on_remove(state, idx, testcase),
+            Self::Power(scheduler) => scheduler.on_remove(state, idx, testcase),
+        }
+    }
+
+    fn on_replace(
+        &mut self,
+        state: &mut Self::State,
+        idx: CorpusId,
+        prev: &Testcase<<Self::State as UsesInput>::Input>,
+    ) -> Result<(), Error> {
+        match self {
+            Self::Entropic(scheduler) => scheduler.on_replace(state, idx, prev),
+            Self::Power(scheduler) => scheduler.on_replace(state, idx, prev),
+        }
+    }
+}
+
+impl<E, P> Scheduler for FuzzScheduler<E, P>
+where
+    E: Scheduler,
+    P: Scheduler<State = E::State>,
+    E::State: HasCorpus,
+{
+    fn on_add(&mut self, state: &mut Self::State, idx: CorpusId) -> Result<(), Error> {
+        match self {
+            Self::Entropic(scheduler) => scheduler.on_add(state, idx),
+            Self::Power(scheduler) => scheduler.on_add(state, idx),
+        }
+    }
+
+    fn on_evaluation<OT>(
+        &mut self,
+        state: &mut Self::State,
+        input: &<Self::State as UsesInput>::Input,
+        observers: &OT,
+    ) -> Result<(), Error>
+    where
+        OT: ObserversTuple<Self::State>,
+    {
+        match self {
+            Self::Entropic(scheduler) => scheduler.on_evaluation(state, input, observers),
+            Self::Power(scheduler) => scheduler.on_evaluation(state, input, observers),
+        }
+    }
+
+    fn next(&mut self, state: &mut Self::State) -> Result<CorpusId, Error> {
+        match self {
+            Self::Entropic(scheduler) => scheduler.next(state),
+            Self::Power(scheduler) => scheduler.next(state),
+        }
+    }
+
+    fn set_current_scheduled(
+        &mut self,
+        state: &mut Self::State,
+        next_idx: Option<CorpusId>,
+    ) -> Result<(), Error> {
+        match self {
+            Self::Entropic(scheduler) => scheduler.set_current_scheduled(state, next_idx),
+            Self::Power(scheduler) => scheduler.set_current_scheduled(state, next_idx),
+        }
+    }
+}
@@ -24,7 +24,7 @@ use libafl_bolts::{
 };
 use libafl_targets::LLVMCustomMutator;
 
-use crate::{options::LibfuzzerOptions, CustomMutationStatus};
+use crate::{mutators::AsciiMutator, options::LibfuzzerOptions, CustomMutationStatus};
 
 type TMinState =
     StdState<BytesInput, InMemoryCorpus<BytesInput>, RomuDuoJrRand, InMemoryCorpus<BytesInput>>;
@@ -113,11 +113,17 @@ fn minimize_crash_with_mutator<M: Mutator<BytesInput, TMinState>>(
     let mut testcase = state.testcase_mut(id)?;
     let input = testcase.load_input(state.corpus())?.bytes().to_vec();
     drop(testcase);
-    if input.len() >= size {
+    if input.len() >= size && options.minimize_crash_internal_step() {
+        // the message of libFuzzer, which scripts driving the minimization may look for
+        eprintln!("INFO: Done MinimizeCrashInputInternalStep, no crashes found");
+    } else if input.len() >= size {
         eprintln!(
             "Unable to reduce {}",
             options.dirs()[0].as_path().as_os_str().to_str().unwrap()
         );
+    } else if let Some(dest) = options.exact_artifact_path() {
+        write(dest, input)?;
+        println!("Wrote minimised input to {}", dest.display());
     } else {
         let (mut dest, filename_prefix) = options.artifact_prefix().map_or_else(
             || (PathBuf::default(), ""),
@@ -173,14 +179,20 @@ pub fn minimize_crash(
 
     // TODO configure with mutation stacking options from libfuzzer
     if mutator_status.custom_mutation {
-        let custom_mutator = unsafe {
-            LLVMCustomMutator::mutate_unchecked(StdScheduledMutator::new(
-                havoc_mutations_no_crossover(),
-            ))
-        };
+        let custom_mutator = AsciiMutator::new(
+            unsafe {
+                LLVMCustomMutator::mutate_unchecked(StdScheduledMutator::new(
+                    havoc_mutations_no_crossover(),
+                ))
+            },
+            options.only_ascii(),
+        );
         minimize_crash_with_mutator(options, harness, custom_mutator, state)
     } else {
-        let std_mutator = StdScheduledMutator::new(havoc_mutations_no_crossover());
+        let std_mutator = AsciiMutator::new(
+            StdScheduledMutator::new(havoc_mutations_no_crossover()),
+            options.only_ascii(),
+        );
         minimize_crash_with_mutator(options, harness, std_mutator, state)
     }
 }
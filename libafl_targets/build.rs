@@ -27,6 +27,13 @@ fn main() {
     let acc_map_size: usize = option_env!("LIBAFL_ACCOUNTING_MAP_SIZE")
         .map_or(Ok(65536), str::parse)
         .expect("Could not parse LIBAFL_ACCOUNTING_MAP_SIZE");
+    let sancov_ngram_size: usize = option_env!("LIBAFL_SANCOV_NGRAM_SIZE")
+        .map_or(Ok(4), str::parse)
+        .expect("Could not parse LIBAFL_SANCOV_NGRAM_SIZE");
+    assert!(
+        (2..=16).contains(&sancov_ngram_size),
+        "LIBAFL_SANCOV_NGRAM_SIZE must be between 2 and 16"
+    );
 
     write!(
         constants_file,
@@ -42,6 +49,8 @@ fn main() {
         pub const CMPLOG_MAP_H: usize = {cmplog_map_h};
         /// The size of the accounting maps
         pub const ACCOUNTING_MAP_SIZE: usize = {acc_map_size};
+        /// The number of edges hashed together by the `sancov_pcguard_ngram` feature
+        pub const SANCOV_NGRAM_SIZE: usize = {sancov_ngram_size};
 "
     )
     .expect("Could not write file");
@@ -51,6 +60,7 @@ fn main() {
     println!("cargo:rerun-if-env-changed=LIBAFL_CMPLOG_MAP_W");
     println!("cargo:rerun-if-env-changed=LIBAFL_CMPLOG_MAP_H");
     println!("cargo:rerun-if-env-changed=LIBAFL_ACCOUNTING_MAP_SIZE");
+    println!("cargo:rerun-if-env-changed=LIBAFL_SANCOV_NGRAM_SIZE");
 
     #[cfg(any(feature = "sancov_value_profile", feature = "sancov_cmplog"))]
     {
@@ -93,6 +103,25 @@ fn main() {
         println!("cargo:rustc-link-arg=--undefined=__sanitizer_cov_trace_const_cmp8");
 
         println!("cargo:rustc-link-arg=--undefined=__sanitizer_cov_trace_switch");
+
+        // Without the value profile, these are no-ops, only there for targets built with them
+        #[cfg(feature = "sancov_value_profile")]
+        {
+            println!("cargo:rustc-link-arg=--undefined=__sanitizer_cov_trace_div4");
+            println!("cargo:rustc-link-arg=--undefined=__sanitizer_cov_trace_div8");
+            println!("cargo:rustc-link-arg=--undefined=__sanitizer_cov_trace_gep");
+        }
+    }
+
+    #[cfg(feature = "sancov_stack_depth")]
+    {
+        println!("cargo:rerun-if-changed=src/sancov_stack_depth.c");
+
+        cc::Build::new()
+            .file(src_dir.join("sancov_stack_depth.c"))
+            .compile("sancov_stack_depth");
+
+        println!("cargo:rustc-link-arg=--undefined=__sancov_lowest_stack");
     }
 
     #[cfg(feature = "libfuzzer")]
@@ -110,6 +139,15 @@ fn main() {
         libfuzzer.compile("libfuzzer");
     }
 
+    #[cfg(feature = "dfsan")]
+    {
+        println!("cargo:rerun-if-changed=src/dfsan.c");
+
+        cc::Build::new()
+            .file(src_dir.join("dfsan.c"))
+            .compile("dfsan");
+    }
+
     println!("cargo:rerun-if-changed=src/common.h");
     println!("cargo:rerun-if-changed=src/common.c");
 
@@ -46,6 +46,70 @@ void __libafl_targets_cmplog_instructions(uintptr_t k, uint8_t shape,
   __libafl_targets_cmplog(k, shape, arg1, arg2);
 }
 
+CmpLogMapExtended  libafl_cmplog_map_extended;
+CmpLogMapExtended *libafl_cmplog_map_extended_ptr = &libafl_cmplog_map_extended;
+
+// The hooks called by the cmplog instructions pass of libafl_cc,
+// with the same signatures as in AFL++'s afl-compiler-rt.c.
+// The shape is the size of the operands in bytes, minus one.
+
+void __cmplog_ins_hook1(uint8_t arg1, uint8_t arg2, uint8_t attr) {
+  uintptr_t k = RETADDR;
+  k = (k >> 4) ^ (k << 8);
+  k &= CMPLOG_EXTENDED_MAP_W - 1;
+
+  __libafl_targets_cmplog_extended(k, 0, arg1, arg2, 0, 0, attr);
+}
+
+void __cmplog_ins_hook2(uint16_t arg1, uint16_t arg2, uint8_t attr) {
+  uintptr_t k = RETADDR;
+  k = (k >> 4) ^ (k << 8);
+  k &= CMPLOG_EXTENDED_MAP_W - 1;
+
+  __libafl_targets_cmplog_extended(k, 1, arg1, arg2, 0, 0, attr);
+}
+
+void __cmplog_ins_hook4(uint32_t arg1, uint32_t arg2, uint8_t attr) {
+  uintptr_t k = RETADDR;
+  k = (k >> 4) ^ (k << 8);
+  k &= CMPLOG_EXTENDED_MAP_W - 1;
+
+  __libafl_targets_cmplog_extended(k, 3, arg1, arg2, 0, 0, attr);
+}
+
+void __cmplog_ins_hook8(uint64_t arg1, uint64_t arg2, uint8_t attr) {
+  uintptr_t k = RETADDR;
+  k = (k >> 4) ^ (k << 8);
+  k &= CMPLOG_EXTENDED_MAP_W - 1;
+
+  __libafl_targets_cmplog_extended(k, 7, arg1, arg2, 0, 0, attr);
+}
+
+#ifdef __SIZEOF_INT128__
+void __cmplog_ins_hook16(__uint128_t arg1, __uint128_t arg2, uint8_t attr) {
+  uintptr_t k = RETADDR;
+  k = (k >> 4) ^ (k << 8);
+  k &= CMPLOG_EXTENDED_MAP_W - 1;
+
+  __libafl_targets_cmplog_extended(k, 15, (uint64_t)arg1, (uint64_t)arg2,
+                                   (uint64_t)(arg1 >> 64),
+                                   (uint64_t)(arg2 >> 64), attr);
+}
+
+void __cmplog_ins_hookN(__uint128_t arg1, __uint128_t arg2, uint8_t attr,
+                        uint8_t size) {
+  if (!size || size > 16) { return; }
+
+  uintptr_t k = RETADDR;
+  k = (k >> 4) ^ (k << 8);
+  k &= CMPLOG_EXTENDED_MAP_W - 1;
+
+  __libafl_targets_cmplog_extended(k, size - 1, (uint64_t)arg1, (uint64_t)arg2,
+                                   (uint64_t)(arg1 >> 64),
+                                   (uint64_t)(arg2 >> 64), attr);
+}
+#endif
+
 // POSIX shenanigan to see if an area is mapped.
 // If it is mapped as X-only, we have a problem, so maybe we should add a check
 // to avoid to call it on .text addresses
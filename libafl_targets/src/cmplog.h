@@ -46,6 +46,57 @@ typedef struct CmpLogMap {
 extern CmpLogMap  libafl_cmplog_map;
 extern CmpLogMap *libafl_cmplog_map_ptr;
 
+// The AFL++ cmplog map layout, filled by the cmplog instructions pass of
+// libafl_cc, see cmp_header, cmp_operands and cmpfn_operands in AFL++'s cmplog.h
+
+#define CMPLOG_EXTENDED_MAP_W 65536
+#define CMPLOG_EXTENDED_MAP_H 32
+#define CMPLOG_EXTENDED_MAP_RTN_H (CMPLOG_EXTENDED_MAP_H / 2)
+
+#define CMPLOG_EXTENDED_TYPE_INS 1
+#define CMPLOG_EXTENDED_TYPE_RTN 2
+
+#pragma pack(push, 1)
+
+typedef struct CmpLogHeaderExtended {
+  uint64_t hits : 24;
+  uint64_t id : 24;
+  uint64_t shape : 5;
+  uint64_t type : 2;
+  uint64_t attribute : 4;
+  uint64_t overflow : 1;
+  uint64_t reserved : 4;
+} CmpLogHeaderExtended;
+
+typedef struct CmpLogInstructionExtended {
+  uint64_t v0;
+  uint64_t v1;
+  uint64_t v0_128;
+  uint64_t v1_128;
+} CmpLogInstructionExtended;
+
+typedef struct CmpLogRoutineExtended {
+  uint8_t v0[31];
+  uint8_t v0_len;
+  uint8_t v1[31];
+  uint8_t v1_len;
+} CmpLogRoutineExtended;
+
+typedef struct CmpLogMapExtended {
+  CmpLogHeaderExtended headers[CMPLOG_EXTENDED_MAP_W];
+  union {
+    CmpLogInstructionExtended operands[CMPLOG_EXTENDED_MAP_W]
+                                      [CMPLOG_EXTENDED_MAP_H];
+    CmpLogRoutineExtended routines[CMPLOG_EXTENDED_MAP_W]
+                                  [CMPLOG_EXTENDED_MAP_RTN_H];
+  } vals;
+} CmpLogMapExtended;
+
+#pragma pack(pop)
+
+extern CmpLogMapExtended  libafl_cmplog_map_extended;
+extern CmpLogMapExtended *libafl_cmplog_map_extended_ptr;
+
 extern uint8_t libafl_cmplog_enabled;
 
 void __libafl_targets_cmplog_instructions(uintptr_t k, uint8_t shape,
@@ -81,4 +132,35 @@ static inline void __libafl_targets_cmplog(uintptr_t k, uint8_t shape,
   libafl_cmplog_enabled = true;
 }
 
+static inline void __libafl_targets_cmplog_extended(uintptr_t k, uint8_t shape,
+                                                    uint64_t arg1, uint64_t arg2,
+                                                    uint64_t arg1_128,
+                                                    uint64_t arg2_128,
+                                                    uint8_t  attr) {
+  if (!libafl_cmplog_enabled) { return; }
+  libafl_cmplog_enabled = false;
+
+  CmpLogHeaderExtended *header = &libafl_cmplog_map_extended_ptr->headers[k];
+  uint32_t              hits;
+  if (header->type != CMPLOG_EXTENDED_TYPE_INS) {
+    header->type = CMPLOG_EXTENDED_TYPE_INS;
+    header->hits = 1;
+    header->shape = shape;
+    hits = 0;
+  } else {
+    hits = header->hits++;
+    if (header->shape < shape) { header->shape = shape; }
+  }
+  header->attribute = attr;
+
+  hits &= CMPLOG_EXTENDED_MAP_H - 1;
+  CmpLogInstructionExtended *operands =
+      &libafl_cmplog_map_extended_ptr->vals.operands[k][hits];
+  operands->v0 = arg1;
+  operands->v1 = arg2;
+  operands->v0_128 = arg1_128;
+  operands->v1_128 = arg2_128;
+  libafl_cmplog_enabled = true;
+}
+
 #endif
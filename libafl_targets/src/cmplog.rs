@@ -8,7 +8,7 @@ use core::fmt::{self, Debug, Formatter};
 use libafl::{
     executors::ExitKind,
     inputs::UsesInput,
-    observers::{cmp::CmpValuesMetadata, CmpMap, CmpObserver, CmpValues, Observer},
+    observers::{cmp::CmpValuesMetadata, AFLppCmpMap, CmpMap, CmpObserver, CmpValues, Observer},
     state::HasMetadata,
     Error,
 };
@@ -38,8 +38,18 @@ extern "C" {
 
     /// Pointer to the `CmpLog` map
     pub static mut libafl_cmplog_map_ptr: *mut CmpLogMap;
+
+    /// The `CmpLog` map in the `AFL++` layout, filled by the `cmplog-instructions-pass` of `libafl_cc`.
+    /// Like the other hooks, it is only written to while [`CMPLOG_ENABLED`] is set.
+    pub static mut libafl_cmplog_map_extended: AFLppCmpMap;
+
+    /// Pointer to the `AFL++`-layout `CmpLog` map.
+    /// A forkserver target points it to the shared map given in `__AFL_CMPLOG_SHM_ID`.
+    pub static mut libafl_cmplog_map_extended_ptr: *mut AFLppCmpMap;
 }
 
+pub use libafl_cmplog_map_extended as CMPLOG_MAP_EXTENDED;
+pub use libafl_cmplog_map_extended_ptr as CMPLOG_MAP_EXTENDED_PTR;
 pub use libafl_cmplog_map_ptr as CMPLOG_MAP_PTR;
 
 /// The header for `CmpLog` hits.
@@ -1,6 +1,10 @@
 //! Coverage maps as static mut array
 
 use alloc::string::String;
+#[cfg(not(feature = "pointer_maps"))]
+use core::sync::atomic::{AtomicBool, Ordering};
+#[cfg(feature = "thread_local_maps")]
+use core::{cell::Cell, ptr};
 
 #[cfg(any(target_os = "linux", target_vendor = "apple"))]
 use libafl::{mutators::Tokens, Error};
@@ -20,6 +24,11 @@ pub use __afl_acc_memop_ptr_local as ACCOUNTING_MEMOP_MAP;
 /// The max count of edges tracked.
 pub static mut MAX_EDGES_NUM: usize = 0;
 
+/// The size of the edges map used by the target, as set by the
+/// `LtoCoverage` pass of `libafl_cc` at startup. `0` without it.
+#[no_mangle]
+pub static mut __afl_final_loc: u32 = 0;
+
 extern "C" {
     /// The area pointer points to the edges map.
     pub static mut __afl_area_ptr: *mut u8;
@@ -119,12 +128,84 @@ pub fn edges_map_mut_ptr() -> *mut u8 {
     }
 }
 
+#[cfg(feature = "thread_local_maps")]
+std::thread_local! {
+    /// The edges map of the current thread, if it got one of its own
+    static THREAD_EDGES_MAP_PTR: Cell<*mut u8> = const { Cell::new(ptr::null_mut()) };
+}
+
+/// Gives the current thread an edges map of its own, for multi-threaded fuzzing.
+/// From now on, the `sancov_pcguard` hooks write the edges of this thread to `map`, instead of the shared map.
+///
+/// Only the edges are kept per thread. The other maps, such as the `CMP_MAP` of `sancov_value_profile`,
+/// the cmplog map of `sancov_cmplog`, and the stack depth of `sancov_stack_depth`, stay shared by all threads,
+/// so observers on them see the runs of all threads mixed.
+///
+/// # Safety
+/// The `map` has to stay valid until [`reset_thread_edges_map`], or the end of the thread.
+#[cfg(feature = "thread_local_maps")]
+pub unsafe fn set_thread_edges_map(map: &mut [u8]) {
+    #[cfg(feature = "pointer_maps")]
+    let len = EDGES_MAP_PTR_NUM;
+    #[cfg(not(feature = "pointer_maps"))]
+    let len = EDGES_MAP.len();
+    assert!(
+        map.len() >= len,
+        "The edges map of a thread needs to be as large as the shared one ({len})."
+    );
+    THREAD_EDGES_MAP_PTR.with(|ptr| ptr.set(map.as_mut_ptr()));
+}
+
+/// Lets the current thread write its edges to the shared map again.
+#[cfg(feature = "thread_local_maps")]
+pub fn reset_thread_edges_map() {
+    THREAD_EDGES_MAP_PTR.with(|ptr| ptr.set(ptr::null_mut()));
+}
+
+/// Gets the edges map of the current thread, as set by [`set_thread_edges_map`],
+/// or the shared [`edges_map_mut_ptr`].
+#[cfg(feature = "thread_local_maps")]
+#[must_use]
+#[inline]
+pub fn thread_edges_map_mut_ptr() -> *mut u8 {
+    let ptr = THREAD_EDGES_MAP_PTR.with(Cell::get);
+    if ptr.is_null() {
+        edges_map_mut_ptr()
+    } else {
+        ptr
+    }
+}
+
+/// If [`edges_max_num`] had to cut `__afl_final_loc` down to the size of the edges map
+#[cfg(not(feature = "pointer_maps"))]
+static FINAL_LOC_CLAMPED: AtomicBool = AtomicBool::new(false);
+
 /// Gets the current maximum number of edges tracked.
+///
+/// If the edges instrumented at link time don't fit the edges map, this is the size of the map,
+/// and an error is logged.
 #[must_use]
 pub fn edges_max_num() -> usize {
     unsafe {
-        if MAX_EDGES_NUM > 0 {
+        // With context or n-gram sensitivity, edges are hashed all over the map
+        if MAX_EDGES_NUM > 0
+            && !cfg!(any(
+                feature = "sancov_pcguard_ngram",
+                feature = "sancov_pcguard_ctx"
+            ))
+        {
             MAX_EDGES_NUM
+        } else if __afl_final_loc > 0 {
+            let edges_num = __afl_final_loc as usize;
+            #[cfg(not(feature = "pointer_maps"))]
+            if edges_num > EDGES_MAP.len() {
+                // Report once, this is queried for every observer created
+                if !FINAL_LOC_CLAMPED.swap(true, Ordering::Relaxed) {
+                    log::error!("The number of edges instrumented at link time ({edges_num}) exceeds the size of the edges map ({}), edges past it are not tracked. Use the LIBAFL_EDGES_MAP_SIZE env to increase it at compile time.", EDGES_MAP.len());
+                }
+                return EDGES_MAP.len();
+            }
+            edges_num
         } else {
             #[cfg(feature = "pointer_maps")]
             {
@@ -249,3 +330,41 @@ mod swap {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    #[cfg(all(feature = "thread_local_maps", not(feature = "pointer_maps")))]
+    fn test_thread_edges_map() {
+        use alloc::vec;
+        use std::thread;
+
+        use super::{
+            edges_map_mut_ptr, reset_thread_edges_map, set_thread_edges_map,
+            thread_edges_map_mut_ptr,
+        };
+        use crate::EDGES_MAP_SIZE;
+
+        assert_eq!(thread_edges_map_mut_ptr(), edges_map_mut_ptr());
+        let mut map = vec![0; EDGES_MAP_SIZE];
+        unsafe { set_thread_edges_map(&mut map) };
+        assert_eq!(thread_edges_map_mut_ptr(), map.as_mut_ptr());
+
+        // other threads keep writing to the shared map
+        let other = thread::spawn(|| thread_edges_map_mut_ptr() as usize)
+            .join()
+            .unwrap();
+        assert_eq!(other, edges_map_mut_ptr() as usize);
+
+        reset_thread_edges_map();
+        assert_eq!(thread_edges_map_mut_ptr(), edges_map_mut_ptr());
+    }
+
+    #[test]
+    #[should_panic = "as large as the shared one"]
+    #[cfg(all(feature = "thread_local_maps", not(feature = "pointer_maps")))]
+    fn test_thread_edges_map_too_small() {
+        let mut map = alloc::vec![0; 16];
+        unsafe { super::set_thread_edges_map(&mut map) };
+    }
+}
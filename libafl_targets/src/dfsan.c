@@ -0,0 +1,97 @@
+// Byte-level taint tracking with DataFlowSanitizer, for the `dfsan` feature.
+//
+// The target is built with -fsanitize=dataflow -fsanitize-coverage=trace-cmp,
+// with the `DataFlowSanitizer` configuration of libafl_cc, which also links
+// its dfsan driver: libafl_dfsan_run, built with DFSan, labels the input bytes
+// and runs the instrumented harness.
+// This file must not be built with DFSan itself: the __dfsw_ callbacks below
+// get the labels of the compare operands as extra arguments.
+//
+// Labels are 8 bit wide since LLVM 13, each bit a base label, so the input is
+// run in windows of 8 bytes, one label per byte of the window.
+
+#include "common.h"
+
+#include <stddef.h>
+#include <stdlib.h>
+#include <string.h>
+
+typedef uint8_t dfsan_label;
+
+#define DFSAN_LABELS 8
+
+// The bits of a taint map byte, for the operands the input byte flows into
+#define TAINT_FIRST_OPERAND 1
+#define TAINT_SECOND_OPERAND 2
+
+// From the dfsan driver of libafl_cc, weak so that the fuzzer itself links
+// without it
+void libafl_dfsan_run(uint8_t *data, size_t len, size_t start, size_t size)
+    __attribute__((weak));
+
+// The labels that reached the first and the second operand of a compare,
+// in the current run
+static dfsan_label libafl_dfsan_first_labels;
+static dfsan_label libafl_dfsan_second_labels;
+
+#define DFSAN_CMP_CALLBACK(name, type)                                   \
+  void __dfsw___sanitizer_cov_trace_##name(type arg1, type arg2,         \
+                                           dfsan_label l1,               \
+                                           dfsan_label l2) {             \
+    (void)arg1;                                                          \
+    (void)arg2;                                                          \
+    libafl_dfsan_first_labels |= l1;                                     \
+    libafl_dfsan_second_labels |= l2;                                    \
+  }
+
+DFSAN_CMP_CALLBACK(cmp1, uint8_t)
+DFSAN_CMP_CALLBACK(cmp2, uint16_t)
+DFSAN_CMP_CALLBACK(cmp4, uint32_t)
+DFSAN_CMP_CALLBACK(cmp8, uint64_t)
+DFSAN_CMP_CALLBACK(const_cmp1, uint8_t)
+DFSAN_CMP_CALLBACK(const_cmp2, uint16_t)
+DFSAN_CMP_CALLBACK(const_cmp4, uint32_t)
+DFSAN_CMP_CALLBACK(const_cmp8, uint64_t)
+
+// The cases are constants, only the value can come from the input
+void __dfsw___sanitizer_cov_trace_switch(uint64_t val, uint64_t *cases,
+                                         dfsan_label val_label,
+                                         dfsan_label cases_label) {
+  (void)val;
+  (void)cases;
+  (void)cases_label;
+  libafl_dfsan_first_labels |= val_label;
+}
+
+// Sets the TAINT_ bits of taint[i] for the compare operands byte i of data
+// flows into. Returns 0 on success, -1 if the target was not built with DFSan.
+int libafl_dfsan_taint(const uint8_t *data, size_t len, uint8_t *taint) {
+  if (!libafl_dfsan_run) { return -1; }
+
+  memset(taint, 0, len);
+
+  // A fresh copy for each run, as the harness may write to its input
+  uint8_t *copy = malloc(len ? len : 1);
+  if (!copy) { return -1; }
+
+  for (size_t start = 0; start < len; start += DFSAN_LABELS) {
+    size_t window = MIN(len - start, (size_t)DFSAN_LABELS);
+
+    memcpy(copy, data, len);
+    libafl_dfsan_first_labels = 0;
+    libafl_dfsan_second_labels = 0;
+    libafl_dfsan_run(copy, len, start, window);
+
+    for (size_t i = 0; i < window; i++) {
+      if (libafl_dfsan_first_labels & (1 << i)) {
+        taint[start + i] |= TAINT_FIRST_OPERAND;
+      }
+      if (libafl_dfsan_second_labels & (1 << i)) {
+        taint[start + i] |= TAINT_SECOND_OPERAND;
+      }
+    }
+  }
+
+  free(copy);
+  return 0;
+}
@@ -0,0 +1,256 @@
+//! Byte-level taint tracking with `DataFlowSanitizer`.
+//!
+//! A second build of the target, with the `dfsan` configuration of `libafl_cc`, labels each input
+//! byte and records which of them flow into the first or the second operand of a compare.
+//! The labeling and the harness run in the `dfsan` driver `libafl_cc` links into that build, so the
+//! harness is instrumented like the rest of the target.
+//! The [`TaintObserver`] turns these bytes into the ranges of the [`TaintMetadata`], so that
+//! the `AFL++` `RedQueen` mutations target exactly the bytes that matter, instead of
+//! inferring them with the `ColorizationStage`.
+//!
+//! The fuzzer shares the taint map with the `dfsan` build via [`DFSAN_SHM_ENV_VAR`]:
+//! 1. it creates a shared map as large as the largest input and writes it to the env,
+//! 2. it runs the `dfsan` build, calling [`taint_to_shmem`] from its `main`, with a command executor
+//!    in a `TracingStage`, with the [`TaintObserver`] on that map among the observers of the executor,
+//! 3. this stage comes before the `AFLppCmplogTracingStage` and the `AFLppRedQueen` mutations,
+//!    which then read the [`TaintMetadata`].
+
+use alloc::{string::String, vec::Vec};
+use core::ops::Range;
+
+use libafl::{
+    executors::ExitKind,
+    inputs::{HasBytesVec, UsesInput},
+    observers::Observer,
+    stages::TaintMetadata,
+    state::HasMetadata,
+    Error,
+};
+use libafl_bolts::{
+    ownedref::OwnedMutSlice,
+    shmem::{ShMemProvider, StdShMemProvider},
+    AsMutSlice, AsSlice, Named,
+};
+use serde::{Deserialize, Serialize};
+
+/// The env var of the shared taint map, set by the fuzzer for the `dfsan` build of the target
+pub const DFSAN_SHM_ENV_VAR: &str = "__LIBAFL_DFSAN_SHM_ID";
+
+/// The bit of a taint map byte set if the input byte flows into the first operand of a compare,
+/// or into the value of a `switch`
+pub const TAINT_FIRST_OPERAND: u8 = 1;
+/// The bit of a taint map byte set if the input byte flows into the second operand of a compare
+pub const TAINT_SECOND_OPERAND: u8 = 2;
+
+extern "C" {
+    /// Runs the harness for each window of 8 labeled bytes, setting the tainted bytes in `taint`
+    fn libafl_dfsan_taint(data: *const u8, len: usize, taint: *mut u8) -> i32;
+}
+
+/// Sets the [`TAINT_FIRST_OPERAND`] and [`TAINT_SECOND_OPERAND`] bits of `taint[i]` for the
+/// compare operands byte `i` of `input` flows into, and clears the others.
+/// Only the first `taint.len()` bytes of `input` are tracked.
+///
+/// The harness runs once per 8 input bytes, as `DataFlowSanitizer` only has 8 distinct labels.
+pub fn taint_input(input: &[u8], taint: &mut [u8]) -> Result<(), Error> {
+    let len = input.len().min(taint.len());
+    let ret = unsafe { libafl_dfsan_taint(input.as_ptr(), len, taint.as_mut_ptr()) };
+    if ret == 0 {
+        Ok(())
+    } else {
+        Err(Error::illegal_state(
+            "The target is not built with DataFlowSanitizer, or has no LLVMFuzzerTestOneInput",
+        ))
+    }
+}
+
+/// Taints `input` into the shared map given by the fuzzer in [`DFSAN_SHM_ENV_VAR`].
+/// To be called from the `main` of the `dfsan` build of the target.
+pub fn taint_to_shmem(input: &[u8]) -> Result<(), Error> {
+    let mut shmem = StdShMemProvider::new()?.existing_from_env(DFSAN_SHM_ENV_VAR)?;
+    taint_input(input, shmem.as_mut_slice())
+}
+
+/// The ranges of the bytes in `map` with any of the bits of `mask` set
+fn tainted_ranges(map: &[u8], mask: u8) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for (i, _) in map.iter().enumerate().filter(|(_, t)| **t & mask != 0) {
+        match ranges.last_mut() {
+            Some(last) if last.end == i => last.end = i + 1,
+            _ => ranges.push(i..i + 1),
+        }
+    }
+    ranges
+}
+
+/// An observer of the input bytes that flow into the compares, as tracked by the `dfsan` build of
+/// the target in a shared map, one byte per input byte, see [`taint_input`].
+/// It sets the ranges of the [`TaintMetadata`] to the bytes tainting any compare operand,
+/// and keeps the ranges of each operand apart.
+#[derive(Serialize, Deserialize, Debug)]
+#[allow(clippy::unsafe_derive_deserialize)]
+pub struct TaintObserver<'a> {
+    name: String,
+    map: OwnedMutSlice<'a, u8>,
+    ranges: Vec<Range<usize>>,
+    first_operand_ranges: Vec<Range<usize>>,
+    second_operand_ranges: Vec<Range<usize>>,
+}
+
+impl<'a> TaintObserver<'a> {
+    /// Creates a new [`TaintObserver`] on the taint `map`
+    #[must_use]
+    pub fn new(name: &str, map: &'a mut [u8]) -> Self {
+        Self {
+            name: name.into(),
+            map: OwnedMutSlice::from(map),
+            ranges: Vec::new(),
+            first_operand_ranges: Vec::new(),
+            second_operand_ranges: Vec::new(),
+        }
+    }
+
+    /// Creates a new [`TaintObserver`] on the taint map at `map_ptr`, such as a shared map
+    ///
+    /// # Safety
+    /// The map must be valid for `len` bytes for the lifetime of the observer
+    #[must_use]
+    pub unsafe fn from_mut_ptr(name: &str, map_ptr: *mut u8, len: usize) -> Self {
+        Self {
+            name: name.into(),
+            map: OwnedMutSlice::from_raw_parts_mut(map_ptr, len),
+            ranges: Vec::new(),
+            first_operand_ranges: Vec::new(),
+            second_operand_ranges: Vec::new(),
+        }
+    }
+
+    /// The tainted ranges of the last input, flowing into any compare operand
+    #[must_use]
+    pub fn ranges(&self) -> &[Range<usize>] {
+        &self.ranges
+    }
+
+    /// The ranges of the last input flowing into the first operand of a compare
+    #[must_use]
+    pub fn first_operand_ranges(&self) -> &[Range<usize>] {
+        &self.first_operand_ranges
+    }
+
+    /// The ranges of the last input flowing into the second operand of a compare
+    #[must_use]
+    pub fn second_operand_ranges(&self) -> &[Range<usize>] {
+        &self.second_operand_ranges
+    }
+}
+
+impl<'a> Named for TaintObserver<'a> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<'a, S> Observer<S> for TaintObserver<'a>
+where
+    S: UsesInput + HasMetadata,
+    S::Input: HasBytesVec,
+{
+    fn pre_exec(&mut self, _state: &mut S, input: &S::Input) -> Result<(), Error> {
+        let map = self.map.as_mut_slice();
+        let len = input.bytes().len().min(map.len());
+        map[..len].fill(0);
+        Ok(())
+    }
+
+    fn post_exec(
+        &mut self,
+        state: &mut S,
+        input: &S::Input,
+        _exit_kind: &ExitKind,
+    ) -> Result<(), Error> {
+        let map = self.map.as_slice();
+        let len = input.bytes().len().min(map.len());
+
+        let map = &map[..len];
+        self.ranges = tainted_ranges(map, TAINT_FIRST_OPERAND | TAINT_SECOND_OPERAND);
+        self.first_operand_ranges = tainted_ranges(map, TAINT_FIRST_OPERAND);
+        self.second_operand_ranges = tainted_ranges(map, TAINT_SECOND_OPERAND);
+
+        if let Some(meta) = state.metadata_map_mut().get_mut::<TaintMetadata>() {
+            meta.update(input.bytes().to_vec(), self.ranges.clone());
+        } else {
+            state.add_metadata(TaintMetadata::new(
+                input.bytes().to_vec(),
+                self.ranges.clone(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use core::ops::Range;
+
+    use libafl::{
+        corpus::InMemoryCorpus,
+        executors::ExitKind,
+        feedbacks::ConstFeedback,
+        inputs::BytesInput,
+        observers::Observer,
+        stages::TaintMetadata,
+        state::{HasMetadata, StdState},
+    };
+    use libafl_bolts::rands::StdRand;
+
+    use crate::dfsan::{TaintObserver, TAINT_FIRST_OPERAND, TAINT_SECOND_OPERAND};
+
+    #[test]
+    fn test_taint_observer() {
+        // libafl is built without `serdeany_autoreg` here
+        unsafe { TaintMetadata::register() };
+        let mut feedback = ConstFeedback::new(false);
+        let mut objective = ConstFeedback::new(false);
+        let mut state = StdState::new(
+            StdRand::with_seed(0),
+            InMemoryCorpus::<BytesInput>::new(),
+            InMemoryCorpus::new(),
+            &mut feedback,
+            &mut objective,
+        )
+        .unwrap();
+
+        let mut map = [0xff_u8; 8];
+        let map_ptr = map.as_mut_ptr();
+        let mut observer = unsafe { TaintObserver::from_mut_ptr("taint", map_ptr, map.len()) };
+        let input = BytesInput::new(b"abcdef".to_vec());
+
+        observer.pre_exec(&mut state, &input).unwrap();
+        // Only the bytes of the input are cleared
+        let cleared = unsafe { core::slice::from_raw_parts(map_ptr, 8) }.to_vec();
+        assert_eq!(cleared, [0, 0, 0, 0, 0, 0, 0xff, 0xff]);
+
+        // What the dfsan build writes to the map
+        let taint = [
+            TAINT_FIRST_OPERAND,
+            TAINT_FIRST_OPERAND | TAINT_SECOND_OPERAND,
+            TAINT_SECOND_OPERAND,
+            0,
+            TAINT_SECOND_OPERAND,
+        ];
+        unsafe { map_ptr.copy_from_nonoverlapping(taint.as_ptr(), taint.len()) };
+        observer
+            .post_exec(&mut state, &input, &ExitKind::Ok)
+            .unwrap();
+
+        assert_eq!(observer.ranges(), [0..3, 4..5]);
+        assert_eq!(
+            observer.first_operand_ranges(),
+            [Range { start: 0, end: 2 }]
+        );
+        assert_eq!(observer.second_operand_ranges(), [1..3, 4..5]);
+        let meta = state.metadata::<TaintMetadata>().unwrap();
+        assert_eq!(meta.input_vec(), b"abcdef");
+        assert_eq!(meta.ranges(), &[0..3, 4..5]);
+    }
+}
@@ -0,0 +1,121 @@
+//! Distance of executions to the targets of directed fuzzing, as in `AFLGo`.
+//! The per-edge distances can be computed at build time from the CFG dumped by the
+//! `libafl_cc` coverage pass, see `libafl_cc::cfg::ControlFlowGraph::calculate_distances_to_targets`.
+
+use alloc::{string::String, vec::Vec};
+
+use libafl::{
+    executors::ExitKind,
+    inputs::UsesInput,
+    observers::{DistanceObserver, Observer},
+    Error,
+};
+use libafl_bolts::{ownedref::OwnedSlice, AsSlice, Named};
+use serde::{Deserialize, Serialize};
+
+use crate::coverage::{edges_map_mut_ptr, edges_max_num};
+
+/// A [`DistanceObserver`] reporting the mean distance of all covered edges of a coverage map
+/// that can reach one of the targets.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct EdgesDistanceObserver<'a> {
+    name: String,
+    map: OwnedSlice<'a, u8>,
+    distances: Vec<Option<f64>>,
+    last_distance: Option<f64>,
+}
+
+impl<'a> EdgesDistanceObserver<'a> {
+    /// Creates a new [`EdgesDistanceObserver`] for the given coverage `map`.
+    ///
+    /// `distances` maps indexes in the coverage map to the distance of the respective edge.
+    /// Indexes without a distance are edges from which no target is reachable.
+    #[must_use]
+    pub fn new<S, D>(name: S, map: OwnedSlice<'a, u8>, distances: D) -> Self
+    where
+        S: Into<String>,
+        D: IntoIterator<Item = (usize, f64)>,
+    {
+        let mut distances_map = vec![None; map.as_slice().len()];
+        for (idx, distance) in distances {
+            if let Some(entry) = distances_map.get_mut(idx) {
+                *entry = Some(distance);
+            }
+        }
+        Self {
+            name: name.into(),
+            map,
+            distances: distances_map,
+            last_distance: None,
+        }
+    }
+
+    /// The distance of every edge in the coverage map
+    #[must_use]
+    pub fn distances(&self) -> &[Option<f64>] {
+        &self.distances
+    }
+}
+
+/// Gets a new [`EdgesDistanceObserver`] observing the current edges map,
+/// see [`crate::coverage::edges_map_mut_slice`].
+///
+/// # Safety
+/// This will dereference [`edges_map_mut_ptr`] and crash if it is not a valid address.
+pub unsafe fn edges_distance_observer<'a, S, D>(name: S, distances: D) -> EdgesDistanceObserver<'a>
+where
+    S: Into<String>,
+    D: IntoIterator<Item = (usize, f64)>,
+{
+    EdgesDistanceObserver::new(
+        name,
+        OwnedSlice::from_raw_parts(edges_map_mut_ptr(), edges_max_num()),
+        distances,
+    )
+}
+
+impl<'a, S> Observer<S> for EdgesDistanceObserver<'a>
+where
+    S: UsesInput,
+{
+    fn pre_exec(&mut self, _state: &mut S, _input: &S::Input) -> Result<(), Error> {
+        self.last_distance = None;
+        Ok(())
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    fn post_exec(
+        &mut self,
+        _state: &mut S,
+        _input: &S::Input,
+        _exit_kind: &ExitKind,
+    ) -> Result<(), Error> {
+        let mut sum = 0.0;
+        let mut count = 0_usize;
+        for (hits, distance) in self.map.as_slice().iter().zip(&self.distances) {
+            if let (true, Some(distance)) = (*hits != 0, distance) {
+                sum += distance;
+                count += 1;
+            }
+        }
+        if count > 0 {
+            self.last_distance = Some(sum / count as f64);
+        }
+        Ok(())
+    }
+}
+
+impl<'a, S> DistanceObserver<S> for EdgesDistanceObserver<'a>
+where
+    S: UsesInput,
+{
+    fn last_distance(&self) -> Option<f64> {
+        self.last_distance
+    }
+}
+
+impl<'a> Named for EdgesDistanceObserver<'a> {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
@@ -1,4 +1,5 @@
 #include "common.h"
+#include "cmplog.h"
 
 #include <stdio.h>
 #include <stdlib.h>
@@ -24,6 +25,7 @@
 #define SHMEM_FUZZ_HDR_SIZE 4
 #define SHM_ENV_VAR "__AFL_SHM_ID"
 #define SHM_FUZZ_ENV_VAR "__AFL_SHM_FUZZ_ID"
+#define CMPLOG_SHM_ENV_VAR "__AFL_CMPLOG_SHM_ID"
 #define DEFAULT_PERMISSION 0600
 
 /* Reporting errors */
@@ -59,6 +61,7 @@ int __afl_sharedmem_fuzzing __attribute__((weak));
 
 extern uint8_t *__afl_area_ptr;
 extern size_t   __afl_map_size;
+extern uint32_t __afl_final_loc;
 extern uint8_t *__token_start;
 extern uint8_t *__token_stop;
 
@@ -100,12 +103,59 @@ static void at_exit(int signal) {
   _exit(0);
 }
 
+/* Map the AFL++-layout cmplog map, if the fuzzer asks for cmplog. */
+
+static void map_cmplog_shared_memory() {
+  char *id_str = getenv(CMPLOG_SHM_ENV_VAR);
+
+  if (!id_str) { return; }
+
+  CmpLogMapExtended *map = NULL;
+
+#ifdef USEMMAP
+  const char *shm_file_path = id_str;
+  int         shm_fd = -1;
+
+  /* create the shared memory segment as if it was a file */
+  shm_fd = shm_open(shm_file_path, O_RDWR, DEFAULT_PERMISSION);
+  if (shm_fd == -1) {
+    fprintf(stderr, "shm_open() failed for cmplog\n");
+    send_forkserver_error(FS_ERROR_SHM_OPEN);
+    exit(1);
+  }
+
+  map = (CmpLogMapExtended *)mmap(0, sizeof(CmpLogMapExtended),
+                                  PROT_READ | PROT_WRITE, MAP_SHARED, shm_fd,
+                                  0);
+  close(shm_fd);
+
+  if (map == MAP_FAILED) { map = NULL; }
+#else
+  uint32_t shm_id = atoi(id_str);
+  map = (CmpLogMapExtended *)shmat(shm_id, NULL, 0);
+#endif
+
+  /* Whooooops. */
+
+  if (!map || map == (void *)-1) {
+    perror("Could not access cmplog shared memory");
+    send_forkserver_error(FS_ERROR_SHM_OPEN);
+    exit(1);
+  }
+
+  libafl_cmplog_map_extended_ptr = map;
+  libafl_cmplog_enabled = 1;
+}
+
 /* SHM fuzzing setup. */
 
 void __afl_map_shm(void) {
   if (already_initialized_shm) return;
   already_initialized_shm = 1;
 
+  /* The LTO instrumentation knows the exact size of the map */
+  if (__afl_final_loc) { __afl_map_size = __afl_final_loc; }
+
   char *id_str = getenv(SHM_ENV_VAR);
 
   if (id_str) {
@@ -160,6 +210,8 @@ void __afl_map_shm(void) {
     send_forkserver_error(FS_ERROR_SHM_OPEN);
     exit(1);
   }
+
+  map_cmplog_shared_memory();
 }
 
 static void map_input_shared_memory() {
@@ -227,6 +279,7 @@ void __afl_start_forkserver(void) {
 
   void (*old_sigchld_handler)(int) = signal(SIGCHLD, SIG_DFL);
 
+  if (__afl_final_loc) { __afl_map_size = __afl_final_loc; }
   if (__afl_map_size <= FS_OPT_MAX_MAPSIZE) {
     status_for_fsrv |= (FS_OPT_SET_MAPSIZE(__afl_map_size) | FS_OPT_MAPSIZE);
   }
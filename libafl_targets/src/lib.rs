@@ -69,6 +69,15 @@ extern crate alloc;
 
 include!(concat!(env!("OUT_DIR"), "/constants.rs"));
 
+#[cfg(all(
+    any(feature = "sancov_pcguard_ngram", feature = "sancov_pcguard_ctx"),
+    not(any(feature = "sancov_pcguard_edges", feature = "sancov_pcguard_hitcounts"))
+))]
+#[cfg(not(any(doc, feature = "clippy")))]
+compile_error!(
+    "the libafl_targets `sancov_pcguard_ngram` and `sancov_pcguard_ctx` features need `sancov_pcguard_edges` or `sancov_pcguard_hitcounts`."
+);
+
 #[cfg(any(feature = "sancov_pcguard_edges", feature = "sancov_pcguard_hitcounts",))]
 pub mod sancov_pcguard;
 #[cfg(any(feature = "sancov_pcguard_edges", feature = "sancov_pcguard_hitcounts",))]
@@ -103,9 +112,17 @@ pub mod sancov_8bit;
 #[cfg(feature = "sancov_8bit")]
 pub use sancov_8bit::*;
 
+#[cfg(feature = "sancov_stack_depth")]
+pub mod sancov_stack_depth;
+#[cfg(feature = "sancov_stack_depth")]
+pub use sancov_stack_depth::*;
+
 pub mod coverage;
 pub use coverage::*;
 
+pub mod distance;
+pub use distance::*;
+
 pub mod value_profile;
 pub use value_profile::*;
 
@@ -115,6 +132,11 @@ pub use cmplog::*;
 #[cfg(feature = "std")]
 pub mod drcov;
 
+#[cfg(feature = "dfsan")]
+pub mod dfsan;
+#[cfg(feature = "dfsan")]
+pub use dfsan::*;
+
 #[cfg(all(windows, feature = "std"))]
 pub mod windows_asan;
 #[cfg(all(windows, feature = "std"))]
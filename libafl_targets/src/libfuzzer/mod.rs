@@ -41,5 +41,7 @@ pub fn libfuzzer_initialize(args: &[String]) -> i32 {
 /// Calls the libfuzzer harness. We actually think the target is unsafe and crashes eventually, that's why we do all this fuzzing.
 #[allow(clippy::must_use_candidate)]
 pub fn libfuzzer_test_one_input(buf: &[u8]) -> i32 {
+    #[cfg(any(feature = "sancov_pcguard_ngram", feature = "sancov_pcguard_ctx"))]
+    crate::sancov_pcguard::reset_pcguard_context();
     unsafe { LLVMFuzzerTestOneInput(buf.as_ptr(), buf.len()) }
 }
@@ -116,6 +116,42 @@ void __sanitizer_cov_trace_const_cmp8(uint64_t arg1, uint64_t arg2) {
   __sanitizer_cov_trace_cmp8(arg1, arg2);
 }
 
+#pragma GCC diagnostic push
+#pragma GCC diagnostic ignored "-Wunused-parameter"
+
+// trace-div and trace-gep: how close the divisor and the index get to zero,
+// as libFuzzer's -use_value_profile does.
+// Only with the value profile, for cmplog alone these do nothing.
+
+void __sanitizer_cov_trace_div4(uint32_t val) {
+#ifdef SANCOV_VALUE_PROFILE
+  uintptr_t k = RETADDR;
+  k = (k >> 4) ^ (k << 8);
+  k &= CMP_MAP_SIZE - 1;
+  __libafl_targets_value_profile4(k, val, 0);
+#endif
+}
+
+void __sanitizer_cov_trace_div8(uint64_t val) {
+#ifdef SANCOV_VALUE_PROFILE
+  uintptr_t k = RETADDR;
+  k = (k >> 4) ^ (k << 8);
+  k &= CMP_MAP_SIZE - 1;
+  __libafl_targets_value_profile8(k, val, 0);
+#endif
+}
+
+void __sanitizer_cov_trace_gep(uintptr_t idx) {
+#ifdef SANCOV_VALUE_PROFILE
+  uintptr_t k = RETADDR;
+  k = (k >> 4) ^ (k << 8);
+  k &= CMP_MAP_SIZE - 1;
+  __libafl_targets_value_profile8(k, (uint64_t)idx, 0);
+#endif
+}
+
+#pragma GCC diagnostic pop
+
 #ifdef SANCOV_CMPLOG
 
 void __sanitizer_weak_hook_memcmp(void *called_pc, const void *s1,
@@ -22,4 +22,15 @@ extern "C" {
     /// Trace a switch statement
     pub fn __sanitizer_cov_trace_switch(val: u64, cases: *const u64);
 
+    /// Trace a 32 bit divisor, into the value profile map.
+    /// Does nothing without the `sancov_value_profile` feature.
+    pub fn __sanitizer_cov_trace_div4(val: u32);
+    /// Trace a 64 bit divisor, into the value profile map.
+    /// Does nothing without the `sancov_value_profile` feature.
+    pub fn __sanitizer_cov_trace_div8(val: u64);
+
+    /// Trace an array index, into the value profile map.
+    /// Does nothing without the `sancov_value_profile` feature.
+    pub fn __sanitizer_cov_trace_gep(idx: usize);
+
 }
@@ -1,8 +1,38 @@
 //! [`LLVM` `PcGuard`](https://clang.llvm.org/docs/SanitizerCoverage.html#tracing-pcs-with-guards) runtime for `LibAFL`.
+//!
+//! With the `sancov_pcguard_ngram` feature, the map position of an edge is hashed with the
+//! [`SANCOV_NGRAM_SIZE`](crate::SANCOV_NGRAM_SIZE)` - 1` edges before it.
+//! With the `sancov_pcguard_ctx` feature, it is hashed with the calling context, maintained by
+//! the `-finstrument-functions` hooks in here, so the target needs to be built with
+//! `-finstrument-functions` (or `-finstrument-functions-after-inlining`).
+//! Both spread the edges over the whole map, and keep their state per thread.
+//! This state has to be reset before each run, with [`reset_pcguard_context`] in the harness,
+//! or by adding a [`PcGuardContextObserver`] to the observers of the executor.
+//! With the `thread_local_maps` feature, each thread can write its edges to a map of its own,
+//! see [`set_thread_edges_map`](crate::coverage::set_thread_edges_map).
+//! The comparison maps of the `sancov_value_profile` and `sancov_cmplog` features stay shared by all threads.
 
+#[cfg(any(feature = "sancov_pcguard_ngram", feature = "sancov_pcguard_ctx"))]
+use alloc::string::{String, ToString};
+#[cfg(any(feature = "sancov_pcguard_ngram", feature = "sancov_pcguard_ctx"))]
+use core::cell::Cell;
+#[cfg(feature = "sancov_pcguard_ctx")]
+use core::ffi::c_void;
+
+#[cfg(any(feature = "sancov_pcguard_ngram", feature = "sancov_pcguard_ctx"))]
+use libafl::{inputs::UsesInput, observers::Observer, Error};
+#[cfg(any(feature = "sancov_pcguard_ngram", feature = "sancov_pcguard_ctx"))]
+use libafl_bolts::Named;
+#[cfg(any(feature = "sancov_pcguard_ngram", feature = "sancov_pcguard_ctx"))]
+use serde::{Deserialize, Serialize};
+
+#[cfg(feature = "thread_local_maps")]
+use crate::coverage::thread_edges_map_mut_ptr;
 use crate::coverage::{EDGES_MAP, MAX_EDGES_NUM};
 #[cfg(feature = "pointer_maps")]
 use crate::coverage::{EDGES_MAP_PTR, EDGES_MAP_PTR_NUM};
+#[cfg(feature = "sancov_pcguard_ngram")]
+use crate::SANCOV_NGRAM_SIZE;
 
 #[cfg(all(feature = "sancov_pcguard_edges", feature = "sancov_pcguard_hitcounts"))]
 #[cfg(not(any(doc, feature = "clippy")))]
@@ -10,15 +40,176 @@ compile_error!(
     "the libafl_targets `sancov_pcguard_edges` and `sancov_pcguard_hitcounts` features are mutually exclusive."
 );
 
+#[cfg(feature = "sancov_pcguard_ngram")]
+thread_local! {
+    /// The guards of the previous edges of this thread, the latest first
+    static NGRAM_HISTORY: Cell<[u32; SANCOV_NGRAM_SIZE - 1]> =
+        const { Cell::new([0; SANCOV_NGRAM_SIZE - 1]) };
+}
+
+#[cfg(feature = "sancov_pcguard_ctx")]
+thread_local! {
+    /// The hash of the call sites leading to the current function of this thread
+    static CALLING_CONTEXT: Cell<u32> = const { Cell::new(0) };
+}
+
+/// Resets the calling context and the previous edges of the current thread,
+/// so that each run starts from the same state.
+///
+/// Needs to be called right before each run, on the thread running the target,
+/// otherwise the edges of a run depend on the runs before it.
+/// [`crate::libfuzzer_test_one_input`] and the [`PcGuardContextObserver`] call it already.
+#[cfg(any(feature = "sancov_pcguard_ngram", feature = "sancov_pcguard_ctx"))]
+pub fn reset_pcguard_context() {
+    #[cfg(feature = "sancov_pcguard_ngram")]
+    NGRAM_HISTORY.with(|history| history.set([0; SANCOV_NGRAM_SIZE - 1]));
+    #[cfg(feature = "sancov_pcguard_ctx")]
+    CALLING_CONTEXT.with(|ctx| ctx.set(0));
+}
+
+/// An observer calling [`reset_pcguard_context`] before each run.
+///
+/// The context is kept per thread, so this works with the executors running the target
+/// in the thread of the fuzzer, such as the `InProcessExecutor`, or in a fork of it.
+#[cfg(any(feature = "sancov_pcguard_ngram", feature = "sancov_pcguard_ctx"))]
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PcGuardContextObserver {
+    name: String,
+}
+
+#[cfg(any(feature = "sancov_pcguard_ngram", feature = "sancov_pcguard_ctx"))]
+impl PcGuardContextObserver {
+    /// Creates a new [`PcGuardContextObserver`] with the given name.
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+        }
+    }
+}
+
+#[cfg(any(feature = "sancov_pcguard_ngram", feature = "sancov_pcguard_ctx"))]
+impl Named for PcGuardContextObserver {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+#[cfg(any(feature = "sancov_pcguard_ngram", feature = "sancov_pcguard_ctx"))]
+impl<S> Observer<S> for PcGuardContextObserver
+where
+    S: UsesInput,
+{
+    fn pre_exec(&mut self, _state: &mut S, _input: &S::Input) -> Result<(), Error> {
+        reset_pcguard_context();
+        Ok(())
+    }
+}
+
+/// The map position of the edge with the given guard, in its context
+#[cfg(any(feature = "sancov_pcguard_ngram", feature = "sancov_pcguard_ctx"))]
+#[inline]
+unsafe fn context_pos(guard: u32) -> usize {
+    #[allow(unused_mut)]
+    let mut hash = guard;
+
+    #[cfg(feature = "sancov_pcguard_ngram")]
+    NGRAM_HISTORY.with(|history| {
+        let mut prev = history.get();
+        // Rotated by age, so that the order of the edges matters
+        for (age, prev_guard) in prev.iter().enumerate() {
+            hash ^= prev_guard.rotate_left(age as u32 + 1);
+        }
+        prev.rotate_right(1);
+        prev[0] = guard;
+        history.set(prev);
+    });
+
+    #[cfg(feature = "sancov_pcguard_ctx")]
+    {
+        hash ^= CALLING_CONTEXT.with(Cell::get);
+    }
+
+    #[cfg(feature = "pointer_maps")]
+    {
+        hash as usize % EDGES_MAP_PTR_NUM
+    }
+    #[cfg(not(feature = "pointer_maps"))]
+    {
+        hash as usize % EDGES_MAP.len()
+    }
+}
+
+/// Hashes a call site, the same way in each run, even with ASLR
+#[cfg(feature = "sancov_pcguard_ctx")]
+#[inline]
+fn call_site_hash(this_fn: *const c_void, call_site: *const c_void) -> u32 {
+    let distance = (this_fn as usize).wrapping_sub(call_site as usize) as u64;
+    (distance.wrapping_mul(0x9E37_79B9_7F4A_7C15) >> 32) as u32
+}
+
+/// Adds a call site to the calling context.
+///
+/// The context is rotated first, so that the same call site twice in a row, as in a recursion,
+/// does not cancel out.
+#[cfg(feature = "sancov_pcguard_ctx")]
+#[inline]
+fn push_call_site(ctx: u32, call_site_hash: u32) -> u32 {
+    ctx.rotate_left(1) ^ call_site_hash
+}
+
+/// Removes the latest call site from the calling context, undoing [`push_call_site`]
+#[cfg(feature = "sancov_pcguard_ctx")]
+#[inline]
+fn pop_call_site(ctx: u32, call_site_hash: u32) -> u32 {
+    (ctx ^ call_site_hash).rotate_right(1)
+}
+
+/// Called on each function entry by targets built with `-finstrument-functions`,
+/// adds the call site to the calling context.
+#[cfg(feature = "sancov_pcguard_ctx")]
+#[no_mangle]
+pub extern "C" fn __cyg_profile_func_enter(this_fn: *const c_void, call_site: *const c_void) {
+    let call_site_hash = call_site_hash(this_fn, call_site);
+    CALLING_CONTEXT.with(|ctx| ctx.set(push_call_site(ctx.get(), call_site_hash)));
+}
+
+/// Called on each function exit by targets built with `-finstrument-functions`,
+/// removes the call site from the calling context.
+#[cfg(feature = "sancov_pcguard_ctx")]
+#[no_mangle]
+pub extern "C" fn __cyg_profile_func_exit(this_fn: *const c_void, call_site: *const c_void) {
+    CALLING_CONTEXT
+        .with(|ctx| ctx.set(pop_call_site(ctx.get(), call_site_hash(this_fn, call_site))));
+}
+
 /// Callback for sancov `pc_guard` - usually called by `llvm` on each block or edge.
 ///
 /// # Safety
-/// Dereferences `guard`, reads the position from there, then dereferences the [`EDGES_MAP`] at that position.
+/// Dereferences `guard`, reads the position from there, then dereferences the [`EDGES_MAP`],
+/// or the map of the current thread, at that position.
 /// Should usually not be called directly.
 #[no_mangle]
 pub unsafe extern "C" fn __sanitizer_cov_trace_pc_guard(guard: *mut u32) {
+    #[cfg(not(any(feature = "sancov_pcguard_ngram", feature = "sancov_pcguard_ctx")))]
     let pos = *guard as usize;
-    #[cfg(feature = "pointer_maps")]
+    #[cfg(any(feature = "sancov_pcguard_ngram", feature = "sancov_pcguard_ctx"))]
+    let pos = context_pos(*guard);
+    #[cfg(feature = "thread_local_maps")]
+    {
+        let map = thread_edges_map_mut_ptr();
+        #[cfg(feature = "sancov_pcguard_edges")]
+        {
+            map.add(pos).write(1);
+        }
+        #[cfg(feature = "sancov_pcguard_hitcounts")]
+        {
+            let addr = map.add(pos);
+            let val = addr.read().wrapping_add(1);
+            addr.write(val);
+        }
+    }
+    #[cfg(all(feature = "pointer_maps", not(feature = "thread_local_maps")))]
     {
         #[cfg(feature = "sancov_pcguard_edges")]
         {
@@ -31,7 +222,7 @@ pub unsafe extern "C" fn __sanitizer_cov_trace_pc_guard(guard: *mut u32) {
             addr.write(val);
         }
     }
-    #[cfg(not(feature = "pointer_maps"))]
+    #[cfg(not(any(feature = "pointer_maps", feature = "thread_local_maps")))]
     {
         #[cfg(feature = "sancov_pcguard_edges")]
         {
@@ -76,3 +267,55 @@ pub unsafe extern "C" fn __sanitizer_cov_trace_pc_guard_init(mut start: *mut u32
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    #[cfg(all(
+        feature = "thread_local_maps",
+        not(any(
+            feature = "pointer_maps",
+            feature = "sancov_pcguard_ngram",
+            feature = "sancov_pcguard_ctx"
+        ))
+    ))]
+    fn test_thread_local_trace_pc_guard() {
+        use alloc::{vec, vec::Vec};
+        use core::ptr::addr_of_mut;
+        use std::thread;
+
+        use super::__sanitizer_cov_trace_pc_guard;
+        use crate::{
+            coverage::{reset_thread_edges_map, set_thread_edges_map},
+            EDGES_MAP_SIZE,
+        };
+
+        let workers: Vec<_> = [3_u32, 5]
+            .into_iter()
+            .map(|pos| {
+                thread::spawn(move || {
+                    let mut map = vec![0; EDGES_MAP_SIZE];
+                    unsafe { set_thread_edges_map(&mut map) };
+                    let mut guard = pos;
+                    unsafe {
+                        __sanitizer_cov_trace_pc_guard(addr_of_mut!(guard));
+                        __sanitizer_cov_trace_pc_guard(addr_of_mut!(guard));
+                    }
+                    reset_thread_edges_map();
+                    map
+                })
+            })
+            .collect();
+        let maps: Vec<_> = workers.into_iter().map(|w| w.join().unwrap()).collect();
+
+        // each thread only sees its own edges
+        #[cfg(feature = "sancov_pcguard_edges")]
+        let hits = 1;
+        #[cfg(feature = "sancov_pcguard_hitcounts")]
+        let hits = 2;
+        assert_eq!(maps[0][3], hits);
+        assert_eq!(maps[0].iter().filter(|&&b| b != 0).count(), 1);
+        assert_eq!(maps[1][5], hits);
+        assert_eq!(maps[1].iter().filter(|&&b| b != 0).count(), 1);
+    }
+}
@@ -0,0 +1,36 @@
+// The stack depth of -fsanitize-coverage=stack-depth, as in libFuzzer.
+// Each instrumented function lowers __sancov_lowest_stack to its stack pointer.
+
+#include "common.h"
+
+#ifdef _MSC_VER
+  #include <intrin.h>
+  #define FRAME_ADDRESS (uintptr_t) _AddressOfReturnAddress()
+#else
+  #define FRAME_ADDRESS (uintptr_t) __builtin_frame_address(0)
+#endif
+
+#if defined(__GNUC__) && defined(THREAD_LOCAL)
+__attribute__((tls_model("initial-exec")))
+#endif
+MAYBE_THREAD_LOCAL uintptr_t __sancov_lowest_stack;
+
+// The stack pointer at the last reset, the deepest the stack may go before a run
+static MAYBE_THREAD_LOCAL uintptr_t libafl_initial_stack;
+
+// Called before each run, from the thread running the target
+void libafl_stack_depth_reset(void) {
+  libafl_initial_stack = FRAME_ADDRESS;
+  __sancov_lowest_stack = libafl_initial_stack;
+}
+
+// The lowest stack pointer of the current thread
+uintptr_t libafl_stack_depth_lowest(void) {
+  return __sancov_lowest_stack;
+}
+
+// The deepest the stack went below its pointer at the last reset, in bytes
+uintptr_t libafl_stack_depth_max(void) {
+  if (__sancov_lowest_stack > libafl_initial_stack) { return 0; }
+  return libafl_initial_stack - __sancov_lowest_stack;
+}
@@ -0,0 +1,102 @@
+//! [`LLVM` `stack-depth`](https://clang.llvm.org/docs/SanitizerCoverage.html#tracing-stack-depth) runtime for `LibAFL`.
+//!
+//! With `-fsanitize-coverage=stack-depth`, each function of the target records the lowest stack
+//! pointer of the run. The [`StackDepthObserver`] reports how deep the stack went, so that a
+//! [`libafl::feedbacks::MaxValueFeedback`] keeps the inputs that recurse deeper than any before,
+//! towards stack exhaustion.
+
+use alloc::string::{String, ToString};
+
+use libafl::{
+    executors::ExitKind,
+    inputs::UsesInput,
+    observers::{Observer, ObserverWithValue},
+    Error,
+};
+use libafl_bolts::Named;
+use serde::{Deserialize, Serialize};
+
+// `__sancov_lowest_stack` itself is thread-local in C, so it is only accessed from there
+extern "C" {
+    fn libafl_stack_depth_reset();
+    fn libafl_stack_depth_max() -> usize;
+    fn libafl_stack_depth_lowest() -> usize;
+}
+
+/// Resets the stack depth of the current thread to its current stack pointer
+pub fn reset_stack_depth() {
+    unsafe { libafl_stack_depth_reset() }
+}
+
+/// The deepest the stack went since the last [`reset_stack_depth`] on the current thread, in bytes
+#[must_use]
+pub fn max_stack_depth() -> usize {
+    unsafe { libafl_stack_depth_max() }
+}
+
+/// The lowest stack pointer of the current thread, the `__sancov_lowest_stack`
+/// each instrumented function lowers
+#[must_use]
+pub fn lowest_stack() -> usize {
+    unsafe { libafl_stack_depth_lowest() }
+}
+
+/// An observer of the maximum stack depth of a run, in bytes.
+///
+/// The stack depth is tracked per thread, so this works with the executors running the target
+/// in the thread of the fuzzer, such as the `InProcessExecutor`, but not with forking ones.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct StackDepthObserver {
+    name: String,
+    depth: Option<u64>,
+}
+
+impl StackDepthObserver {
+    /// Creates a new [`StackDepthObserver`] with the given name.
+    #[must_use]
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            depth: None,
+        }
+    }
+
+    /// The maximum stack depth of the last run, in bytes
+    #[must_use]
+    pub fn depth(&self) -> Option<u64> {
+        self.depth
+    }
+}
+
+impl Named for StackDepthObserver {
+    fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+impl<S> Observer<S> for StackDepthObserver
+where
+    S: UsesInput,
+{
+    fn pre_exec(&mut self, _state: &mut S, _input: &S::Input) -> Result<(), Error> {
+        self.depth = None;
+        reset_stack_depth();
+        Ok(())
+    }
+
+    fn post_exec(
+        &mut self,
+        _state: &mut S,
+        _input: &S::Input,
+        _exit_kind: &ExitKind,
+    ) -> Result<(), Error> {
+        self.depth = Some(max_stack_depth() as u64);
+        Ok(())
+    }
+}
+
+impl ObserverWithValue for StackDepthObserver {
+    fn value(&self) -> Option<u64> {
+        self.depth
+    }
+}
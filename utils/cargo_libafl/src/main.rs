@@ -0,0 +1,279 @@
+//! `cargo libafl`: fuzz the `libfuzzer-sys` targets of a crate with `LibAFL`
+//!
+//! The targets live in the `fuzz/` directory of the crate, as for `cargo fuzz`, and depend on
+//! `libafl_libfuzzer` in place of `libfuzzer-sys`. `cargo libafl` builds them with the `sancov`
+//! and `CmpLog` instrumentation the `libafl_libfuzzer` runtime expects, and runs them with the
+//! corpora in `fuzz/corpus/<target>/` and the artifacts in `fuzz/artifacts/<target>/`.
+//!
+//! ```sh
+//! cargo libafl init
+//! cargo libafl run fuzz_target_1 -j 8
+//! cargo libafl cmin fuzz_target_1
+//! cargo libafl tmin fuzz_target_1 fuzz/artifacts/fuzz_target_1/crash-...
+//! ```
+
+use std::{
+    env,
+    ffi::OsString,
+    fs,
+    path::PathBuf,
+    process::{self, ExitStatus},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use clap::{Args, Parser, Subcommand};
+
+mod options;
+mod project;
+
+use options::BuildOptions;
+use project::FuzzProject;
+
+/// The result of the subcommands
+pub type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>;
+
+/// Invoked by cargo as `cargo-libafl libafl <args>`
+#[derive(Debug, Parser)]
+#[command(name = "cargo", bin_name = "cargo")]
+enum Cargo {
+    Libafl(Cli),
+}
+
+/// Fuzz the `libfuzzer-sys` targets of a crate with `LibAFL`
+#[derive(Debug, Args)]
+#[command(version, about)]
+struct Cli {
+    /// The fuzz crate, by default `fuzz/` in the current directory or in one of its parents
+    #[arg(long, global = true)]
+    fuzz_dir: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Debug, Subcommand)]
+enum Command {
+    /// Create the fuzz crate of the crate in the current directory
+    Init {
+        /// The name of the first fuzz target
+        #[arg(short, long, default_value = "fuzz_target_1")]
+        target: String,
+    },
+
+    /// Add a new fuzz target
+    Add {
+        /// The name of the fuzz target
+        target: String,
+    },
+
+    /// List the fuzz targets
+    List,
+
+    /// Build the fuzz targets
+    Build {
+        #[command(flatten)]
+        build: BuildOptions,
+
+        /// The fuzz target to build, all of them by default
+        target: Option<String>,
+    },
+
+    /// Fuzz a target
+    Run {
+        #[command(flatten)]
+        build: BuildOptions,
+
+        /// The fuzz target
+        target: String,
+
+        /// The corpus directories, the first one getting the new inputs.
+        /// By default `fuzz/corpus/<target>/`
+        corpus: Vec<PathBuf>,
+
+        /// The number of cores to fuzz on, with the `LibAFL` launcher
+        #[arg(short, long, default_value_t = 1)]
+        jobs: usize,
+
+        /// Additional arguments for the `libafl_libfuzzer` runtime, e.g. `-timeout=5`
+        #[arg(last = true)]
+        args: Vec<String>,
+    },
+
+    /// Minimize the corpus of a target, keeping the inputs with new coverage
+    Cmin {
+        #[command(flatten)]
+        build: BuildOptions,
+
+        /// The fuzz target
+        target: String,
+
+        /// The corpus to minimize in place, by default `fuzz/corpus/<target>/`
+        corpus: Option<PathBuf>,
+
+        /// Additional arguments for the `libafl_libfuzzer` runtime
+        #[arg(last = true)]
+        args: Vec<String>,
+    },
+
+    /// Minimize a crashing input of a target, into the artifacts of the target
+    Tmin {
+        #[command(flatten)]
+        build: BuildOptions,
+
+        /// The fuzz target
+        target: String,
+
+        /// The crashing input
+        testcase: PathBuf,
+
+        /// The number of minimization attempts
+        #[arg(short, long, default_value_t = 255)]
+        runs: usize,
+
+        /// Additional arguments for the `libafl_libfuzzer` runtime
+        #[arg(last = true)]
+        args: Vec<String>,
+    },
+}
+
+/// Exits with the status of the target, if it failed
+fn exit_on_failure(status: ExitStatus) {
+    if !status.success() {
+        process::exit(status.code().unwrap_or(1));
+    }
+}
+
+fn run(cli: Cli) -> Result<()> {
+    if let Command::Init { target } = &cli.command {
+        let crate_dir = env::current_dir()?;
+        FuzzProject::init(&crate_dir, target)?;
+        println!(
+            "Created the fuzz crate in {}",
+            crate_dir.join("fuzz").display()
+        );
+        return Ok(());
+    }
+
+    let project = FuzzProject::find(cli.fuzz_dir)?;
+    match cli.command {
+        Command::Init { .. } => unreachable!(),
+        Command::Add { target } => project.add_target(&target)?,
+        Command::List => {
+            for target in project.targets()? {
+                println!("{target}");
+            }
+        }
+        Command::Build { build, target } => project.build(&build, target.as_deref())?,
+        Command::Run {
+            build,
+            target,
+            mut corpus,
+            jobs,
+            args,
+        } => {
+            if corpus.is_empty() {
+                corpus.push(project.corpus_dir(&target));
+            }
+            for dir in &corpus {
+                fs::create_dir_all(dir)?;
+            }
+
+            let mut runtime_args = vec![project.artifact_prefix(&target)?];
+            if jobs > 1 {
+                runtime_args.push(format!("-fork={jobs}").into());
+            }
+            runtime_args.extend(args.into_iter().map(OsString::from));
+            runtime_args.extend(corpus.into_iter().map(PathBuf::into_os_string));
+
+            exit_on_failure(project.exec(&build, &target, runtime_args)?);
+        }
+        Command::Cmin {
+            build,
+            target,
+            corpus,
+            args,
+        } => {
+            let corpus = corpus.unwrap_or_else(|| project.corpus_dir(&target));
+            if !corpus.is_dir() {
+                return Err(format!("No corpus at {}", corpus.display()).into());
+            }
+
+            // Merge into a fresh directory, which then replaces the corpus
+            let mut minimized = corpus.clone().into_os_string();
+            minimized.push(".cmin");
+            let minimized = PathBuf::from(minimized);
+            if minimized.exists() {
+                fs::remove_dir_all(&minimized)?;
+            }
+            fs::create_dir_all(&minimized)?;
+
+            let mut runtime_args = vec![OsString::from("-merge=1")];
+            runtime_args.extend(args.into_iter().map(OsString::from));
+            runtime_args.push(minimized.clone().into_os_string());
+            runtime_args.push(corpus.clone().into_os_string());
+
+            let status = project.exec(&build, &target, runtime_args)?;
+            if !status.success() {
+                fs::remove_dir_all(&minimized)?;
+                exit_on_failure(status);
+            }
+
+            let before = fs::read_dir(&corpus)?.count();
+            let after = fs::read_dir(&minimized)?.count();
+            if after == 0 && before > 0 {
+                fs::remove_dir_all(&minimized)?;
+                return Err(format!(
+                    "Minimizing {} kept no inputs, leaving the corpus as it is",
+                    corpus.display()
+                )
+                .into());
+            }
+
+            // Keep the old corpus aside, in case the minimized one misses something
+            let mut backup = corpus.clone().into_os_string();
+            backup.push(".pre-cmin");
+            let mut backup = PathBuf::from(backup);
+            if backup.exists() {
+                let secs = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |elapsed| elapsed.as_secs());
+                let mut timestamped = backup.into_os_string();
+                timestamped.push(format!("-{secs}"));
+                backup = PathBuf::from(timestamped);
+            }
+            fs::rename(&corpus, &backup)?;
+            fs::rename(&minimized, &corpus)?;
+            println!(
+                "Minimized {} from {before} to {after} inputs, the old corpus is in {}",
+                corpus.display(),
+                backup.display()
+            );
+        }
+        Command::Tmin {
+            build,
+            target,
+            testcase,
+            runs,
+            args,
+        } => {
+            let mut runtime_args = vec![
+                OsString::from("-minimize_crash=1"),
+                format!("-runs={runs}").into(),
+                project.artifact_prefix(&target)?,
+            ];
+            runtime_args.extend(args.into_iter().map(OsString::from));
+            runtime_args.push(testcase.into_os_string());
+
+            exit_on_failure(project.exec(&build, &target, runtime_args)?);
+        }
+    }
+    Ok(())
+}
+
+fn main() {
+    let Cargo::Libafl(cli) = Cargo::parse();
+    if let Err(err) = run(cli) {
+        eprintln!("Error: {err}");
+        process::exit(1);
+    }
+}
@@ -0,0 +1,184 @@
+//! The build options shared by the subcommands, and the flags they turn into
+
+use clap::{Args, ValueEnum};
+
+/// The sanitizers the fuzz targets can be built with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Sanitizer {
+    /// No sanitizer
+    None,
+    /// `AddressSanitizer`, or its hardware-assisted variant on `aarch64`
+    Address,
+}
+
+impl Sanitizer {
+    /// The name of this sanitizer for `-fsanitize` and `-Zsanitizer`, if any.
+    ///
+    /// Matches the flags of the `AddressSanitizer` configuration of `libafl_cc`.
+    fn name(self) -> Option<&'static str> {
+        match self {
+            Sanitizer::None => None,
+            // hardware asan is more memory efficient than asan on arm64
+            #[cfg(all(
+                any(target_os = "linux", target_os = "android"),
+                target_arch = "aarch64"
+            ))]
+            Sanitizer::Address => Some("hwaddress"),
+            #[cfg(not(all(
+                any(target_os = "linux", target_os = "android"),
+                target_arch = "aarch64"
+            )))]
+            Sanitizer::Address => Some("address"),
+        }
+    }
+}
+
+/// Options for building the fuzz targets
+#[derive(Debug, Clone, Args)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct BuildOptions {
+    /// Build without optimizations
+    #[arg(short = 'D', long, conflicts_with = "release")]
+    pub dev: bool,
+
+    /// Build with optimizations, the default
+    #[arg(short = 'O', long)]
+    pub release: bool,
+
+    /// Build with debug assertions
+    #[arg(short = 'a', long)]
+    pub debug_assertions: bool,
+
+    /// The sanitizer to build with; anything but `none` needs a nightly toolchain
+    #[arg(short, long, value_enum, default_value_t = Sanitizer::Address)]
+    pub sanitizer: Sanitizer,
+
+    /// Build without tracing the compares, which `LibAFL` uses for its `CmpLog` mutations
+    #[arg(long)]
+    pub no_cmplog: bool,
+
+    /// Space or comma separated list of features of the fuzz crate to activate
+    #[arg(long)]
+    pub features: Option<String>,
+
+    /// Do not activate the `default` feature of the fuzz crate
+    #[arg(long)]
+    pub no_default_features: bool,
+
+    /// Activate all the features of the fuzz crate
+    #[arg(long)]
+    pub all_features: bool,
+
+    /// The target triple to build for, the host by default
+    #[arg(long = "target", value_name = "TRIPLE")]
+    pub triple: Option<String>,
+}
+
+impl BuildOptions {
+    /// The name of the cargo profile directory of this build
+    #[must_use]
+    pub fn profile_dir(&self) -> &'static str {
+        if self.dev {
+            "debug"
+        } else {
+            "release"
+        }
+    }
+
+    /// The `RUSTFLAGS` instrumenting the Rust code for the `libafl_libfuzzer` runtime:
+    /// `sancov` 8 bit counters with a pc table for the coverage, and compare tracing for `CmpLog`.
+    #[must_use]
+    pub fn rustflags(&self) -> Vec<String> {
+        let mut flags = vec![
+            "-Cpasses=sancov-module".to_string(),
+            "-Cllvm-args=-sanitizer-coverage-level=3".to_string(),
+            "-Cllvm-args=-sanitizer-coverage-inline-8bit-counters".to_string(),
+            "-Cllvm-args=-sanitizer-coverage-pc-table".to_string(),
+        ];
+        if !self.no_cmplog {
+            flags.push("-Cllvm-args=-sanitizer-coverage-trace-compares".to_string());
+        }
+        flags.push("--cfg".to_string());
+        flags.push("fuzzing".to_string());
+        if self.debug_assertions {
+            flags.push("-Cdebug-assertions".to_string());
+        }
+
+        // Same sanitizer as for the C code, so both runtimes agree
+        if let Some(sanitizer) = self.sanitizer.name() {
+            flags.push(format!("-Zsanitizer={sanitizer}"));
+        }
+
+        flags
+    }
+
+    /// The `CFLAGS` and `CXXFLAGS` for C and C++ code built by the build scripts of the target
+    #[must_use]
+    pub fn cflags(&self) -> Vec<String> {
+        let mut flags = vec!["-fsanitize-coverage=inline-8bit-counters,pc-table".to_string()];
+        if let Some(sanitizer) = self.sanitizer.name() {
+            flags.push(format!("-fsanitize={sanitizer}"));
+        }
+        if !self.no_cmplog {
+            flags.push("-fsanitize-coverage=trace-cmp".to_string());
+        }
+        flags
+    }
+
+    /// The feature args for `cargo build`
+    #[must_use]
+    pub fn cargo_feature_args(&self) -> Vec<String> {
+        let mut args = vec![];
+        if let Some(features) = &self.features {
+            args.push("--features".to_string());
+            args.push(features.clone());
+        }
+        if self.no_default_features {
+            args.push("--no-default-features".to_string());
+        }
+        if self.all_features {
+            args.push("--all-features".to_string());
+        }
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::Parser;
+
+    use super::{BuildOptions, Sanitizer};
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        build: BuildOptions,
+    }
+
+    #[test]
+    fn test_build_flags() {
+        let build = Cli::parse_from(["cargo-libafl"]).build;
+        assert_eq!(build.sanitizer, Sanitizer::Address);
+        assert_eq!(build.profile_dir(), "release");
+        let rustflags = build.rustflags();
+        assert!(rustflags.contains(&"-Cllvm-args=-sanitizer-coverage-trace-compares".to_string()));
+        assert!(rustflags
+            .iter()
+            .any(|flag| flag.starts_with("-Zsanitizer=")));
+        assert!(build
+            .cflags()
+            .contains(&"-fsanitize-coverage=trace-cmp".to_string()));
+
+        let build = Cli::parse_from(["cargo-libafl", "-D", "-s", "none", "--no-cmplog"]).build;
+        assert_eq!(build.profile_dir(), "debug");
+        let rustflags = build.rustflags();
+        assert!(!rustflags.iter().any(|flag| flag.contains("trace-compares")));
+        assert!(!rustflags
+            .iter()
+            .any(|flag| flag.starts_with("-Zsanitizer=")));
+        assert_eq!(
+            build.cflags(),
+            ["-fsanitize-coverage=inline-8bit-counters,pc-table"]
+        );
+    }
+}
@@ -0,0 +1,297 @@
+//! The `fuzz/` directory of a crate, with its targets, corpora and artifacts
+
+use std::{
+    env,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    process::{Command, ExitStatus},
+};
+
+use crate::{options::BuildOptions, Result};
+
+/// The `.gitignore` of a new `fuzz/` directory
+const GITIGNORE: &str = "target
+corpus
+artifacts
+";
+
+/// A new fuzz target
+const TARGET_TEMPLATE: &str = "#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // fuzzed code goes here
+});
+";
+
+/// The manifest of a new fuzz crate, for the crate `name`.
+/// The `libfuzzer-sys` dependency is `libafl_libfuzzer`, which runs the targets with `LibAFL`.
+fn fuzz_manifest(name: &str) -> String {
+    format!(
+        r#"[package]
+name = "{name}-fuzz"
+version = "0.0.0"
+publish = false
+edition = "2021"
+
+[package.metadata]
+cargo-fuzz = true
+
+[dependencies]
+libfuzzer-sys = {{ version = "{}", package = "libafl_libfuzzer" }}
+
+[dependencies.{name}]
+path = ".."
+
+# Prevent this from interfering with workspaces
+[workspace]
+members = ["."]
+
+[profile.release]
+debug = 1
+"#,
+        env!("CARGO_PKG_VERSION")
+    )
+}
+
+/// The `[[bin]]` entry of the fuzz target `target`
+fn bin_entry(target: &str) -> String {
+    format!(
+        r#"
+[[bin]]
+name = "{target}"
+path = "fuzz_targets/{target}.rs"
+test = false
+doc = false
+"#
+    )
+}
+
+/// The names of the `[[bin]]` targets in a fuzz crate manifest
+fn bin_targets(manifest: &str) -> Result<Vec<String>> {
+    let manifest: toml::Table = manifest.parse()?;
+    let Some(bins) = manifest.get("bin").and_then(toml::Value::as_array) else {
+        return Ok(vec![]);
+    };
+    Ok(bins
+        .iter()
+        .filter_map(|bin| bin.get("name").and_then(toml::Value::as_str))
+        .map(String::from)
+        .collect())
+}
+
+/// The `host` triple of `rustc`
+fn host_triple() -> Result<String> {
+    let rustc = env::var_os("RUSTC").unwrap_or_else(|| "rustc".into());
+    let output = Command::new(rustc).arg("-vV").output()?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.strip_prefix("host: "))
+        .map(|host| host.trim().to_string())
+        .ok_or_else(|| "Could not find the host triple in `rustc -vV`".into())
+}
+
+/// Prepends the `flags` to those already in the env var `var`
+fn prepend_flags(var: &str, flags: &[String]) -> String {
+    let mut value = flags.join(" ");
+    if let Ok(existing) = env::var(var) {
+        if !existing.trim().is_empty() {
+            value.push(' ');
+            value.push_str(&existing);
+        }
+    }
+    value
+}
+
+/// A fuzz crate, in the layout of `cargo fuzz`:
+/// the targets in `fuzz_targets/`, their corpora in `corpus/<target>/`
+/// and their crashes, timeouts and the like in `artifacts/<target>/`.
+#[derive(Debug)]
+pub struct FuzzProject {
+    dir: PathBuf,
+}
+
+impl FuzzProject {
+    /// The fuzz crate at `dir`, or else at `fuzz/` in the current directory or in one of its parents
+    pub fn find(dir: Option<PathBuf>) -> Result<Self> {
+        if let Some(dir) = dir {
+            if !dir.join("Cargo.toml").is_file() {
+                return Err(format!("No fuzz crate at {}", dir.display()).into());
+            }
+            return Ok(Self { dir });
+        }
+
+        let cwd = env::current_dir()?;
+        for ancestor in cwd.ancestors() {
+            let dir = ancestor.join("fuzz");
+            if dir.join("Cargo.toml").is_file() {
+                return Ok(Self { dir });
+            }
+        }
+        Err("Could not find a `fuzz/` directory, run `cargo libafl init` first".into())
+    }
+
+    /// Creates the fuzz crate of the crate in `crate_dir`, with a first target
+    pub fn init(crate_dir: &Path, target: &str) -> Result<Self> {
+        let manifest: toml::Table = fs::read_to_string(crate_dir.join("Cargo.toml"))?.parse()?;
+        let name = manifest
+            .get("package")
+            .and_then(|package| package.get("name"))
+            .and_then(toml::Value::as_str)
+            .ok_or("The crate to fuzz has no package name")?;
+
+        let dir = crate_dir.join("fuzz");
+        if dir.exists() {
+            return Err(format!("{} already exists", dir.display()).into());
+        }
+        fs::create_dir_all(dir.join("fuzz_targets"))?;
+        fs::write(dir.join("Cargo.toml"), fuzz_manifest(name))?;
+        fs::write(dir.join(".gitignore"), GITIGNORE)?;
+
+        let project = Self { dir };
+        project.add_target(target)?;
+        Ok(project)
+    }
+
+    /// Adds a new fuzz target, from the template
+    pub fn add_target(&self, target: &str) -> Result<()> {
+        if self.targets()?.iter().any(|existing| existing == target) {
+            return Err(format!("The fuzz target {target} already exists").into());
+        }
+
+        let source = self.dir.join("fuzz_targets").join(format!("{target}.rs"));
+        fs::create_dir_all(self.dir.join("fuzz_targets"))?;
+        fs::write(source, TARGET_TEMPLATE)?;
+
+        let mut manifest = fs::read_to_string(self.manifest())?;
+        manifest.push_str(&bin_entry(target));
+        fs::write(self.manifest(), manifest)?;
+        Ok(())
+    }
+
+    /// The manifest of the fuzz crate
+    #[must_use]
+    pub fn manifest(&self) -> PathBuf {
+        self.dir.join("Cargo.toml")
+    }
+
+    /// The names of the fuzz targets
+    pub fn targets(&self) -> Result<Vec<String>> {
+        bin_targets(&fs::read_to_string(self.manifest())?)
+    }
+
+    /// The default corpus of `target`
+    #[must_use]
+    pub fn corpus_dir(&self, target: &str) -> PathBuf {
+        self.dir.join("corpus").join(target)
+    }
+
+    /// Where the crashes and timeouts of `target` go
+    #[must_use]
+    pub fn artifacts_dir(&self, target: &str) -> PathBuf {
+        self.dir.join("artifacts").join(target)
+    }
+
+    /// The `-artifact_prefix` of `target` for `libafl_libfuzzer`, creating its directory.
+    /// It ends with a separator, so that the artifacts go into the directory.
+    pub fn artifact_prefix(&self, target: &str) -> Result<OsString> {
+        let dir = self.artifacts_dir(target);
+        fs::create_dir_all(&dir)?;
+        let mut prefix = OsString::from("-artifact_prefix=");
+        prefix.push(dir.as_os_str());
+        prefix.push(std::path::MAIN_SEPARATOR.to_string());
+        Ok(prefix)
+    }
+
+    /// The target directory of cargo for the fuzz crate
+    fn target_dir(&self) -> PathBuf {
+        env::var_os("CARGO_TARGET_DIR").map_or_else(|| self.dir.join("target"), PathBuf::from)
+    }
+
+    /// Builds `target`, or all targets, instrumented for `LibAFL`
+    pub fn build(&self, options: &BuildOptions, target: Option<&str>) -> Result<()> {
+        // An explicit --target keeps the instrumentation away from build scripts and proc macros
+        let triple = match &options.triple {
+            Some(triple) => triple.clone(),
+            None => host_triple()?,
+        };
+
+        let mut cargo = Command::new(env::var_os("CARGO").unwrap_or_else(|| "cargo".into()));
+        cargo
+            .arg("build")
+            .arg("--manifest-path")
+            .arg(self.manifest())
+            .arg("--target")
+            .arg(&triple)
+            .args(options.cargo_feature_args());
+        if !options.dev {
+            cargo.arg("--release");
+        }
+        match target {
+            Some(target) => cargo.arg("--bin").arg(target),
+            None => cargo.arg("--bins"),
+        };
+
+        let cflags = options.cflags();
+        cargo
+            .env(
+                "RUSTFLAGS",
+                prepend_flags("RUSTFLAGS", &options.rustflags()),
+            )
+            .env("CFLAGS", prepend_flags("CFLAGS", &cflags))
+            .env("CXXFLAGS", prepend_flags("CXXFLAGS", &cflags));
+
+        let status = cargo.status()?;
+        if !status.success() {
+            return Err(format!("Failed to build the fuzz targets: {status}").into());
+        }
+        Ok(())
+    }
+
+    /// The path of the built `target`
+    pub fn binary(&self, options: &BuildOptions, target: &str) -> Result<PathBuf> {
+        let triple = match &options.triple {
+            Some(triple) => triple.clone(),
+            None => host_triple()?,
+        };
+        Ok(self
+            .target_dir()
+            .join(triple)
+            .join(options.profile_dir())
+            .join(target))
+    }
+
+    /// Builds and runs `target` with the `libafl_libfuzzer` arguments `args`
+    pub fn exec<I, S>(&self, options: &BuildOptions, target: &str, args: I) -> Result<ExitStatus>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<OsString>,
+    {
+        if !self.targets()?.iter().any(|existing| existing == target) {
+            return Err(format!("No fuzz target named {target}").into());
+        }
+        self.build(options, Some(target))?;
+
+        let status = Command::new(self.binary(options, target)?)
+            .args(args.into_iter().map(Into::into))
+            .status()?;
+        Ok(status)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bin_entry, bin_targets, fuzz_manifest};
+
+    #[test]
+    fn test_bin_targets() {
+        let mut manifest = fuzz_manifest("my_crate");
+        assert!(bin_targets(&manifest).unwrap().is_empty());
+
+        manifest.push_str(&bin_entry("parse"));
+        manifest.push_str(&bin_entry("decode"));
+        assert_eq!(bin_targets(&manifest).unwrap(), ["parse", "decode"]);
+    }
+}